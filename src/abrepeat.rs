@@ -0,0 +1,116 @@
+use lofty::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+use crate::convert::{copy_tags, extract_segments, Mp3Sink};
+use crate::device_io::write_file;
+use crate::utils::parse_timestamp;
+
+// ─── Language-learning A/B repeat ────────────────────────────────────────────
+
+/// A/B 复读分段所在目录（位于 iPod_Control 下，扫描时跳过）
+pub const ABREPEAT_DIR: &str = "ABRepeat";
+
+/// 与音频同名的切分列表扩展名，如 "Lesson 1.mp3" 对应 "Lesson 1.cuts"
+pub const CUTS_EXT: &str = ".cuts";
+
+/// 记录生成参数的标记文件，参数或切分列表未变时沿用已有分段
+const STAMP_FILE: &str = ".abrepeat";
+
+/// 解析切分列表：每行 "开始 结束"（秒、mm:ss 或 hh:mm:ss），
+/// 行尾的其余文字与 "#" 开头的行被忽略
+pub fn parse_cuts(text: &str) -> Result<Vec<(u64, u64)>, String> {
+    let mut cuts = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') { continue; }
+        let mut fields = line.split_whitespace();
+        let (Some(start), Some(end)) = (fields.next(), fields.next()) else {
+            return Err(format!("第 {} 行缺少结束时间", i + 1));
+        };
+        let start = parse_timestamp(start).map_err(|e| format!("第 {} 行: {}", i + 1, e))?;
+        let end = parse_timestamp(end).map_err(|e| format!("第 {} 行: {}", i + 1, e))?;
+        if end <= start {
+            return Err(format!("第 {} 行: 结束时间必须晚于开始时间", i + 1));
+        }
+        cuts.push((start, end));
+    }
+    Ok(cuts)
+}
+
+fn modified(path: &Path) -> Option<std::time::SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// 为 `src` 按切分列表生成复读分段：每段重复 `repeat` 次，每次之后停顿 `pause_ms` 毫秒。
+/// 分段写入 `out_dir`，返回按顺序排列的分段路径
+pub fn generate_ab_repeat(
+    src: &Path,
+    cuts_file: &Path,
+    out_dir: &Path,
+    repeat: u32,
+    pause_ms: u64,
+    bitrate: u32,
+) -> Result<Vec<PathBuf>, String> {
+    let text = fs::read_to_string(cuts_file).map_err(|e| format!("无法读取切分列表: {}", e))?;
+    let cuts = parse_cuts(&text)?;
+    if cuts.is_empty() {
+        return Err("切分列表为空".to_string());
+    }
+
+    let stem = src.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let outputs: Vec<PathBuf> = (1..=cuts.len())
+        .map(|i| out_dir.join(format!("{} {:03}.mp3", stem, i)))
+        .collect();
+
+    // Reuse the previous run's segments when nothing that shapes them changed
+    let stamp_path = out_dir.join(STAMP_FILE);
+    let stamp = format!("repeat={} pause={} bitrate={}\n{}", repeat, pause_ms, bitrate, text);
+    let fresh = fs::read_to_string(&stamp_path).ok().as_deref() == Some(stamp.as_str())
+        && modified(&stamp_path) >= modified(src)
+        && outputs.iter().all(|o| o.exists());
+    if fresh {
+        debug!("复读分段已是最新: {}", stem);
+        return Ok(outputs);
+    }
+
+    debug!("生成复读分段: {} ({} 段)", stem, cuts.len());
+    let (segments, sample_rate, channels) = extract_segments(src, &cuts)?;
+    let _ = fs::remove_dir_all(out_dir);
+    fs::create_dir_all(out_dir).map_err(|e| format!("无法创建目录: {}", e))?;
+
+    let title = lofty::read_from_path(src).ok()
+        .and_then(|t| t.primary_tag().or_else(|| t.first_tag()).and_then(|tag| tag.title().map(|s| s.to_string())))
+        .unwrap_or_else(|| stem.clone());
+    let silence = vec![0.0f32; (pause_ms * sample_rate as u64 / 1000) as usize * channels];
+
+    for (i, (samples, out)) in segments.iter().zip(&outputs).enumerate() {
+        if samples.is_empty() {
+            return Err(format!("第 {} 段超出音频长度", i + 1));
+        }
+        let mut sink = Mp3Sink::new(sample_rate, channels, bitrate);
+        for _ in 0..repeat {
+            sink.push(samples, channels);
+            sink.push(&silence, channels);
+        }
+        write_file(out, sink.finish()).map_err(|e| format!("写入 MP3 失败: {}", e))?;
+        copy_tags(src, out);
+        set_title(out, &format!("{} {:03}", title, i + 1));
+    }
+
+    write_file(&stamp_path, stamp).map_err(|e| format!("写入标记文件失败: {}", e))?;
+    Ok(outputs)
+}
+
+/// 为分段设置独立的标题，使曲目语音播报能区分各段
+fn set_title(path: &Path, title: &str) {
+    let Ok(mut tagged) = lofty::read_from_path(path) else { return };
+    if tagged.primary_tag().is_none() {
+        tagged.insert_tag(lofty::tag::Tag::new(lofty::tag::TagType::Id3v2));
+    }
+    if let Some(tag) = tagged.primary_tag_mut() {
+        tag.set_title(title.to_string());
+    }
+    let _ = tagged.save_to_path(path, lofty::config::WriteOptions::default());
+}
@@ -0,0 +1,161 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use rayon::prelude::*;
+
+use crate::converters::load_converters;
+use crate::convert::convert_flac_to_mp3;
+use crate::profiles::load_host_config;
+use crate::tts::{self, text_to_speech_file};
+use crate::utils::ext_lower;
+
+pub use crate::convert::EncodeOptions;
+pub use crate::task::{Confirmation, PhaseProgress, SyncTask, TaskState};
+pub use crate::tts::TtsSettings;
+pub use crate::tts_engine::TtsEngine;
+
+// ─── Embedding API ───────────────────────────────────────────────────────────
+
+/// 批量任务处理完一个条目后送出的进度
+pub struct Progress {
+    /// 已处理的条目数
+    pub done: usize,
+    pub total: usize,
+    /// 刚处理完的条目（转换的源文件或旁白的 WAV）
+    pub item: PathBuf,
+}
+
+/// 在后台线程中运行的一批转换或语音合成。每处理完一个条目，`progress` 中就多一条进度；
+/// `cancel` 之后不再开始新的条目，进行中的条目照常完成，不会留下写了一半的文件
+pub struct BatchTask<T> {
+    progress: mpsc::Receiver<Progress>,
+    cancelled: Arc<AtomicBool>,
+    worker: JoinHandle<Vec<T>>,
+}
+
+/// 工作线程一侧：检查是否已取消，并报告进度
+struct Reporter {
+    sender: Mutex<mpsc::Sender<Progress>>,
+    cancelled: Arc<AtomicBool>,
+    done: AtomicUsize,
+    total: usize,
+}
+
+impl Reporter {
+    fn cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn finished(&self, item: &Path) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        // The receiver may have been dropped by a caller that only waits for the result
+        let _ = self.sender.lock().unwrap().send(Progress { done, total: self.total, item: item.to_path_buf() });
+    }
+}
+
+impl<T: Send + 'static> BatchTask<T> {
+    fn spawn(total: usize, work: impl FnOnce(&Reporter) -> Vec<T> + Send + 'static) -> Self {
+        let (sender, progress) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let reporter = Reporter { sender: Mutex::new(sender), cancelled: cancelled.clone(), done: AtomicUsize::new(0), total };
+        let worker = thread::spawn(move || work(&reporter));
+        BatchTask { progress, cancelled, worker }
+    }
+
+    /// 进度流，任务结束后断开
+    pub fn progress(&self) -> &mpsc::Receiver<Progress> {
+        &self.progress
+    }
+
+    /// 请求停止：不再开始新的条目
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn finished(&self) -> bool {
+        self.worker.is_finished()
+    }
+
+    /// 等待任务结束，返回已处理的条目的结果；取消后没有开始的条目不在其中
+    pub fn wait(self) -> Vec<T> {
+        self.worker.join().expect("batch worker panicked")
+    }
+}
+
+/// 一个转换：源文件与输出的 MP3。`overwrite` 为 `false` 时已存在的 MP3 直接沿用
+pub struct ConvertJob {
+    pub source: PathBuf,
+    pub mp3: PathBuf,
+    pub overwrite: bool,
+}
+
+/// 一个转换的结果：成功时为 MP3 的路径（源文件已删除），失败时为原因（源文件保留）
+pub struct Converted {
+    pub source: PathBuf,
+    pub result: Result<PathBuf, String>,
+}
+
+/// 并发地将一批文件转换为 MP3，与同步的转换阶段相同：FLAC 使用内置的编码器，
+/// 其他格式使用电脑上的配置文件中的 [converters]，成功后删除源文件
+pub fn convert_batch(jobs: Vec<ConvertJob>, opts: EncodeOptions) -> Result<BatchTask<Converted>, String> {
+    let converters = load_host_config().and_then(|c| load_converters(c.as_ref()))?;
+    Ok(BatchTask::spawn(jobs.len(), move |reporter| {
+        let converted = Mutex::new(Vec::new());
+        jobs.par_iter().for_each(|job| {
+            if reporter.cancelled() { return; }
+            let result = match converters.get(&ext_lower(&job.source)) {
+                Some(converter) => converter.convert(&job.source, &job.mp3, job.overwrite, opts),
+                None => convert_flac_to_mp3(&job.source, &job.mp3, job.overwrite, opts),
+            };
+            converted.lock().unwrap().push(Converted { source: job.source.clone(), result });
+            reporter.finished(&job.source);
+        });
+        converted.into_inner().unwrap()
+    }))
+}
+
+/// 一条语音旁白：朗读的文本、输出的 WAV 与语音（`None` 时使用 `configure_tts` 设置的语音）
+pub struct VoiceoverJob {
+    pub text: String,
+    pub wav: PathBuf,
+    pub voice: Option<String>,
+}
+
+/// 一条旁白的结果：`generated` 为 `false` 表示合成失败
+pub struct Voiceover {
+    pub wav: PathBuf,
+    pub generated: bool,
+}
+
+/// 设置语音合成的引擎、语音与时限，须在第一次 `tts_batch` 之前调用
+pub fn configure_tts(settings: TtsSettings) {
+    tts::configure(settings);
+}
+
+/// 依次合成一批语音旁白，已存在的 WAV 直接沿用
+pub fn tts_batch(jobs: Vec<VoiceoverJob>) -> BatchTask<Voiceover> {
+    BatchTask::spawn(jobs.len(), move |reporter| {
+        let mut done = Vec::new();
+        for job in &jobs {
+            if reporter.cancelled() { break; }
+            if let Some(dir) = job.wav.parent() {
+                let _ = std::fs::create_dir_all(dir);
+            }
+            let generated = text_to_speech_file(&job.wav, &job.text, None, job.voice.as_deref());
+            done.push(Voiceover { wav: job.wav.clone(), generated });
+            reporter.finished(&job.wav);
+        }
+        done
+    })
+}
+
+/// 在子进程中运行一次同步（build 子命令），行为与命令行完全一致。`program` 为本程序的可执行文件，
+/// `args` 为 build 的参数（包括设备路径）。各阶段的进度见 `SyncTask::state`，`SyncTask::cancel`
+/// 与按下 Ctrl+C 一样在安全的位置停止
+pub fn build(program: &Path, args: &[String], on_update: impl Fn() + Send + Sync + 'static) -> Result<SyncTask, String> {
+    let mut full = vec!["build".to_string()];
+    full.extend(args.iter().cloned());
+    SyncTask::spawn(program, &full, on_update)
+}
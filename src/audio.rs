@@ -9,8 +9,9 @@ use symphonia::core::probe::Hint;
 
 // ─── Loudness estimation ─────────────────────────────────────────────────────
 
-/// 估算音轨的 RMS 响度（dBFS），最多分析 `max_seconds` 秒
-pub fn estimate_track_loudness_db(path: &Path, max_seconds: f64) -> Option<f64> {
+/// 估算音轨的 RMS 响度（dBFS），最多分析开头的 `max_seconds` 秒，结果不低于 `floor_db`。
+/// 短于分析时长的曲目直接分析整个文件
+pub fn estimate_track_loudness_db(path: &Path, max_seconds: f64, floor_db: f64) -> Option<f64> {
     let file = fs::File::open(path).ok()?;
     let mss = MediaSourceStream::new(Box::new(file), Default::default());
     let mut hint = Hint::new();
@@ -26,7 +27,12 @@ pub fn estimate_track_loudness_db(path: &Path, max_seconds: f64) -> Option<f64>
     let track = format.default_track()?.clone();
     let codec_params = track.codec_params.clone();
     let sample_rate = codec_params.sample_rate.unwrap_or(44100) as f64;
-    let max_samples = (max_seconds * sample_rate * 2.0) as usize;
+    let channels = codec_params.channels.map(|c| c.count()).unwrap_or(2);
+    let window_frames = (max_seconds * sample_rate) as u64;
+    let max_samples = match codec_params.n_frames {
+        Some(n) if n <= window_frames => usize::MAX,
+        _ => window_frames as usize * channels,
+    };
 
     let mut decoder = symphonia::default::get_codecs()
         .make(&codec_params, &DecoderOptions::default())
@@ -61,7 +67,7 @@ pub fn estimate_track_loudness_db(path: &Path, max_seconds: f64) -> Option<f64>
 
     let rms = (sum_squares / sample_count as f64).sqrt();
     if rms <= 1e-12 {
-        return Some(-120.0);
+        return Some(floor_db);
     }
-    Some(20.0 * rms.log10())
+    Some((20.0 * rms.log10()).max(floor_db))
 }
@@ -0,0 +1,160 @@
+use md5::{Digest, Md5};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::database::{dbid_to_filename, playlist_dbid, TrackInfo};
+use crate::device_io::write_file_atomic;
+use crate::listing::playlist_display_name;
+use crate::playlist::PlaylistOptions;
+
+// ─── iTunesSD.meta.json ──────────────────────────────────────────────────────
+
+/// 与 iTunesSD 放在一起的构建信息，记录生成数据库时的版本、选项与每条记录的来源
+pub const META_FILE: &str = "iTunesSD.meta.json";
+
+/// 计算来源指纹时读取文件开头和结尾的字节数
+const FINGERPRINT_BYTES: u64 = 64 * 1024;
+
+/// 本次运行实际生效的命令行参数（含配置文件与预设展开的选项）
+static ARGS: OnceLock<Vec<String>> = OnceLock::new();
+
+/// 记录展开后的命令行参数，写入构建信息的 `args`
+pub fn record_args(args: Vec<String>) {
+    let _ = ARGS.set(args);
+}
+
+/// 重建数据库前设备记录的每首曲目的播放次数（见 `stats::play_counts`），无法读取时不记录
+static PLAY_COUNTS: OnceLock<HashMap<String, u32>> = OnceLock::new();
+
+/// 记录重建数据库前的播放次数，写入构建信息中每首曲目的 `play_count`
+pub fn record_play_counts(counts: HashMap<String, u32>) {
+    let _ = PLAY_COUNTS.set(counts);
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 文件的快速指纹：大小加上开头与结尾各 64 KiB 的 MD5，不必读取整个文件
+pub fn fingerprint(path: &Path) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut hasher = Md5::new();
+    hasher.update(size.to_le_bytes());
+    let mut buf = Vec::new();
+    (&mut file).take(FINGERPRINT_BYTES).read_to_end(&mut buf)?;
+    if size > FINGERPRINT_BYTES {
+        file.seek(SeekFrom::Start(size.saturating_sub(FINGERPRINT_BYTES).max(FINGERPRINT_BYTES)))?;
+        file.read_to_end(&mut buf)?;
+    }
+    hasher.update(&buf);
+    Ok(hex(&hasher.finalize()))
+}
+
+/// 上次写入的构建信息中每首曲目的 (iPod 路径, 指纹)。文件不存在、无法解析或曲目没有指纹时略过
+pub fn previous_fingerprints(base: &Path) -> Vec<(String, String)> {
+    let path = base.join("iPod_Control").join("iTunes").join(META_FILE);
+    let Some(meta) = fs::read_to_string(path).ok().and_then(|t| serde_json::from_str::<serde_json::Value>(&t).ok()) else {
+        return Vec::new();
+    };
+    meta["tracks"].as_array().into_iter().flatten()
+        .filter_map(|t| Some((t["filename"].as_str()?.to_string(), t["fingerprint"].as_str()?.to_string())))
+        .collect()
+}
+
+/// 上次写入的构建信息中每首曲目（iPod 路径）已连续写入数据库的同步次数，没有构建信息时为空
+pub fn previous_sync_counts(base: &Path) -> HashMap<String, u32> {
+    let path = base.join("iPod_Control").join("iTunes").join(META_FILE);
+    let Some(meta) = fs::read_to_string(path).ok().and_then(|t| serde_json::from_str::<serde_json::Value>(&t).ok()) else {
+        return HashMap::new();
+    };
+    meta["tracks"].as_array().into_iter().flatten()
+        .filter_map(|t| Some((t["filename"].as_str()?.to_string(), t["syncs"].as_u64().unwrap_or(1) as u32)))
+        .collect()
+}
+
+/// 上次写入的构建信息中每首曲目（iPod 路径）在那次同步时的播放次数。没有构建信息或当时无法读取播放统计的曲目不在其中
+pub fn previous_play_counts(base: &Path) -> HashMap<String, u32> {
+    let path = base.join("iPod_Control").join("iTunes").join(META_FILE);
+    let Some(meta) = fs::read_to_string(path).ok().and_then(|t| serde_json::from_str::<serde_json::Value>(&t).ok()) else {
+        return HashMap::new();
+    };
+    meta["tracks"].as_array().into_iter().flatten()
+        .filter_map(|t| Some((t["filename"].as_str()?.to_string(), t["play_count"].as_u64()? as u32)))
+        .collect()
+}
+
+/// 上次同步被 --rotate-after 轮换出去、还在等待换入的曲目（iPod 路径），先轮换出去的在前
+pub fn previous_rotated_out(base: &Path) -> Vec<String> {
+    let path = base.join("iPod_Control").join("iTunes").join(META_FILE);
+    let Some(meta) = fs::read_to_string(path).ok().and_then(|t| serde_json::from_str::<serde_json::Value>(&t).ok()) else {
+        return Vec::new();
+    };
+    meta["rotated_out"].as_array().into_iter().flatten()
+        .filter_map(|t| t.as_str().map(str::to_string))
+        .collect()
+}
+
+/// 写入 `iPod_Control/iTunes/iTunesSD.meta.json`，返回写入的路径。每首曲目的 `syncs` 为它已连续写入数据库的同步次数，
+/// `play_count` 为同步时设备记录的播放次数（见 `record_play_counts`，无法读取时为 null），`rotated_out` 为等待 --rotate-after 换入的曲目
+pub fn write_build_meta(
+    base: &Path,
+    db: &[u8],
+    track_infos: &[TrackInfo],
+    playlists: &[(String, Vec<u32>)],
+    options: &HashMap<String, PlaylistOptions>,
+    playlist_voiceover: bool,
+    rotated_out: &[String],
+) -> io::Result<PathBuf> {
+    let previous = previous_sync_counts(base);
+    let fingerprints: Vec<Option<String>> = track_infos.par_iter()
+        .map(|t| fingerprint(&base.join(t.filename.trim_start_matches('/'))).ok())
+        .collect();
+    let tracks: Vec<serde_json::Value> = track_infos.iter().zip(fingerprints).enumerate()
+        .map(|(i, (t, fp))| serde_json::json!({
+            "index": i,
+            "filename": t.filename,
+            "dbid": dbid_to_filename(&t.dbid),
+            "start_at_pos_ms": t.start_at_pos_ms,
+            "stop_at_pos_ms": t.stop_at_pos_ms,
+            "volume_gain": t.volume_gain,
+            "fingerprint": fp,
+            "syncs": previous.get(&t.filename).map_or(1, |n| n.saturating_add(1)),
+            // A track new to the device has not been played yet
+            "play_count": PLAY_COUNTS.get().map(|c| c.get(&t.filename).copied().unwrap_or(0)),
+        }))
+        .collect();
+    let no_options = PlaylistOptions::default();
+    let playlists: Vec<serde_json::Value> = playlists.iter()
+        .map(|(name, indices)| {
+            let listtype = if name == "__master__" { 1 } else { options.get(name).unwrap_or(&no_options).listtype_code() };
+            serde_json::json!({
+                "name": playlist_display_name(name),
+                "dbid": dbid_to_filename(&playlist_dbid(name, playlist_voiceover)),
+                "listtype": listtype,
+                "tracks": indices,
+            })
+        })
+        .collect();
+    let generated_at = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+
+    let meta = serde_json::json!({
+        "tool": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "generated_at": generated_at,
+        "args": ARGS.get().cloned().unwrap_or_default(),
+        "database": { "size": db.len(), "md5": hex(&Md5::digest(db)) },
+        "tracks": tracks,
+        "playlists": playlists,
+        "rotated_out": rotated_out,
+    });
+    let path = base.join("iPod_Control").join("iTunes").join(META_FILE);
+    let text = serde_json::to_string_pretty(&meta).expect("JSON values always serialize");
+    write_file_atomic(&path, text)?;
+    Ok(path)
+}
@@ -0,0 +1,37 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::device_lock;
+use crate::exit_code;
+use crate::i18n::tr;
+use crate::progress;
+
+// ─── Ctrl+C cancellation ─────────────────────────────────────────────────────
+
+/// 同步开始后为 true：第一次 Ctrl+C 只请求停止，由同步在安全的位置退出
+static GRACEFUL: AtomicBool = AtomicBool::new(false);
+
+/// 已按过 Ctrl+C，正在停止
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// 安装 Ctrl+C 处理函数（SIGTERM 同样处理）。同步以外的命令与再次按下 Ctrl+C 时立即退出
+pub fn install() {
+    let _ = ctrlc::set_handler(|| {
+        if GRACEFUL.load(Ordering::Relaxed) && !REQUESTED.swap(true, Ordering::Relaxed) {
+            progress::suspend(|| eprintln!("{}", tr!("\n正在停止：等待进行中的转换与旁白完成（再按一次 Ctrl+C 立即退出）...")));
+            return;
+        }
+        eprintln!("{}", tr!("\n检测到中断，正在退出..."));
+        device_lock::release();
+        std::process::exit(exit_code::INTERRUPTED);
+    });
+}
+
+/// 同步开始修改设备，此后 Ctrl+C 由 `requested` 的调用方处理
+pub fn enable_graceful() {
+    GRACEFUL.store(true, Ordering::Relaxed);
+}
+
+/// 是否已按下 Ctrl+C。并发的转换与分析在开始每个文件前检查，已开始的文件照常完成
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::Relaxed)
+}
@@ -0,0 +1,108 @@
+use std::collections::{HashMap, HashSet};
+use tracing::debug;
+
+use crate::database::{playlist_dbid, ParsedDatabase, TrackInfo};
+use crate::i18n::tr;
+use crate::listing::playlist_display_name;
+
+// ─── Change summary against the previous database ────────────────────────────
+
+/// 本次写入的数据库与设备上原有数据库之间的差异
+#[derive(Default)]
+pub struct ChangeSummary {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub playlists_added: usize,
+    pub playlists_removed: usize,
+    /// 同名播放列表中曲目或顺序有变化的数量
+    pub playlists_changed: usize,
+    /// 增益发生变化的曲目：(路径, 原增益, 新增益)
+    pub gains: Vec<(String, u32, u32)>,
+    /// 上次同步之后被移动或重命名的曲目：(原路径, 新路径)，不计入新增与移除
+    pub moved: Vec<(String, String)>,
+}
+
+impl ChangeSummary {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.gains.is_empty() && self.moved.is_empty()
+            && self.playlists_added + self.playlists_removed + self.playlists_changed == 0
+    }
+
+    /// 单行摘要，如 "新增 3 首曲目，移除 1 首，2 个播放列表有变化（…），5 首曲目的增益被调整"
+    pub fn line(&self) -> String {
+        if self.is_empty() {
+            return tr!("与上次的数据库相比没有变化");
+        }
+        let playlists = self.playlists_added + self.playlists_removed + self.playlists_changed;
+        let line = tr!(
+            "新增 {} 首曲目，移除 {} 首，{} 个播放列表有变化（新增 {}、移除 {}、修改 {}），{} 首曲目的增益被调整",
+            self.added.len(), self.removed.len(), playlists,
+            self.playlists_added, self.playlists_removed, self.playlists_changed, self.gains.len(),
+        );
+        if self.moved.is_empty() {
+            return line;
+        }
+        tr!("{}，{} 首曲目被移动", line, self.moved.len())
+    }
+}
+
+/// 比较原有数据库与即将写入的曲目和播放列表。
+/// iTunesSD 不保存播放列表名称，播放列表按由名称生成的 dbid 对应，内容按曲目路径比较
+pub fn summarize_changes(
+    previous: &ParsedDatabase,
+    tracks: &[TrackInfo],
+    playlists: &[(String, Vec<u32>)],
+    playlist_voiceover: bool,
+) -> ChangeSummary {
+    let mut summary = ChangeSummary::default();
+
+    let old_gains: HashMap<&str, u32> = previous.tracks.iter()
+        .map(|t| (t.filename.as_str(), t.volume_gain))
+        .collect();
+    let new_names: HashSet<&str> = tracks.iter().map(|t| t.filename.as_str()).collect();
+    for t in tracks {
+        match old_gains.get(t.filename.as_str()) {
+            None => summary.added.push(t.filename.clone()),
+            Some(&old) if old != t.volume_gain => summary.gains.push((t.filename.clone(), old, t.volume_gain)),
+            Some(_) => {}
+        }
+    }
+    summary.removed = previous.tracks.iter()
+        .filter(|t| !new_names.contains(t.filename.as_str()))
+        .map(|t| t.filename.clone())
+        .collect();
+
+    let old_lists: HashMap<[u8; 8], Vec<&str>> = previous.playlists.iter()
+        .map(|p| {
+            let files = p.track_indices.iter()
+                .filter_map(|&i| previous.tracks.get(i as usize).map(|t| t.filename.as_str()))
+                .collect();
+            // The master list's dbid depends on whether playlist voiceover was on
+            let key = if p.listtype == 1 { [0xFF; 8] } else { p.dbid };
+            (key, files)
+        })
+        .collect();
+    let mut seen = HashSet::new();
+    for (name, indices) in playlists {
+        let key = if name == "__master__" { [0xFF; 8] } else { playlist_dbid(name, playlist_voiceover) };
+        seen.insert(key);
+        let name = playlist_display_name(name);
+        let files: Vec<&str> = indices.iter()
+            .filter_map(|&i| tracks.get(i as usize).map(|t| t.filename.as_str()))
+            .collect();
+        match old_lists.get(&key) {
+            None => {
+                debug!("[+] 新增播放列表 {}", name);
+                summary.playlists_added += 1;
+            }
+            Some(old) if *old != files => {
+                debug!("[~] 播放列表有变化 {}", name);
+                summary.playlists_changed += 1;
+            }
+            Some(_) => {}
+        }
+    }
+    summary.playlists_removed = old_lists.keys().filter(|k| !seen.contains(*k)).count();
+
+    summary
+}
@@ -0,0 +1,133 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+// ─── ID3v2 chapters (CHAP/CTOC) ──────────────────────────────────────────────
+
+/// MP3 中由 ID3v2 CHAP 帧描述的一个章节
+pub struct Chapter {
+    pub start_ms: u32,
+    pub end_ms: u32,
+    /// 章节内 TIT2 子帧给出的标题
+    pub title: Option<String>,
+}
+
+fn syncsafe(b: &[u8]) -> usize {
+    b.iter().take(4).fold(0usize, |acc, &x| (acc << 7) | (x & 0x7F) as usize)
+}
+
+fn be_u32(b: &[u8]) -> u32 {
+    u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+}
+
+/// 一个 ID3v2 帧：(帧 ID, 内容)
+type RawFrame<'a> = (&'a [u8], &'a [u8]);
+
+/// 按 ID3v2.3/2.4 的帧格式拆分帧序列，遇到填充或损坏的帧时停止
+fn split_frames(data: &[u8], major: u8) -> Vec<RawFrame<'_>> {
+    let mut frames = Vec::new();
+    let mut pos = 0;
+    while pos + 10 <= data.len() {
+        let id = &data[pos..pos + 4];
+        if !id.iter().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()) { break; }
+        let size = if major >= 4 { syncsafe(&data[pos + 4..pos + 8]) } else { be_u32(&data[pos + 4..pos + 8]) as usize };
+        let Some(body) = data.get(pos + 10..pos + 10 + size) else { break };
+        frames.push((id, body));
+        pos += 10 + size;
+    }
+    frames
+}
+
+/// 拆出以 0 结尾的字符串（元素 ID），返回其余部分
+fn split_cstr(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let end = data.iter().position(|&b| b == 0)?;
+    Some((&data[..end], &data[end + 1..]))
+}
+
+/// 解码文本帧的内容（首字节为编码）
+fn decode_text(body: &[u8]) -> Option<String> {
+    let (&encoding, text) = body.split_first()?;
+    let decoded = match encoding {
+        0 => text.iter().map(|&b| b as char).collect(),
+        1 | 2 => {
+            let (big_endian, text) = match text {
+                [0xFE, 0xFF, rest @ ..] => (true, rest),
+                [0xFF, 0xFE, rest @ ..] => (false, rest),
+                _ => (encoding == 2, text),
+            };
+            let units: Vec<u16> = text.chunks_exact(2)
+                .map(|c| if big_endian { u16::from_be_bytes([c[0], c[1]]) } else { u16::from_le_bytes([c[0], c[1]]) })
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(text).to_string(),
+    };
+    let decoded = decoded.trim_end_matches('\0').trim().to_string();
+    (!decoded.is_empty()).then_some(decoded)
+}
+
+/// 读取 MP3 开头 ID3v2 标签中的章节。有顶层 CTOC 目录时按目录排列，否则按开始时间排列；
+/// 没有标签或章节时返回空列表
+pub fn read_chapters(path: &Path) -> Result<Vec<Chapter>, String> {
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    let mut header = [0u8; 10];
+    if file.read_exact(&mut header).is_err() || &header[..3] != b"ID3" {
+        return Ok(Vec::new());
+    }
+    let major = header[3];
+    let flags = header[5];
+    // Whole-tag unsynchronisation would need undoing first; chaptered podcasts don't use it
+    if !(3..=4).contains(&major) || flags & 0x80 != 0 {
+        return Ok(Vec::new());
+    }
+    let mut tag = vec![0u8; syncsafe(&header[6..10])];
+    file.read_exact(&mut tag).map_err(|e| format!("ID3 标签被截断: {}", e))?;
+
+    let mut body: &[u8] = &tag;
+    if flags & 0x40 != 0 && body.len() >= 4 {
+        let ext = if major >= 4 { syncsafe(body) } else { be_u32(body) as usize + 4 };
+        body = body.get(ext..).unwrap_or_default();
+    }
+
+    let mut chapters: Vec<(&[u8], Chapter)> = Vec::new();
+    let mut order: Option<Vec<&[u8]>> = None;
+    for (id, frame) in split_frames(body, major) {
+        match id {
+            b"CHAP" => {
+                let Some((element, rest)) = split_cstr(frame) else { continue };
+                if rest.len() < 16 { continue; }
+                let title = split_frames(&rest[16..], major).into_iter()
+                    .find(|(sub, _)| *sub == b"TIT2")
+                    .and_then(|(_, text)| decode_text(text));
+                chapters.push((element, Chapter { start_ms: be_u32(&rest[0..4]), end_ms: be_u32(&rest[4..8]), title }));
+            }
+            b"CTOC" => {
+                let Some((_, rest)) = split_cstr(frame) else { continue };
+                let [toc_flags, count, rest @ ..] = rest else { continue };
+                // Only the top-level table of contents gives the playing order
+                if toc_flags & 0x02 == 0 { continue; }
+                let mut entries = Vec::new();
+                let mut rest = rest;
+                for _ in 0..*count {
+                    let Some((entry, tail)) = split_cstr(rest) else { break };
+                    entries.push(entry);
+                    rest = tail;
+                }
+                order = Some(entries);
+            }
+            _ => {}
+        }
+    }
+
+    let Some(order) = order else {
+        chapters.sort_by_key(|(_, c)| c.start_ms);
+        return Ok(chapters.into_iter().map(|(_, c)| c).collect());
+    };
+    let mut ordered = Vec::with_capacity(order.len());
+    for element in order {
+        if let Some(i) = chapters.iter().position(|(id, _)| *id == element) {
+            ordered.push(chapters.swap_remove(i).1);
+        }
+    }
+    Ok(ordered)
+}
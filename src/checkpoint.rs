@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::debug;
+
+use crate::i18n::tr;
+
+// ─── Sync checkpoint ─────────────────────────────────────────────────────────
+
+/// iPod_Control/iTunes 下的检查点文件：同步开始修改设备时创建，写入数据库后删除。
+/// 文件仍然存在说明上次同步被拔线或 Ctrl+C 打断
+pub const CHECKPOINT_FILE: &str = "sync_checkpoint.txt";
+
+const HEADER: &str = "# ipod-shuffle-4g: 同步未完成，下次同步从这里继续\n";
+
+/// 一次同步的检查点。每个转换开始前记录源文件与输出的 MP3，而 MP3 只在完整写入后才出现
+/// （见 `convert::partial_path`），因此续传时输出存在即说明转换已完成；
+/// 已完成的旁白记录在 `tts::PROGRESS_FILE`，续传时按 --tts-resume 的方式沿用
+pub struct Checkpoint {
+    base: PathBuf,
+    path: PathBuf,
+    interrupted: bool,
+    /// 上次被中断的同步开始过的转换：源文件 -> MP3（均为相对 iPod 根目录的路径）
+    conversions: HashMap<String, String>,
+    file: Mutex<Option<File>>,
+}
+
+fn key(base: &Path, path: &Path) -> String {
+    path.strip_prefix(base).unwrap_or(path).to_string_lossy().replace('\\', "/")
+}
+
+impl Checkpoint {
+    /// 读取上次同步留下的检查点
+    pub fn load(base: &Path) -> Self {
+        let path = base.join("iPod_Control").join("iTunes").join(CHECKPOINT_FILE);
+        let text = fs::read_to_string(&path).ok();
+        let conversions = text.iter()
+            .flat_map(|t| t.lines())
+            .filter_map(|line| match line.split('\t').collect::<Vec<_>>()[..] {
+                ["convert", src, mp3] => Some((src.to_string(), mp3.to_string())),
+                _ => None,
+            })
+            .collect();
+        Checkpoint {
+            base: base.to_path_buf(),
+            path,
+            interrupted: text.is_some(),
+            conversions,
+            file: Mutex::new(None),
+        }
+    }
+
+    /// 上次同步是否被打断
+    pub fn interrupted(&self) -> bool {
+        self.interrupted
+    }
+
+    /// 放弃上次同步的进度（--no-resume），本次同步从头开始
+    pub fn discard(&mut self) {
+        self.interrupted = false;
+        self.conversions.clear();
+    }
+
+    /// 开始记录本次同步，在第一次修改设备之前调用。续传时保留上次的记录，本次再被打断时仍然有效
+    pub fn start(&self) -> Result<(), String> {
+        let mut options = OpenOptions::new();
+        if self.interrupted {
+            options.append(true);
+        } else {
+            options.write(true).truncate(true);
+        }
+        let mut file = options.create(true).open(&self.path)
+            .map_err(|e| tr!("无法创建检查点 {}: {}", self.path.display(), e))?;
+        if !self.interrupted {
+            file.write_all(HEADER.as_bytes()).map_err(|e| tr!("无法写入检查点 {}: {}", self.path.display(), e))?;
+        }
+        *self.file.lock().unwrap() = Some(file);
+        Ok(())
+    }
+
+    /// 上次被中断的同步已将 `src` 转换为仍在设备上的 MP3 时返回该 MP3
+    pub fn converted(&self, src: &Path) -> Option<PathBuf> {
+        let mp3 = self.base.join(self.conversions.get(&key(&self.base, src))?);
+        mp3.is_file().then_some(mp3)
+    }
+
+    /// 在转换开始前记录源文件与输出；立即写入设备，使意外中断的同步也能续传
+    pub fn record_conversion(&self, src: &Path, mp3: &Path) {
+        let mut guard = self.file.lock().unwrap();
+        let Some(file) = guard.as_mut() else { return };
+        let line = format!("convert\t{}\t{}\n", key(&self.base, src), key(&self.base, mp3));
+        if let Err(e) = file.write_all(line.as_bytes()).and_then(|_| file.sync_data()) {
+            debug!("{}", tr!("无法记录转换进度 {}: {}", self.path.display(), e));
+        }
+    }
+
+    /// 同步正常结束（数据库已写入），删除检查点
+    pub fn finish(&self) {
+        self.file.lock().unwrap().take();
+        let _ = fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,71 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::abrepeat::ABREPEAT_DIR;
+use crate::generators::BEDTIME_DIR;
+use crate::textbook::TEXTBOOK_DIR;
+use crate::tts::PROGRESS_FILE;
+
+// ─── clean subcommand ────────────────────────────────────────────────────────
+
+/// clean 将删除的一项内容
+pub struct CleanTarget {
+    pub paths: Vec<PathBuf>,
+    /// 说明，路径相对 iPod 根目录
+    pub label: String,
+}
+
+/// 列出同步生成、clean 会删除的文件。Speakable 的两个子目录只清空其中的文件，数据库保持不变
+pub fn clean_targets(base: &Path, textbooks: bool) -> Vec<CleanTarget> {
+    let control = base.join("iPod_Control");
+    let relative = |path: &Path| path.strip_prefix(base).unwrap_or(path).display().to_string();
+    let mut targets = Vec::new();
+
+    for subdir in ["Playlists", "Tracks"] {
+        let dir = control.join("Speakable").join(subdir);
+        let Ok(entries) = fs::read_dir(&dir) else { continue };
+        let paths: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+        if !paths.is_empty() {
+            targets.push(CleanTarget { label: format!("{} 中的 {} 个文件", relative(&dir), paths.len()), paths });
+        }
+    }
+
+    let mut paths = vec![
+        control.join(ABREPEAT_DIR),
+        control.join(BEDTIME_DIR),
+        control.join("iTunes").join(PROGRESS_FILE),
+        control.join("last_sync_report.html"),
+        control.join("last_sync_report.md"),
+    ];
+    if textbooks {
+        paths.push(control.join(TEXTBOOK_DIR));
+    }
+    for path in paths {
+        if path.exists() {
+            targets.push(CleanTarget { label: relative(&path), paths: vec![path] });
+        }
+    }
+    targets
+}
+
+/// 删除 `clean_targets` 列出的内容，返回已删除内容的说明
+pub fn remove_targets(targets: &[CleanTarget]) -> Result<Vec<String>, String> {
+    let remove = |path: &Path| -> Result<(), String> {
+        let result = if path.is_dir() {
+            fs::remove_dir_all(path)
+        } else if path.is_file() {
+            fs::remove_file(path)
+        } else {
+            return Ok(());
+        };
+        result.map_err(|e| format!("无法删除 {}: {}", path.display(), e))
+    };
+    let mut removed = Vec::new();
+    for target in targets {
+        for path in &target.paths {
+            remove(path)?;
+        }
+        removed.push(target.label.clone());
+    }
+    Ok(removed)
+}
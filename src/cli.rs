@@ -1,10 +1,172 @@
-use clap::Parser;
+use clap::builder::{PossibleValue, PossibleValuesParser, TypedValueParser};
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::convert::BITRATES;
+use crate::playlist::{ListEncoding, NameTemplate};
+use crate::track_rules::DEFAULT_GAP;
+use crate::tts::{COMMON_TTS_VOICES, DEFAULT_TTS_VOICE};
+use crate::tts_engine::TtsEngine;
+use crate::utils::{parse_duration, parse_size, parse_title_pattern, GlobPattern};
 
 // ─── Constants ───────────────────────────────────────────────────────────────
 
 pub const AUDIO_EXT: &[&str] = &[".mp3", ".m4a", ".m4b", ".m4p", ".aa", ".wav", ".flac"];
 pub const MUSIC_EXT: &[&str] = &[".mp3", ".m4a", ".m4b", ".m4p", ".aa", ".wav"];
-pub const LIST_EXT: &[&str] = &[".pls", ".m3u"];
+/// 有声书格式，不在有声书目录中的这类文件同样作为有声书
+pub const AUDIOBOOK_EXT: &[&str] = &[".m4b", ".aa"];
+
+/// 选项对应的环境变量的前缀，如 --track-gain 对应 IPOD_SHUFFLE_TRACK_GAIN
+pub const ENV_PREFIX: &str = "IPOD_SHUFFLE_";
+
+/// 输出消息使用的语言
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Lang {
+    /// 中文
+    Zh,
+    /// English
+    En,
+}
+
+/// 同步报告的输出格式
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    Html,
+    Markdown,
+    None,
+}
+
+/// 同步结束时的汇总输出格式
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// 供人阅读的文字
+    Text,
+    /// 在标准输出打印一个 JSON 对象，其余输出改写到标准错误
+    Json,
+}
+
+/// 标签修正（乱码修复、从文件名推断标题）的应用方式
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TagFixPolicy {
+    /// 不修正
+    Off,
+    /// 只用于数据库、语音旁白与分组，不修改任何文件
+    Memory,
+    /// 同时写回 iPod 上的文件；路径不是 iPod 时按 memory 处理，不会修改本地曲库
+    Device,
+}
+
+/// 待转换的源文件（FLAC 或配置了外部转换程序的格式）与同名 MP3 并存时的处理策略
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ShadowPolicy {
+    /// 保留已有 MP3，删除源文件
+    PreferMp3,
+    /// 由源文件重新转换并覆盖已有 MP3
+    PreferFlac,
+    /// 两者都保留，源文件转换为带格式后缀（如 " (FLAC)"）的 MP3
+    KeepBothWithSuffix,
+}
+
+/// 扫描时对以 "." 开头的文件和目录的处理方式
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HiddenPolicy {
+    /// 跳过所有隐藏文件和目录
+    Skip,
+    /// 跳过隐藏文件，但扫描隐藏目录中的内容
+    SkipFiles,
+    /// 扫描所有隐藏项（系统目录与 "._" 资源分叉文件除外）
+    Include,
+}
+
+/// 曲目类别，由所在的库根目录决定
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, ValueEnum)]
+pub enum TrackKind {
+    Music,
+    Podcast,
+    Audiobook,
+}
+
+/// 设备上的一个库根目录，格式为 `类别:目录` 或 `目录`（默认类别为 music）
+#[derive(Clone, Debug)]
+pub struct LibraryRoot {
+    pub kind: TrackKind,
+    pub dir: String,
+}
+
+/// 接受任意字符串的参数，只为 shell 补全脚本提供常用取值
+#[derive(Clone)]
+struct Suggest(&'static [&'static str]);
+
+impl TypedValueParser for Suggest {
+    type Value = String;
+
+    fn parse_ref(&self, cmd: &clap::Command, arg: Option<&clap::Arg>, value: &OsStr) -> Result<String, clap::Error> {
+        clap::builder::StringValueParser::new().parse_ref(cmd, arg, value)
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        Some(Box::new(self.0.iter().map(|v| PossibleValue::new(*v))))
+    }
+}
+
+/// --auto-id3-playlists 的常用模板
+const ID3_TEMPLATES: &[&str] = &["{artist}", "{album}", "{genre}", "{artist} - {album}"];
+
+/// --dir-playlist-name 的常用模板
+const DIR_NAME_TEMPLATES: &[&str] = &["{name}", "{parent} - {name}", "{path}", "{path:2}"];
+
+/// --list-encoding 的常用编码
+const LIST_ENCODINGS: &[&str] = &["auto", "utf-8", "gbk", "big5", "shift_jis", "euc-kr", "latin1"];
+
+fn parse_library_root(s: &str) -> Result<LibraryRoot, String> {
+    if let Some((kind, dir)) = s.split_once(':') {
+        if let Ok(kind) = TrackKind::from_str(kind, true) {
+            if dir.is_empty() { return Err(format!("库根目录为空: {}", s)); }
+            return Ok(LibraryRoot { kind, dir: dir.to_string() });
+        }
+    }
+    Ok(LibraryRoot { kind: TrackKind::Music, dir: s.to_string() })
+}
+
+fn parse_tts_engine(s: &str) -> Result<TtsEngine, String> {
+    match s.split_once(':') {
+        _ if s == "edge" => Ok(TtsEngine::Edge),
+        Some(("command", program)) if !program.is_empty() => Ok(TtsEngine::Command(PathBuf::from(program))),
+        _ => Err(format!("无效的语音合成引擎: {}（应为 \"edge\" 或 \"command:<程序路径>\"）", s)),
+    }
+}
+
+fn parse_throttle(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(v) if v > 0.0 && v.is_finite() => Ok(v),
+        _ => Err(format!("无效的速率: {}", s)),
+    }
+}
+
+fn parse_gain_range(s: &str) -> Result<(u32, u32), String> {
+    let (min, max) = s.split_once('-').ok_or_else(|| format!("增益范围的格式应为 \"最小-最大\": {}", s))?;
+    let min: u32 = min.trim().parse().map_err(|_| format!("无效的增益: {}", min))?;
+    let max: u32 = max.trim().parse().map_err(|_| format!("无效的增益: {}", max))?;
+    if min > max || max > 99 {
+        return Err(format!("增益范围必须满足 0 ≤ 最小 ≤ 最大 ≤ 99: {}", s));
+    }
+    Ok((min, max))
+}
+
+/// 主列表（All songs）的曲目顺序
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TrackOrder {
+    /// 按路径排序
+    Path,
+    /// 按路径排序后将同一艺术家的曲目均匀分散开
+    ArtistSpread,
+    /// 随机打乱。种子保存在设备上，每次同步沿用，增删曲目时其余曲目的先后不变
+    Shuffle,
+}
 
 // ─── CLI ─────────────────────────────────────────────────────────────────────
 
@@ -12,8 +174,188 @@ pub const LIST_EXT: &[&str] = &[".pls", ".m3u"];
 #[command(
     version,
     about = "用于为较新一代 iPod Shuffle 构建曲目和播放列表数据库的工具",
+    args_override_self = true,
+    subcommand_negates_reqs = true,
+    override_usage = "ipod-shuffle-4g [OPTIONS] [PATH]\n       ipod-shuffle-4g [全局选项] <COMMAND>",
 )]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// 不带子命令时即为 build
+    #[command(flatten)]
+    pub sync: SyncArgs,
+
+    /// 将 man 手册页输出到标准输出后退出
+    #[arg(long = "generate-manpage", exclusive = true)]
+    pub generate_manpage: bool,
+
+    /// 显示详细输出；-vv 还显示跟踪信息，如读取的每个标签与数据库中每条记录的偏移
+    #[arg(short = 'v', long = "verbose", global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// 只显示错误，不显示进度、警告、同步结果与其他子命令的文字输出（dump --json 与 --output json 的 JSON 照常输出），适合由 cron 等定时运行的同步
+    #[arg(short = 'q', long = "quiet", global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// 输出消息的语言，默认按系统区域设置（LC_ALL、LC_MESSAGES、LANG）选择
+    #[arg(long = "lang", value_enum, global = true)]
+    pub lang: Option<Lang>,
+
+    /// 将日志（含调试信息与时间戳）追加写入指定文件
+    #[arg(long = "log-file", value_name = "PATH", global = true)]
+    pub log_file: Option<String>,
+
+    /// 按模块设置日志级别，追加在默认级别之后，如 "ipod_shuffle_4g::convert=trace"
+    #[arg(long = "log-filter", value_name = "FILTER", global = true)]
+    pub log_filter: Option<String>,
+
+    /// 删除或覆盖设备上的文件前不再询问。标准输入不是终端时无法询问，不给出此选项则不删除
+    #[arg(short = 'y', long = "yes", global = true)]
+    pub yes: bool,
+
+    /// 供 cron、systemd 等定时运行：从不询问（同 --yes）、不显示进度条，同步结束时输出 JSON 汇总
+    /// （同 --output json）
+    #[arg(long = "non-interactive", global = true)]
+    pub non_interactive: bool,
+
+    /// 不绘制进度条，改为在标准错误逐行输出各阶段进度的 JSON，供在子进程中运行本程序的前端显示
+    #[arg(long = "progress-json", global = true)]
+    pub progress_json: bool,
+
+    /// 在标准错误逐行输出同步事件的 JSON（track_added、conversion_started、conversion_failed、
+    /// voiceover_generated、db_written 等），日志也改为 JSON 行，供脚本与前端解析
+    #[arg(long = "log-json", global = true)]
+    pub log_json: bool,
+}
+
+/// 为 `cmd` 及其子命令的每个长选项加上对应的环境变量
+fn with_env(cmd: clap::Command) -> clap::Command {
+    cmd.mut_args(|arg| {
+        let name = match arg.get_long() {
+            None | Some("generate-manpage") => return arg,
+            Some(long) => long.to_uppercase().replace('-', "_"),
+        };
+        // A switch only takes "true"/"false" by default, which is awkward in unit files
+        let arg = match arg.get_action() {
+            clap::ArgAction::SetTrue => arg.value_parser(clap::builder::BoolishValueParser::new()),
+            _ => arg,
+        };
+        arg.env(format!("{}{}", ENV_PREFIX, name))
+    })
+    .mut_subcommands(with_env)
+}
+
+impl Cli {
+    /// 命令行定义。命令行上没有给出的选项可以由 IPOD_SHUFFLE_<选项名> 环境变量给出，
+    /// 开关取 "1"/"true"/"yes"/"on" 为开，"0"/"false"/"no"/"off" 为关
+    pub fn command_with_env() -> clap::Command {
+        with_env(Cli::command())
+    }
+
+    /// 解析命令行，未给出的选项读取对应的环境变量（见 `command_with_env`）
+    pub fn try_parse_with_env<I, T>(args: I) -> Result<Cli, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        Self::try_parse_with_matches(args).map(|(cli, _)| cli)
+    }
+
+    /// 同 `try_parse_with_env`，同时返回 clap 的解析结果，用于查询选项的来源
+    pub fn try_parse_with_matches<I, T>(args: I) -> Result<(Cli, ArgMatches), clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let mut cmd = Self::command_with_env();
+        let matches = cmd.try_get_matches_from_mut(args)?;
+        if let Some((name, _)) = matches.subcommand() {
+            // Global options may go before the subcommand, sync options and PATH may not
+            let misplaced = cmd.get_arguments()
+                .filter(|a| !a.is_global_set())
+                .find(|a| matches.value_source(a.get_id().as_str()) == Some(ValueSource::CommandLine));
+            if let Some(arg) = misplaced {
+                let shown = match arg.get_long() {
+                    Some(long) => format!("--{}", long),
+                    None => format!("<{}>", arg.get_id().as_str().to_uppercase()),
+                };
+                let message = format!("{} 不能与子命令 {} 一起使用（同步的选项请写在 build 之后）", shown, name);
+                return Err(clap::Error::raw(ErrorKind::ArgumentConflict, message).format(&mut cmd));
+            }
+        }
+        let cli = Cli::from_arg_matches(&matches).map_err(|e| e.format(&mut cmd))?;
+        Ok((cli, matches))
+    }
+
+    /// 形如 `--name` 或 `--name=value` 的参数对应的同步选项是否已在命令行上或由环境变量给出
+    pub fn set_explicitly(matches: &ArgMatches, arg: &str) -> bool {
+        let cmd = Self::command_with_env();
+        let (cmd, matches) = match matches.subcommand() {
+            Some((name, sub)) => match cmd.find_subcommand(name) {
+                Some(sub_cmd) => (sub_cmd.clone(), sub),
+                None => return false,
+            },
+            None => (cmd, matches),
+        };
+        let long = arg.trim_start_matches("--").split('=').next().unwrap_or_default();
+        let Some(option) = cmd.get_arguments().find(|a| a.get_long() == Some(long)) else { return false };
+        matches!(
+            matches.value_source(option.get_id().as_str()),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        )
+    }
+
+    /// 子命令在 `args` 中的位置，跳过子命令之前的全局选项及其值；没有子命令时为 `None`
+    pub fn subcommand_index(args: &[OsString]) -> Option<usize> {
+        let cmd = Self::command_with_env();
+        let takes_value = |a: &clap::Arg| a.get_action().takes_values();
+        let mut iter = args.iter().enumerate().skip(1);
+        while let Some((i, arg)) = iter.next() {
+            let arg = arg.to_string_lossy();
+            if arg == "--" {
+                return None;
+            }
+            if let Some(long) = arg.strip_prefix("--") {
+                if !long.contains('=') && cmd.get_arguments().any(|a| a.get_long() == Some(long) && takes_value(a)) {
+                    iter.next();
+                }
+            } else if let Some(shorts) = arg.strip_prefix('-').filter(|s| !s.is_empty()) {
+                // In a group like -vq only the last option reads its value from the next argument
+                let shorts: Vec<char> = shorts.chars().collect();
+                let valued = shorts.iter().position(|&c| cmd.get_arguments().any(|a| a.get_short() == Some(c) && takes_value(a)));
+                if valued == Some(shorts.len() - 1) {
+                    iter.next();
+                }
+            } else {
+                // Positional arguments never precede the subcommand, so the first one is it
+                return cmd.find_subcommand(&*arg).map(|_| i);
+            }
+        }
+        None
+    }
+
+    /// 本次要执行的同步：`build`、`tui`、`inspect` 子命令或不带子命令的调用，其他子命令时为 `None`
+    pub fn sync_args(&self) -> Option<&SyncArgs> {
+        match self.command {
+            Some(Command::Build(ref args) | Command::Tui(ref args) | Command::Inspect(ref args)) => Some(args),
+            Some(_) => None,
+            None => Some(&self.sync),
+        }
+    }
+
+    pub fn sync_args_mut(&mut self) -> Option<&mut SyncArgs> {
+        match self.command {
+            Some(Command::Build(ref mut args) | Command::Tui(ref mut args) | Command::Inspect(ref mut args)) => Some(args),
+            Some(_) => None,
+            None => Some(&mut self.sync),
+        }
+    }
+}
+
+/// 扫描设备并重建数据库的选项
+#[derive(Args)]
+pub struct SyncArgs {
     /// 启用曲目旁白功能
     #[arg(short = 't', long = "track-voiceover")]
     pub track_voiceover: bool,
@@ -26,6 +368,12 @@ pub struct Cli {
     #[arg(short = 'u', long = "rename-unicode")]
     pub rename_unicode: bool,
 
+    /// 不是 UTF-8 的 .m3u 与 .pls 列表文件的编码，如 gbk、big5、shift_jis、latin1。
+    /// auto 依次尝试 UTF-8、GBK 与 Latin-1；.m3u8 总是按 UTF-8 读取
+    #[arg(long = "list-encoding", value_name = "ENCODING", default_value = "auto", hide_possible_values = true,
+          value_parser = Suggest(LIST_ENCODINGS).try_map(|s| ListEncoding::parse(&s)))]
+    pub list_encoding: ListEncoding,
+
     /// 指定所有曲目的音量增益 (0-99)
     #[arg(short = 'g', long = "track-gain", default_value_t = 0, value_parser = clap::value_parser!(u32).range(0..=99))]
     pub track_gain: u32,
@@ -34,21 +382,674 @@ pub struct Cli {
     #[arg(long = "auto-track-gain")]
     pub auto_track_gain: bool,
 
+    #[command(flatten)]
+    pub loudness: LoudnessArgs,
+
+    #[command(flatten)]
+    pub gain_rules: GainRuleArgs,
+
     /// 为 "iPod_Control/Music/" 内的每个文件夹递归生成自动播放列表。
     /// 可选限制深度: 0=根目录, 1=艺术家, 2=专辑, n=子文件夹, 默认=-1 (无限制)
     #[arg(short = 'd', long = "auto-dir-playlists", num_args = 0..=1, default_missing_value = "-1")]
     pub auto_dir_playlists: Option<i32>,
 
+    /// 自动目录播放列表的命名模板：{name} 为目录名，{parent} 为上级目录名，
+    /// {path} 为相对 Music 目录（或 --root 目录）的路径，{path:N} 为其最后 N 级，各级以 " - " 连接
+    #[arg(long = "dir-playlist-name", value_name = "TEMPLATE", default_value = "{name}", hide_possible_values = true,
+          value_parser = Suggest(DIR_NAME_TEMPLATES).try_map(|s| NameTemplate::parse(&s)))]
+    pub dir_playlist_name: NameTemplate,
+
     /// 根据 ID3 标签生成自动播放列表。可指定模板字符串，如
     /// '{artist} - {album}' 按艺术家+专辑分组，'{genre}' 按流派分组。
     /// 默认模板: '{artist}'
-    #[arg(short = 'i', long = "auto-id3-playlists", num_args = 0..=1, default_missing_value = "{artist}")]
+    #[arg(short = 'i', long = "auto-id3-playlists", num_args = 0..=1, default_missing_value = "{artist}",
+          hide_possible_values = true, value_parser = Suggest(ID3_TEMPLATES))]
     pub auto_id3_playlists: Option<String>,
 
-    /// 显示详细输出
-    #[arg(short = 'v', long = "verbose")]
-    pub verbose: bool,
+    /// 为每张专辑生成一个播放列表，曲目按碟号与曲目编号排列，与目录结构无关，
+    /// 在只会随机播放全部曲目的设备上按专辑顺序收听
+    #[arg(long = "auto-album-playlists")]
+    pub auto_album_playlists: bool,
+
+    /// 将标题匹配该正则表达式（不区分大小写，可重复）的曲目移出主列表，如 "\((Instrumental|Karaoke|Live)\)"；
+    /// 没有标题标签时匹配文件名。这些曲目仍在数据库中，可以通过播放列表播放
+    #[arg(long = "exclude-title", value_name = "REGEX", value_parser = |s: &str| parse_title_pattern(s))]
+    pub exclude_title: Vec<regex::Regex>,
+
+    /// 排除短于该时长的曲目，如 "15s"、"1m"。被排除的 FLAC 等源文件不转换，保留在设备上
+    #[arg(long = "min-duration", value_parser = parse_duration)]
+    pub min_duration: Option<Duration>,
+
+    /// 排除长于该时长的曲目，如 "2h"、"90m"
+    #[arg(long = "max-duration", value_parser = parse_duration)]
+    pub max_duration: Option<Duration>,
+
+    /// 写入数据库的曲目总大小上限，如 "1.8GiB"、"700MB"。按路径顺序依次选入曲目，
+    /// 放不下的曲目跳过并列在同步报告中，后面较小的曲目仍可选入
+    #[arg(long = "max-size", value_name = "SIZE", value_parser = parse_size)]
+    pub max_size: Option<u64>,
+
+    /// 写入数据库的曲目总时长上限，如 "20h"，选取方式同 --max-size
+    #[arg(long = "max-total-duration", value_name = "DURATION", value_parser = parse_duration)]
+    pub max_total_duration: Option<Duration>,
+
+    /// 与 --max-size 或 --max-total-duration 一起使用：连续 N 次同步都写入了数据库的曲目让位给上次没有选入的曲目，
+    /// 在容量有限的设备上自动轮换曲目。同步次数记录在构建信息中
+    #[arg(long = "rotate-after", value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub rotate_after: Option<u32>,
+
+    /// "X.flac" 与 "X.mp3" 同时存在时的处理方式
+    #[arg(long = "shadow-policy", value_enum, default_value_t = ShadowPolicy::PreferMp3)]
+    pub shadow_policy: ShadowPolicy,
+
+    /// 隐藏文件与目录的扫描策略
+    #[arg(long = "hidden", value_enum, default_value_t = HiddenPolicy::Skip)]
+    pub hidden: HiddenPolicy,
+
+    /// 无论隐藏策略如何都扫描该名称的隐藏文件或目录（可重复），如 ".syncthing"
+    #[arg(long = "allow-hidden", value_name = "NAME")]
+    pub allow_hidden: Vec<String>,
+
+    /// 扫描时跳过匹配该 glob 模式的文件或目录（可重复），如 "*.txt" 或 "iPod_Control/Music/Do Not Sync"。
+    /// 不含 "/" 的模式匹配任意一级的名称，否则匹配相对 iPod 根目录的路径；"**" 匹配任意多级目录
+    #[arg(long = "exclude", value_name = "GLOB", value_parser = |s: &str| GlobPattern::parse(s))]
+    pub exclude: Vec<GlobPattern>,
+
+    /// 扫描时跟随符号链接，使通过符号链接组织在 "iPod_Control/Music" 中的曲目也被加入。
+    /// 形成循环的链接与指向 iPod 之外的链接会被跳过并给出警告
+    #[arg(long = "follow-symlinks")]
+    pub follow_symlinks: bool,
+
+    /// 内容（大小与 MD5）完全相同的音频文件只加入数据库一次，保留文件名最短的一个（如 b.mp3 而不是 b_copy.mp3），
+    /// 其余的不转换、不写入数据库，列表文件中引用它们时改用保留的那个；报告中列出可以删除的重复文件及其占用的空间
+    #[arg(long = "dedup")]
+    pub dedup: bool,
+
+    /// 将曲库分成 N 个总时长大致相等的播放列表 "Trip Mix 1..N"
+    #[arg(long = "trip-mix", value_name = "N", value_parser = clap::value_parser!(u32).range(1..=99))]
+    pub trip_mix: Option<u32>,
+
+    /// 仅拆分指定名称的播放列表，而不是整个曲库
+    #[arg(long = "trip-mix-source", value_name = "PLAYLIST", requires = "trip_mix")]
+    pub trip_mix_source: Option<String>,
+
+    /// 主列表的曲目顺序
+    #[arg(long = "order", value_enum, default_value_t = TrackOrder::Path)]
+    pub order: TrackOrder,
+
+    /// 保持上次数据库中的曲目顺序，新曲目追加在末尾，使曲目索引尽量不变。order.txt 的规则仍然生效
+    #[arg(long = "keep-order")]
+    pub keep_order: bool,
+
+    /// 为 --order shuffle 与 order = "shuffle" 的播放列表换一个新的随机种子，得到全新的顺序
+    #[arg(long = "reshuffle")]
+    pub reshuffle: bool,
+
+    /// 根据设备的 iTunesStats，将完整播放过的曲目累积到根目录的 "Favorites.m3u" 播放列表
+    #[arg(long = "favorites")]
+    pub favorites: bool,
+
+    /// 生成指定总时长的睡前播放列表 "Bedtime"，如 "45m"；最后一首会被截断并淡出
+    #[arg(long = "bedtime", value_name = "DURATION", value_parser = parse_duration)]
+    pub bedtime: Option<Duration>,
+
+    /// 睡前播放列表的来源播放列表，默认为整个曲库
+    #[arg(long = "bedtime-source", value_name = "PLAYLIST", requires = "bedtime")]
+    pub bedtime_source: Option<String>,
+
+    /// 睡前播放列表最后一首的淡出时长
+    #[arg(long = "bedtime-fade", value_name = "DURATION", value_parser = parse_duration, default_value = "30s")]
+    pub bedtime_fade: Duration,
+
+    /// 为带有同名 ".cuts" 切分列表的音频生成 A/B 复读分段，每段重复 N 次，
+    /// 并为每个音频生成 "<名称> A-B" 播放列表。切分列表每行为 "开始 结束"，
+    /// 如 "0:12.5 0:16"
+    #[arg(long = "ab-repeat", value_name = "N", value_parser = clap::value_parser!(u32).range(1..=99))]
+    pub ab_repeat: Option<u32>,
+
+    /// A/B 复读每次重复之后的停顿时长
+    #[arg(long = "ab-pause", value_name = "DURATION", value_parser = parse_duration, default_value = "2s")]
+    pub ab_pause: Duration,
+
+    /// 将带 ID3 章节（CHAP 帧）的长 MP3（如播客）的每一章作为单独的曲目写入数据库，
+    /// 并生成以该文件标题命名的章节播放列表。章节条目记住播放位置，且不参与随机播放
+    #[arg(long = "chapters")]
+    pub chapters: bool,
+
+    /// 只拆分不短于该时长的 MP3 的章节
+    #[arg(long = "chapter-min-length", value_name = "DURATION", value_parser = parse_duration, default_value = "20m")]
+    pub chapter_min_length: Duration,
+
+    /// 将该目录（相对 iPod 根目录）中的 ".txt"/".epub" 文件通过语音合成生成有声书，
+    /// 按章节与 --audiobook-part 切分为多首曲目并记住播放位置
+    #[arg(long = "text-audiobooks", value_name = "DIR")]
+    pub text_audiobooks: Option<String>,
+
+    /// 合成有声书时每首曲目的目标时长
+    #[arg(long = "audiobook-part", value_name = "DURATION", value_parser = parse_duration, default_value = "30m")]
+    pub audiobook_part: Duration,
+
+    /// 同步前将设备上的录音（见 --memo-dir）移动到本地的该目录中归档，
+    /// 使其不再出现在音乐数据库里
+    #[arg(long = "archive-memos", value_name = "DIR")]
+    pub archive_memos: Option<PathBuf>,
+
+    /// 存放录音的目录（相对 iPod 根目录，可重复）
+    #[arg(long = "memo-dir", value_name = "DIR", default_values = ["Recordings", "Voice Memos"])]
+    pub memo_dirs: Vec<String>,
+
+    /// FLAC 转换及生成曲目的 MP3 码率 (kbps)
+    #[arg(long = "bitrate", value_name = "KBPS", default_value = "320",
+          value_parser = PossibleValuesParser::new(BITRATES).map(|s| s.parse::<u32>().unwrap()))]
+    pub bitrate: u32,
+
+    /// 写入数据库的每首曲目的 pregap 值。现场专辑、DJ 混音等需要无缝衔接时设为 0，
+    /// 曲目开头被吞掉时可以调大；可在配置文件的 [folder."<目录>"] 中按目录覆盖
+    #[arg(long = "pregap", value_name = "N", default_value_t = DEFAULT_GAP)]
+    pub pregap: u32,
+
+    /// 写入数据库的每首曲目的 postgap 值，可按目录覆盖（见 --pregap）
+    #[arg(long = "postgap", value_name = "N", default_value_t = DEFAULT_GAP)]
+    pub postgap: u32,
+
+    /// 语音旁白与有声书使用的 Edge TTS 语音
+    #[arg(long = "tts-voice", value_name = "VOICE", default_value = DEFAULT_TTS_VOICE,
+          hide_possible_values = true, value_parser = Suggest(COMMON_TTS_VOICES))]
+    pub tts_voice: String,
+
+    /// 语音旁白的合成引擎："edge"（内置的 Edge TTS）或 "command:<程序路径>"（外部程序，
+    /// 每条旁白运行一次，从标准输入读取 JSON 请求并写出 WAV，协议见 README；不能写在设备上的配置文件中）。
+    /// 由文本合成的有声书总是使用 Edge TTS
+    #[arg(long = "tts-engine", value_name = "ENGINE", default_value = "edge", value_parser = parse_tts_engine)]
+    pub tts_engine: TtsEngine,
+
+    /// 外部语音合成引擎的 JSON 配置文件，内容原样放在每次请求的 "config" 字段中
+    #[arg(long = "tts-engine-config", value_name = "FILE")]
+    pub tts_engine_config: Option<PathBuf>,
+
+    /// 应用预设的一组选项。可在 iPod 根目录的 "ipod_shuffle.toml" 中以
+    /// [preset.<名称>] 定义，内置: podcast-heavy, audiophile, kids。
+    /// 命令行或环境变量中给出的选项优先于预设
+    #[arg(long = "preset", value_name = "NAME")]
+    pub preset: Option<String>,
+
+    /// 使用电脑上的配置档案，在用户配置目录的 "ipod-shuffle-4g/profiles.toml" 中
+    /// 以 [profile.<名称>] 定义。档案中的选项优先于设备上的配置与预设；
+    /// 未给出 PATH 时使用档案中的 path，或上次使用该档案时的设备路径
+    #[arg(long = "profile", value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// 忽略 "ipod_shuffle.toml" 顶层的默认选项（播放列表、目录等表仍然生效）
+    #[arg(long = "no-config-defaults")]
+    pub no_config_defaults: bool,
+
+    /// 排序曲目、目录与播放列表名称时使用的区域规则，如 "zh"（拼音）、"ja"、"de"；
+    /// "codepoint" 表示按码位排序
+    #[arg(long = "collation", value_name = "LOCALE", default_value = "zh")]
+    pub collation: String,
+
+    /// 单个文件读取标签或解码的时限，超时或崩溃的文件会被隔离并列在同步报告中
+    #[arg(long = "file-timeout", value_name = "DURATION", value_parser = parse_duration, default_value = "30s")]
+    pub file_timeout: Duration,
+
+    /// 单次语音合成网络请求的时限
+    #[arg(long = "tts-timeout", value_name = "DURATION", value_parser = parse_duration, default_value = "30s")]
+    pub tts_timeout: Duration,
+
+    /// 单次同步最多合成的语音旁白条数。Edge TTS 在长时间生成时可能限流，
+    /// 超出的旁白留待之后以 --tts-resume 分批生成
+    #[arg(long = "tts-limit", value_name = "N")]
+    pub tts_limit: Option<usize>,
+
+    /// 保留上次同步已完整生成的语音旁白，只生成缺少或未完成的部分
+    /// （进度记录在 "iPod_Control/iTunes/tts_progress.txt"）
+    #[arg(long = "tts-resume")]
+    pub tts_resume: bool,
+
+    /// 忽略上次被打断的同步留下的检查点（"iPod_Control/iTunes/sync_checkpoint.txt"），从头开始同步。
+    /// 默认从中断处继续：已完成的转换与语音旁白不会重做
+    #[arg(long = "no-resume")]
+    pub no_resume: bool,
+
+    /// 单个文件转换编码的时限
+    #[arg(long = "convert-timeout", value_name = "DURATION", value_parser = parse_duration, default_value = "10m")]
+    pub convert_timeout: Duration,
+
+    /// 语音合成或转换超时、失败后的重试次数
+    #[arg(long = "retries", value_name = "N", default_value_t = 2)]
+    pub retries: u32,
+
+    /// 限制写入设备的速率 (MB/s)，避免占满廉价闪存的带宽导致系统卡顿。
+    /// 作用于 FLAC 转换、生成的曲目、语音旁白和数据库的写入
+    #[arg(long = "io-throttle", value_name = "MB/s", value_parser = parse_throttle)]
+    pub io_throttle: Option<f64>,
+
+    /// 不在每个写入设备的文件之后执行 fsync（更快，但中途拔线可能损坏文件系统）
+    #[arg(long = "no-fsync")]
+    pub no_fsync: bool,
 
+    /// 修正乱码标签并为无标题的曲目从文件名推断标题
+    #[arg(long = "tag-fixes", value_name = "POLICY", value_enum, default_value_t = TagFixPolicy::Off)]
+    pub tag_fixes: TagFixPolicy,
+
+    /// 从 export-csv 导出并编辑过的表格（CSV 或 TSV）读取覆盖值：按 path 列匹配曲目，
+    /// title、gain 与 voiceover 列中与本次同步算出的值不同的单元格分别覆盖标题、增益与旁白文字
+    #[arg(long = "metadata-csv", value_name = "FILE")]
+    pub metadata_csv: Option<PathBuf>,
+
+    /// 同步中出现任何问题（跳过的播放列表、转换失败等）时以非零状态码退出，
+    /// 转换失败为 6，播放列表问题为 7，其他警告为 8
+    #[arg(long = "strict")]
+    pub strict: bool,
+
+    /// 同步结束时的汇总格式，json 供脚本解析
+    #[arg(long = "output", value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+
+    /// 同步报告格式，写入 "iPod_Control/last_sync_report.*"
+    #[arg(long = "report", value_enum, default_value_t = ReportFormat::Html)]
+    pub report: ReportFormat,
+
+    /// 同步结束或失败时显示桌面通知，提示可以拔出设备（Linux 通过 D-Bus 通知服务，
+    /// macOS 与 Windows 使用系统通知中心）
+    #[arg(long = "notify")]
+    pub notify: bool,
+
+    /// 同步结束时通过 shell 运行该命令，如发送邮件。标准输入为 JSON 汇总（同 --output json），
+    /// 环境变量 IPOD_SYNC_STATUS（success、failure 或 dry-run）、IPOD_SYNC_MESSAGE 与 IPOD_SYNC_DEVICE 给出结果。
+    /// 不能写在设备上的配置文件中
+    #[arg(long = "notify-command", value_name = "COMMAND")]
+    pub notify_command: Option<String>,
+
+    /// 仅扫描指定的库根目录（相对 iPod 根目录，须位于 iPod 上，可重复），如 "music:Music"、
+    /// "podcast:Podcasts"、"audiobook:Audiobooks"。未指定时扫描整个设备。
+    /// 播客与有声书会记住播放位置，并各自生成以目录命名的播放列表
+    #[arg(long = "root", value_name = "KIND:DIR", value_parser = parse_library_root)]
+    pub roots: Vec<LibraryRoot>,
+
+    /// 将该目录（相对 iPod 根目录，须位于 iPod 上，可重复）中的曲目作为播客，如 "iPod_Control/Podcasts"：
+    /// 记住播放位置、不参与随机播放，并生成以目录命名的播客列表。与 --root podcast:DIR 不同，不限制扫描范围
+    #[arg(long = "podcast-dir", value_name = "DIR")]
+    pub podcast_dirs: Vec<String>,
+
+    /// 将该目录（相对 iPod 根目录，须位于 iPod 上，可重复）中的曲目作为有声书，如 "Audiobooks"：记住播放位置、移出主列表，
+    /// 并生成以目录命名的有声书列表。其他位置的 .m4b 与 .aa 文件归入 "Audiobooks" 列表
+    #[arg(long = "audiobook-dir", value_name = "DIR")]
+    pub audiobook_dirs: Vec<String>,
+
+    /// 只扫描这些子目录（相对 iPod 根目录，可重复），如 "iPod_Control/Music"，设备上其余的数据不再遍历。
+    /// 与 --root 一起使用时只扫描各库根目录中位于这些子目录内的部分；播放列表名称与类型不受影响
+    #[arg(long = "only", value_name = "DIR")]
+    pub only: Vec<String>,
+
+    /// 即使目标目录中没有 "iPod_Control" 也继续运行（会转换并删除其中所有 FLAC 文件）
+    #[arg(long = "force")]
+    pub force: bool,
+
+    /// 演练：报告将转换并删除的 FLAC、将生成的旁白与播放列表以及最终的数据库内容，
+    /// 不写入或删除设备上的任何文件
+    #[arg(long = "dry-run", conflicts_with = "simulate")]
+    pub dry_run: bool,
+
+    /// 同步后继续运行，iPod_Control/Music（及 --root 的目录）中的文件增删时自动重新同步，
+    /// 直到按下 Ctrl+C。之后的同步不再询问（同 --yes）
+    #[arg(long = "watch")]
+    pub watch: bool,
+
+    /// 将普通的空目录当作模拟的 iPod 进行完整同步（代替 PATH）：自动创建 iPod_Control
+    /// 目录结构并跳过设备检查，用于试用设置与集成测试
+    #[arg(long = "simulate", value_name = "DIR", conflicts_with = "path")]
+    pub simulate: Option<String>,
+
+    /// 模拟前把现有的设备或音乐库放入 --simulate 的目录（可重复）：含 iPod_Control 的目录按原有结构放入，
+    /// 其他目录放入 iPod_Control/Music/<目录名>。同一文件系统上使用硬链接或写时复制（reflink），
+    /// 不复制音频数据，模拟同步不会修改原来的文件；已存在的文件保持不变
+    #[arg(long = "simulate-from", value_name = "DIR", requires = "simulate", conflicts_with = "path")]
+    pub simulate_from: Vec<String>,
+
+    /// iPod 根目录的路径
+    #[arg(required_unless_present_any = ["simulate", "profile"])]
+    pub path: Option<String>,
+}
+
+impl SyncArgs {
+    /// 同步目标路径：PATH 或 --simulate 的目录。由 clap 保证其一存在，
+    /// 只给出 --profile 时 PATH 在解析命令行时由配置档案补上
+    pub fn target(&self) -> Option<&str> {
+        self.path.as_deref().or(self.simulate.as_deref())
+    }
+
+    /// 同步目标的 iPod 根目录
+    pub fn device_path(&self) -> &Path {
+        Path::new(self.target().expect("path or --simulate is required"))
+    }
+}
+
+/// 子命令。不带子命令时执行 build
+#[derive(Subcommand)]
+pub enum Command {
+    /// 扫描设备并重建数据库（不带子命令时的默认操作）
+    Build(Box<SyncArgs>),
+
+    /// 与 build 相同地扫描设备，写入数据库前在终端界面中检查结果
+    ///
+    /// 列出扫描到的曲目与播放列表、FLAC 转换与语音旁白的情况以及每首曲目的警告，
+    /// 可以取消选择部分曲目或播放列表，确认后才写入数据库。选项与 build 相同
+    Tui(Box<SyncArgs>),
+
+    /// 只读分析设备：照常扫描、读取标签、估算响度并解析播放列表，打印将生成的数据库内容
+    ///
+    /// 同 build --dry-run，但不接受 --simulate：不创建目录、不做写入测试、不加锁，
+    /// 不删除或移动任何文件，适合检查别人的 iPod。选项与 build 相同
+    Inspect(Box<SyncArgs>),
+
+    /// 列出设备上现有 iTunesSD 中的曲目与播放列表，不重建数据库、不修改任何文件
+    List(ListArgs),
+
+    /// 删除同步生成的文件：语音旁白、A/B 复读分段、睡前淡出曲目、同步报告与旁白进度
+    ///
+    /// 这些文件会在下次 build 时重新生成。数据库不会被修改，引用了已删除分段的条目
+    /// 在重新 build 之前无法播放
+    Clean(CleanArgs),
+
+    /// 校验现有的 iTunesSD：结构、曲目文件与语音旁白是否完整
+    ///
+    /// 不扫描曲库、不修改设备，发现问题时以状态码 1 退出
+    Verify(DeviceArgs),
+
+    /// 逐字段输出现有 iTunesSD 的内容，用于排查数据库问题
+    ///
+    /// 加上 --json 时输出一个 JSON 对象：头部、每条曲目记录（路径、增益、dbid、文件是否存在）
+    /// 与每个播放列表（名称、类型、dbid 与曲目），便于排查某首曲目为什么没有出现在设备上
+    Dump(DumpArgs),
+
+    /// 将现有 iTunesSD 中曲目的元数据导出为 CSV 或 TSV
+    ///
+    /// 每首曲目一行：路径、标签、时长、增益、dbid、旁白文字与所在的播放列表，便于在电子表格中检查。
+    /// 编辑过的表格可以用 build --metadata-csv 读回，覆盖标题、增益与旁白文字
+    ExportCsv(ExportCsvArgs),
+
+    /// 统计设备上的曲库：曲目数、总时长、各艺术家/专辑/流派的曲目数、各格式占用的空间与剩余空间
+    ///
+    /// 读取现有的 iTunesSD，build 之后运行即为刚写入的数据库。不修改设备
+    Stats(StatsArgs),
+
+    /// 检查设备的健康状况
+    ///
+    /// 检查可用空间与容量、零字节或无法读取的音频、数据库中的孤立条目以及
+    /// FAT 目录异常（重复名称），发现问题时以状态码 1 退出。不会修改设备
+    CheckDevice(DeviceArgs),
+
+    /// 诊断设备上的常见问题并给出修复建议
+    ///
+    /// 检查 iPod_Control 目录结构、缺失的数据库、指向不存在文件的数据库条目、缺失、损坏或
+    /// 不再使用的语音旁白、路径超过 256 字节的曲目、不支持的音频格式与零字节的音频。
+    /// 不完整读取文件，比 check-device 快；不修改设备，发现问题时以状态码 1 退出
+    Doctor(DeviceArgs),
+
+    /// 按标题、艺术家、专辑或路径搜索曲目
+    ///
+    /// 列出匹配曲目所在的播放列表与 dbid（与 Speakable 中的旁白文件名一致），
+    /// 用于排查某首曲目为什么没有出现在某个播放列表中。多个词需全部匹配，不区分大小写
+    Search(SearchArgs),
+
+    /// 检查语音旁白是否完整
+    ///
+    /// 启用旁白时，数据库引用的每个 dbid 都应在 Speakable 中有一个能解码的 WAV 文件；
+    /// 同时列出没有被数据库引用的旁白。发现问题时以状态码 1 退出
+    CheckVoiceover(VoiceoverCheckArgs),
+
+    /// 单独运行响度分析与音量增益，无需完整同步
+    #[command(subcommand)]
+    Gain(GainCommand),
+
+    /// 打开图形界面：选择已挂载的设备、查看现有数据库、调整增益与旁白选项并同步
+    ///
+    /// 需要以 `--features gui` 编译
+    #[cfg(feature = "gui")]
+    Gui,
+
+    /// 将指定 shell 的补全脚本输出到标准输出
+    Completions {
+        /// 目标 shell
+        shell: clap_complete::Shell,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum GainCommand {
+    /// 分析目录（设备或本地曲库）中音频的响度，显示每首曲目将获得的增益
+    Analyze(GainArgs),
+    /// 分析设备数据库中的曲目，并将增益直接写入现有的 iTunesSD。
+    /// 下次同步时增益会按同步选项重新计算
+    Apply(GainArgs),
+    /// 将设备数据库中所有曲目的增益重置
+    Clear {
+        /// iPod 根目录的路径
+        path: String,
+
+        /// 重置后的增益 (0-99)
+        #[arg(long = "gain", default_value_t = 0, value_parser = clap::value_parser!(u32).range(0..=99))]
+        gain: u32,
+    },
+}
+
+/// 响度分析与增益换算的参数，同步与 gain 子命令共用
+#[derive(Args, Clone, Copy)]
+pub struct LoudnessArgs {
+    /// 每首曲目分析开头多长的音频，短于该时长的曲目分析整个文件
+    #[arg(long = "gain-window", value_name = "DURATION", value_parser = parse_duration, default_value = "45s")]
+    pub window: Duration,
+
+    /// 响度下限 (dBFS)，静音或极安静的曲目按该值计算，避免被提升到最大增益
+    #[arg(long = "gain-floor", value_name = "DB", allow_hyphen_values = true, default_value_t = -120.0)]
+    pub floor_db: f64,
+
+    /// 自动增益的取值范围
+    #[arg(long = "gain-range", value_name = "MIN-MAX", value_parser = parse_gain_range, default_value = "0-99")]
+    pub range: (u32, u32),
+}
+
+/// 不参与自动增益的曲目，同步与 gain 子命令共用
+#[derive(Args, Clone)]
+pub struct GainRuleArgs {
+    /// 不对该流派的曲目做自动增益，保留其原本的动态（可重复，不区分大小写），如 "Classical"
+    #[arg(long = "no-gain-genre", value_name = "GENRE")]
+    pub genres: Vec<String>,
+
+    /// 不对该目录（相对 iPod 根目录或曲库根目录，可重复）中的曲目做自动增益
+    #[arg(long = "no-gain-dir", value_name = "DIR")]
+    pub dirs: Vec<String>,
+}
+
+#[derive(Args)]
+pub struct GainArgs {
+    /// 设备或本地曲库的路径
+    pub path: String,
+
+    #[command(flatten)]
+    pub loudness: LoudnessArgs,
+
+    #[command(flatten)]
+    pub rules: GainRuleArgs,
+
+    /// 参考响度 (dBFS)，低于它的曲目按差值提升增益；默认以最响的曲目为参考
+    #[arg(long = "reference", value_name = "DBFS", allow_hyphen_values = true)]
+    pub reference: Option<f64>,
+
+    /// 单个文件解码的时限
+    #[arg(long = "file-timeout", value_name = "DURATION", value_parser = parse_duration, default_value = "30s")]
+    pub file_timeout: Duration,
+}
+
+#[derive(Args)]
+pub struct SearchArgs {
+    /// iPod 根目录的路径
+    pub path: String,
+
+    /// 搜索词
+    #[arg(required = true, num_args = 1..)]
+    pub query: Vec<String>,
+
+    /// 搜索设备上的所有音频文件而不是数据库，可以找到还未同步进数据库的文件
+    #[arg(long = "live")]
+    pub live: bool,
+}
+
+#[derive(Args)]
+pub struct ListArgs {
+    /// iPod 根目录的路径
+    pub path: String,
+
+    /// 按播放列表分组列出曲目
+    #[arg(long = "playlists")]
+    pub playlists: bool,
+}
+
+#[derive(Args)]
+pub struct DumpArgs {
+    /// iPod 根目录的路径
+    pub path: String,
+
+    /// 以 JSON 输出，并反查播放列表的名称
+    #[arg(long = "json")]
+    pub json: bool,
+}
+
+#[derive(Args)]
+pub struct StatsArgs {
+    /// iPod 根目录的路径
+    pub path: String,
+
+    /// 艺术家、专辑与流派各只列出曲目最多的 N 项，0 表示全部列出
+    #[arg(long = "top", value_name = "N", default_value_t = 10)]
+    pub top: usize,
+}
+
+#[derive(Args)]
+pub struct ExportCsvArgs {
+    /// iPod 根目录的路径
+    pub path: String,
+
+    /// 写入该文件而不是标准输出
+    #[arg(short = 'o', long = "out", value_name = "FILE")]
+    pub out: Option<PathBuf>,
+
+    /// 以制表符分隔（输出文件以 ".tsv" 结尾时也会如此）
+    #[arg(long = "tsv")]
+    pub tsv: bool,
+}
+
+#[derive(Args)]
+pub struct CleanArgs {
     /// iPod 根目录的路径
     pub path: String,
+
+    /// 同时删除由文本合成的有声书（"iPod_Control/TextBooks"），重新合成需要较长时间
+    #[arg(long = "textbooks")]
+    pub textbooks: bool,
+}
+
+#[derive(Args)]
+pub struct VoiceoverCheckArgs {
+    /// iPod 根目录的路径
+    pub path: String,
+
+    /// 更正 Speakable 的目录布局，并只重新生成缺失或损坏的旁白，其余旁白保持不变
+    #[arg(long = "repair")]
+    pub repair: bool,
+
+    /// 重新生成旁白使用的 Edge TTS 语音，应与同步时一致
+    #[arg(long = "tts-voice", value_name = "VOICE", default_value = DEFAULT_TTS_VOICE,
+          hide_possible_values = true, value_parser = Suggest(COMMON_TTS_VOICES))]
+    pub tts_voice: String,
+
+    /// 重新生成旁白使用的合成引擎，应与同步时一致（见 build --tts-engine）
+    #[arg(long = "tts-engine", value_name = "ENGINE", default_value = "edge", value_parser = parse_tts_engine)]
+    pub tts_engine: TtsEngine,
+
+    /// 外部语音合成引擎的 JSON 配置文件
+    #[arg(long = "tts-engine-config", value_name = "FILE")]
+    pub tts_engine_config: Option<PathBuf>,
+
+    /// 单次语音合成网络请求的时限
+    #[arg(long = "tts-timeout", value_name = "DURATION", value_parser = parse_duration, default_value = "30s")]
+    pub tts_timeout: Duration,
+
+    /// 语音合成超时或失败后的重试次数
+    #[arg(long = "retries", value_name = "N", default_value_t = 2)]
+    pub retries: u32,
+}
+
+#[derive(Args)]
+pub struct DeviceArgs {
+    /// iPod 根目录的路径
+    pub path: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_with_env(std::iter::once("ipod-shuffle-4g").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn verbose_before_subcommand() {
+        let cli = parse(&["-v", "list", "DIR"]).unwrap();
+        assert_eq!(cli.verbose, 1);
+        assert!(matches!(cli.command, Some(Command::List(ref a)) if a.path == "DIR" && !a.playlists));
+    }
+
+    #[test]
+    fn yes_before_subcommand() {
+        let cli = parse(&["--yes", "clean", "DIR"]).unwrap();
+        assert!(cli.yes);
+        assert!(matches!(cli.command, Some(Command::Clean(ref a)) if a.path == "DIR"));
+    }
+
+    #[test]
+    fn lang_before_subcommand() {
+        let cli = parse(&["--lang", "en", "list", "--playlists", "DIR"]).unwrap();
+        assert!(cli.lang == Some(Lang::En));
+        assert!(matches!(cli.command, Some(Command::List(ref a)) if a.path == "DIR" && a.playlists));
+    }
+
+    #[test]
+    fn bare_path_is_build() {
+        let cli = parse(&["-t", "DIR"]).unwrap();
+        assert!(cli.command.is_none());
+        assert!(cli.sync.track_voiceover);
+        assert_eq!(cli.sync.path.as_deref(), Some("DIR"));
+    }
+
+    #[test]
+    fn sync_option_before_subcommand_is_rejected() {
+        assert!(parse(&["-t", "list", "DIR"]).is_err());
+    }
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        std::iter::once("ipod-shuffle-4g").chain(args.iter().copied()).map(OsString::from).collect()
+    }
+
+    #[test]
+    fn subcommand_index_skips_option_values() {
+        assert_eq!(Cli::subcommand_index(&args(&["--log-file", "build", "build", "DIR"])), Some(3));
+        assert_eq!(Cli::subcommand_index(&args(&["--log-file=build", "-v", "tui", "DIR"])), Some(3));
+        assert_eq!(Cli::subcommand_index(&args(&["build", "build"])), Some(1));
+    }
+
+    #[test]
+    fn subcommand_index_without_subcommand() {
+        assert_eq!(Cli::subcommand_index(&args(&["-t", "DIR"])), None);
+        assert_eq!(Cli::subcommand_index(&args(&["--", "build"])), None);
+    }
+
+    #[test]
+    fn set_explicitly_on_command_line() {
+        let (_, matches) = Cli::try_parse_with_matches(args(&["build", "--bitrate=192", "DIR"])).unwrap();
+        assert!(Cli::set_explicitly(&matches, "--bitrate=320"));
+        assert!(!Cli::set_explicitly(&matches, "--track-gain=0"));
+    }
 }
@@ -0,0 +1,56 @@
+use icu_collator::options::CollatorOptions;
+use icu_collator::preferences::CollationNumericOrdering;
+use icu_collator::{CollatorBorrowed, CollatorPreferences};
+use icu_locale_core::Locale;
+use std::cmp::Ordering;
+use std::path::Path;
+use std::sync::OnceLock;
+
+// ─── Collation ───────────────────────────────────────────────────────────────
+
+/// 不使用区域规则、按小写后的码位排序（旧版行为）
+pub const CODEPOINT: &str = "codepoint";
+
+static COLLATOR: OnceLock<Option<CollatorBorrowed<'static>>> = OnceLock::new();
+
+/// 设置排序使用的区域，如 "zh"（拼音）、"ja"、"de"；"codepoint" 表示按码位排序。
+/// 数字按数值比较，使 "2 - x" 排在 "10 - x" 之前。需在首次比较前调用
+pub fn set_locale(name: &str) -> Result<(), String> {
+    let collator = if name.eq_ignore_ascii_case(CODEPOINT) {
+        None
+    } else {
+        let locale: Locale = name.parse().map_err(|e| format!("无效的区域 \"{}\": {}", name, e))?;
+        let mut prefs = CollatorPreferences::from(&locale);
+        prefs.numeric_ordering = Some(CollationNumericOrdering::True);
+        let collator = CollatorBorrowed::try_new(prefs, CollatorOptions::default())
+            .map_err(|e| format!("无法加载区域 \"{}\" 的排序规则: {}", name, e))?;
+        Some(collator)
+    };
+    let _ = COLLATOR.set(collator);
+    Ok(())
+}
+
+/// 按当前区域比较两个名称
+pub fn compare(a: &str, b: &str) -> Ordering {
+    match COLLATOR.get().and_then(|c| c.as_ref()) {
+        Some(collator) => collator.compare(a, b).then_with(|| a.cmp(b)),
+        None => a.to_lowercase().cmp(&b.to_lowercase()),
+    }
+}
+
+/// 逐级比较路径的各个分量
+pub fn compare_paths(a: &Path, b: &Path) -> Ordering {
+    let mut ca = a.components();
+    let mut cb = b.components();
+    loop {
+        match (ca.next(), cb.next()) {
+            (Some(x), Some(y)) => {
+                let ord = compare(&x.as_os_str().to_string_lossy(), &y.as_os_str().to_string_lossy());
+                if ord != Ordering::Equal { return ord; }
+            }
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (None, None) => return Ordering::Equal,
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::converters::CONVERTER_TABLE;
+use crate::playlist::CONFIG_TABLE as PLAYLIST_TABLE;
+use crate::profiles::PROFILES_FILE;
+use crate::speakable::LAYOUT_TABLE;
+use crate::track_rules::{FOLDER_TABLE, GENRE_TABLE};
+
+// ─── Config file and presets ─────────────────────────────────────────────────
+
+/// iPod 根目录（或 iPod_Control）下的配置文件
+pub const CONFIG_FILE: &str = "ipod_shuffle.toml";
+
+/// 配置文件中定义预设的表，如 `[preset.kids]`
+const PRESET_TABLE: &str = "preset";
+
+/// 配置文件中有专门含义的表，其余顶层键都是默认的命令行选项
+const SECTION_TABLES: &[&str] = &[PRESET_TABLE, PLAYLIST_TABLE, FOLDER_TABLE, GENRE_TABLE, LAYOUT_TABLE];
+
+/// 会在电脑上运行程序的选项（展开后的参数前缀）。设备上的配置文件与其中的预设不能给出它们，
+/// 否则插入的任何设备都能在电脑上执行命令
+const HOST_COMMAND_ARGS: &[&str] = &["--notify-command=", "--tts-engine=command:"];
+
+/// 内置预设，配置文件中的同名预设会完全替换它们
+const BUILTIN_PRESETS: &[(&str, &[&str])] = &[
+    ("podcast-heavy", &[
+        "--root=music:iPod_Control/Music",
+        "--root=podcast:Podcasts",
+        "--playlist-voiceover",
+        "--auto-track-gain",
+    ]),
+    ("audiophile", &[
+        "--bitrate=320",
+        "--shadow-policy=prefer-flac",
+        "--track-gain=0",
+    ]),
+    ("kids", &[
+        "--track-voiceover",
+        "--playlist-voiceover",
+        "--auto-track-gain",
+        "--bitrate=192",
+        "--max-duration=10m",
+        "--order=artist-spread",
+    ]),
+];
+
+/// 设备上的配置文件：优先使用根目录下的，其次为 iPod_Control 下的
+pub fn config_path(base: &Path) -> Option<PathBuf> {
+    [base.join(CONFIG_FILE), base.join("iPod_Control").join(CONFIG_FILE)]
+        .into_iter()
+        .find(|p| p.is_file())
+}
+
+/// 读取设备上的配置文件，不存在时返回 `None`
+pub fn load_config(base: &Path) -> Result<Option<toml::Table>, String> {
+    let Some(path) = config_path(base) else { return Ok(None) };
+    let text = fs::read_to_string(&path).map_err(|e| format!("无法读取 {}: {}", path.display(), e))?;
+    text.parse::<toml::Table>()
+        .map(Some)
+        .map_err(|e| format!("{} 格式错误: {}", path.display(), e))
+}
+
+/// 将 `选项名 = 值` 转换为命令行参数：true 为开关，false 被忽略，数组表示重复的选项
+fn value_to_args(key: &str, value: &toml::Value, out: &mut Vec<String>) -> Result<(), String> {
+    match value {
+        toml::Value::Boolean(true) => out.push(format!("--{}", key)),
+        toml::Value::Boolean(false) => {}
+        toml::Value::String(s) => out.push(format!("--{}={}", key, s)),
+        toml::Value::Integer(i) => out.push(format!("--{}={}", key, i)),
+        toml::Value::Float(f) => out.push(format!("--{}={}", key, f)),
+        toml::Value::Array(items) => {
+            for item in items {
+                value_to_args(key, item, out)?;
+            }
+        }
+        _ => return Err(format!("选项 \"{}\" 的值类型不受支持", key)),
+    }
+    Ok(())
+}
+
+/// 将一个 TOML 表展开为命令行参数
+pub fn table_to_args(table: &toml::Table) -> Result<Vec<String>, String> {
+    let mut out = Vec::new();
+    for (key, value) in table {
+        if key == PRESET_TABLE || key == "path" {
+            return Err(format!("选项 \"{}\" 不能出现在预设或配置中", key));
+        }
+        value_to_args(key, value, &mut out)?;
+    }
+    Ok(out)
+}
+
+/// 拒绝设备上的配置文件中会在电脑上运行程序的选项
+fn reject_host_commands(args: &[String]) -> Result<(), String> {
+    let Some(arg) = args.iter().find(|a| HOST_COMMAND_ARGS.iter().any(|p| a.starts_with(p))) else { return Ok(()) };
+    let key = arg.split('=').next().unwrap_or(arg);
+    Err(format!(
+        "选项 \"{}\" 会在电脑上运行程序，不能写在设备上的配置文件中；请在命令行或配置档案（{}）中指定", key, PROFILES_FILE,
+    ))
+}
+
+/// 解析预设 `name` 对应的命令行参数：优先使用配置文件中的 [preset.<name>]，其次为内置预设
+pub fn preset_args(base: &Path, name: &str) -> Result<Vec<String>, String> {
+    let config = load_config(base)?;
+    let presets = config.as_ref().and_then(|c| c.get(PRESET_TABLE)).and_then(|p| p.as_table());
+    if let Some(value) = presets.and_then(|p| p.get(name)) {
+        let table = value.as_table().ok_or_else(|| format!("预设 \"{}\" 必须是一个表", name))?;
+        let args = table_to_args(table).map_err(|e| format!("预设 \"{}\": {}", name, e))?;
+        reject_host_commands(&args).map_err(|e| format!("预设 \"{}\": {}", name, e))?;
+        return Ok(args);
+    }
+    if let Some((_, args)) = BUILTIN_PRESETS.iter().find(|(n, _)| *n == name) {
+        return Ok(args.iter().map(|a| a.to_string()).collect());
+    }
+
+    let mut known: Vec<String> = BUILTIN_PRESETS.iter().map(|(n, _)| n.to_string()).collect();
+    if let Some(p) = presets {
+        known.extend(p.keys().filter(|k| !known.contains(k)).cloned().collect::<Vec<_>>());
+    }
+    Err(format!("未知的预设 \"{}\"。可用的预设: {}", name, known.join(", ")))
+}
+
+/// 配置文件顶层的默认选项展开成的命令行参数，优先级低于预设与命令行
+pub fn default_args(base: &Path) -> Result<Vec<String>, String> {
+    let Some(config) = load_config(base)? else { return Ok(Vec::new()) };
+    let mut defaults = toml::Table::new();
+    for (key, value) in config {
+        if SECTION_TABLES.contains(&key.as_str()) { continue; }
+        if key == CONVERTER_TABLE {
+            return Err(format!(
+                "[{}] 会在电脑上运行程序，不能写在设备上的配置文件中；请移到用户配置目录的 {}", CONVERTER_TABLE, PROFILES_FILE,
+            ));
+        }
+        if value.is_table() {
+            return Err(format!("未知的配置表 [{}]，可用的表: {}", key, SECTION_TABLES.join(", ")));
+        }
+        defaults.insert(key, value);
+    }
+    let args = table_to_args(&defaults).map_err(|e| format!("{}: {}", CONFIG_FILE, e))?;
+    reject_host_commands(&args).map_err(|e| format!("{}: {}", CONFIG_FILE, e))?;
+    Ok(args)
+}
@@ -0,0 +1,61 @@
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::i18n::tr;
+use crate::progress;
+
+// ─── Confirmation prompts ────────────────────────────────────────────────────
+
+/// --yes 时为 true：不再询问，直接执行删除与覆盖
+static ASSUME_YES: AtomicBool = AtomicBool::new(false);
+
+/// 列出的条目超过这个数量时只显示前面的部分
+const MAX_LISTED: usize = 20;
+
+/// 之后的确认都视为同意（--yes）
+pub fn assume_yes() {
+    ASSUME_YES.store(true, Ordering::Relaxed);
+}
+
+/// 列出将被删除或覆盖的内容，并在终端上询问是否继续。--yes 时不询问；
+/// 标准输入不是终端（如在脚本或 cron 中运行）时无法询问，视为不同意，并提示加上 --yes
+pub fn confirm(title: &str, items: &[String]) -> bool {
+    if items.is_empty() || ASSUME_YES.load(Ordering::Relaxed) {
+        return true;
+    }
+    if !io::stdin().is_terminal() {
+        progress::suspend(|| {
+            let mut err = io::stderr().lock();
+            list_items(&mut err, title, items);
+            let _ = writeln!(err, "{}", tr!("标准输入不是终端，无法询问。确认要删除时请加上 --yes 或 --non-interactive"));
+        });
+        return false;
+    }
+    progress::suspend(|| {
+        let mut err = io::stderr().lock();
+        list_items(&mut err, title, items);
+        let _ = write!(err, "{}", tr!("是否继续？[y/N] "));
+        let _ = err.flush();
+        let mut answer = String::new();
+        if io::stdin().lock().read_line(&mut answer).is_err() { return false; }
+        matches!(answer.trim().to_lowercase().as_str(), "y" | "yes" | "是")
+    })
+}
+
+fn list_items(err: &mut impl Write, title: &str, items: &[String]) {
+    let _ = writeln!(err, "{}", title);
+    for item in items.iter().take(MAX_LISTED) {
+        let _ = writeln!(err, "  - {}", item);
+    }
+    if items.len() > MAX_LISTED {
+        let _ = writeln!(err, "{}", tr!("  ……另有 {} 项", items.len() - MAX_LISTED));
+    }
+}
+
+/// 演练时代替 `confirm`：不询问，只在 --progress-json 时输出一行 `{"confirm":{"title":"…","items":["…"]}}`，
+/// 供图形界面在真正同步前显示确认对话框。终端上的演练已逐项列出将要进行的修改，不再重复
+pub fn preview(title: &str, items: &[String]) {
+    if items.is_empty() || !progress::json() { return; }
+    let line = serde_json::json!({ "confirm": { "title": title, "items": items } });
+    progress::suspend(|| eprintln!("{}", line));
+}
@@ -1,15 +1,20 @@
-use crate::vprintln;
+use crate::i18n::tr;
 use lofty::prelude::*;
 use std::fs;
 use std::io;
 use std::mem::MaybeUninit;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use symphonia::core::audio::SampleBuffer;
-use symphonia::core::codecs::DecoderOptions;
-use symphonia::core::formats::FormatOptions;
+use symphonia::core::codecs::{Decoder, DecoderOptions};
+use symphonia::core::formats::{FormatOptions, FormatReader};
 use symphonia::core::io::MediaSourceStream;
 use symphonia::core::meta::MetadataOptions;
 use symphonia::core::probe::Hint;
+use tracing::{debug, error};
+
+use crate::device_io::{sync_file, write_file};
+use crate::timeout::run_with_retry;
 
 /// 从 MaybeUninit 缓冲区的前 `len` 个元素中安全地收集已初始化字节
 ///
@@ -22,124 +27,308 @@ fn collect_initialized_bytes(buf: &[MaybeUninit<u8>], len: usize) -> Vec<u8> {
         .collect()
 }
 
-/// 将 FLAC 文件转换为 MP3，成功后返回 MP3 路径，并删除源 FLAC 文件
-pub fn convert_flac_to_mp3(flac_path: &Path) -> Option<PathBuf> {
-    let mp3_path = flac_path.with_extension("mp3");
+/// 在 `end_ms` 处截断音频，并在截断前的 `fade_ms` 毫秒内线性淡出
+#[derive(Clone, Copy)]
+pub struct FadeOut {
+    pub end_ms: u64,
+    pub fade_ms: u64,
+}
 
-    if mp3_path.exists() {
-        if flac_path.exists() {
-            vprintln!("MP3 已存在，删除源文件: {}", flac_path.file_name().unwrap_or_default().to_string_lossy());
-            let _ = fs::remove_file(flac_path);
+impl FadeOut {
+    /// 第 `frame` 帧的增益；超出截断点时返回 `None`
+    fn gain_at(&self, frame: u64, sample_rate: u32) -> Option<f32> {
+        let t_ms = frame * 1000 / sample_rate.max(1) as u64;
+        if t_ms >= self.end_ms { return None; }
+        let fade_start = self.end_ms.saturating_sub(self.fade_ms);
+        if t_ms < fade_start { return Some(1.0); }
+        Some((self.end_ms - t_ms) as f32 / self.fade_ms.max(1) as f32)
+    }
+}
+
+/// 逐个数据包解码音频文件的流式解码器
+pub struct DecodeStream {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    pub sample_rate: u32,
+    pub channels: usize,
+}
+
+impl DecodeStream {
+    pub fn open(src: &Path) -> Result<Self, String> {
+        let file = fs::File::open(src).map_err(|e| tr!("无法打开文件: {}", e))?;
+        let mss = MediaSourceStream::new(Box::new(file), Default::default());
+        let mut hint = Hint::new();
+        if let Some(ext) = src.extension() {
+            hint.with_extension(&ext.to_string_lossy());
         }
-        return Some(mp3_path);
+
+        let probed = symphonia::default::get_probe()
+            .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())
+            .map_err(|e| tr!("无法探测格式: {}", e))?;
+
+        let format = probed.format;
+        let track = format.default_track().ok_or("无音频轨道")?.clone();
+        let codec_params = track.codec_params.clone();
+        let decoder = symphonia::default::get_codecs()
+            .make(&codec_params, &DecoderOptions::default())
+            .map_err(|e| tr!("无法创建解码器: {}", e))?;
+
+        Ok(DecodeStream {
+            format,
+            decoder,
+            track_id: track.id,
+            sample_rate: codec_params.sample_rate.unwrap_or(44100),
+            channels: codec_params.channels.map(|c| c.count()).unwrap_or(2),
+        })
     }
 
-    vprintln!("转换 FLAC -> MP3: {}", flac_path.file_name().unwrap_or_default().to_string_lossy());
+    /// 解码下一个数据包，返回交错样本及其声道数；到达文件末尾时返回 `None`
+    pub fn next_chunk(&mut self) -> Option<(SampleBuffer<f32>, usize)> {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(p) => p,
+                Err(symphonia::core::errors::Error::IoError(ref e))
+                    if e.kind() == io::ErrorKind::UnexpectedEof => return None,
+                Err(_) => return None,
+            };
+            if packet.track_id() != self.track_id { continue; }
 
-    let file = match fs::File::open(flac_path) {
-        Ok(f) => f,
-        Err(e) => { eprintln!("转换失败: 无法打开文件: {}", e); return None; }
-    };
-    let mss = MediaSourceStream::new(Box::new(file), Default::default());
-    let mut hint = Hint::new();
-    hint.with_extension("flac");
-
-    let probed = match symphonia::default::get_probe().format(
-        &hint, mss, &FormatOptions::default(), &MetadataOptions::default(),
-    ) {
-        Ok(p) => p,
-        Err(e) => { eprintln!("转换失败: 无法探测格式: {}", e); return None; }
-    };
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(d) => d,
+                Err(_) => continue,
+            };
 
-    let mut format = probed.format;
-    let track = match format.default_track() {
-        Some(t) => t.clone(),
-        None => { eprintln!("转换失败: 无音频轨道"); return None; }
-    };
+            let spec = *decoded.spec();
+            let num_frames = decoded.frames();
+            let mut sample_buf = SampleBuffer::<f32>::new(num_frames as u64, spec);
+            sample_buf.copy_interleaved_ref(decoded);
+            return Some((sample_buf, spec.channels.count().max(1)));
+        }
+    }
+}
 
-    let codec_params = track.codec_params.clone();
-    let sample_rate = codec_params.sample_rate.unwrap_or(44100);
-    let channels = codec_params.channels.map(|c| c.count()).unwrap_or(2);
+/// 可选的 MP3 码率 (kbps)
+pub const BITRATES: &[&str] = &["96", "128", "160", "192", "256", "320"];
 
-    let mut decoder = match symphonia::default::get_codecs().make(&codec_params, &DecoderOptions::default()) {
-        Ok(d) => d,
-        Err(e) => { eprintln!("转换失败: 无法创建解码器: {}", e); return None; }
-    };
+fn lame_bitrate(kbps: u32) -> mp3lame_encoder::Bitrate {
+    use mp3lame_encoder::Bitrate::*;
+    match kbps {
+        0..=96 => Kbps96,
+        97..=128 => Kbps128,
+        129..=160 => Kbps160,
+        161..=192 => Kbps192,
+        193..=256 => Kbps256,
+        _ => Kbps320,
+    }
+}
 
-    // 使用源文件实际采样率，LAME 不会自动重采样
-    let mut lame = mp3lame_encoder::Builder::new().expect("lame builder");
-    lame.set_sample_rate(sample_rate).expect("set sample rate");
-    lame.set_num_channels(if channels >= 2 { 2 } else { 1 }).expect("set channels");
-    lame.set_brate(mp3lame_encoder::Bitrate::Kbps320).expect("set bitrate");
-    lame.set_quality(mp3lame_encoder::Quality::Best).expect("set quality");
-    let mut encoder = lame.build().expect("build lame encoder");
-
-    let mut mp3_data: Vec<u8> = Vec::new();
-
-    loop {
-        let packet = match format.next_packet() {
-            Ok(p) => p,
-            Err(symphonia::core::errors::Error::IoError(ref e))
-                if e.kind() == io::ErrorKind::UnexpectedEof => break,
-            Err(_) => break,
-        };
-        if packet.track_id() != track.id { continue; }
-
-        let decoded = match decoder.decode(&packet) {
-            Ok(d) => d,
-            Err(_) => continue,
-        };
-
-        let spec = *decoded.spec();
-        let num_frames = decoded.frames();
-        let mut sample_buf = SampleBuffer::<f32>::new(num_frames as u64, spec);
-        sample_buf.copy_interleaved_ref(decoded);
-        let samples = sample_buf.samples();
+/// 接收交错 PCM 样本的 LAME MP3 编码器
+pub struct Mp3Sink {
+    encoder: mp3lame_encoder::Encoder,
+    data: Vec<u8>,
+}
+
+impl Mp3Sink {
+    pub fn new(sample_rate: u32, channels: usize, bitrate: u32) -> Self {
+        // 使用源文件实际采样率，LAME 不会自动重采样
+        let mut lame = mp3lame_encoder::Builder::new().expect("lame builder");
+        lame.set_sample_rate(sample_rate).expect("set sample rate");
+        lame.set_num_channels(if channels >= 2 { 2 } else { 1 }).expect("set channels");
+        lame.set_brate(lame_bitrate(bitrate)).expect("set bitrate");
+        lame.set_quality(mp3lame_encoder::Quality::Best).expect("set quality");
+        Mp3Sink { encoder: lame.build().expect("build lame encoder"), data: Vec::new() }
+    }
 
-        let actual_ch = spec.channels.count().min(2);
-        if actual_ch >= 2 {
-            let frame_count = samples.len() / actual_ch;
+    /// 写入 `src_ch` 声道的交错样本（多于两个声道时只取前两个）
+    pub fn push(&mut self, samples: &[f32], src_ch: usize) {
+        if src_ch >= 2 {
+            let frame_count = samples.len() / src_ch;
             let mut left = Vec::with_capacity(frame_count);
             let mut right = Vec::with_capacity(frame_count);
             for i in 0..frame_count {
-                left.push(samples[i * actual_ch]);
-                right.push(samples[i * actual_ch + 1]);
+                left.push(samples[i * src_ch]);
+                right.push(samples[i * src_ch + 1]);
             }
             let input = mp3lame_encoder::DualPcm { left: &left, right: &right };
             let mut buf = vec![MaybeUninit::uninit(); mp3lame_encoder::max_required_buffer_size(frame_count)];
-            let written = encoder.encode(input, &mut buf).unwrap_or(0);
-            mp3_data.extend_from_slice(&collect_initialized_bytes(&buf, written));
+            let written = self.encoder.encode(input, &mut buf).unwrap_or(0);
+            self.data.extend_from_slice(&collect_initialized_bytes(&buf, written));
         } else {
             let input = mp3lame_encoder::MonoPcm(samples);
             let mut buf = vec![MaybeUninit::uninit(); mp3lame_encoder::max_required_buffer_size(samples.len())];
-            let written = encoder.encode(input, &mut buf).unwrap_or(0);
-            mp3_data.extend_from_slice(&collect_initialized_bytes(&buf, written));
+            let written = self.encoder.encode(input, &mut buf).unwrap_or(0);
+            self.data.extend_from_slice(&collect_initialized_bytes(&buf, written));
         }
     }
 
-    // Flush LAME
-    let mut flush_buf = vec![MaybeUninit::uninit(); 7200];
-    let flushed = encoder.flush::<mp3lame_encoder::FlushNoGap>(&mut flush_buf).unwrap_or(0);
-    mp3_data.extend_from_slice(&collect_initialized_bytes(&flush_buf, flushed));
+    /// 刷新编码器并返回完整的 MP3 数据
+    pub fn finish(mut self) -> Vec<u8> {
+        let mut flush_buf = vec![MaybeUninit::uninit(); 7200];
+        let flushed = self.encoder.flush::<mp3lame_encoder::FlushNoGap>(&mut flush_buf).unwrap_or(0);
+        self.data.extend_from_slice(&collect_initialized_bytes(&flush_buf, flushed));
+        self.data
+    }
+}
+
+/// 解码任意 symphonia 支持的音频文件并编码为 `bitrate` kbps 的 MP3 数据
+pub fn encode_to_mp3(src: &Path, fade: Option<FadeOut>, bitrate: u32) -> Result<Vec<u8>, String> {
+    let mut stream = DecodeStream::open(src)?;
+    let sample_rate = stream.sample_rate;
+    let mut sink = Mp3Sink::new(sample_rate, stream.channels, bitrate);
+    let mut frame_pos: u64 = 0;
+
+    while let Some((mut sample_buf, src_ch)) = stream.next_chunk() {
+        let mut keep_frames = sample_buf.samples().len() / src_ch;
+        let mut finished = false;
+        if let Some(fade) = fade {
+            let samples = sample_buf.samples_mut();
+            let total_frames = keep_frames;
+            for f in 0..total_frames {
+                let Some(g) = fade.gain_at(frame_pos + f as u64, sample_rate) else {
+                    keep_frames = f;
+                    finished = true;
+                    break;
+                };
+                for s in &mut samples[f * src_ch..(f + 1) * src_ch] { *s *= g; }
+            }
+        }
+        frame_pos += keep_frames as u64;
+        sink.push(&sample_buf.samples()[..keep_frames * src_ch], src_ch);
+        if finished { break; }
+    }
+
+    Ok(sink.finish())
+}
 
-    if let Err(e) = fs::write(&mp3_path, &mp3_data) {
-        eprintln!("转换失败: 写入 MP3 失败: {}", e);
-        let _ = fs::remove_file(&mp3_path);
+/// 解码音频文件中若干 `(start_ms, end_ms)` 区间的交错样本，
+/// 返回各区间的样本以及采样率和声道数
+pub fn extract_segments(src: &Path, segments: &[(u64, u64)]) -> Result<(Vec<Vec<f32>>, u32, usize), String> {
+    let mut stream = DecodeStream::open(src)?;
+    let sample_rate = stream.sample_rate;
+    let to_frame = |ms: u64| ms * sample_rate as u64 / 1000;
+    let ranges: Vec<(u64, u64)> = segments.iter().map(|&(s, e)| (to_frame(s), to_frame(e))).collect();
+    let last_frame = ranges.iter().map(|r| r.1).max().unwrap_or(0);
+    let mut out: Vec<Vec<f32>> = vec![Vec::new(); segments.len()];
+    let mut channels = stream.channels.max(1);
+    let mut frame_pos: u64 = 0;
+
+    while let Some((sample_buf, src_ch)) = stream.next_chunk() {
+        let samples = sample_buf.samples();
+        let frames = (samples.len() / src_ch) as u64;
+        channels = src_ch;
+        for (seg, &(start, end)) in out.iter_mut().zip(&ranges) {
+            let from = start.max(frame_pos);
+            let to = end.min(frame_pos + frames);
+            if from < to {
+                let a = (from - frame_pos) as usize * src_ch;
+                let b = (to - frame_pos) as usize * src_ch;
+                seg.extend_from_slice(&samples[a..b]);
+            }
+        }
+        frame_pos += frames;
+        if frame_pos >= last_frame { break; }
+    }
+
+    Ok((out, sample_rate, channels))
+}
+
+/// 转换任务的编码参数与看门狗设置
+#[derive(Clone, Copy)]
+pub struct EncodeOptions {
+    pub bitrate: u32,
+    /// 单次解码/编码的时限
+    pub timeout: Duration,
+    /// 超时或崩溃后的重试次数
+    pub retries: u32,
+}
+
+/// 在看门狗保护下编码：卡住的任务会被放弃并重试，而不是占住线程池
+fn encode_guarded(src: &Path, fade: Option<FadeOut>, opts: EncodeOptions) -> Result<Vec<u8>, String> {
+    let src = src.to_path_buf();
+    run_with_retry(opts.timeout, opts.retries, false, "编码", move || {
+        encode_to_mp3(&src, fade, opts.bitrate)
+    })
+}
+
+/// 将音频文件截断并淡出后编码为 `mp3_path`，同时复制标签
+pub fn fade_out_to_mp3(src: &Path, mp3_path: &Path, fade: FadeOut, opts: EncodeOptions) -> Option<PathBuf> {
+    debug!("{}", tr!("生成淡出版本: {}", src.file_name().unwrap_or_default().to_string_lossy()));
+    let data = match encode_guarded(src, Some(fade), opts) {
+        Ok(d) => d,
+        Err(e) => { error!("{}", tr!("淡出编码失败: {}", e)); return None; }
+    };
+    if let Some(parent) = mp3_path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if let Err(e) = write_file(mp3_path, &data) {
+        error!("{}", tr!("淡出编码失败: 写入 MP3 失败: {}", e));
+        let _ = fs::remove_file(mp3_path);
         return None;
     }
+    copy_tags(src, mp3_path);
+    Some(mp3_path.to_path_buf())
+}
+
+/// 将 FLAC 文件转换为 `mp3_path`，成功后返回 MP3 路径，并删除源 FLAC 文件；失败时返回原因
+///
+/// `overwrite` 为 `false` 时若目标已存在则直接沿用现有 MP3
+pub fn convert_flac_to_mp3(flac_path: &Path, mp3_path: &Path, overwrite: bool, opts: EncodeOptions) -> Result<PathBuf, String> {
+    let mp3_path = mp3_path.to_path_buf();
+
+    if mp3_path.exists() && !overwrite {
+        if flac_path.exists() {
+            debug!("{}", tr!("MP3 已存在，删除源文件: {}", flac_path.file_name().unwrap_or_default().to_string_lossy()));
+            let _ = fs::remove_file(flac_path);
+        }
+        return Ok(mp3_path);
+    }
+
+    debug!("{}", tr!("转换 FLAC -> MP3: {}", flac_path.file_name().unwrap_or_default().to_string_lossy()));
 
+    let mp3_data = encode_guarded(flac_path, None, opts)?;
+
+    let partial = partial_path(&mp3_path);
+    if let Err(e) = write_file(&partial, &mp3_data) {
+        let _ = fs::remove_file(&partial);
+        return Err(format!("写入 MP3 失败: {}", e));
+    }
+    finish_conversion(flac_path, &partial, mp3_path)
+}
+
+/// 转换中的 MP3 的扩展名，见 `partial_path`
+const PARTIAL_EXT: &str = "converting.mp3";
+
+/// 转换过程中 MP3 先写到同目录下的 "<名称>.converting.mp3"，完整写入后才重命名为 `mp3_path`，
+/// 中途被打断时设备上不会留下截断的 MP3 冒充转换结果
+pub fn partial_path(mp3_path: &Path) -> PathBuf {
+    mp3_path.with_extension(PARTIAL_EXT)
+}
+
+/// 被打断的转换留下的半成品（见 `partial_path`），扫描时删除而不是当作曲目
+pub fn is_partial(path: &Path) -> bool {
+    path.file_name().is_some_and(|n| n.to_string_lossy().to_lowercase().ends_with(&format!(".{}", PARTIAL_EXT)))
+}
+
+/// 转换后的收尾：将源文件的标签复制到写好的 `partial`，确认已写入设备后重命名为 `mp3_path`，再删除源文件
+pub fn finish_conversion(src: &Path, partial: &Path, mp3_path: PathBuf) -> Result<PathBuf, String> {
     // Copy tags using lofty
-    copy_tags(flac_path, &mp3_path);
+    copy_tags(src, partial);
+    // The source is only removed once its replacement is on disk
+    if let Err(e) = sync_file(partial).and_then(|_| fs::rename(partial, &mp3_path)) {
+        let _ = fs::remove_file(partial);
+        return Err(format!("同步 MP3 失败: {}", e));
+    }
 
-    // Delete source FLAC
-    if flac_path.exists() {
-        vprintln!("删除源文件: {}", flac_path.display());
-        if let Err(e) = fs::remove_file(flac_path) {
-            eprintln!("删除源文件失败: {}", e);
+    // Delete source file
+    if src.exists() {
+        debug!("{}", tr!("删除源文件: {}", src.display()));
+        if let Err(e) = fs::remove_file(src) {
+            error!("{}", tr!("删除源文件失败: {}", e));
         }
     }
 
-    Some(mp3_path)
+    Ok(mp3_path)
 }
 
 /// 将源文件的 ID3 标签复制到目标文件
@@ -173,5 +362,5 @@ pub fn copy_tags(src: &Path, dest: &Path) {
     if let Some(v) = src_tag.disk() { dest_tag.set_disk(v); }
 
     let _ = dest_tagged.save_to_path(dest, lofty::config::WriteOptions::default());
-    vprintln!("已复制标签");
+    debug!("{}", tr!("已复制标签"));
 }
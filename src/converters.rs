@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::debug;
+
+use crate::cli::MUSIC_EXT;
+use crate::convert::{finish_conversion, partial_path, DecodeStream, EncodeOptions};
+use crate::i18n::tr;
+use crate::timeout::run_process;
+
+// ─── External converters ─────────────────────────────────────────────────────
+
+/// 电脑上的配置文件（见 `profiles::load_host_config`）中按源文件扩展名定义外部转换程序的表，
+/// 如 `[converters.".wma"]`。设备上的配置文件不能定义转换程序
+pub const CONVERTER_TABLE: &str = "converters";
+
+/// 外部转换程序的命令中源文件与输出文件的占位符
+const INPUT_PLACEHOLDER: &str = "{in}";
+const OUTPUT_PLACEHOLDER: &str = "{out}";
+
+/// 一个外部转换程序：以源文件与临时 MP3 路径替换占位符后运行
+#[derive(Clone)]
+pub struct Converter {
+    /// 程序与参数，每项可以含有 `{in}`、`{out}`
+    args: Vec<String>,
+}
+
+/// 按扩展名（小写、带 "."，如 ".wma"）索引的外部转换程序
+pub type Converters = HashMap<String, Converter>;
+
+/// 读取电脑上的配置文件中的 `[converters."<扩展名>"]` 表。`cmd` 可以是以空白分隔的字符串
+/// （占位符在分隔后替换，因此路径中的空格不受影响），也可以是字符串数组
+pub fn load_converters(config: Option<&toml::Table>) -> Result<Converters, String> {
+    let mut converters = Converters::new();
+    let Some(tables) = config.and_then(|c| c.get(CONVERTER_TABLE)) else { return Ok(converters) };
+    let tables = tables.as_table().ok_or_else(|| format!("[{}] 必须是一个表", CONVERTER_TABLE))?;
+    for (key, table) in tables {
+        let ext = format!(".{}", key.trim_start_matches('.').to_lowercase());
+        if MUSIC_EXT.contains(&ext.as_str()) {
+            return Err(format!("[{}.\"{}\"]: iPod 可以直接播放该格式，不需要转换", CONVERTER_TABLE, key));
+        }
+        let table = table.as_table().ok_or_else(|| format!("[{}.\"{}\"] 必须是一个表", CONVERTER_TABLE, key))?;
+        let mut args = None;
+        for (name, value) in table {
+            match name.as_str() {
+                "cmd" => args = Some(match value {
+                    toml::Value::String(s) => s.split_whitespace().map(str::to_string).collect::<Vec<_>>(),
+                    toml::Value::Array(items) => items.iter()
+                        .map(|v| v.as_str().map(str::to_string))
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or_else(|| format!("[{}.\"{}\"] 中 \"cmd\" 的每一项必须是字符串", CONVERTER_TABLE, key))?,
+                    _ => return Err(format!("[{}.\"{}\"] 中 \"cmd\" 必须是字符串或字符串数组", CONVERTER_TABLE, key)),
+                }),
+                _ => return Err(format!("[{}.\"{}\"]: 未知的转换选项 \"{}\"", CONVERTER_TABLE, key, name)),
+            }
+        }
+        let args = args.filter(|a| !a.is_empty())
+            .ok_or_else(|| format!("[{}.\"{}\"] 缺少 \"cmd\"", CONVERTER_TABLE, key))?;
+        if !args.iter().any(|a| a.contains(OUTPUT_PLACEHOLDER)) {
+            return Err(format!("[{}.\"{}\"] 的 \"cmd\" 中没有 {}", CONVERTER_TABLE, key, OUTPUT_PLACEHOLDER));
+        }
+        converters.insert(ext, Converter { args });
+    }
+    Ok(converters)
+}
+
+impl Converter {
+    fn command(&self, src: &Path, out: &Path) -> Command {
+        let fill = |arg: &String| arg
+            .replace(INPUT_PLACEHOLDER, &src.to_string_lossy())
+            .replace(OUTPUT_PLACEHOLDER, &out.to_string_lossy());
+        let mut command = Command::new(fill(&self.args[0]));
+        command.args(self.args[1..].iter().map(fill));
+        command
+    }
+
+    /// 运行转换程序，写出的文件能解码出音频才算成功。超时或失败时最多重试 `retries` 次
+    fn run(&self, src: &Path, out: &Path, opts: EncodeOptions) -> Result<(), String> {
+        let mut last = String::new();
+        for attempt in 0..=opts.retries {
+            if attempt > 0 {
+                debug!("转换失败，正在重试 ({}/{}): {}", attempt, opts.retries, last);
+            }
+            let _ = fs::remove_file(out);
+            match run_process(self.command(src, out), None, opts.timeout) {
+                Ok(()) => {
+                    return match DecodeStream::open(out).map(|mut s| s.next_chunk().is_some()) {
+                        Ok(true) => Ok(()),
+                        Ok(false) => Err("输出文件中没有音频".to_string()),
+                        Err(e) => Err(tr!("无法解码输出文件: {}", e)),
+                    };
+                }
+                Err(e) => last = e,
+            }
+        }
+        Err(last)
+    }
+
+    /// 与 `convert_flac_to_mp3` 相同，只是由外部程序完成转换：成功后返回 MP3 路径并删除源文件，
+    /// 失败时返回原因；`overwrite` 为 `false` 时若目标已存在则直接沿用现有 MP3
+    pub fn convert(&self, src: &Path, mp3_path: &Path, overwrite: bool, opts: EncodeOptions) -> Result<PathBuf, String> {
+        let mp3_path = mp3_path.to_path_buf();
+        if mp3_path.exists() && !overwrite {
+            if src.exists() {
+                debug!("{}", tr!("MP3 已存在，删除源文件: {}", src.file_name().unwrap_or_default().to_string_lossy()));
+                let _ = fs::remove_file(src);
+            }
+            return Ok(mp3_path);
+        }
+
+        debug!("{}", tr!("外部程序转换 -> MP3: {}", src.file_name().unwrap_or_default().to_string_lossy()));
+        let partial = partial_path(&mp3_path);
+        if let Err(e) = self.run(src, &partial, opts) {
+            let _ = fs::remove_file(&partial);
+            return Err(e);
+        }
+        finish_conversion(src, &partial, mp3_path)
+    }
+}
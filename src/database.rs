@@ -1,15 +1,26 @@
 use byteorder::{LittleEndian, WriteBytesExt};
-use lofty::prelude::*;
 use md5::{Digest, Md5};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
-
-use crate::tts::text_to_speech_file;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::trace;
+
+use crate::cli::TrackKind;
+use crate::i18n::tr;
+use crate::listing::playlist_display_name;
+use crate::track_rules::TrackRules;
+use crate::metadata::TrackMeta;
+use crate::playlist::{ListType, PlaylistOptions};
+use crate::tts::{default_voice, spoken_date, text_to_speech_file};
 use crate::utils::{ext_lower, path_to_ipod};
 
 // ─── iTunesSD binary database construction ───────────────────────────────────
 
+/// 曲目记录中文件名字段的长度（UTF-8 字节，以 0 填充）。更长的 iPod 路径会被截断，设备无法找到该曲目
+pub const MAX_FILENAME_BYTES: usize = 256;
+
 pub fn make_dbid(text: &[u8]) -> [u8; 8] {
     let mut hasher = Md5::new();
     hasher.update(text);
@@ -23,28 +34,81 @@ pub fn dbid_to_filename(dbid: &[u8; 8]) -> String {
     dbid.iter().rev().map(|b| format!("{:02x}", b)).collect()
 }
 
+/// 旁白文件名是否使用大写十六进制，见配置文件的 [speakable] 表
+static UPPER_CASE_VOICEOVERS: AtomicBool = AtomicBool::new(false);
+
+/// 设置旁白文件名的大小写，需在生成或检查旁白之前调用
+pub fn set_upper_case_voiceovers(upper: bool) {
+    UPPER_CASE_VOICEOVERS.store(upper, Ordering::Relaxed);
+}
+
+/// dbid 对应的语音旁白文件名，如 "<dbid>.wav"
+pub fn voiceover_file_name(dbid: &[u8; 8]) -> String {
+    let name = dbid_to_filename(dbid);
+    if UPPER_CASE_VOICEOVERS.load(Ordering::Relaxed) {
+        format!("{}.wav", name.to_ascii_uppercase())
+    } else {
+        format!("{}.wav", name)
+    }
+}
+
+/// dbid 对应的语音旁白文件，如 "iPod_Control/Speakable/Tracks/<dbid>.wav"
+pub fn voiceover_path(base: &Path, dbid: &[u8; 8], is_playlist: bool) -> PathBuf {
+    let subdir = if is_playlist { "Playlists" } else { "Tracks" };
+    base.join("iPod_Control").join("Speakable").join(subdir).join(voiceover_file_name(dbid))
+}
+
+/// 生成语音旁白。`level` 为目标响度 (dBFS)，给出时旁白会调整到该响度；
+/// `voice` 覆盖全局的 TTS 语音
 pub fn do_text_to_speech(
-    text: &str, dbid: &[u8; 8], is_playlist: bool,
-    base: &Path, track_voiceover: bool, playlist_voiceover: bool,
+    text: &str, dbid: &[u8; 8], is_playlist: bool, base: &Path, level: Option<f64>, voice: Option<&str>,
 ) {
-    let should_speak = if is_playlist { playlist_voiceover } else { track_voiceover };
-    if !should_speak { return; }
+    text_to_speech_file(&voiceover_path(base, dbid, is_playlist), text, level, voice);
+}
 
-    let fn_name = dbid_to_filename(dbid);
-    let subdir = if is_playlist { "Playlists" } else { "Tracks" };
-    let wav_path = base.join("iPod_Control").join("Speakable").join(subdir).join(format!("{}.wav", fn_name));
-    text_to_speech_file(&wav_path, text);
+/// 曲目旁白朗读的文本，同时决定曲目的 dbid：有标题和艺术家时为 "标题 - 艺术家"，否则为文件名
+pub fn track_voiceover_text(filepath: &Path, meta: &TrackMeta) -> String {
+    let title = meta.title.clone().unwrap_or_default();
+    let artist = meta.artist.clone().unwrap_or_default();
+    if meta.has_tag && !title.is_empty() && !artist.is_empty() {
+        format!("{} - {}", title, artist)
+    } else {
+        filepath.file_stem().unwrap_or_default().to_string_lossy().to_string()
+    }
+}
+
+/// 播客单集旁白朗读的文本："节目 - 单集标题, 发行日期"，日期按 `voice` 的语言朗读。
+/// 节目名取专辑标签，没有时取艺术家；没有标题时为 `None`，沿用 `track_voiceover_text`
+pub fn podcast_voiceover_text(meta: &TrackMeta, voice: &str) -> Option<String> {
+    let title = meta.title.as_deref().filter(|t| !t.trim().is_empty())?;
+    let show = [&meta.album, &meta.artist].into_iter().flatten().find(|s| !s.trim().is_empty());
+    let mut text = match show {
+        Some(show) => format!("{} - {}", show, title),
+        None => title.to_string(),
+    };
+    if let Some(date) = meta.date.as_deref().and_then(|d| spoken_date(d, voice)) {
+        text.push_str(", ");
+        text.push_str(&date);
+    }
+    Some(text)
 }
 
 pub struct TrackInfo {
     pub filename: String,       // iPod-relative path
     pub filetype: u32,          // 1=mp3, 2=aac
+    /// 从文件中的该位置开始播放，用于章节等只播放文件一部分的条目
+    pub start_at_pos_ms: u32,
     pub stop_at_pos_ms: u32,
     pub volume_gain: u32,
     pub album_id: u32,
     pub artist_id: u32,
     pub track_num: u16,
     pub disc_num: u16,
+    pub remember: bool,
+    /// 为 false 时随机播放在列表之间选曲会跳过该曲目（见 dont_skip_on_shuffle）
+    pub dont_skip: bool,
+    pub pregap: u32,
+    pub postgap: u32,
     pub dbid: [u8; 8],
 }
 
@@ -53,12 +117,32 @@ pub struct BuildContext<'a> {
     pub base: &'a Path,
     pub trackgain: u32,
     pub track_gain_overrides: &'a HashMap<PathBuf, u32>,
+    /// 曲目播放时的响度（响度 + 增益，dBFS），对应的语音旁白按该响度生成
+    pub voiceover_levels: &'a HashMap<PathBuf, f64>,
+    pub metadata: &'a HashMap<PathBuf, TrackMeta>,
+    pub track_kinds: &'a HashMap<PathBuf, TrackKind>,
+    pub rules: &'a TrackRules,
     pub albums: &'a mut Vec<String>,
     pub album_index: &'a mut HashMap<String, u32>,
     pub artists: &'a mut Vec<String>,
     pub artist_index: &'a mut HashMap<String, u32>,
     pub track_voiceover: bool,
-    pub playlist_voiceover: bool,
+}
+
+/// 由名称推算专辑或艺术家 ID，每次同步都相同，设备上按专辑分组的随机播放不会因曲目增减而变动。
+/// 0 留给没有标签的曲目；两个名称的 ID 相同时顺延到下一个未使用的值
+fn stable_id(name: &str, ids: &mut HashMap<String, u32>, names: &mut Vec<String>) -> u32 {
+    if let Some(&id) = ids.get(name) {
+        return id;
+    }
+    let digest = make_dbid(name.as_bytes());
+    let mut id = u32::from_le_bytes([digest[0], digest[1], digest[2], digest[3]]).max(1);
+    while ids.values().any(|&v| v == id) {
+        id = id.wrapping_add(1).max(1);
+    }
+    ids.insert(name.to_string(), id);
+    names.push(name.to_string());
+    id
 }
 
 pub fn build_track_info(
@@ -74,90 +158,137 @@ pub fn build_track_info(
         volume_gain = g;
     }
 
-    let stem = filepath.file_stem().unwrap_or_default().to_string_lossy().to_string();
-    let mut text = stem.clone();
+    // Podcasts and audiobooks resume where they were left off
+    let kind = ctx.track_kinds.get(filepath).copied().unwrap_or(TrackKind::Music);
+    let remember = kind != TrackKind::Music;
+
     let mut stop_at_pos_ms = 0u32;
     let mut album_id = 0u32;
     let mut artist_id = 0u32;
     let mut track_num = 1u16;
     let mut disc_num = 0u16;
 
-    // Try reading tags with lofty
-    if let Ok(tagged) = lofty::read_from_path(filepath) {
-        if let Some(tag) = tagged.primary_tag().or_else(|| tagged.first_tag()) {
-            if let Ok(props) = u32::try_from(tagged.properties().duration().as_millis()) {
-                stop_at_pos_ms = props;
-            }
+    let meta = ctx.metadata.get(filepath).cloned().unwrap_or_default();
+    if let Some(ms) = meta.duration_ms {
+        stop_at_pos_ms = u32::try_from(ms).unwrap_or(u32::MAX);
+    }
 
-            let artist_name = tag.artist().map(|s| s.to_string()).unwrap_or_else(|| "Unknown".into());
-            let idx = ctx.artist_index.get(&artist_name).copied().unwrap_or_else(|| {
-                let idx = ctx.artists.len() as u32;
-                ctx.artist_index.insert(artist_name.clone(), idx);
-                ctx.artists.push(artist_name.clone());
-                idx
-            });
-            artist_id = idx;
-
-            let album_name = tag.album().map(|s| s.to_string()).unwrap_or_else(|| "Unknown".into());
-            let idx = ctx.album_index.get(&album_name).copied().unwrap_or_else(|| {
-                let idx = ctx.albums.len() as u32;
-                ctx.album_index.insert(album_name.clone(), idx);
-                ctx.albums.push(album_name.clone());
-                idx
-            });
-            album_id = idx;
-
-            if let Some(t) = tag.track() { track_num = t as u16; }
-            if let Some(d) = tag.disk() { disc_num = d as u16; }
-
-            let title = tag.title().map(|s| s.to_string()).unwrap_or_default();
-            let artist_str = tag.artist().map(|s| s.to_string()).unwrap_or_default();
-            if !title.is_empty() && !artist_str.is_empty() {
-                text = format!("{} - {}", title, artist_str);
-            }
-        }
+    if meta.has_tag {
+        let artist_name = meta.artist.clone().unwrap_or_else(|| "Unknown".into());
+        artist_id = stable_id(&artist_name, ctx.artist_index, ctx.artists);
+
+        let album_name = meta.album.clone().unwrap_or_else(|| "Unknown".into());
+        album_id = stable_id(&album_name, ctx.album_index, ctx.albums);
+
+        if let Some(t) = meta.track { track_num = t as u16; }
+        if let Some(d) = meta.disc { disc_num = d as u16; }
     }
 
-    let text_bytes = text.as_bytes();
-    let dbid = make_dbid(text_bytes);
-    do_text_to_speech(&text, &dbid, false, ctx.base, ctx.track_voiceover, ctx.playlist_voiceover);
+    let (pregap, postgap) = ctx.rules.gaps(filepath);
+    let podcast_text = match kind {
+        TrackKind::Podcast => podcast_voiceover_text(&meta, default_voice()),
+        _ => None,
+    };
+    let text = meta.voiceover.clone()
+        .or(podcast_text)
+        .unwrap_or_else(|| track_voiceover_text(filepath, &meta));
+    let dbid = make_dbid(text.as_bytes());
+    if ctx.track_voiceover {
+        let level = ctx.voiceover_levels.get(filepath).copied();
+        do_text_to_speech(&text, &dbid, false, ctx.base, level, None);
+    }
 
     TrackInfo {
         filename: ipod_path,
         filetype,
+        start_at_pos_ms: 0,
         stop_at_pos_ms,
         volume_gain,
         album_id,
         artist_id,
         track_num,
         disc_num,
+        remember,
+        dont_skip: ctx.rules.shuffle(filepath, &meta),
+        pregap,
+        postgap,
         dbid,
     }
 }
 
+// ─── Reserved fields carried over from the previous database ─────────────────
+
+/// 数据库头部中没有建模的字段 (偏移, 长度)：unknown2 与 max_volume、unknown3、unknown4
+const DB_HEADER_RESERVED: &[(usize, usize)] = &[(20, 9), (30, 2), (44, 20)];
+
+/// 曲目列表头部的 unknown1
+const TRACK_HEADER_RESERVED: &[(usize, usize)] = &[(12, 8)];
+
+/// 播放列表头部的 unknown2
+const PLAYLIST_HEADER_RESERVED: &[(usize, usize)] = &[(18, 2)];
+
+/// 曲目记录的 unintalbum 与 unknown、unknown2、unknown3、unknown4、unknown5。
+/// bookmark、numsamples 与 gapless 取决于文件内容，不会沿用
+const TRACK_RESERVED: &[(usize, usize)] = &[(286, 2), (300, 4), (308, 4), (320, 8), (340, 32)];
+
+/// 播放列表记录的 unknown1
+const PLAYLIST_RESERVED: &[(usize, usize)] = &[(28, 16)];
+
+/// 现有 iTunesSD 中没有建模的字段。重建数据库时原样写回，而不是清零，
+/// 以免固件依赖的字节在同步后丢失
+#[derive(Default)]
+pub struct ReservedFields {
+    db_header: Option<Vec<u8>>,
+    track_header: Option<Vec<u8>>,
+    playlist_header: Option<Vec<u8>>,
+    /// 按 (文件名, start_at_pos_ms) 区分，同一文件的多个章节各有一条记录
+    tracks: HashMap<(String, u32), Vec<u8>>,
+    /// 按 dbid 区分
+    playlists: HashMap<[u8; 8], Vec<u8>>,
+}
+
+/// 依次取出记录中各保留字段的字节，记录被截断时返回 `None`
+fn extract_reserved(data: &[u8], offset: usize, ranges: &[(usize, usize)]) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    for &(start, len) in ranges {
+        out.extend_from_slice(data.get(offset + start..offset + start + len)?);
+    }
+    Some(out)
+}
+
+/// 将 `extract_reserved` 取出的字节写回新生成的记录
+fn restore_reserved(record: &mut [u8], ranges: &[(usize, usize)], saved: Option<&Vec<u8>>) {
+    let Some(saved) = saved else { return };
+    let mut pos = 0;
+    for &(start, len) in ranges {
+        record[start..start + len].copy_from_slice(&saved[pos..pos + len]);
+        pos += len;
+    }
+}
+
 pub fn write_track_record(track: &TrackInfo) -> Vec<u8> {
     let mut buf = Vec::with_capacity(0x174);
     buf.write_all(b"rths").unwrap();                          // header_id
     buf.write_u32::<LittleEndian>(0x174).unwrap();            // header_length
-    buf.write_u32::<LittleEndian>(0).unwrap();                // start_at_pos_ms
+    buf.write_u32::<LittleEndian>(track.start_at_pos_ms).unwrap(); // start_at_pos_ms
     buf.write_u32::<LittleEndian>(track.stop_at_pos_ms).unwrap(); // stop_at_pos_ms
     buf.write_u32::<LittleEndian>(track.volume_gain).unwrap(); // volume_gain
     buf.write_u32::<LittleEndian>(track.filetype).unwrap();   // filetype
 
     // filename: 256 bytes, utf-8, zero-padded
     let fname_bytes = track.filename.as_bytes();
-    let mut fname_buf = [0u8; 256];
-    let copy_len = fname_bytes.len().min(256);
+    let mut fname_buf = [0u8; MAX_FILENAME_BYTES];
+    let copy_len = fname_bytes.len().min(MAX_FILENAME_BYTES);
     fname_buf[..copy_len].copy_from_slice(&fname_bytes[..copy_len]);
     buf.write_all(&fname_buf).unwrap();
 
     buf.write_u32::<LittleEndian>(0).unwrap();                // bookmark
-    buf.write_u8(1).unwrap();                                 // dontskip
-    buf.write_u8(0).unwrap();                                 // remember
+    buf.write_u8(track.dont_skip as u8).unwrap();             // dontskip
+    buf.write_u8(track.remember as u8).unwrap();              // remember
     buf.write_u8(0).unwrap();                                 // unintalbum
     buf.write_u8(0).unwrap();                                 // unknown
-    buf.write_u32::<LittleEndian>(0x200).unwrap();            // pregap
-    buf.write_u32::<LittleEndian>(0x200).unwrap();            // postgap
+    buf.write_u32::<LittleEndian>(track.pregap).unwrap();     // pregap
+    buf.write_u32::<LittleEndian>(track.postgap).unwrap();    // postgap
     buf.write_u32::<LittleEndian>(0).unwrap();                // numsamples
     buf.write_u32::<LittleEndian>(0).unwrap();                // unknown2
     buf.write_u32::<LittleEndian>(0).unwrap();                // gapless
@@ -173,14 +304,17 @@ pub fn write_track_record(track: &TrackInfo) -> Vec<u8> {
     buf
 }
 
-pub fn build_track_header(tracks: &[TrackInfo], base_offset: u32) -> Vec<u8> {
+pub fn build_track_header(tracks: &[TrackInfo], base_offset: u32, reserved: &ReservedFields) -> Vec<u8> {
     let num_tracks = tracks.len() as u32;
     let header_len = 20 + (num_tracks * 4);
 
     // Build individual track records first
     let mut track_chunks: Vec<Vec<u8>> = Vec::new();
     for t in tracks {
-        track_chunks.push(write_track_record(t));
+        let mut record = write_track_record(t);
+        let key = (t.filename.clone(), t.start_at_pos_ms);
+        restore_reserved(&mut record, TRACK_RESERVED, reserved.tracks.get(&key));
+        track_chunks.push(record);
     }
 
     // Header
@@ -189,10 +323,12 @@ pub fn build_track_header(tracks: &[TrackInfo], base_offset: u32) -> Vec<u8> {
     buf.write_u32::<LittleEndian>(header_len).unwrap();       // total_length
     buf.write_u32::<LittleEndian>(num_tracks).unwrap();       // number_of_tracks
     buf.write_u64::<LittleEndian>(0).unwrap();                // unknown1
+    restore_reserved(&mut buf, TRACK_HEADER_RESERVED, reserved.track_header.as_ref());
 
     // Offsets
     let mut chunk_offset = 0u32;
-    for tc in &track_chunks {
+    for (t, tc) in tracks.iter().zip(&track_chunks) {
+        trace!("{}", tr!("曲目记录 {}: {}", format!("{:#x}", base_offset + header_len + chunk_offset), t.filename));
         buf.write_u32::<LittleEndian>(base_offset + header_len + chunk_offset).unwrap();
         chunk_offset += tc.len() as u32;
     }
@@ -227,30 +363,62 @@ pub fn write_playlist_record(
     buf
 }
 
+/// 播放列表的 dbid，由名称决定；未启用播放列表旁白时主列表的 dbid 为全零
+pub fn playlist_dbid(name: &str, playlist_voiceover: bool) -> [u8; 8] {
+    if name == "__master__" && !playlist_voiceover {
+        return [0u8; 8];
+    }
+    let text = if name == "__master__" { "masterlist" } else { name };
+    make_dbid(text.as_bytes())
+}
+
+/// 某一类型（如播客、有声书）的播放列表中的曲目
+pub fn tracks_of_listtype(
+    playlists: &[(String, Vec<u32>)], options: &HashMap<String, PlaylistOptions>, listtype: ListType,
+) -> HashSet<u32> {
+    playlists.iter()
+        .filter(|(name, _)| name != "__master__" && options.get(name).is_some_and(|o| o.listtype == Some(listtype)))
+        .flat_map(|(_, indices)| indices.iter().copied())
+        .collect()
+}
+
+/// 某类播放列表之外的列表数量，没有该类列表时为 0xFFFF
+fn count_other_than(listtypes: &[u32], listtype: u32) -> u16 {
+    if listtypes.contains(&listtype) {
+        listtypes.iter().filter(|&&t| t != listtype).count() as u16
+    } else {
+        0xFFFF
+    }
+}
+
 pub fn build_playlist_header(
     playlists: &[(String, Vec<u32>)],
     base_offset: u32,
     base: &Path,
-    track_voiceover: bool,
     playlist_voiceover: bool,
+    options: &HashMap<String, PlaylistOptions>,
+    reserved: &ReservedFields,
 ) -> Vec<u8> {
     // Build playlist chunks
-    let mut chunks: Vec<Vec<u8>> = Vec::new();
+    let mut chunks: Vec<(u32, Vec<u8>)> = Vec::new();
+    let no_options = PlaylistOptions::default();
 
     for (name, indices) in playlists {
-        let dbid = if name == "__master__" && !playlist_voiceover {
-            [0u8; 8]
-        } else {
-            let text = if name == "__master__" { "masterlist" } else { name.as_str() };
-            let d = make_dbid(text.as_bytes());
-            let speech_text = if name == "__master__" { "All songs" } else { name.as_str() };
-            do_text_to_speech(speech_text, &d, true, base, track_voiceover, playlist_voiceover);
-            d
-        };
-
-        let listtype = if name == "__master__" { 1u32 } else { 2u32 };
-        chunks.push(write_playlist_record(&dbid, listtype, indices));
+        let opts = options.get(name).unwrap_or(&no_options);
+        let dbid = playlist_dbid(name, playlist_voiceover);
+        if dbid != [0u8; 8] && opts.voiceover.unwrap_or(playlist_voiceover) {
+            do_text_to_speech(playlist_display_name(name), &dbid, true, base, None, opts.voice.as_deref());
+        }
+
+        let listtype = if name == "__master__" { 1u32 } else { opts.listtype_code() };
+        let mut record = write_playlist_record(&dbid, listtype, indices);
+        restore_reserved(&mut record, PLAYLIST_RESERVED, reserved.playlists.get(&dbid));
+        chunks.push((listtype, record));
     }
+    // The shuffle assumes the podcast playlist comes last
+    chunks.sort_by_key(|(listtype, _)| *listtype == ListType::Podcast.code());
+    let listtypes: Vec<u32> = chunks.iter().map(|(t, _)| *t).collect();
+    let chunks: Vec<Vec<u8>> = chunks.into_iter().map(|(_, c)| c).collect();
 
     let num_playlists = chunks.len() as u32;
     let header_fixed = 0x14u32; // 20 bytes fixed header
@@ -260,14 +428,16 @@ pub fn build_playlist_header(
     buf.write_all(b"hphs").unwrap();                                  // header_id (shph)
     buf.write_u32::<LittleEndian>(total_length).unwrap();             // total_length
     buf.write_u32::<LittleEndian>(num_playlists).unwrap();            // number_of_playlists
-    buf.write_all(&[0xFF, 0xFF]).unwrap();                            // non_podcast
+    buf.write_u16::<LittleEndian>(count_other_than(&listtypes, ListType::Podcast.code())).unwrap(); // non_podcast
     buf.write_all(&[0x01, 0x00]).unwrap();                            // master
-    buf.write_all(&[0xFF, 0xFF]).unwrap();                            // non_audiobook
+    buf.write_u16::<LittleEndian>(count_other_than(&listtypes, ListType::Audiobook.code())).unwrap(); // non_audiobook
     buf.write_all(&[0x00, 0x00]).unwrap();                            // unknown2
+    restore_reserved(&mut buf, PLAYLIST_HEADER_RESERVED, reserved.playlist_header.as_ref());
 
     // Offsets for each playlist
     let mut offset = base_offset + total_length;
     for chunk in &chunks {
+        trace!("{}", tr!("播放列表记录 {}: {} 首曲目", format!("{:#x}", offset), (chunk.len() - 44) / 4));
         buf.write_u32::<LittleEndian>(offset).unwrap();
         offset += chunk.len() as u32;
     }
@@ -286,20 +456,24 @@ pub fn build_itunes_sd(
     track_voiceover: bool,
     playlist_voiceover: bool,
     base: &Path,
+    options: &HashMap<String, PlaylistOptions>,
+    reserved: &ReservedFields,
 ) -> Vec<u8> {
     let db_header_len = 64u32;
 
     // Build track header
-    let track_header = build_track_header(track_infos, db_header_len);
+    let track_header = build_track_header(track_infos, db_header_len, reserved);
     let playlist_header_offset = db_header_len + track_header.len() as u32;
+    trace!("{}", tr!("曲目表头 {}，播放列表表头 {}", format!("{:#x}", db_header_len), format!("{:#x}", playlist_header_offset)));
 
     // Build playlist header
     let playlist_header = build_playlist_header(
-        playlists, playlist_header_offset, base, track_voiceover, playlist_voiceover,
+        playlists, playlist_header_offset, base, playlist_voiceover, options, reserved,
     );
 
     let num_tracks = track_infos.len() as u32;
     let num_playlists = playlists.len() as u32;
+    let num_podcast_tracks = tracks_of_listtype(playlists, options, ListType::Podcast).len() as u32;
 
     // Database header (bdhs / shdb)
     let mut buf = Vec::new();
@@ -312,12 +486,244 @@ pub fn build_itunes_sd(
     buf.write_u8(0).unwrap();                                         // max_volume
     buf.write_u8(if track_voiceover { 1 } else { 0 }).unwrap();      // voiceover_enabled
     buf.write_u16::<LittleEndian>(0).unwrap();                        // unknown3
-    buf.write_u32::<LittleEndian>(num_tracks).unwrap();               // tracks_without_podcasts
+    buf.write_u32::<LittleEndian>(num_tracks - num_podcast_tracks).unwrap(); // tracks_without_podcasts
     buf.write_u32::<LittleEndian>(64).unwrap();                       // track_header_offset
     buf.write_u32::<LittleEndian>(playlist_header_offset).unwrap();   // playlist_header_offset
     buf.write_all(&[0u8; 20]).unwrap();                               // unknown4
+    restore_reserved(&mut buf, DB_HEADER_RESERVED, reserved.db_header.as_ref());
 
     buf.extend_from_slice(&track_header);
     buf.extend_from_slice(&playlist_header);
     buf
 }
+
+// ─── iTunesSD reader ─────────────────────────────────────────────────────────
+
+/// 从现有 iTunesSD 中解析出的曲目记录
+pub struct ParsedTrack {
+    pub filename: String,
+    pub filetype: u32,
+    pub start_at_pos_ms: u32,
+    pub stop_at_pos_ms: u32,
+    pub volume_gain: u32,
+    pub dont_skip: bool,
+    pub remember: bool,
+    pub pregap: u32,
+    pub postgap: u32,
+    pub album_id: u32,
+    pub artist_id: u32,
+    pub track_num: u16,
+    pub disc_num: u16,
+    pub dbid: [u8; 8],
+    /// 记录在 iTunesSD 中的偏移，用于原地修改
+    pub offset: usize,
+}
+
+/// 从现有 iTunesSD 中解析出的播放列表记录
+pub struct ParsedPlaylist {
+    pub dbid: [u8; 8],
+    pub listtype: u32,
+    pub track_indices: Vec<u32>,
+}
+
+/// 从现有 iTunesSD 中解析出的数据库内容
+pub struct ParsedDatabase {
+    /// 头部的 voiceover_enabled，即同步时是否启用了曲目旁白
+    pub track_voiceover: bool,
+    pub tracks: Vec<ParsedTrack>,
+    pub playlists: Vec<ParsedPlaylist>,
+    pub reserved: ReservedFields,
+}
+
+impl ParsedDatabase {
+    /// 将曲目记录中的文件名 `from` 改为 `to`，使移动过的文件沿用原记录的保留字段
+    pub fn rename_track(&mut self, from: &str, to: &str) {
+        for track in self.tracks.iter_mut().filter(|t| t.filename == from) {
+            track.filename = to.to_string();
+            if let Some(saved) = self.reserved.tracks.remove(&(from.to_string(), track.start_at_pos_ms)) {
+                self.reserved.tracks.insert((to.to_string(), track.start_at_pos_ms), saved);
+            }
+        }
+    }
+}
+
+pub fn read_u32_at(data: &[u8], offset: usize) -> Result<u32, String> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| format!("iTunesSD 在偏移 0x{:x} 处被截断", offset))
+}
+
+fn read_dbid_at(data: &[u8], offset: usize) -> Result<[u8; 8], String> {
+    data.get(offset..offset + 8)
+        .and_then(|b| <[u8; 8]>::try_from(b).ok())
+        .ok_or_else(|| format!("iTunesSD 在偏移 0x{:x} 处被截断", offset))
+}
+
+fn expect_magic(data: &[u8], offset: usize, magic: &[u8; 4]) -> Result<(), String> {
+    match data.get(offset..offset + 4) {
+        Some(m) if m == magic => Ok(()),
+        _ => Err(format!("iTunesSD 在偏移 0x{:x} 处缺少 {} 头", offset, String::from_utf8_lossy(magic))),
+    }
+}
+
+fn parse_track_record(data: &[u8], offset: usize) -> Result<ParsedTrack, String> {
+    expect_magic(data, offset, b"rths")?;
+    let fname = data.get(offset + 24..offset + 24 + 256)
+        .ok_or_else(|| format!("iTunesSD 在偏移 0x{:x} 处被截断", offset))?;
+    let end = fname.iter().position(|&b| b == 0).unwrap_or(fname.len());
+    let flags = data.get(offset + 284..offset + 286)
+        .ok_or_else(|| format!("iTunesSD 在偏移 0x{:x} 处被截断", offset))?;
+    let numbers = read_u32_at(data, offset + 316)?;
+    Ok(ParsedTrack {
+        filename: String::from_utf8_lossy(&fname[..end]).to_string(),
+        filetype: read_u32_at(data, offset + 20)?,
+        start_at_pos_ms: read_u32_at(data, offset + 8)?,
+        stop_at_pos_ms: read_u32_at(data, offset + 12)?,
+        volume_gain: read_u32_at(data, offset + 16)?,
+        dont_skip: flags[0] != 0,
+        remember: flags[1] != 0,
+        pregap: read_u32_at(data, offset + 288)?,
+        postgap: read_u32_at(data, offset + 292)?,
+        album_id: read_u32_at(data, offset + 312)?,
+        artist_id: read_u32_at(data, offset + 336)?,
+        track_num: numbers as u16,
+        disc_num: (numbers >> 16) as u16,
+        dbid: read_dbid_at(data, offset + 328)?,
+        offset,
+    })
+}
+
+fn parse_playlist_record(data: &[u8], offset: usize) -> Result<ParsedPlaylist, String> {
+    expect_magic(data, offset, b"lphs")?;
+    let num_songs = read_u32_at(data, offset + 8)? as usize;
+    let dbid = read_dbid_at(data, offset + 16)?;
+    let listtype = read_u32_at(data, offset + 24)?;
+    let track_indices = (0..num_songs)
+        .map(|i| read_u32_at(data, offset + 44 + i * 4))
+        .collect::<Result<_, _>>()?;
+    Ok(ParsedPlaylist { dbid, listtype, track_indices })
+}
+
+/// 原地修改一条曲目记录的 volume_gain，无需重建整个数据库
+pub fn set_track_gain(data: &mut [u8], track: &ParsedTrack, gain: u32) {
+    data[track.offset + 16..track.offset + 20].copy_from_slice(&gain.to_le_bytes());
+}
+
+/// 读取并解析设备上的 iTunesSD
+pub fn read_device_db(base: &Path) -> Result<ParsedDatabase, String> {
+    let db_path = base.join("iPod_Control").join("iTunes").join("iTunesSD");
+    let data = fs::read(&db_path).map_err(|e| format!("无法读取 iTunesSD: {}", e))?;
+    parse_itunes_sd(&data)
+}
+
+/// 解析现有的 iTunesSD 二进制数据库
+pub fn parse_itunes_sd(data: &[u8]) -> Result<ParsedDatabase, String> {
+    expect_magic(data, 0, b"bdhs")?;
+    let num_tracks = read_u32_at(data, 12)? as usize;
+    let track_header = read_u32_at(data, 36)? as usize;
+
+    let mut reserved = ReservedFields {
+        db_header: extract_reserved(data, 0, DB_HEADER_RESERVED),
+        ..Default::default()
+    };
+    let mut tracks = Vec::with_capacity(num_tracks);
+    if num_tracks > 0 {
+        expect_magic(data, track_header, b"hths")?;
+        reserved.track_header = extract_reserved(data, track_header, TRACK_HEADER_RESERVED);
+        for i in 0..num_tracks {
+            let offset = read_u32_at(data, track_header + 20 + i * 4)? as usize;
+            let track = parse_track_record(data, offset)?;
+            if let Some(saved) = extract_reserved(data, offset, TRACK_RESERVED) {
+                reserved.tracks.insert((track.filename.clone(), track.start_at_pos_ms), saved);
+            }
+            tracks.push(track);
+        }
+    }
+
+    let num_playlists = read_u32_at(data, 16)? as usize;
+    let playlist_header = read_u32_at(data, 40)? as usize;
+    let mut playlists = Vec::with_capacity(num_playlists);
+    if num_playlists > 0 {
+        expect_magic(data, playlist_header, b"hphs")?;
+        reserved.playlist_header = extract_reserved(data, playlist_header, PLAYLIST_HEADER_RESERVED);
+        for i in 0..num_playlists {
+            let offset = read_u32_at(data, playlist_header + 20 + i * 4)? as usize;
+            let playlist = parse_playlist_record(data, offset)?;
+            if let Some(saved) = extract_reserved(data, offset, PLAYLIST_RESERVED) {
+                reserved.playlists.insert(playlist.dbid, saved);
+            }
+            playlists.push(playlist);
+        }
+    }
+    let track_voiceover = data.get(29).is_some_and(|&b| b != 0);
+    Ok(ParsedDatabase { track_voiceover, tracks, playlists, reserved })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(filename: &str, dbid: u8) -> TrackInfo {
+        TrackInfo {
+            filename: filename.to_string(),
+            filetype: 1,
+            start_at_pos_ms: 0,
+            stop_at_pos_ms: 180_000,
+            volume_gain: 4,
+            album_id: 7,
+            artist_id: 9,
+            track_num: 3,
+            disc_num: 2,
+            remember: false,
+            dont_skip: true,
+            pregap: 0,
+            postgap: 0,
+            dbid: [dbid; 8],
+        }
+    }
+
+    fn build(tracks: &[TrackInfo], reserved: &ReservedFields) -> Vec<u8> {
+        let playlists = vec![("__master__".to_string(), vec![0, 1]), ("News".to_string(), vec![1])];
+        let news = PlaylistOptions { listtype: Some(ListType::Podcast), ..Default::default() };
+        let options = HashMap::from([("News".to_string(), news)]);
+        build_itunes_sd(tracks, &playlists, true, false, Path::new("/nonexistent"), &options, reserved)
+    }
+
+    #[test]
+    fn parse_reads_back_built_database() {
+        let mut podcast = track("/Podcasts/News/ep1.mp3", 2);
+        podcast.remember = true;
+        podcast.dont_skip = false;
+        podcast.start_at_pos_ms = 60_000;
+        let data = build(&[track("/iPod_Control/Music/a.mp3", 1), podcast], &ReservedFields::default());
+
+        let parsed = parse_itunes_sd(&data).unwrap();
+        assert!(parsed.track_voiceover);
+        assert_eq!(parsed.tracks.len(), 2);
+        let (a, ep) = (&parsed.tracks[0], &parsed.tracks[1]);
+        assert_eq!(a.filename, "/iPod_Control/Music/a.mp3");
+        assert_eq!((a.filetype, a.stop_at_pos_ms, a.volume_gain), (1, 180_000, 4));
+        assert_eq!((a.album_id, a.artist_id, a.track_num, a.disc_num), (7, 9, 3, 2));
+        assert!(a.dont_skip && !a.remember);
+        assert_eq!(a.dbid, [1; 8]);
+        assert_eq!(ep.filename, "/Podcasts/News/ep1.mp3");
+        assert_eq!(ep.start_at_pos_ms, 60_000);
+        assert!(!ep.dont_skip && ep.remember);
+
+        // The podcast playlist goes last
+        let lists: Vec<_> = parsed.playlists.iter().map(|p| (p.dbid, p.listtype, p.track_indices.clone())).collect();
+        assert_eq!(lists, vec![([0; 8], 1, vec![0, 1]), (playlist_dbid("News", false), 3, vec![1])]);
+    }
+
+    #[test]
+    fn reserved_fields_survive_a_rebuild() {
+        let tracks = [track("/iPod_Control/Music/a.mp3", 1), track("/iPod_Control/Music/b.mp3", 2)];
+        let mut data = build(&tracks, &ReservedFields::default());
+        let first_track = read_u32_at(&data, 64 + 20).unwrap() as usize;
+        data[first_track + 300] = 0xAB;
+        data[44] = 0xCD;
+
+        let parsed = parse_itunes_sd(&data).unwrap();
+        assert_eq!(build(&tracks, &parsed.reserved), data);
+    }
+}
@@ -0,0 +1,67 @@
+use md5::{Digest, Md5};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::collation::compare_paths;
+
+// ─── Content deduplication ───────────────────────────────────────────────────
+
+/// 一个与其他文件内容完全相同、不会加入数据库的文件
+pub struct Duplicate {
+    pub path: PathBuf,
+    /// 保留的那个文件
+    pub original: PathBuf,
+    pub size: u64,
+}
+
+fn file_md5(path: &Path) -> io::Result<[u8; 16]> {
+    let mut hasher = Md5::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().into())
+}
+
+/// 保留文件名最短的一个，这样保留的是 "b.wav" 而不是 "b_copy.wav" 或 "b (1).wav"；
+/// 文件名一样长时保留路径较短、排序靠前的
+fn keep_first(a: &Path, b: &Path) -> std::cmp::Ordering {
+    let name_len = |p: &Path| p.file_name().map_or(0, |n| n.to_string_lossy().chars().count());
+    let path_len = |p: &Path| p.as_os_str().len();
+    name_len(a).cmp(&name_len(b))
+        .then_with(|| path_len(a).cmp(&path_len(b)))
+        .then_with(|| compare_paths(a, b))
+}
+
+/// 按大小与 MD5 查找内容相同的文件。每组保留一个（见 `keep_first`），
+/// 其余的作为重复项返回；只有大小相同的文件才会被读取
+pub fn find_duplicates(files: &[PathBuf]) -> Vec<Duplicate> {
+    let mut by_size: HashMap<u64, Vec<&PathBuf>> = HashMap::new();
+    for file in files {
+        if let Ok(meta) = fs::metadata(file) {
+            by_size.entry(meta.len()).or_default().push(file);
+        }
+    }
+    let candidates: Vec<(u64, &PathBuf)> = by_size.into_iter()
+        .filter(|(size, group)| *size > 0 && group.len() > 1)
+        .flat_map(|(size, group)| group.into_iter().map(move |f| (size, f)))
+        .collect();
+    // Unreadable files are left to the tag reader to report
+    let hashed: Vec<(u64, [u8; 16], &PathBuf)> = candidates.par_iter()
+        .filter_map(|&(size, f)| file_md5(f).ok().map(|h| (size, h, f)))
+        .collect();
+
+    let mut groups: HashMap<(u64, [u8; 16]), Vec<&PathBuf>> = HashMap::new();
+    for (size, hash, file) in hashed {
+        groups.entry((size, hash)).or_default().push(file);
+    }
+    let mut duplicates = Vec::new();
+    for ((size, _), mut group) in groups {
+        if group.len() < 2 { continue; }
+        group.sort_by(|a, b| keep_first(a, b));
+        let original = group[0].clone();
+        duplicates.extend(group[1..].iter().map(|&p| Duplicate { path: p.clone(), original: original.clone(), size }));
+    }
+    duplicates.sort_by(|a, b| compare_paths(&a.path, &b.path));
+    duplicates
+}
@@ -0,0 +1,192 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// ─── Device writes: throttling and durability ────────────────────────────────
+
+/// 每次写入的块大小，限速在块之间生效
+const CHUNK: usize = 256 * 1024;
+
+/// 所有线程共享的写入速率上限
+struct Throttle {
+    bytes_per_sec: f64,
+    /// 下一块数据最早可以写入的时间
+    next_slot: Mutex<Instant>,
+}
+
+static THROTTLE: OnceLock<Throttle> = OnceLock::new();
+
+/// 是否在每次写入后执行 fsync（--no-fsync 关闭）
+static FSYNC: AtomicBool = AtomicBool::new(true);
+
+/// 关闭写入后的 fsync
+pub fn disable_fsync() {
+    FSYNC.store(false, Ordering::Relaxed);
+}
+
+/// 是否为 --dry-run 演练：只报告将要进行的修改，不写入或删除设备上的任何文件
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+/// 开启演练模式，需在同步开始前调用
+pub fn enable_dry_run() {
+    DRY_RUN.store(true, Ordering::Relaxed);
+}
+
+pub fn dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+/// 设置写入设备的速率上限 (MB/s)，需在首次写入前调用
+pub fn set_limit(mb_per_sec: f64) {
+    let _ = THROTTLE.set(Throttle {
+        bytes_per_sec: mb_per_sec * 1024.0 * 1024.0,
+        next_slot: Mutex::new(Instant::now()),
+    });
+}
+
+/// 为 `len` 字节预留写入时间，必要时休眠
+fn reserve(len: usize) {
+    let Some(t) = THROTTLE.get() else { return };
+    let wait_until = {
+        let mut next = t.next_slot.lock().unwrap();
+        let start = (*next).max(Instant::now());
+        *next = start + Duration::from_secs_f64(len as f64 / t.bytes_per_sec);
+        start
+    };
+    let now = Instant::now();
+    if wait_until > now {
+        thread::sleep(wait_until - now);
+    }
+}
+
+/// 同步目录项，使新建、重命名的文件在 FAT 上真正落盘。
+/// Windows 无法打开目录句柄，文件本身的 fsync 已经足够
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    if cfg!(windows) { return Ok(()); }
+    fs::File::open(dir)?.sync_all()
+}
+
+fn write_synced(path: &Path, data: &[u8]) -> io::Result<()> {
+    // Truncating a hard-linked file would rewrite the other end of the link too
+    if is_shared(path) {
+        fs::remove_file(path)?;
+    }
+    let mut file = fs::File::create(path)?;
+    if THROTTLE.get().is_some() {
+        for chunk in data.chunks(CHUNK) {
+            reserve(chunk.len());
+            file.write_all(chunk)?;
+        }
+    } else {
+        file.write_all(data)?;
+    }
+    if FSYNC.load(Ordering::Relaxed) {
+        file.sync_all()?;
+    }
+    Ok(())
+}
+
+/// 与 `fs::write` 相同，但遵守 --io-throttle 的速率上限，并在写入后 fsync 文件及其所在目录，
+/// 中途拔线最多丢失正在写入的文件
+pub fn write_file(path: &Path, data: impl AsRef<[u8]>) -> io::Result<()> {
+    write_synced(path, data.as_ref())?;
+    if FSYNC.load(Ordering::Relaxed) {
+        if let Some(parent) = path.parent() {
+            sync_dir(parent)?;
+        }
+    }
+    Ok(())
+}
+
+/// fsync 一个已被其他代码（如标签写入）修改过的文件
+pub fn sync_file(path: &Path) -> io::Result<()> {
+    if !FSYNC.load(Ordering::Relaxed) { return Ok(()); }
+    fs::OpenOptions::new().write(true).open(path)?.sync_all()
+}
+
+/// 先写入同目录下的临时文件再重命名覆盖，中途拔线时旧文件保持完整
+pub fn write_file_atomic(path: &Path, data: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp = path.with_file_name(tmp_name);
+    if let Err(e) = write_synced(&tmp, data.as_ref()) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+    fs::rename(&tmp, path)?;
+    if FSYNC.load(Ordering::Relaxed) {
+        if let Some(parent) = path.parent() {
+            sync_dir(parent)?;
+        }
+    }
+    Ok(())
+}
+
+// ─── Hard-linked files ───────────────────────────────────────────────────────
+
+/// 文件是否以硬链接与其他路径共享数据，如 --simulate-from 链接进来的曲目。
+/// 只在 Unix 上判断，其他平台上 --simulate-from 不使用硬链接
+fn is_shared(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        fs::metadata(path).is_ok_and(|m| m.nlink() > 1)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        false
+    }
+}
+
+/// 原地修改文件（如写入标签）前调用：文件以硬链接与其他路径共享数据时先换成独立的副本，
+/// 修改不会改到链接另一端的音乐库或设备
+pub fn unshare(path: &Path) -> io::Result<()> {
+    if !is_shared(path) { return Ok(()); }
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp = path.with_file_name(tmp_name);
+    if let Err(e) = fs::copy(path, &tmp) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+    fs::rename(&tmp, path)
+}
+
+// ─── Write access check ──────────────────────────────────────────────────────
+
+/// Linux 上 `path` 所在的挂载点是否以只读方式挂载，无法判断时为 `None`
+fn mounted_read_only(path: &Path) -> Option<bool> {
+    let path = fs::canonicalize(path).ok()?;
+    let mountinfo = fs::read_to_string("/proc/self/mountinfo").ok()?;
+    // Fields: id, parent, major:minor, root, mount point, mount options, ...
+    mountinfo.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(' ').collect();
+            let point = fields.get(4)?.replace("\\040", " ");
+            let options = fields.get(5)?;
+            path.starts_with(&point).then(|| (point.len(), options.split(',').any(|o| o == "ro")))
+        })
+        .max_by_key(|&(len, _)| len)
+        .map(|(_, ro)| ro)
+}
+
+/// 按元数据检查能否写入设备（只读属性、只读挂载），不触碰设备。
+/// 实际的写入测试由同步加锁时在 iPod_Control 中创建锁文件完成（见 `device_lock::lock_device`）
+pub fn check_writable(base: &Path) -> Result<(), String> {
+    let control = base.join("iPod_Control");
+    for dir in [base, control.as_path()] {
+        let Ok(meta) = fs::metadata(dir) else { continue };
+        if meta.permissions().readonly() {
+            return Err(format!("{} 是只读的", dir.display()));
+        }
+    }
+    if mounted_read_only(base) == Some(true) {
+        return Err(format!("{} 所在的分区以只读方式挂载", base.display()));
+    }
+    Ok(())
+}
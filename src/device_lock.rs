@@ -0,0 +1,161 @@
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+use crate::i18n::tr;
+
+// ─── Device lock ─────────────────────────────────────────────────────────────
+
+/// 写入设备期间持有的锁文件，放在 iPod_Control 中
+pub const LOCK_FILE: &str = ".ipod_shuffle.lock";
+
+/// 文件系统不支持文件锁时，无法检查持有者进程（如持有者在另一台主机上）的锁超过这个时长视为过期
+const STALE_AFTER: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// 持有到进程退出；进程以任何方式结束时操作系统都会释放文件锁
+static HELD: OnceLock<File> = OnceLock::new();
+
+/// 无法锁定设备的原因
+pub enum LockError {
+    /// 无法创建或写入锁文件，即设备不可写入。锁文件是同步在设备上写入的第一个文件，同时充当写入测试
+    Unwritable(String),
+    /// 另一个进程正在写入设备
+    Busy(String),
+}
+
+fn lock_path(base: &Path) -> PathBuf {
+    let control = base.join("iPod_Control");
+    if control.is_dir() { control.join(LOCK_FILE) } else { base.join(LOCK_FILE) }
+}
+
+fn host_name() -> String {
+    std::env::var("HOSTNAME").or_else(|_| std::env::var("COMPUTERNAME"))
+        .or_else(|_| fs::read_to_string("/etc/hostname").map(|h| h.trim().to_string()))
+        .unwrap_or_default()
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// 锁文件记录的持有者：进程号、主机名与加锁时间
+struct Holder {
+    pid: u32,
+    host: String,
+    since: u64,
+}
+
+impl Holder {
+    fn current() -> Self {
+        Holder { pid: std::process::id(), host: host_name(), since: now_secs() }
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let mut holder = Holder { pid: 0, host: String::new(), since: 0 };
+        for line in text.lines() {
+            match line.split_once(' ') {
+                Some(("pid", v)) => holder.pid = v.trim().parse().ok()?,
+                Some(("host", v)) => holder.host = v.trim().to_string(),
+                Some(("since", v)) => holder.since = v.trim().parse().ok()?,
+                _ => {}
+            }
+        }
+        (holder.pid != 0).then_some(holder)
+    }
+
+    fn describe(&self) -> String {
+        let age = now_secs().saturating_sub(self.since);
+        tr!("进程号 {}，主机 {}，已持有 {} 分钟", self.pid, self.host, age / 60)
+    }
+
+    /// 持有者所在的进程是否已经结束。只能检查本机进程，其他主机只看时长
+    fn is_stale(&self) -> bool {
+        if self.host == host_name() && cfg!(any(unix, windows)) {
+            return !process_alive(self.pid);
+        }
+        now_secs().saturating_sub(self.since) > STALE_AFTER.as_secs()
+    }
+}
+
+/// 本机上进程号为 `pid` 的进程是否还在运行
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    use nix::errno::Errno;
+    use nix::sys::signal::kill;
+    use nix::unistd::Pid;
+    let Ok(pid) = i32::try_from(pid) else { return false };
+    // No signal is sent, only checked; EPERM means the process exists but belongs to another user
+    matches!(kill(Pid::from_raw(pid), None), Ok(()) | Err(Errno::EPERM))
+}
+
+/// 本机上进程号为 `pid` 的进程是否还在运行
+#[cfg(windows)]
+fn process_alive(pid: u32) -> bool {
+    use windows_sys::Win32::Foundation::{CloseHandle, GetLastError, ERROR_ACCESS_DENIED, STILL_ACTIVE};
+    use windows_sys::Win32::System::Threading::{GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION};
+    // SAFETY: the handle is checked before use and closed once
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle.is_null() {
+            // Access is denied for a running process of another user, other errors mean there is no such process
+            return GetLastError() == ERROR_ACCESS_DENIED;
+        }
+        let mut code = 0;
+        let alive = GetExitCodeProcess(handle, &mut code) == 0 || code == STILL_ACTIVE as u32;
+        CloseHandle(handle);
+        alive
+    }
+}
+
+/// 其他平台无法检查进程，锁只按 `STALE_AFTER` 过期
+#[cfg(not(any(unix, windows)))]
+fn process_alive(_pid: u32) -> bool {
+    true
+}
+
+/// 锁定设备，防止两个进程（如 --watch 与手动运行的同步）同时写入同一台设备。
+/// 另一个进程已持有锁时返回 `Busy`，说明中包含锁文件记录的持有者；无法创建或写入锁文件时返回 `Unwritable`。
+/// 文件系统不支持文件锁时退而检查锁文件记录的进程是否还在运行，已结束的视为过期的锁
+pub fn lock_device(base: &Path) -> Result<(), LockError> {
+    let path = lock_path(base);
+    let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path)
+        .map_err(|e| LockError::Unwritable(tr!("无法创建锁文件 {}: {}", path.display(), e)))?;
+    let previous = fs::read_to_string(&path).ok().and_then(|t| Holder::parse(&t));
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            let holder = previous.map_or_else(|| tr!("未知进程"), |h| h.describe());
+            return Err(LockError::Busy(tr!("设备正被另一个进程写入（{}），锁文件: {}", holder, path.display())));
+        }
+        Err(TryLockError::Error(e)) => {
+            debug!("{}", tr!("无法对 {} 加文件锁（{}），改为检查锁文件中记录的进程", path.display(), e));
+            if let Some(holder) = previous {
+                if !holder.is_stale() {
+                    return Err(LockError::Busy(tr!(
+                        "设备正被另一个进程写入（{}），锁文件: {}。确认没有其他同步在运行时可以删除该文件",
+                        holder.describe(), path.display(),
+                    )));
+                }
+                warn!("{}", tr!("已清除过期的锁（{}）", holder.describe()));
+            }
+        }
+    }
+    let current = Holder::current();
+    file.set_len(0)
+        .and_then(|_| write!(file, "pid {}\nhost {}\nsince {}\n", current.pid, current.host, current.since))
+        .and_then(|_| file.sync_all())
+        .map_err(|e| LockError::Unwritable(tr!("无法写入锁文件 {}: {}", path.display(), e)))?;
+    let _ = HELD.set(file);
+    Ok(())
+}
+
+/// 正常结束时清空锁文件中的持有者，使不支持文件锁的文件系统上下次运行不必等待过期
+pub fn release() {
+    if let Some(mut file) = HELD.get() {
+        let _ = file.set_len(0);
+        let _ = file.flush();
+    }
+}
@@ -0,0 +1,184 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::outln;
+use crate::cli::AUDIO_EXT;
+use crate::converters::load_converters;
+use crate::database::{parse_itunes_sd, MAX_FILENAME_BYTES};
+use crate::i18n::{self, tr};
+use crate::profiles::load_host_config;
+use crate::speakable::check_speakable;
+use crate::utils::{ext_lower, is_system_hidden, path_to_ipod};
+
+// ─── doctor subcommand ───────────────────────────────────────────────────────
+
+/// 常见但 iPod Shuffle 无法播放、同步时也不会自动转换的音频格式（除非配置了外部转换程序）
+const UNSUPPORTED_EXT: &[&str] = &[
+    ".ogg", ".oga", ".opus", ".wma", ".aif", ".aiff", ".ape", ".wv", ".mpc", ".alac", ".dsf", ".mka",
+];
+
+/// 一类问题：标题、每处问题的描述与修复建议
+pub struct Diagnosis {
+    pub title: &'static str,
+    pub items: Vec<String>,
+    pub fix: &'static str,
+}
+
+/// 诊断结果，只包含发现了问题的类别
+#[derive(Default)]
+pub struct DoctorReport {
+    pub diagnoses: Vec<Diagnosis>,
+    pub audio_files: usize,
+    pub db_tracks: Option<usize>,
+}
+
+impl DoctorReport {
+    fn add(&mut self, title: &'static str, items: Vec<String>, fix: &'static str) {
+        if !items.is_empty() {
+            self.diagnoses.push(Diagnosis { title, items, fix });
+        }
+    }
+
+    pub fn problem_count(&self) -> usize {
+        self.diagnoses.iter().map(|d| d.items.len()).sum()
+    }
+}
+
+fn relative(path: &Path, base: &Path) -> String {
+    path.strip_prefix(base).unwrap_or(path).display().to_string()
+}
+
+/// 检查设备上的音频文件：零字节、iPod 路径超出数据库文件名字段、无法播放的格式。
+/// Speakable 中的旁白由旁白检查负责
+fn check_files(base: &Path, report: &mut DoctorReport) {
+    let speakable = base.join("iPod_Control").join("Speakable");
+    // Formats with a configured external converter are converted on the next build
+    let converters = load_host_config().and_then(|c| load_converters(c.as_ref())).unwrap_or_default();
+    let (mut zero_byte, mut long_paths, mut unsupported) = (Vec::new(), Vec::new(), Vec::new());
+    let walker = WalkDir::new(base).into_iter()
+        .filter_entry(|e| {
+            e.depth() == 0 || (!is_system_hidden(&e.file_name().to_string_lossy()) && e.path() != speakable)
+        });
+    for entry in walker.filter_map(|e| e.ok()).filter(|e| e.file_type().is_file()) {
+        let path = entry.path();
+        let ext = ext_lower(path);
+        if UNSUPPORTED_EXT.contains(&ext.as_str()) && !converters.contains_key(&ext) {
+            unsupported.push(relative(path, base));
+            continue;
+        }
+        if !AUDIO_EXT.contains(&ext.as_str()) { continue; }
+
+        report.audio_files += 1;
+        if entry.metadata().map(|m| m.len()).unwrap_or(0) == 0 {
+            zero_byte.push(relative(path, base));
+        }
+        if let Ok(ipod_path) = path_to_ipod(path, base) {
+            if ipod_path.len() > MAX_FILENAME_BYTES {
+                long_paths.push(tr!("{}（{} 字节）", ipod_path, ipod_path.len()));
+            }
+        }
+    }
+    report.add("零字节的音频文件", zero_byte,
+        "文件在复制时中断，请删除后重新复制到设备，再运行 build");
+    report.add("路径超过 256 字节的曲目", long_paths,
+        "数据库的文件名字段只有 256 字节，超出的路径会被截断而无法播放；请缩短目录或文件名后运行 build");
+    report.add("不支持的音频格式", unsupported,
+        "iPod Shuffle 只能播放 MP3、AAC 与 WAV，FLAC 会在同步时自动转换；请先将这些文件转换为 MP3 或 FLAC，或在电脑上的 profiles.toml 的 [converters] 中为该格式指定转换程序");
+}
+
+/// 检查 iTunesSD 是否存在、能否解析，以及是否引用了设备上已不存在的文件
+fn check_database(base: &Path, report: &mut DoctorReport) -> bool {
+    let db_path = base.join("iPod_Control").join("iTunes").join("iTunesSD");
+    let data = match fs::read(&db_path) {
+        Ok(d) => d,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            report.add("缺少数据库", vec![relative(&db_path, base)], "运行 build 生成数据库");
+            return false;
+        }
+        Err(e) => {
+            report.add("无法读取数据库", vec![tr!("{}: {}", relative(&db_path, base), e)],
+                "运行 check-device 检查设备是否有坏扇区，再运行 build 重建数据库");
+            return false;
+        }
+    };
+    let db = match parse_itunes_sd(&data) {
+        Ok(db) => db,
+        Err(e) => {
+            report.add("数据库已损坏", vec![e], "运行 build 重建数据库");
+            return false;
+        }
+    };
+    report.db_tracks = Some(db.tracks.len());
+    let missing = db.tracks.iter()
+        .filter(|t| !base.join(t.filename.trim_start_matches('/')).is_file())
+        .map(|t| t.filename.clone())
+        .collect();
+    report.add("数据库中指向不存在文件的曲目", missing,
+        "这些文件已被删除或移动，运行 build 重建数据库；被移动的曲目会按文件指纹识别并沿用原有记录");
+    true
+}
+
+/// 检查 Speakable：缺失或损坏的旁白、不再被数据库引用的旁白与目录布局
+fn check_voiceovers(base: &Path, report: &mut DoctorReport) {
+    let speakable = match check_speakable(base, false) {
+        Ok(s) => s,
+        Err(e) => {
+            report.add("无法检查语音旁白", vec![e], "运行 build 重建数据库与旁白");
+            return;
+        }
+    };
+    let broken = speakable.missing.iter()
+        .map(|item| tr!("{}: {}（缺失）", item.label, relative(&item.path, base)))
+        .chain(speakable.corrupt.iter().map(|(item, e)| tr!("{}: {}（{}）", item.label, relative(&item.path, base), e)))
+        .collect();
+    report.add("缺失或损坏的语音旁白", broken, "运行 check-voiceover --repair 只重新生成这些旁白");
+    let orphaned = speakable.orphaned.iter().map(|p| relative(p, base)).collect();
+    report.add("不再使用的语音旁白", orphaned,
+        "这些旁白没有被数据库引用，只占用空间；运行 build --tts-resume 会删除它们并保留其余旁白");
+    let layout = speakable.layout.iter().filter(|i| i.fix.is_some()).map(|i| i.describe(base)).collect();
+    report.add("旁白目录布局的问题", layout, "运行 check-voiceover --repair 更正目录与文件名");
+}
+
+/// 只读诊断设备上的常见问题，并为每类问题给出修复建议
+pub fn diagnose(base: &Path) -> DoctorReport {
+    let mut report = DoctorReport::default();
+    let control = base.join("iPod_Control");
+    if !control.is_dir() {
+        report.add("缺少 iPod_Control 目录", vec![base.display().to_string()],
+            "确认给出的是 iPod 的挂载点；全新或格式化过的设备请先创建 iPod_Control 目录，再运行 build");
+        return report;
+    }
+    if !control.join("iTunes").is_dir() {
+        report.add("缺少 iPod_Control/iTunes 目录", vec![relative(&control.join("iTunes"), base)],
+            "运行 build 会创建该目录与数据库");
+    }
+
+    check_files(base, &mut report);
+    if check_database(base, &mut report) {
+        check_voiceovers(base, &mut report);
+    }
+    report
+}
+
+/// 打印诊断结果
+pub fn print_doctor_report(report: &DoctorReport) {
+    outln!("{}", tr!("已检查 {} 个音频文件", report.audio_files));
+    if let Some(n) = report.db_tracks {
+        outln!("{}", tr!("iTunesSD 中有 {} 首曲目", n));
+    }
+    for diagnosis in &report.diagnoses {
+        outln!();
+        outln!("{} ({}):", i18n::text(diagnosis.title), diagnosis.items.len());
+        for item in &diagnosis.items {
+            outln!("  [!] {}", item);
+        }
+        outln!("  {}", tr!("建议: {}", i18n::text(diagnosis.fix)));
+    }
+    outln!();
+    match report.problem_count() {
+        0 => outln!("{}", i18n::text("没有发现问题。")),
+        n => outln!("{}", tr!("发现 {} 个问题，请按上面的建议处理。", n)),
+    }
+}
@@ -0,0 +1,68 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::progress;
+
+// ─── JSON log events ─────────────────────────────────────────────────────────
+
+/// --log-json 时为 true：同步过程中的事件以 JSON 行写到标准错误
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// 开始输出事件，须在第一次输出之前调用
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// 一行事件，如 `{"event":"track_added","index":0,"path":"/iPod_Control/Music/a.mp3"}`。
+/// 事件名与字段名不随 --lang 变化
+fn line(event: &str, mut fields: serde_json::Value) -> String {
+    fields["event"] = event.into();
+    fields.to_string()
+}
+
+/// 在标准错误输出一行事件
+fn emit(event: &str, fields: serde_json::Value) {
+    if !ENABLED.load(Ordering::Relaxed) { return; }
+    let line = line(event, fields);
+    progress::suspend(|| eprintln!("{}", line));
+}
+
+/// 一首曲目加入数据库：`index` 为在主列表中的位置，`path` 为数据库中的 iPod 路径
+pub fn track_added(index: usize, path: &str) {
+    emit("track_added", serde_json::json!({ "index": index, "path": path }));
+}
+
+/// 开始将 `source`（iPod 路径）转换为 MP3
+pub fn conversion_started(source: &str) {
+    emit("conversion_started", serde_json::json!({ "source": source }));
+}
+
+/// `source` 已转换为 `output`（均为 iPod 路径）
+pub fn conversion_finished(source: &str, output: &str) {
+    emit("conversion_finished", serde_json::json!({ "source": source, "output": output }));
+}
+
+/// `source` 转换失败，源文件保留在设备上；`reason` 为转换程序或编码器给出的错误
+pub fn conversion_failed(source: &str, reason: &str) {
+    emit("conversion_failed", serde_json::json!({ "source": source, "reason": reason }));
+}
+
+/// 新合成了一条语音旁白：`file` 为 WAV 文件的路径，`text` 为朗读的文字
+pub fn voiceover_generated(file: &Path, text: &str, voice: &str) {
+    emit("voiceover_generated", serde_json::json!({
+        "file": file.to_string_lossy(), "text": text, "voice": voice,
+    }));
+}
+
+/// 数据库已写入设备
+pub fn db_written(path: &Path, tracks: usize, playlists: usize) {
+    emit("db_written", serde_json::json!({
+        "path": path.to_string_lossy(), "tracks": tracks, "playlists": playlists,
+    }));
+}
+
+/// 一条日志的事件行，代替终端上的文字日志：`level` 为 "error"、"warn"、"info" 或 "debug"，
+/// `message` 为按 --lang 输出的文字
+pub fn log_line(level: &str, message: &str) -> String {
+    line("log", serde_json::json!({ "level": level, "message": message }))
+}
@@ -0,0 +1,24 @@
+// ─── Exit codes ──────────────────────────────────────────────────────────────
+
+/// 成功
+pub const SUCCESS: i32 = 0;
+/// 一般错误；verify、check-device、check-voiceover 发现问题，或 search 没有结果
+pub const FAILURE: i32 = 1;
+/// 命令行参数、配置文件或预设有误；check-voiceover 与 search 无法读取设备数据时也使用此状态码
+pub const USAGE: i32 = 2;
+/// 找不到 iPod 目录，或目录看起来不像 iPod
+pub const DEVICE_NOT_FOUND: i32 = 3;
+/// 无法写入 iPod 目录
+pub const PERMISSION_DENIED: i32 = 4;
+/// 写入 iTunesSD 失败
+pub const DB_WRITE_FAILED: i32 = 5;
+/// --strict：有文件转换失败
+pub const CONVERSION_FAILED: i32 = 6;
+/// --strict：有播放列表被跳过或其中的曲目找不到
+pub const PLAYLIST_FAILED: i32 = 7;
+/// --strict：同步中出现了其他警告
+pub const WARNINGS: i32 = 8;
+/// 另一个进程正在写入该设备（见 `device_lock`）
+pub const DEVICE_BUSY: i32 = 9;
+/// 被 Ctrl+C 中断
+pub const INTERRUPTED: i32 = 130;
@@ -0,0 +1,38 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::playlist::parse_m3u;
+use crate::stats::played_tracks;
+
+// ─── On-device favorites ─────────────────────────────────────────────────────
+
+pub const FAVORITES_FILE: &str = "Favorites.m3u";
+
+/// 根据 iTunesStats 将设备上完整播放过的曲目累积到根目录的 `Favorites.m3u`。
+///
+/// 须在重建数据库之前调用（见 `played_tracks`）。返回收藏列表路径与本次新增的曲目数
+pub fn update_favorites(base: &Path) -> Result<(PathBuf, usize), String> {
+    let played = played_tracks(base)?;
+
+    let fav_path = base.join(FAVORITES_FILE);
+    let existing = fs::read_to_string(&fav_path).unwrap_or_default();
+    let mut entries: Vec<String> = parse_m3u(&existing, false);
+
+    let mut added = 0;
+    for filename in &played {
+        let entry = filename.trim_start_matches('/').to_string();
+        if !entries.contains(&entry) {
+            entries.push(entry);
+            added += 1;
+        }
+    }
+    entries.retain(|e| base.join(e).exists());
+
+    let mut content = String::from("#EXTM3U\n");
+    for e in &entries {
+        content.push_str(e);
+        content.push('\n');
+    }
+    fs::write(&fav_path, content).map_err(|e| format!("无法写入 {}: {}", fav_path.display(), e))?;
+    Ok((fav_path, added))
+}
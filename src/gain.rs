@@ -0,0 +1,329 @@
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use walkdir::WalkDir;
+use tracing::{debug, warn};
+
+use crate::outln;
+use crate::audio::estimate_track_loudness_db;
+use crate::cancel;
+use crate::cli::{GainArgs, GainRuleArgs, LoudnessArgs, AUDIO_EXT};
+use crate::collation::compare_paths;
+use crate::database::{parse_itunes_sd, set_track_gain};
+use crate::device_io::{dry_run, write_file, write_file_atomic};
+use crate::timeout::run_with_timeout;
+use crate::metadata::{read_all_meta, TrackMeta};
+use crate::progress;
+use crate::utils::{ext_lower, is_subpath, looks_like_ipod, normalize_path};
+
+// ─── Loudness analysis and track gain ────────────────────────────────────────
+
+/// 响度分析结果的缓存文件。文件大小和修改时间未变的曲目不再重新解码
+const LOUDNESS_CACHE: &str = "loudness.tsv";
+
+/// 响度分析的结果
+#[derive(Default)]
+pub struct LoudnessAnalysis {
+    /// 成功估算的曲目响度 (dBFS)
+    pub loudness: HashMap<PathBuf, f64>,
+    /// 解码超时或崩溃的曲目及原因
+    pub failures: Vec<(PathBuf, String)>,
+}
+
+/// 缓存条目：文件大小、修改时间、分析参数与响度。参数不同的旧结果视为过期
+#[derive(Clone, Copy)]
+struct CacheEntry {
+    size: u64,
+    mtime: u64,
+    window_ms: u64,
+    floor_db: f64,
+    db: f64,
+}
+
+/// 文件大小与修改时间
+fn fingerprint(path: &Path) -> Option<(u64, u64)> {
+    let meta = fs::metadata(path).ok()?;
+    let mtime = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some((meta.len(), mtime))
+}
+
+/// 设备上缓存放在 iPod_Control/iTunes 中，本地曲库放在根目录的隐藏文件中
+fn cache_path(root: &Path) -> PathBuf {
+    if looks_like_ipod(root) {
+        root.join("iPod_Control").join("iTunes").join(LOUDNESS_CACHE)
+    } else {
+        root.join(format!(".ipod_{}", LOUDNESS_CACHE))
+    }
+}
+
+fn load_cache(root: &Path) -> HashMap<String, CacheEntry> {
+    let text = fs::read_to_string(cache_path(root)).unwrap_or_default();
+    text.lines()
+        .filter_map(|line| {
+            let f: Vec<&str> = line.split('\t').collect();
+            let [rel, size, mtime, window_ms, floor_db, db] = f[..] else { return None };
+            Some((rel.to_string(), CacheEntry {
+                size: size.parse().ok()?,
+                mtime: mtime.parse().ok()?,
+                window_ms: window_ms.parse().ok()?,
+                floor_db: floor_db.parse().ok()?,
+                db: db.parse().ok()?,
+            }))
+        })
+        .collect()
+}
+
+fn save_cache(root: &Path, entries: &HashMap<String, CacheEntry>) {
+    let mut keys: Vec<&String> = entries.keys().collect();
+    keys.sort();
+    let mut text = String::new();
+    for k in keys {
+        let e = entries[k];
+        text.push_str(&format!("{}\t{}\t{}\t{}\t{}\t{}\n", k, e.size, e.mtime, e.window_ms, e.floor_db, e.db));
+    }
+    if let Err(e) = write_file(&cache_path(root), text) {
+        warn!("无法写入响度缓存: {}", e);
+    }
+}
+
+fn cache_key(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root).unwrap_or(path).to_string_lossy().replace('\\', "/")
+}
+
+/// 将 (原路径, 新路径) 中原路径的缓存响度转给新路径，移动过的文件不必重新分析
+pub fn carry_over_loudness(root: &Path, moves: &[(PathBuf, PathBuf)]) {
+    let mut cache = load_cache(root);
+    let mut changed = false;
+    for (from, to) in moves {
+        if let Some(entry) = cache.remove(&cache_key(from, root)) {
+            cache.insert(cache_key(to, root), entry);
+            changed = true;
+        }
+    }
+    if changed && !dry_run() {
+        save_cache(root, &cache);
+    }
+}
+
+/// 并发估算 `tracks` 的响度，单个文件的解码受 `limit` 限制。
+/// 结果缓存在 `root` 中，文件与分析参数都未变化时直接使用缓存值
+pub fn analyze_loudness(root: &Path, tracks: &[PathBuf], limit: Duration, opts: &LoudnessArgs) -> LoudnessAnalysis {
+    let window_ms = opts.window.as_millis() as u64;
+    let window = opts.window.as_secs_f64();
+    let floor_db = opts.floor_db;
+    let mut cache = load_cache(root);
+    let mut result = LoudnessAnalysis::default();
+    let mut pending = Vec::new();
+    for t in tracks {
+        let fp = fingerprint(t);
+        match (cache.get(&cache_key(t, root)), fp) {
+            (Some(e), Some(fp)) if (e.size, e.mtime) == fp && e.window_ms == window_ms && e.floor_db == floor_db => {
+                result.loudness.insert(t.clone(), e.db);
+            }
+            _ => pending.push((t.clone(), fp)),
+        }
+    }
+    if tracks.len() > pending.len() {
+        debug!("使用缓存的响度: {} 首", tracks.len() - pending.len());
+    }
+    if pending.is_empty() {
+        return result;
+    }
+
+    let bar = progress::bar("分析响度", pending.len());
+    let analyzed: Vec<_> = pending.into_par_iter()
+        .map(|(track, fp)| {
+            if cancel::requested() {
+                bar.inc(1);
+                return (track, None, Ok(None));
+            }
+            let path = track.clone();
+            let r = run_with_timeout(limit, move || estimate_track_loudness_db(&path, window, floor_db))
+                .map_err(|e| e.describe("解码"));
+            bar.inc(1);
+            (track, fp, r)
+        })
+        .collect();
+    bar.finish_and_clear();
+
+    for (track, fp, r) in analyzed {
+        match r {
+            Ok(Some(db)) => {
+                if let Some((size, mtime)) = fp {
+                    cache.insert(cache_key(&track, root), CacheEntry { size, mtime, window_ms, floor_db, db });
+                }
+                result.loudness.insert(track, db);
+            }
+            Ok(None) => {}
+            Err(e) => result.failures.push((track, e)),
+        }
+    }
+    cache.retain(|k, _| root.join(k).is_file());
+    if !dry_run() {
+        save_cache(root, &cache);
+    }
+    result
+}
+
+/// 将响度换算为增益：与参考响度相差多少 dB 就提升多少，并限制在 `range` 内。
+/// `reference` 为 `None` 时以最响的曲目为参考。返回增益表与实际使用的参考响度
+pub fn compute_gains(
+    loudness: &HashMap<PathBuf, f64>, reference: Option<f64>, (min, max): (u32, u32),
+) -> (HashMap<PathBuf, u32>, f64) {
+    let reference = reference.unwrap_or_else(|| loudness.values().cloned().fold(f64::NEG_INFINITY, f64::max));
+    let gains = loudness.iter()
+        .map(|(track, db)| (track.clone(), ((reference - db).round().max(0.0) as u32).clamp(min, max)))
+        .collect();
+    (gains, reference)
+}
+
+/// 不参与自动增益的曲目规则：流派或所在目录匹配时保留原本的动态
+pub struct GainRules {
+    genres: Vec<String>,
+    dirs: Vec<PathBuf>,
+}
+
+impl GainRules {
+    pub fn new(base: &Path, args: &GainRuleArgs) -> Self {
+        GainRules {
+            genres: args.genres.iter().map(|g| g.to_lowercase()).collect(),
+            dirs: args.dirs.iter().map(|d| normalize_path(&base.join(d))).collect(),
+        }
+    }
+
+    pub fn needs_genre(&self) -> bool {
+        !self.genres.is_empty()
+    }
+
+    pub fn skips(&self, track: &Path, meta: Option<&TrackMeta>) -> bool {
+        if self.dirs.iter().any(|d| is_subpath(track, d)) {
+            return true;
+        }
+        meta.and_then(|m| m.genre.as_deref())
+            .is_some_and(|g| self.genres.contains(&g.trim().to_lowercase()))
+    }
+
+    /// 将曲目分为 (参与自动增益的, 跳过的)
+    pub fn partition(&self, tracks: &[PathBuf], metadata: &HashMap<PathBuf, TrackMeta>) -> (Vec<PathBuf>, Vec<PathBuf>) {
+        tracks.iter().cloned().partition(|t| !self.skips(t, metadata.get(t)))
+    }
+}
+
+// ─── gain subcommand ─────────────────────────────────────────────────────────
+
+fn itunes_sd_path(base: &Path) -> PathBuf {
+    base.join("iPod_Control").join("iTunes").join("iTunesSD")
+}
+
+/// 目录中所有待分析的音频文件（跳过隐藏项与语音旁白）
+fn collect_audio(dir: &Path) -> Vec<PathBuf> {
+    let speakable = dir.join("iPod_Control").join("Speakable");
+    let mut files: Vec<PathBuf> = WalkDir::new(dir).into_iter()
+        .filter_entry(|e| e.depth() == 0 || (!e.file_name().to_string_lossy().starts_with('.') && e.path() != speakable))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && AUDIO_EXT.contains(&ext_lower(e.path()).as_str()))
+        .map(|e| e.into_path())
+        .collect();
+    files.sort_by(|a, b| compare_paths(a, b));
+    files
+}
+
+fn report_failures(failures: &[(PathBuf, String)]) {
+    for (t, reason) in failures {
+        warn!("无法分析 {}（{}），跳过。", t.display(), reason);
+    }
+}
+
+/// `gain analyze`：分析目录（设备或本地曲库）中音频的响度，打印每首曲目将获得的增益
+pub fn analyze_dir(dir: &Path, args: &GainArgs) -> Result<(), String> {
+    let files = collect_audio(dir);
+    if files.is_empty() {
+        return Err(format!("\"{}\" 中没有音频文件", dir.display()));
+    }
+    let (files, skipped) = split_by_rules(dir, &files, args);
+    outln!("正在分析 {} 个文件的响度...", files.len());
+    let analysis = analyze_loudness(dir, &files, args.file_timeout, &args.loudness);
+    report_failures(&analysis.failures);
+    if analysis.loudness.is_empty() {
+        return Err("未能分析任何曲目的响度".to_string());
+    }
+
+    let (gains, reference) = compute_gains(&analysis.loudness, args.reference, args.loudness.range);
+    outln!("  增益          响度  文件");
+    for f in &files {
+        let rel = f.strip_prefix(dir).unwrap_or(f);
+        match analysis.loudness.get(f) {
+            Some(db) => outln!("{:>6}  {:>9.2} dBFS  {}", gains[f], db, rel.display()),
+            None => outln!("{:>6}  {:>14}  {}", "-", "估算失败", rel.display()),
+        }
+    }
+    for f in &skipped {
+        outln!("{:>6}  {:>14}  {}", "-", "按规则跳过", f.strip_prefix(dir).unwrap_or(f).display());
+    }
+    outln!("参考响度 {:.2} dBFS，{}/{} 首曲目需要提升增益",
+        reference, gains.values().filter(|&&g| g > 0).count(), gains.len());
+    Ok(())
+}
+
+/// `gain apply`：分析设备数据库中的曲目，并将增益直接写入现有的 iTunesSD
+/// 按 --no-gain-* 规则将曲目分为 (参与分析的, 跳过的)
+fn split_by_rules(root: &Path, files: &[PathBuf], args: &GainArgs) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let rules = GainRules::new(root, &args.rules);
+    let metadata = if rules.needs_genre() {
+        read_all_meta(files, args.file_timeout).0
+    } else {
+        HashMap::new()
+    };
+    rules.partition(files, &metadata)
+}
+
+/// `gain apply`：分析设备数据库中的曲目，并将增益直接写入现有的 iTunesSD。
+/// 按规则跳过的曲目增益设为 0
+pub fn apply_to_device(base: &Path, args: &GainArgs) -> Result<(), String> {
+    let db_path = itunes_sd_path(base);
+    let mut data = fs::read(&db_path).map_err(|e| format!("无法读取 iTunesSD: {}", e))?;
+    let parsed = parse_itunes_sd(&data)?;
+    let paths: Vec<PathBuf> = parsed.tracks.iter()
+        .map(|t| base.join(t.filename.trim_start_matches('/')))
+        .collect();
+    let (analyzed, skipped) = split_by_rules(base, &paths, args);
+
+    outln!("正在分析 {} 首曲目的响度...", analyzed.len());
+    let analysis = analyze_loudness(base, &analyzed, args.file_timeout, &args.loudness);
+    report_failures(&analysis.failures);
+    if analysis.loudness.is_empty() {
+        return Err("未能分析任何曲目的响度".to_string());
+    }
+    let (mut gains, reference) = compute_gains(&analysis.loudness, args.reference, args.loudness.range);
+    gains.extend(skipped.into_iter().map(|p| (p, 0)));
+
+    let mut changed = 0;
+    for (track, path) in parsed.tracks.iter().zip(&paths) {
+        let Some(&gain) = gains.get(path) else { continue };
+        if gain != track.volume_gain {
+            debug!("[~] {} ({} -> {})", track.filename, track.volume_gain, gain);
+            set_track_gain(&mut data, track, gain);
+            changed += 1;
+        }
+    }
+    write_file_atomic(&db_path, &data).map_err(|e| format!("写入 iTunesSD 失败: {}", e))?;
+    outln!("已更新 {} 首曲目的增益（参考响度 {:.2} dBFS）", changed, reference);
+    Ok(())
+}
+
+/// `gain clear`：将设备数据库中所有曲目的增益设为 `gain`
+pub fn clear_device(base: &Path, gain: u32) -> Result<(), String> {
+    let db_path = itunes_sd_path(base);
+    let mut data = fs::read(&db_path).map_err(|e| format!("无法读取 iTunesSD: {}", e))?;
+    let parsed = parse_itunes_sd(&data)?;
+    let mut changed = 0;
+    for track in parsed.tracks.iter().filter(|t| t.volume_gain != gain) {
+        set_track_gain(&mut data, track, gain);
+        changed += 1;
+    }
+    write_file_atomic(&db_path, &data).map_err(|e| format!("写入 iTunesSD 失败: {}", e))?;
+    outln!("已将 {} 首曲目的增益重置为 {}", changed, gain);
+    Ok(())
+}
@@ -0,0 +1,49 @@
+// ─── Generated playlists ─────────────────────────────────────────────────────
+
+/// 睡前播放列表的淡出曲目所在目录（位于 iPod_Control 下，扫描时跳过）
+pub const BEDTIME_DIR: &str = "Bedtime";
+
+/// 将曲目按总时长均衡地分成 `n` 组（最长处理时间优先的贪心分配），
+/// 每组内保持原有顺序
+pub fn split_balanced(indices: &[u32], durations_ms: &[u64], n: usize) -> Vec<Vec<u32>> {
+    let n = n.max(1);
+    let mut order: Vec<u32> = indices.to_vec();
+    order.sort_by_key(|&i| std::cmp::Reverse(durations_ms.get(i as usize).copied().unwrap_or(0)));
+
+    let mut bins: Vec<(u64, Vec<u32>)> = vec![(0, Vec::new()); n];
+    for idx in order {
+        let lightest = bins.iter_mut().min_by_key(|(total, _)| *total).unwrap();
+        lightest.0 += durations_ms.get(idx as usize).copied().unwrap_or(0);
+        lightest.1.push(idx);
+    }
+
+    let position: std::collections::HashMap<u32, usize> = indices.iter()
+        .enumerate()
+        .map(|(pos, &i)| (i, pos))
+        .collect();
+    bins.into_iter()
+        .map(|(_, mut group)| {
+            group.sort_by_key(|i| position[i]);
+            group
+        })
+        .filter(|g| !g.is_empty())
+        .collect()
+}
+
+/// 按顺序选取曲目直到总时长达到 `target_ms`。返回选中的曲目，以及最后一首
+/// 需要截断的位置（毫秒）；恰好不超过目标时不需要截断
+pub fn select_for_duration(indices: &[u32], durations_ms: &[u64], target_ms: u64) -> (Vec<u32>, Option<u64>) {
+    let mut selected = Vec::new();
+    let mut total = 0u64;
+    for &idx in indices {
+        if total >= target_ms { break; }
+        let d = durations_ms.get(idx as usize).copied().unwrap_or(0);
+        if d == 0 { continue; }
+        selected.push(idx);
+        if total + d > target_ms {
+            return (selected, Some(target_ms - total));
+        }
+        total += d;
+    }
+    (selected, None)
+}
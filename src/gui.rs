@@ -0,0 +1,386 @@
+use eframe::egui;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::cli::Lang;
+use crate::database::read_device_db;
+use crate::i18n::{self, tr};
+use crate::listing::{labels_for, listtype_name};
+use crate::task::{Confirmation, SyncTask};
+use crate::utils::looks_like_ipod;
+
+// ─── gui subcommand ──────────────────────────────────────────────────────────
+
+/// 常见的中文字体位置。egui 自带的字体不含中文，找不到时界面中的中文显示为方框
+const CJK_FONTS: &[&str] = &[
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/System/Library/Fonts/PingFang.ttc",
+    "/System/Library/Fonts/STHeiti Medium.ttc",
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "C:\\Windows\\Fonts\\simhei.ttf",
+];
+
+fn install_cjk_font(ctx: &egui::Context) {
+    let Some(data) = CJK_FONTS.iter().find_map(|p| std::fs::read(p).ok()) else { return };
+    let mut fonts = egui::FontDefinitions::default();
+    fonts.font_data.insert("cjk".to_string(), Arc::new(egui::FontData::from_owned(data)));
+    for family in [egui::FontFamily::Proportional, egui::FontFamily::Monospace] {
+        fonts.families.entry(family).or_default().push("cjk".to_string());
+    }
+    ctx.set_fonts(fonts);
+}
+
+/// 在常见的挂载位置中查找 iPod
+fn find_devices() -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = vec![PathBuf::from("/Volumes"), PathBuf::from("/media"), PathBuf::from("/mnt")];
+    // Linux desktops mount removable drives under a per-user directory
+    for parent in ["/media", "/run/media"] {
+        if let Ok(entries) = std::fs::read_dir(parent) {
+            roots.extend(entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()));
+        }
+    }
+    let mut devices: Vec<PathBuf> = roots.iter()
+        .filter_map(|r| std::fs::read_dir(r).ok())
+        .flat_map(|entries| entries.flatten().map(|e| e.path()))
+        .filter(|p| looks_like_ipod(p))
+        .collect();
+    if cfg!(windows) {
+        devices.extend((b'D'..=b'Z').map(|d| PathBuf::from(format!("{}:\\", d as char))).filter(|p| looks_like_ipod(p)));
+    }
+    devices.sort();
+    devices.dedup();
+    devices
+}
+
+/// 设备上现有数据库的内容
+#[derive(Default)]
+struct Library {
+    /// iPod 路径与增益
+    tracks: Vec<(String, u32)>,
+    /// 名称、类型与曲目数
+    playlists: Vec<(String, &'static str, usize)>,
+    error: Option<String>,
+    /// 播放列表的名称需要读取曲目标签反查，在后台读取
+    loading: bool,
+}
+
+impl Library {
+    fn load(base: &Path) -> Self {
+        match read_device_db(base) {
+            Ok(db) => Library {
+                tracks: db.tracks.iter().map(|t| (t.filename.clone(), t.volume_gain)).collect(),
+                playlists: labels_for(base, &db).into_iter().zip(&db.playlists)
+                    .map(|(label, p)| (label, listtype_name(p.listtype), p.track_indices.len()))
+                    .collect(),
+                error: None,
+                loading: false,
+            },
+            Err(e) => Library { error: Some(e), ..Default::default() },
+        }
+    }
+}
+
+struct GuiApp {
+    devices: Vec<PathBuf>,
+    path: String,
+    library: Arc<Mutex<Library>>,
+    library_path: String,
+    track_voiceover: bool,
+    playlist_voiceover: bool,
+    auto_track_gain: bool,
+    track_gain: u32,
+    dry_run: bool,
+    run: Option<SyncTask>,
+    /// 当前（或上一次）运行的是演练
+    run_dry: bool,
+    /// 当前运行的是同步前的演练，结束后列出需要确认的删除
+    preview: bool,
+    /// 等待用户在对话框中确认的删除
+    confirmation: Option<Confirmation>,
+    /// 无法启动同步时的错误
+    run_error: Option<String>,
+    /// 上一次同步结束后是否已重新读取数据库
+    reloaded: bool,
+    lang: Option<Lang>,
+}
+
+impl GuiApp {
+    fn new(lang: Option<Lang>) -> Self {
+        let devices = find_devices();
+        let path = devices.first().map(|p| p.display().to_string()).unwrap_or_default();
+        GuiApp {
+            devices,
+            path,
+            library: Arc::default(),
+            library_path: String::new(),
+            track_voiceover: false,
+            playlist_voiceover: false,
+            auto_track_gain: false,
+            track_gain: 0,
+            dry_run: false,
+            run: None,
+            run_dry: false,
+            preview: false,
+            confirmation: None,
+            run_error: None,
+            reloaded: false,
+            lang,
+        }
+    }
+
+    fn running(&self) -> bool {
+        self.run.as_ref().is_some_and(SyncTask::running)
+    }
+
+    /// 按当前选项组成 build 的命令行
+    fn sync_args(&self, dry_run: bool) -> Vec<String> {
+        let mut args = vec!["build".to_string()];
+        if let Some(lang) = self.lang {
+            args.extend(["--lang".to_string(), if lang == Lang::En { "en" } else { "zh" }.to_string()]);
+        }
+        if self.track_voiceover { args.push("--track-voiceover".to_string()); }
+        if self.playlist_voiceover { args.push("--playlist-voiceover".to_string()); }
+        if self.auto_track_gain { args.push("--auto-track-gain".to_string()); }
+        if self.track_gain > 0 { args.extend(["--track-gain".to_string(), self.track_gain.to_string()]); }
+        // The deletions were confirmed in the dialog after the preview (see finish_preview)
+        args.push(if dry_run { "--dry-run" } else { "--yes" }.to_string());
+        args.push(self.path.clone());
+        args
+    }
+
+    /// 点击"同步"：勾选了演练时直接演练；否则先演练一遍，列出同步将删除的文件请用户确认（见 `finish_preview`）
+    fn sync_clicked(&mut self, ctx: &egui::Context) {
+        self.preview = !self.dry_run;
+        self.start_sync(ctx, true);
+    }
+
+    /// 同步前的演练结束后，有需要确认的删除时打开对话框，没有时直接同步
+    fn finish_preview(&mut self, ctx: &egui::Context) {
+        self.preview = false;
+        let Some(ref run) = self.run else { return };
+        let mut state = run.state();
+        if state.cancelled || !matches!(state.status, Some(Ok(status)) if status.success()) { return; }
+        let confirmation = state.confirm.take();
+        drop(state);
+        match confirmation {
+            Some(c) => self.confirmation = Some(c),
+            None => self.start_sync(ctx, false),
+        }
+    }
+
+    /// 在子进程中运行同步，与 ui/ 中的 Flutter 界面一样调用命令行，
+    /// 因此同步的行为与命令行完全一致，出错退出也不会关闭窗口。
+    /// 子进程的标准输入已关闭，无法询问；需要确认的删除已在演练后的对话框中确认，真正同步时加上 --yes
+    fn start_sync(&mut self, ctx: &egui::Context, dry_run: bool) {
+        self.reloaded = false;
+        self.run_dry = dry_run;
+        let args = self.sync_args(dry_run);
+        let ctx = ctx.clone();
+        let spawned = std::env::current_exe()
+            .map_err(|e| tr!("无法启动同步: {}", e))
+            .and_then(|exe| SyncTask::spawn(&exe, &args, move || ctx.request_repaint()));
+        match spawned {
+            Ok(task) => {
+                task.state().log.insert(0, format!("$ ipod-shuffle-4g {}", args.join(" ")));
+                self.run = Some(task);
+                self.run_error = None;
+            }
+            Err(e) => {
+                self.run = None;
+                self.run_error = Some(e);
+            }
+        }
+    }
+
+    fn reload_library(&mut self, ctx: &egui::Context) {
+        let base = PathBuf::from(&self.path);
+        if !looks_like_ipod(&base) {
+            self.library = Arc::default();
+            return;
+        }
+        // A fresh slot, so a slow read of the previous device cannot overwrite this one
+        let library = Arc::new(Mutex::new(Library { loading: true, ..Default::default() }));
+        self.library = library.clone();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            *library.lock().unwrap() = Library::load(&base);
+            ctx.request_repaint();
+        });
+    }
+
+    fn options(&mut self, ui: &mut egui::Ui) {
+        ui.heading(i18n::text("设备"));
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.path);
+            if ui.button(i18n::text("重新查找")).clicked() {
+                self.devices = find_devices();
+            }
+        });
+        for device in &self.devices {
+            let label = device.display().to_string();
+            if ui.selectable_label(self.path == label, &label).clicked() {
+                self.path = label;
+            }
+        }
+        if self.devices.is_empty() {
+            ui.label(i18n::text("没有找到已挂载的 iPod，请手动输入路径"));
+        }
+
+        ui.separator();
+        ui.heading(i18n::text("选项"));
+        ui.checkbox(&mut self.track_voiceover, i18n::text("曲目旁白"));
+        ui.checkbox(&mut self.playlist_voiceover, i18n::text("播放列表旁白"));
+        ui.checkbox(&mut self.auto_track_gain, i18n::text("自动音量均衡"));
+        ui.add_enabled(!self.auto_track_gain, egui::Slider::new(&mut self.track_gain, 0..=99).text(i18n::text("曲目增益")));
+        ui.checkbox(&mut self.dry_run, i18n::text("演练（不修改设备）"));
+
+        ui.separator();
+        let valid = looks_like_ipod(Path::new(&self.path));
+        let running = self.running();
+        let idle = !running && self.confirmation.is_none();
+        if ui.add_enabled(valid && idle, egui::Button::new(i18n::text("同步"))).clicked() {
+            self.sync_clicked(ui.ctx());
+        }
+        if !valid && !self.path.is_empty() {
+            ui.colored_label(egui::Color32::YELLOW, i18n::text("该目录中没有 iPod_Control"));
+        }
+        if running {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                if ui.button(i18n::text("取消")).clicked() {
+                    if let Some(ref run) = self.run {
+                        run.cancel();
+                    }
+                }
+            });
+        }
+    }
+
+    fn library_view(&self, ui: &mut egui::Ui) {
+        let library = self.library.lock().unwrap();
+        if library.loading {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(i18n::text("正在读取数据库..."));
+            });
+            return;
+        }
+        if let Some(ref e) = library.error {
+            ui.label(e);
+            return;
+        }
+        ui.heading(tr!("播放列表 ({})", library.playlists.len()));
+        for (label, kind, count) in &library.playlists {
+            ui.label(tr!("{} [{}] ({} 首)", label, i18n::text(kind), count));
+        }
+        ui.separator();
+        ui.heading(tr!("曲目 ({})", library.tracks.len()));
+        egui::ScrollArea::vertical().id_salt("tracks").max_height(ui.available_height() / 2.0).show(ui, |ui| {
+            for (name, gain) in &library.tracks {
+                ui.label(tr!("{}  增益: {}", name, gain));
+            }
+        });
+    }
+
+    /// 演练后列出同步将删除的文件，确认后才真正同步
+    fn confirm_dialog(&mut self, ctx: &egui::Context) {
+        let Some(ref confirmation) = self.confirmation else { return };
+        let mut answer = None;
+        egui::Modal::new(egui::Id::new("confirm")).show(ctx, |ui| {
+            ui.set_max_width(560.0);
+            ui.heading(&confirmation.title);
+            egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                for item in &confirmation.items {
+                    ui.label(format!("- {}", item));
+                }
+            });
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button(i18n::text("继续同步")).clicked() { answer = Some(true); }
+                if ui.button(i18n::text("取消")).clicked() { answer = Some(false); }
+            });
+        });
+        match answer {
+            Some(true) => {
+                self.confirmation = None;
+                self.start_sync(ctx, false);
+            }
+            Some(false) => self.confirmation = None,
+            None => {}
+        }
+    }
+
+    fn log_view(&self, ui: &mut egui::Ui) {
+        if let Some(ref e) = self.run_error {
+            ui.colored_label(egui::Color32::RED, e);
+        }
+        let Some(ref run) = self.run else { return };
+        let run = run.state();
+        match run.status {
+            _ if run.cancelled => { ui.colored_label(egui::Color32::YELLOW, i18n::text("已取消")); }
+            Some(Ok(status)) if status.success() => { ui.label(i18n::text(if self.run_dry { "演练完成" } else { "同步完成" })); }
+            Some(Ok(status)) => { ui.colored_label(egui::Color32::RED, tr!("同步失败，退出码 {}", status.code().unwrap_or(-1))); }
+            Some(Err(ref e)) => { ui.colored_label(egui::Color32::RED, e); }
+            None => {}
+        }
+        for phase in &run.phases {
+            let bar = match phase.total {
+                Some(total) if total > 0 => egui::ProgressBar::new(phase.pos as f32 / total as f32)
+                    .text(format!("{}  {}/{}", phase.label, phase.pos, total)),
+                _ if phase.done => egui::ProgressBar::new(1.0).text(phase.label.as_str()),
+                _ => egui::ProgressBar::new(0.0).animate(true).text(phase.label.as_str()),
+            };
+            ui.add(bar);
+        }
+        egui::ScrollArea::vertical().id_salt("log").stick_to_bottom(true).show(ui, |ui| {
+            for line in &run.log {
+                ui.monospace(line);
+            }
+        });
+    }
+}
+
+impl eframe::App for GuiApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Reload the library when another device is picked and once each sync has finished
+        let finished = self.run.as_ref().is_some_and(|r| !r.running());
+        if self.library_path != self.path || (finished && !self.reloaded) {
+            self.library_path = self.path.clone();
+            self.reloaded = finished;
+            self.reload_library(ctx);
+        }
+
+        if self.preview && finished {
+            self.finish_preview(ctx);
+        }
+        self.confirm_dialog(ctx);
+
+        egui::SidePanel::left("options").min_width(280.0).show(ctx, |ui| self.options(ui));
+        egui::CentralPanel::default().show(ctx, |ui| {
+            self.library_view(ui);
+            ui.separator();
+            self.log_view(ui);
+        });
+    }
+}
+
+/// 打开图形界面：选择已挂载的设备、查看现有数据库、调整增益与旁白选项并同步
+pub fn run_gui(lang: Option<Lang>) -> Result<(), String> {
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default().with_inner_size([960.0, 640.0]),
+        ..Default::default()
+    };
+    eframe::run_native(
+        &format!("iPod Shuffle 4G Manager v{}", env!("CARGO_PKG_VERSION")),
+        options,
+        Box::new(move |cc| {
+            install_cjk_font(&cc.egui_ctx);
+            Ok(Box::new(GuiApp::new(lang)))
+        }),
+    )
+    .map_err(|e| format!("无法打开图形界面: {}", e))
+}
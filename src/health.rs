@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use walkdir::WalkDir;
+use tracing::debug;
+
+use crate::outln;
+use crate::cli::AUDIO_EXT;
+use crate::database::parse_itunes_sd;
+use crate::listing::print_section;
+use crate::report::{device_space, format_bytes};
+use crate::utils::{ext_lower, is_system_hidden};
+
+// ─── Device health check ─────────────────────────────────────────────────────
+
+/// 剩余空间低于该值时，同步可能无法写入语音旁白与数据库
+const LOW_SPACE: u64 = 16 * 1024 * 1024;
+
+/// 最大的 iPod Shuffle 4G 为 4 GB，报告的容量明显更大说明不是原装设备或分区异常
+const MAX_SHUFFLE_CAPACITY: u64 = 4_100_000_000;
+
+/// FAT 文件名中不允许出现的字符
+const FAT_INVALID: &[char] = &['"', '*', ':', '<', '>', '?', '\\', '|'];
+
+/// 体检结果：每一项为一条问题描述
+#[derive(Default)]
+pub struct HealthReport {
+    pub space: Vec<String>,
+    pub unreadable: Vec<String>,
+    pub zero_byte: Vec<String>,
+    pub orphaned: Vec<String>,
+    pub fat: Vec<String>,
+    pub files: usize,
+    pub audio_files: usize,
+    pub db_tracks: Option<usize>,
+}
+
+impl HealthReport {
+    pub fn problem_count(&self) -> usize {
+        self.space.len() + self.unreadable.len() + self.zero_byte.len() + self.orphaned.len() + self.fat.len()
+    }
+}
+
+fn relative<'a>(path: &'a Path, base: &Path) -> &'a Path {
+    path.strip_prefix(base).unwrap_or(path)
+}
+
+/// 完整读取文件以发现坏扇区或断开的簇链
+fn read_through(path: &Path) -> io::Result<()> {
+    let mut file = fs::File::open(path)?;
+    let mut buf = vec![0u8; 1024 * 1024];
+    while file.read(&mut buf)? > 0 {}
+    Ok(())
+}
+
+/// 检查同一目录中的重复名称：FAT 不区分大小写，同名或仅大小写不同的条目都说明目录表已损坏
+fn check_names(dir: &Path, base: &Path, report: &mut HealthReport) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    let mut seen: HashMap<String, String> = HashMap::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = dir.join(&name);
+        let rel = relative(&path, base);
+        if name.chars().any(|c| FAT_INVALID.contains(&c) || c.is_control()) {
+            report.fat.push(format!("{}: 文件名包含 FAT 不允许的字符", rel.display()));
+        }
+        match seen.get(&name.to_lowercase()) {
+            Some(prev) if *prev == name => report.fat.push(format!("{}: 目录中出现重复的条目", rel.display())),
+            Some(prev) => report.fat.push(format!("{}: 与 \"{}\" 仅大小写不同", rel.display(), prev)),
+            None => { seen.insert(name.to_lowercase(), name); }
+        }
+    }
+}
+
+fn check_space(base: &Path, file_bytes: u64, report: &mut HealthReport) {
+    let Some((free, total)) = device_space(base) else {
+        report.space.push("无法查询设备的可用空间".to_string());
+        return;
+    };
+    outln!("容量: {} 可用 / 共 {}，文件合计 {}", format_bytes(free), format_bytes(total), format_bytes(file_bytes));
+    let used = total.saturating_sub(free);
+    if file_bytes > used {
+        report.space.push(format!(
+            "文件合计 {} 超过已用空间 {}，文件分配表可能存在交叉链接，请运行磁盘检查",
+            format_bytes(file_bytes), format_bytes(used),
+        ));
+    }
+    if free < LOW_SPACE {
+        report.space.push(format!("剩余空间仅 {}，同步时可能无法写入语音旁白与数据库", format_bytes(free)));
+    }
+    if total > MAX_SHUFFLE_CAPACITY {
+        report.space.push(format!("报告的容量 {} 超过 iPod Shuffle 4G 的最大容量 (4 GB)", format_bytes(total)));
+    }
+}
+
+/// 检查 iTunesSD 中引用、但设备上已不存在的曲目
+fn check_database(base: &Path, report: &mut HealthReport) {
+    let db_path = base.join("iPod_Control").join("iTunes").join("iTunesSD");
+    let data = match fs::read(&db_path) {
+        Ok(d) => d,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            outln!("设备上还没有 iTunesSD 数据库");
+            return;
+        }
+        Err(e) => {
+            report.unreadable.push(format!("{}: {}", relative(&db_path, base).display(), e));
+            return;
+        }
+    };
+    let parsed = match parse_itunes_sd(&data) {
+        Ok(p) => p,
+        Err(e) => {
+            report.unreadable.push(format!("iTunesSD 已损坏: {}", e));
+            return;
+        }
+    };
+    report.db_tracks = Some(parsed.tracks.len());
+    for track in &parsed.tracks {
+        if !base.join(track.filename.trim_start_matches('/')).is_file() {
+            report.orphaned.push(track.filename.clone());
+        }
+    }
+}
+
+/// 检查设备的健康状况：空间、无法读取或零字节的音频、数据库中的孤立条目与 FAT 目录异常
+pub fn check_device(base: &Path) -> HealthReport {
+    let mut report = HealthReport::default();
+    let mut file_bytes = 0u64;
+
+    let walker = WalkDir::new(base).into_iter()
+        .filter_entry(|e| e.depth() == 0 || !is_system_hidden(&e.file_name().to_string_lossy()));
+    for entry in walker {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(e) => {
+                let path = e.path().map(|p| relative(p, base)).unwrap_or(Path::new(""));
+                report.unreadable.push(format!("{}: {}", path.display(), e));
+                continue;
+            }
+        };
+        let path = entry.path();
+        if entry.file_type().is_dir() {
+            check_names(path, base, &mut report);
+            continue;
+        }
+        if !entry.file_type().is_file() { continue; }
+
+        report.files += 1;
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        file_bytes += size;
+        if !AUDIO_EXT.contains(&ext_lower(path).as_str()) { continue; }
+
+        report.audio_files += 1;
+        let rel = relative(path, base);
+        debug!("[?] 检查 {}", rel.display());
+        if size == 0 {
+            report.zero_byte.push(rel.display().to_string());
+        } else if let Err(e) = read_through(path) {
+            report.unreadable.push(format!("{}: {}", rel.display(), e));
+        }
+    }
+
+    check_space(base, file_bytes, &mut report);
+    check_database(base, &mut report);
+    report
+}
+
+/// 打印体检报告
+pub fn print_health_report(report: &HealthReport) {
+    outln!("已检查 {} 个文件，其中音频 {} 个", report.files, report.audio_files);
+    if let Some(n) = report.db_tracks {
+        outln!("iTunesSD 中有 {} 首曲目", n);
+    }
+    print_section("空间", &report.space);
+    print_section("无法读取的文件", &report.unreadable);
+    print_section("零字节的音频文件", &report.zero_byte);
+    print_section("数据库中的孤立条目", &report.orphaned);
+    print_section("FAT 目录异常", &report.fat);
+    outln!();
+    match report.problem_count() {
+        0 => outln!("设备状况良好。"),
+        n => outln!("发现 {} 个问题，建议在长时间同步前先处理。", n),
+    }
+}
@@ -0,0 +1,414 @@
+/// 英文译文，键为源码中的中文消息
+pub const MESSAGES: &[(&str, &str)] = &[
+    // main
+    ("无法生成手册页: {}", "failed to generate the man page: {}"),
+    ("错误: {}", "Error: {}"),
+    ("模拟模式: 将 \"{}\" 当作 iPod 同步", "Simulation mode: syncing \"{}\" as if it were an iPod"),
+    ("--simulate-from 的目录 \"{}\" 不存在", "The --simulate-from directory \"{}\" does not exist"),
+    ("--simulate-from 的目录 \"{}\" 与模拟目录相互包含", "The --simulate-from directory \"{}\" and the simulation directory contain each other"),
+    ("无法放入 \"{}\": {}", "Cannot place \"{}\": {}"),
+    ("已放入 \"{}\": 硬链接 {} 个，写时复制 {} 个，复制 {} 个，{} 个已存在",
+        "Placed \"{}\": {} hard-linked, {} reflinked, {} copied, {} already present"),
+    ("请求播放列表旁白: {}", "Playlist voiceover requested: {}"),
+    ("请求曲目旁白: {}", "Track voiceover requested: {}"),
+    ("配置文件: {}", "Config file: {}"),
+    ("使用预设: {}", "Using preset: {}"),
+    ("寻找 iPod 目录出错。也许它没有连接或挂载？", "Could not find the iPod directory. Is it connected and mounted?"),
+    ("目录 \"{}\" 中没有 iPod_Control，看起来不像 iPod。", "\"{}\" has no iPod_Control directory and does not look like an iPod."),
+    ("本工具会转换并删除其中所有 FLAC 文件。如果确定要继续，请使用 --force。",
+        "This tool converts and deletes every FLAC file in it. Use --force if you are sure."),
+    ("无法获得 iPod 目录的写入权限", "No write permission on the iPod directory"),
+    ("无法创建模拟目录 \"{}\": {}", "Cannot create the simulation directory \"{}\": {}"),
+    ("无法读取 \"{}\": {}", "Cannot read \"{}\": {}"),
+    ("\"{}\" 不是空目录，也不是之前模拟过的 iPod。请使用空目录，或加上 --force。",
+        "\"{}\" is neither empty nor a previously simulated iPod. Use an empty directory or add --force."),
+    ("无法创建 \"{}\": {}", "Cannot create \"{}\": {}"),
+    ("正在检查设备 {}，需要完整读取每个音频文件，这可能需要一段时间...",
+        "Checking device {}. Every audio file is read in full, this may take a while..."),
+    ("找不到目录 \"{}\"。", "Directory \"{}\" not found."),
+    ("将删除以下同步生成的文件：", "These generated files will be deleted:"),
+    ("将删除 Speakable 中的以下文件：", "These files in Speakable will be deleted:"),
+    ("已取消，设备未被修改。", "Cancelled, the device was not modified."),
+    ("没有需要清理的文件。", "Nothing to clean."),
+    ("已清理，下次 build 时会重新生成这些文件。", "Cleaned. These files are regenerated by the next build."),
+    ("提示: 部分选项来自配置文件、预设或配置档案: {}", "Hint: some options came from the config file, a preset or a profile: {}"),
+    ("\n检测到中断，正在退出...", "\nInterrupted, exiting..."),
+    ("\n正在停止：等待进行中的转换与旁白完成（再按一次 Ctrl+C 立即退出）...",
+     "\nStopping: waiting for running conversions and voiceovers to finish (press Ctrl+C again to exit immediately)..."),
+    ("同步已中断：本次转换了 {} 个文件，iPod 数据库没有修改。下次同步会从中断处继续。",
+     "Sync interrupted: {} files were converted and the iPod database was not changed. The next sync picks up where this one stopped."),
+    ("已中断，{} 个文件没有转换，源文件保留在设备上。", "Interrupted: {} files were not converted; their sources remain on the device."),
+
+    // convert
+    ("无法打开文件: {}", "cannot open file: {}"),
+    ("无法探测格式: {}", "cannot probe format: {}"),
+    ("无法创建解码器: {}", "cannot create decoder: {}"),
+    ("生成淡出版本: {}", "Creating faded version: {}"),
+    ("淡出编码失败: {}", "Fade-out encoding failed: {}"),
+    ("淡出编码失败: 写入 MP3 失败: {}", "Fade-out encoding failed: cannot write MP3: {}"),
+    ("MP3 已存在，删除源文件: {}", "MP3 already exists, deleting source: {}"),
+    ("[-] 删除中断的转换留下的 {}", "[-] removing {} left behind by an interrupted conversion"),
+    ("上次同步没有完成，从中断处继续: 已完成的转换与语音旁白不会重做（--no-resume 从头开始）",
+        "The last sync did not finish, resuming where it stopped: finished conversions and voiceovers are kept (--no-resume starts over)"),
+    ("转换 FLAC -> MP3: {}", "Converting FLAC -> MP3: {}"),
+    ("转换失败 {}: {}", "Conversion failed for {}: {}"),
+    ("外部程序转换 -> MP3: {}", "Converting with external program -> MP3: {}"),
+    ("无法解码输出文件: {}", "cannot decode the output file: {}"),
+    ("删除源文件: {}", "Deleting source: {}"),
+    ("删除源文件失败: {}", "Failed to delete source: {}"),
+    ("已复制标签", "Tags copied"),
+
+    // shuffler
+    ("跳过 {}（{}: {}）。", "Skipping {} ({}: {})."),
+    ("读取标签 {}: 标题 {}，艺术家 {}，专辑 {}，曲目 {}，碟号 {}，时长 {} ms",
+     "Read tags of {}: title {}, artist {}, album {}, track {}, disc {}, duration {} ms"),
+    ("无法读取标签 {}: {}", "Cannot read tags of {}: {}"),
+    ("曲目表头 {}，播放列表表头 {}", "Track header at {}, playlist header at {}"),
+    ("曲目记录 {}: {}", "Track record at {}: {}"),
+    ("播放列表记录 {}: {} 首曲目", "Playlist record at {}: {} tracks"),
+    ("无法解码", "undecodable"),
+    ("受 DRM 保护", "DRM-protected"),
+    ("路径过长", "path too long"),
+    ("转换失败", "conversion failed"),
+    ("[!] {} 与 {} 同名: {}", "[!] {} has the same name as {}: {}"),
+    ("保留 MP3，删除源文件", "keep the MP3, delete the source"),
+    ("由源文件重新转换覆盖", "re-convert from the source and overwrite"),
+    ("两者都保留", "keep both"),
+    ("沿用现有的 MP3", "reuse the existing MP3"),
+    ("转换", "convert"),
+    ("[演练] 将删除 {}", "[dry run] would delete {}"),
+    ("警告: {}", "Warning: {}"),
+    ("[-] 已将 {} 首曲目移出主列表", "[-] Removed {} tracks from the master playlist"),
+    ("[-] 已将 {} 首有声书移出主列表", "[-] Removed {} audiobook tracks from the master playlist"),
+    ("保存随机种子失败: {}", "Failed to save the shuffle seed: {}"),
+    ("[-] 已从播放列表 \"{}\" 中去掉 {} 首标题匹配 exclude-titles 的曲目", "[-] Playlist \"{}\": removed {} tracks matching exclude-titles"),
+    ("[-] 已排除 {}", "[-] Excluded {}"),
+    ("所有曲目都被移出了主列表，\"All songs\" 将为空。",
+        "every track was removed from the master playlist, \"All songs\" will be empty."),
+    ("[演练] 数据库将包含 {} 首曲目:", "[dry run] the database would contain {} tracks:"),
+    ("  {}. {}  增益: {}", "  {}. {}  gain: {}"),
+    ("[演练] 以及 {} 个播放列表:", "[dry run] and {} playlists:"),
+    ("  {} [{}] ({} 首)", "  {} [{}] ({} tracks)"),
+    ("主列表", "master"),
+    ("普通", "normal"),
+    ("播客", "podcast"),
+    ("有声书", "audiobook"),
+    ("未知", "unknown"),
+    ("[演练] 将生成 {} 条语音旁白", "[dry run] would generate {} voiceovers"),
+    ("[演练] {} 条语音旁白超出 --tts-limit，将留待下次同步",
+        "[dry run] {} voiceovers exceed --tts-limit and would be left for the next sync"),
+    ("[演练] 将删除 {} 条不再使用的旁白", "[dry run] would delete {} unused voiceovers"),
+    ("同步时会出现 {} 个问题，见上方的警告。", "The sync would run into {} problems, see the warnings above."),
+    ("演练结束，设备未被修改。", "Dry run finished, the device was not modified."),
+    ("演练模式: 只报告将要进行的修改，不会写入或删除设备上的任何文件",
+        "Dry run: only reporting what would change, nothing on the device is written or deleted"),
+    ("[演练] 不更新收藏列表，使用现有的 {}", "[dry run] not updating favorites, using the existing {}"),
+    ("收藏列表已更新: 新增 {} 首曲目", "Favorites updated: {} tracks added"),
+    ("无法更新收藏列表: {}", "cannot update favorites: {}"),
+    ("无法清除 {} 中已播放的曲目: {}", "cannot clear played tracks from {}: {}"),
+    ("[演练] 将从 {} 清除 {} 首已播放的曲目", "[dry run] {}: would clear {} played tracks"),
+    ("已从 {} 清除 {} 首已播放的曲目", "{}: cleared {} played tracks"),
+    ("[-] 已播放，移出队列: {}", "[-] played, removed from the queue: {}"),
+    ("{} 中已播放的 {} 首曲目（移出队列）", "{}: {} played tracks (removed from the queue)"),
+    ("[演练] 将把 {} 个录音归档到 {}", "[dry run] would archive {} recordings to {}"),
+    ("已将 {} 个录音归档到 {}", "Archived {} recordings to {}"),
+    ("无法归档录音 {}", "cannot archive recording {}"),
+    ("库根目录 \"{}\" 不存在。跳过。", "library root \"{}\" does not exist. Skipping."),
+    ("库根目录 \"{}\" 不在 iPod 上。跳过。", "library root \"{}\" is not on the iPod. Skipping."),
+    ("播客目录 \"{}\" 不存在。跳过。", "podcast directory \"{}\" does not exist. Skipping."),
+    ("播客目录 \"{}\" 不在 iPod 上。跳过。", "podcast directory \"{}\" is not on the iPod. Skipping."),
+    ("有声书目录 \"{}\" 不存在。跳过。", "audiobook directory \"{}\" does not exist. Skipping."),
+    ("有声书目录 \"{}\" 不在 iPod 上。跳过。", "audiobook directory \"{}\" is not on the iPod. Skipping."),
+    ("符号链接 {} 指向其上级目录 {}，形成循环。跳过。", "symlink {} points to its ancestor {}, forming a loop. Skipping."),
+    ("符号链接 {} 指向 iPod 之外的 {}，设备无法播放。跳过。", "symlink {} points to {} outside the iPod, which the device cannot play. Skipping."),
+    ("扫描目录 \"{}\" 不存在。跳过。", "scan directory \"{}\" does not exist. Skipping."),
+    ("[-] 跳过隐藏项 {}", "[-] Skipping hidden item {}"),
+    ("[>] 检测到移动的曲目 {} -> {}", "[>] Detected moved track {} -> {}"),
+    ("{} 首曲目在上次同步后被移动或重命名，沿用其原有记录", "{} tracks were moved or renamed since the last sync; keeping their existing records"),
+    ("[=] {} 与 {} 内容相同，跳过", "[=] {} has the same content as {}, skipping"),
+    ("跳过 {} 个内容重复的文件，删除它们可以节省 {}", "Skipped {} files with duplicate content; deleting them would free {}"),
+    ("设备文件系统不区分大小写，按不区分大小写的路径去重",
+        "The device file system is case-insensitive, deduplicating paths case-insensitively"),
+    ("[演练] 将转换 {} 个文件为 MP3，转换后删除源文件:",
+        "[dry run] would convert {} files to MP3 and delete the sources afterwards:"),
+    ("发现 {} 个需要转换为 MP3 的文件，开始并发转换...", "Found {} files to convert to MP3, converting in parallel..."),
+    ("转换完成！", "Conversion finished!"),
+    ("\"{}\" 不是 iPod，标签修正只在内存中使用，不会写入文件。",
+        "\"{}\" is not an iPod, tag fixes are only applied in memory and not written to files."),
+    ("[演练] 标签修正只在内存中使用，不会写回文件。", "[dry run] tag fixes are only applied in memory and not written back."),
+    ("无法写入标签 {}: {}", "cannot write tags to {}: {}"),
+    ("已修正并写回 {} 首曲目的 {} 处标签。", "Fixed and wrote back tags of {} tracks ({} fields)."),
+    ("已在内存中修正 {} 首曲目的 {} 处标签（未修改文件）。", "Fixed tags of {} tracks in memory ({} fields, files unchanged)."),
+    ("[!] {} 的{} \"{}\" 不合理，{}", "[!] {}: {} \"{}\" is implausible, {}"),
+    ("曲目编号", "track number"),
+    ("碟号", "disc number"),
+    ("按 {} 写入", "written as {}"),
+    ("已忽略", "ignored"),
+    ("{} 处曲目编号或碟号标签不合理，已忽略或按开头的数字写入，详见同步报告",
+        "{} implausible track or disc number tags were ignored or truncated to their leading digits, see the sync report"),
+    ("找不到切分列表 \"{}\" 对应的音频文件。跳过。", "no audio file found for cut list \"{}\". Skipping."),
+    ("[演练] 将为 {} 生成复读分段与播放列表 \"{} A-B\"（不包含在下面的预览中）",
+        "[dry run] would generate repeat segments for {} and playlist \"{} A-B\" (not included in the preview below)"),
+    ("无法生成 \"{}\" 的复读分段: {}", "cannot generate repeat segments for \"{}\": {}"),
+    ("有声书文本目录 \"{}\" 不存在。跳过。", "audiobook text directory \"{}\" does not exist. Skipping."),
+    ("[演练] 将合成有声书 \"{}\"（不包含在下面的预览中）", "[dry run] would synthesize audiobook \"{}\" (not included in the preview below)"),
+    ("无法合成有声书 \"{}\": {}", "cannot synthesize audiobook \"{}\": {}"),
+    ("[-] 排除曲目 {} ({}, {}s)", "[-] Excluding track {} ({}, {}s)"),
+    ("短于 --min-duration", "shorter than --min-duration"),
+    ("标题匹配 --exclude-title（移出主列表）", "title matches --exclude-title (removed from the master playlist)"),
+    ("长于 --max-duration", "longer than --max-duration"),
+    ("[-] 排除曲目 {} ({})", "[-] Excluding track {} ({})"),
+    ("超出 --max-size", "over --max-size"),
+    ("超出 --max-total-duration", "over --max-total-duration"),
+    ("已轮换（--rotate-after）", "rotated out (--rotate-after)"),
+    ("已轮换 {} 首曲目，换入上次没有选入的曲目", "Rotated out {} tracks to make room for tracks left out last time"),
+    ("--rotate-after 需要与 --max-size 或 --max-total-duration 一起使用，已忽略", "--rotate-after needs --max-size or --max-total-duration; ignored"),
+    ("已达到曲目总量上限，{} 首曲目没有写入数据库（见同步报告）", "Reached the library cap, {} tracks were left out of the database (see the sync report)"),
+    ("正在分析曲目响度并计算自动增益...", "Analyzing track loudness and computing automatic gain..."),
+    ("[-] 按规则跳过自动增益 {}", "[-] Skipping automatic gain by rule: {}"),
+    ("未能分析任何曲目的响度，自动音量均衡已跳过。",
+        "no track loudness could be analyzed, automatic volume leveling skipped."),
+    ("自动音量均衡完成: 已为 {}/{} 首曲目写入增益（参考响度 {} dBFS）。",
+        "Automatic volume leveling finished: gain written for {}/{} tracks (reference loudness {} dBFS)."),
+    ("已按 {} 调整主列表顺序", "Master playlist reordered according to {}"),
+    ("已保持上次数据库中的曲目顺序", "Kept the track order of the previous database"),
+    ("[*] 添加曲目 {}", "[*] Adding track {}"),
+    ("播放列表 \"{}\" 不包含任何曲目。跳过。", "playlist \"{}\" contains no tracks. Skipping."),
+    ("[+] 添加播放列表 {}", "[+] Adding playlist {}"),
+    ("[+] 添加播放列表 {} ({} 首, {} 分钟)", "[+] Adding playlist {} ({} tracks, {} minutes)"),
+    ("找不到 --trip-mix-source 指定的播放列表 \"{}\"。", "playlist \"{}\" given by --trip-mix-source not found."),
+    ("[演练] 将生成淡出曲目 {}", "[dry run] would generate faded track {}"),
+    ("[+] 添加播放列表 Bedtime ({} 首)", "[+] Adding playlist Bedtime ({} tracks)"),
+    ("无法从 \"{}\" 生成睡前播放列表。", "cannot build the bedtime playlist from \"{}\"."),
+    ("[*] 添加生成的曲目 {}", "[*] Adding generated track {}"),
+    ("[+] 添加播放列表 {} ({} 首)", "[+] Adding playlist {} ({} tracks)"),
+    ("无法读取章节 {}: {}", "cannot read chapters of {}: {}"),
+    ("第 {} 章", "Chapter {}"),
+    ("[+] 添加章节播放列表 {} ({} 章)", "[+] Adding chapter playlist {} ({} chapters)"),
+    ("正在写入数据库。这可能需要一段时间...", "Writing the database. This may take a while..."),
+    ("已删除 {} 条不再使用的旁白", "Deleted {} unused voiceovers"),
+    ("{} 条旁白的文字与语音的语言可能不一致（如中文语音朗读英文标题）{}",
+        "{} voiceovers may use a voice whose language differs from the text (e.g. a Chinese voice reading an English title){}"),
+    ("，详见同步报告", ", see the sync report"),
+    ("[+] 新增曲目 {}", "[+] Added track {}"),
+    ("[-] 移除曲目 {}", "[-] Removed track {}"),
+    ("[~] 调整增益 {} ({} -> {})", "[~] Gain changed {} ({} -> {})"),
+    ("构建信息已写入 {}", "Build metadata written to {}"),
+    ("写入构建信息失败: {}", "failed to write the build metadata: {}"),
+    ("同步报告已写入 {}", "Sync report written to {}"),
+    ("写入同步报告失败: {}", "failed to write the sync report: {}"),
+    ("数据库写入成功:", "Database written:"),
+    ("曲目 {}", "Tracks {}"),
+    ("专辑 {}", "Albums {}"),
+    ("艺术家 {}", "Artists {}"),
+    ("播放列表 {}", "Playlists {}"),
+    ("本次生成 {} 条语音旁白，已达到 --tts-limit，还有 {} 条未生成；请稍后加上 --tts-resume 再次同步以继续",
+        "Generated {} voiceovers and reached --tts-limit, {} are still missing; sync again later with --tts-resume to continue"),
+    ("写入 iPod 数据库失败: {}", "failed to write the iPod database: {}"),
+    ("演练完成，设备未被修改", "Dry run finished, the device was not modified"),
+    ("同步完成，可以安全拔出 iPod: {} 首曲目，{} 个播放列表", "Sync finished, the iPod can be unplugged: {} tracks, {} playlists"),
+    ("同步完成，可以安全拔出 iPod: {} 首曲目，{} 个播放列表，{} 个问题",
+        "Sync finished, the iPod can be unplugged: {} tracks, {} playlists, {} problems"),
+    ("同步失败: 写入 iPod 数据库失败: {}", "Sync failed: cannot write the iPod database: {}"),
+    ("同步失败: {}", "Sync failed: {}"),
+    ("同步已被 Ctrl+C 中断，iPod 数据库没有修改", "the sync was interrupted by Ctrl+C and the iPod database was not changed"),
+    ("已发送桌面通知: {}", "Desktop notification sent: {}"),
+    ("无法显示桌面通知: {}", "cannot show a desktop notification: {}"),
+    ("--notify-command 失败: {}", "--notify-command failed: {}"),
+    ("--strict: 同步时出现 {} 个问题，以状态码 {} 退出", "--strict: {} problems during the sync, exiting with status {}"),
+    ("--strict: 同步时出现 {} 个问题", "--strict: {} problems during the sync"),
+    ("已取消。", "Cancelled."),
+    ("iPod_Control/Speakable 中的 {} 个语音旁白（之后重新生成）", "{} voiceovers in iPod_Control/Speakable (regenerated afterwards)"),
+    ("{}（转换为 MP3 后删除）", "{} (deleted after conversion to MP3)"),
+    ("同步将删除设备上的以下文件：", "The sync will delete these files on the device:"),
+    ("库根目录 \"{}\" 不存在", "library root \"{}\" does not exist"),
+    ("库根目录 \"{}\" 不在 iPod 上", "library root \"{}\" is not on the iPod"),
+    ("播客目录 \"{}\" 不存在", "podcast directory \"{}\" does not exist"),
+    ("播客目录 \"{}\" 不在 iPod 上", "podcast directory \"{}\" is not on the iPod"),
+    ("有声书目录 \"{}\" 不存在", "audiobook directory \"{}\" does not exist"),
+    ("有声书目录 \"{}\" 不在 iPod 上", "audiobook directory \"{}\" is not on the iPod"),
+    ("扫描目录 \"{}\" 不存在", "scan directory \"{}\" does not exist"),
+    ("符号链接 {} 形成循环", "symlink {} forms a loop"),
+    ("符号链接 {} 指向 iPod 之外的 {}", "symlink {} points outside the iPod to {}"),
+    ("转换失败: {}", "conversion failed: {}"),
+    ("找不到切分列表 \"{}\" 对应的音频文件", "no audio file for the cut list \"{}\""),
+    ("有声书文本目录 \"{}\" 不存在", "audiobook text directory \"{}\" does not exist"),
+    ("播放列表 \"{}\" 中的曲目 \"{}\" 不存在", "playlist \"{}\": track \"{}\" does not exist"),
+    ("播放列表 \"{}\" 不包含任何曲目，已跳过", "playlist \"{}\" contains no tracks and was skipped"),
+    ("找不到旅行混音的来源播放列表 \"{}\"", "trip mix source playlist \"{}\" not found"),
+    ("睡前播放列表的淡出编码失败: {}", "fade-out encoding for the bedtime playlist failed: {}"),
+    ("无法从 \"{}\" 生成睡前播放列表", "cannot generate the bedtime playlist from \"{}\""),
+    ("写入 {} 失败: {}", "failed to write {}: {}"),
+    ("iPod 只能播放未加密的音频", "the iPod only plays unencrypted audio"),
+    ("{} 字节，上限为 {} 字节", "{} bytes, the limit is {} bytes"),
+
+    // changes
+    ("与上次的数据库相比没有变化", "No changes since the previous database"),
+    ("新增 {} 首曲目，移除 {} 首，{} 个播放列表有变化（新增 {}、移除 {}、修改 {}），{} 首曲目的增益被调整",
+        "{} tracks added, {} removed, {} playlists changed ({} added, {} removed, {} modified), gain adjusted on {} tracks"),
+    ("{}，{} 首曲目被移动", "{}, {} tracks moved"),
+
+    // progress
+    ("剩余", "ETA"),
+    ("读取标签", "Tags"),
+    ("分析响度", "Loudness"),
+    ("曲目旁白", "Voiceover"),
+    ("生成数据库", "Building database"),
+    ("写入数据库", "Writing database"),
+    ("合成", "Synthesis"),
+
+    // confirm
+    ("  ……另有 {} 项", "  ... and {} more"),
+    ("是否继续？[y/N] ", "Continue? [y/N] "),
+    ("标准输入不是终端，无法询问。确认要删除时请加上 --yes 或 --non-interactive",
+     "Standard input is not a terminal, so there is no one to ask. Pass --yes or --non-interactive to go ahead with the deletion"),
+
+    // tui
+    ("已取消，数据库未被修改。已完成的转换与生成的旁白保留在设备上，下次同步时直接使用。",
+     "Cancelled, the database was not modified. Conversions and voiceovers already done stay on the device and are used by the next sync."),
+    ("tui 需要在终端中运行，不能与 --non-interactive 一起使用", "tui needs a terminal and cannot be combined with --non-interactive"),
+    ("曲目", "Tracks"),
+    ("播放列表", "Playlists"),
+    ("转换与旁白", "Conversion & voiceover"),
+    ("警告", "Warnings"),
+    ("{} \"{}\" 不合理，{}", "{} \"{}\" looks wrong, {}"),
+    ("{} 转换失败", "{} failed to convert"),
+    ("本次没有需要转换的 FLAC 文件", "No FLAC files to convert this time"),
+    ("曲目旁白: 已就绪 {} 条", "Track voiceovers: {} ready"),
+    ("播放列表旁白在写入数据库时生成", "Playlist voiceovers are generated when the database is written"),
+    ("已跳过 {}（{}）: {}", "Skipped {} ({}): {}"),
+    ("  增益 {}", "  gain {}"),
+    ("{} {} ({}/{} 首)", "{} {} ({}/{} tracks)"),
+    ("写入数据库？y 确认，其他键返回", "Write the database? y to confirm, any other key to go back"),
+    ("Tab/←→ 切换  ↑↓ 移动  空格 选择/取消  a 全选  w 写入  q 放弃", "Tab/←→ switch  ↑↓ move  Space toggle  a select all  w write  q quit"),
+
+    // inspect
+    ("inspect 只读取现有设备，不能与 --simulate 一起使用", "inspect only reads an existing device and cannot be combined with --simulate"),
+
+    // gui
+    ("无法启动同步: {}", "Cannot start the sync: {}"),
+    ("播放列表 ({})", "Playlists ({})"),
+    ("曲目 ({})", "Tracks ({})"),
+    ("{} [{}] ({} 首)", "{} [{}] ({} tracks)"),
+    ("{}  增益: {}", "{}  gain: {}"),
+    ("同步失败，退出码 {}", "Sync failed with exit code {}"),
+    ("同步", "Sync"),
+    ("同步完成", "Sync finished"),
+    ("演练完成", "Dry run finished"),
+    ("继续同步", "Continue syncing"),
+    ("取消", "Cancel"),
+    ("已取消", "Cancelled"),
+    ("播放列表旁白", "Playlist voiceover"),
+    ("曲目增益", "Track gain"),
+    ("正在读取数据库...", "Reading the database..."),
+    ("没有找到已挂载的 iPod，请手动输入路径", "No mounted iPod found, enter the path by hand"),
+    ("演练（不修改设备）", "Dry run (device is not modified)"),
+    ("自动音量均衡", "Automatic volume levelling"),
+    ("设备", "Device"),
+    ("该目录中没有 iPod_Control", "There is no iPod_Control in this directory"),
+    ("选项", "Options"),
+    ("重新查找", "Search again"),
+
+    // watch
+    ("--watch 不能用于 tui", "--watch cannot be used with tui"),
+    ("同步以状态码 {} 结束，继续监视", "The sync ended with exit code {}, still watching"),
+    ("无法监视设备: {}", "Cannot watch the device: {}"),
+    ("无法监视 {}: {}", "Cannot watch {}: {}"),
+    ("设备上没有可监视的曲目目录", "There is no track directory to watch on the device"),
+    ("正在监视 {} 中的变化，按 Ctrl+C 退出", "Watching {} for changes, press Ctrl+C to quit"),
+    ("[~] 变化: {}", "[~] Changed: {}"),
+    ("监视出错: {}", "Watch error: {}"),
+    ("检测到 {} 个文件有变化，重新同步...", "{} files changed, syncing again..."),
+
+    // profiles
+    ("错误: 配置档案 \"{}\" 没有设备路径。请给出 PATH，或在档案中设置 path", "Error: profile \"{}\" has no device path. Give PATH, or set path in the profile"),
+    ("使用配置档案: {}", "Using profile: {}"),
+
+    // export-csv / --metadata-csv
+    ("已将 {} 首曲目导出到 {}", "Exported {} tracks to {}"),
+    ("已按表格覆盖 {} 首曲目的元数据", "Applied table overrides to {} tracks"),
+    ("表格中有 {} 首曲目不在本次同步中，已忽略", "{} tracks in the table are not part of this sync, ignored"),
+
+    // .meta.toml sidecars
+    ("已按 {} 个元数据文件覆盖标签", "Applied {} metadata sidecar files"),
+
+    // list / dump
+    ("（无效的曲目索引）", "(invalid track index)"),
+    ("曲目 ({}):", "Tracks ({}):"),
+    ("播放列表 ({}):", "Playlists ({}):"),
+    ("曲目旁白: {}", "Track voiceover: {}"),
+    ("已启用", "enabled"),
+    ("未启用", "disabled"),
+    ("无法读取 iTunesSD: {}", "cannot read iTunesSD: {}"),
+    ("{} ({} 字节)", "{} ({} bytes)"),
+
+    // device lock
+    ("进程号 {}，主机 {}，已持有 {} 分钟", "pid {}, host {}, held for {} minutes"),
+    ("未知进程", "unknown process"),
+    ("无法创建锁文件 {}: {}", "cannot create the lock file {}: {}"),
+    ("无法写入锁文件 {}: {}", "cannot write the lock file {}: {}"),
+    ("设备正被另一个进程写入（{}），锁文件: {}", "the device is being written by another process ({}), lock file: {}"),
+    ("设备正被另一个进程写入（{}），锁文件: {}。确认没有其他同步在运行时可以删除该文件",
+     "the device is being written by another process ({}), lock file: {}. If no other sync is running, the file can be deleted"),
+    ("无法对 {} 加文件锁（{}），改为检查锁文件中记录的进程", "cannot lock {} ({}), checking the process recorded in the lock file instead"),
+    ("已清除过期的锁（{}）", "Cleared a stale lock ({})"),
+
+    // checkpoint
+    ("无法创建检查点 {}: {}", "cannot create the checkpoint {}: {}"),
+    ("无法写入检查点 {}: {}", "cannot write the checkpoint {}: {}"),
+    ("无法记录转换进度 {}: {}", "cannot record conversion progress in {}: {}"),
+
+    // stats
+    ("……另有 {} 项", "... {} more"),
+    ("曲目: {}", "Tracks: {}"),
+    ("播放列表: {}", "Playlists: {}"),
+    ("总时长: {}", "Total duration: {}"),
+    ("艺术家", "Artists"),
+    ("专辑", "Albums"),
+    ("流派", "Genres"),
+    ("（未知艺术家）", "(unknown artist)"),
+    ("（未知专辑）", "(unknown album)"),
+    ("（未知流派）", "(unknown genre)"),
+    ("按格式占用的空间:", "Space used by format:"),
+    ("{} 首", "{} tracks"),
+    ("合计: {}", "Total: {}"),
+    ("剩余空间: {} / 共 {}", "Free space: {} of {}"),
+    ("剩余空间: 未知", "Free space: unknown"),
+
+    // doctor
+    ("{}（{} 字节）", "{} ({} bytes)"),
+    ("零字节的音频文件", "Zero-byte audio files"),
+    ("文件在复制时中断，请删除后重新复制到设备，再运行 build", "The copy was interrupted; delete these files, copy them to the device again and run build"),
+    ("路径超过 256 字节的曲目", "Tracks with paths longer than 256 bytes"),
+    ("数据库的文件名字段只有 256 字节，超出的路径会被截断而无法播放；请缩短目录或文件名后运行 build", "The database stores at most 256 bytes of each path, so longer paths are cut off and cannot be played; shorten the folder or file names and run build"),
+    ("不支持的音频格式", "Unsupported audio formats"),
+    ("iPod Shuffle 只能播放 MP3、AAC 与 WAV，FLAC 会在同步时自动转换；请先将这些文件转换为 MP3 或 FLAC，或在电脑上的 profiles.toml 的 [converters] 中为该格式指定转换程序",
+        "The iPod Shuffle only plays MP3, AAC and WAV, and FLAC is converted during sync; convert these files to MP3 or FLAC first, or set a converter for the format in the [converters] table of profiles.toml on the computer"),
+    ("缺少数据库", "Missing database"),
+    ("运行 build 生成数据库", "Run build to create the database"),
+    ("无法读取数据库", "Cannot read the database"),
+    ("运行 check-device 检查设备是否有坏扇区，再运行 build 重建数据库", "Run check-device to look for bad sectors, then run build to rebuild the database"),
+    ("数据库已损坏", "Corrupt database"),
+    ("运行 build 重建数据库", "Run build to rebuild the database"),
+    ("数据库中指向不存在文件的曲目", "Database entries pointing at missing files"),
+    ("这些文件已被删除或移动，运行 build 重建数据库；被移动的曲目会按文件指纹识别并沿用原有记录", "These files were deleted or moved; run build to rebuild the database, moved tracks are recognised by their fingerprint and keep their records"),
+    ("无法检查语音旁白", "Cannot check the voiceovers"),
+    ("运行 build 重建数据库与旁白", "Run build to rebuild the database and voiceovers"),
+    ("{}: {}（缺失）", "{}: {} (missing)"),
+    ("{}: {}（{}）", "{}: {} ({})"),
+    ("缺失或损坏的语音旁白", "Missing or corrupt voiceovers"),
+    ("运行 check-voiceover --repair 只重新生成这些旁白", "Run check-voiceover --repair to regenerate only these voiceovers"),
+    ("不再使用的语音旁白", "Unused voiceovers"),
+    ("这些旁白没有被数据库引用，只占用空间；运行 build --tts-resume 会删除它们并保留其余旁白", "The database does not refer to these voiceovers, they only take up space; build --tts-resume removes them and keeps the rest"),
+    ("旁白目录布局的问题", "Problems with the voiceover folder layout"),
+    ("运行 check-voiceover --repair 更正目录与文件名", "Run check-voiceover --repair to fix the folder and file names"),
+    ("缺少 iPod_Control 目录", "Missing iPod_Control folder"),
+    ("确认给出的是 iPod 的挂载点；全新或格式化过的设备请先创建 iPod_Control 目录，再运行 build", "Make sure the path is where the iPod is mounted; on a new or formatted device create the iPod_Control folder first, then run build"),
+    ("缺少 iPod_Control/iTunes 目录", "Missing iPod_Control/iTunes folder"),
+    ("运行 build 会创建该目录与数据库", "Running build creates the folder and the database"),
+    ("已检查 {} 个音频文件", "Checked {} audio files"),
+    ("iTunesSD 中有 {} 首曲目", "iTunesSD has {} tracks"),
+    ("建议: {}", "Suggestion: {}"),
+    ("没有发现问题。", "No problems found."),
+    ("发现 {} 个问题，请按上面的建议处理。", "Found {} problems, see the suggestions above."),
+];
@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+use crate::cli::Lang;
+
+mod en;
+
+// ─── Message catalog ─────────────────────────────────────────────────────────
+
+/// 当前使用的输出语言，未设置时为中文
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+/// 由 --lang 或系统区域设置选择输出语言，需在输出任何消息前调用
+pub fn init(lang: Option<Lang>) {
+    let _ = LANG.set(lang.unwrap_or_else(system_lang));
+}
+
+/// 按 LC_ALL、LC_MESSAGES、LANG 的顺序判断系统语言。未设置、"C" 或 "POSIX" 时使用中文，
+/// 其余非中文的区域使用英文
+fn system_lang() -> Lang {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+        .filter_map(|key| env::var(key).ok())
+        .find(|v| !v.is_empty());
+    match locale.as_deref() {
+        None | Some("C") | Some("POSIX") => Lang::Zh,
+        Some(l) if l.to_lowercase().starts_with("zh") => Lang::Zh,
+        Some(_) => Lang::En,
+    }
+}
+
+fn lang() -> Lang {
+    LANG.get().copied().unwrap_or(Lang::Zh)
+}
+
+/// 消息的译文。消息以中文原文为键，没有译文时原样返回
+pub fn text(source: &'static str) -> &'static str {
+    static CATALOG: OnceLock<HashMap<&'static str, &'static str>> = OnceLock::new();
+    match lang() {
+        Lang::Zh => source,
+        Lang::En => CATALOG.get_or_init(|| en::MESSAGES.iter().copied().collect())
+            .get(source)
+            .copied()
+            .unwrap_or(source),
+    }
+}
+
+/// 依次用 `args` 替换模板中的 `{}`
+pub fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut rest = template;
+    while let Some(pos) = rest.find("{}") {
+        out.push_str(&rest[..pos]);
+        match args.next() {
+            Some(arg) => out.push_str(&arg.to_string()),
+            None => out.push_str("{}"),
+        }
+        rest = &rest[pos + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// 翻译并格式化一条消息：`tr!("已写入 {} 首曲目", n)`。模板只支持 `{}`，
+/// 需要精度或宽度时先用 `format!` 格式化参数
+#[macro_export]
+macro_rules! tr {
+    ($msg:literal $(, $arg:expr)* $(,)?) => {
+        $crate::i18n::fill($crate::i18n::text($msg), &[$(&$arg as &dyn ::std::fmt::Display),*])
+    };
+}
+
+pub use crate::tr;
@@ -0,0 +1,86 @@
+use std::sync::atomic::AtomicBool;
+
+pub mod api;
+
+// The modules behind the command line, public only so that the binary can use them;
+// programs that embed this crate use `api`
+pub mod cli;
+pub mod utils;
+pub mod convert;
+pub mod checkpoint;
+pub mod converters;
+pub mod audio;
+pub mod tts;
+pub mod tts_engine;
+pub mod database;
+pub mod playlist;
+pub mod shuffler;
+pub mod report;
+pub mod metadata;
+pub mod generators;
+pub mod ordering;
+pub mod stats;
+pub mod favorites;
+pub mod queue;
+pub mod notify;
+pub mod abrepeat;
+pub mod textbook;
+pub mod memos;
+pub mod config;
+pub mod collation;
+pub mod timeout;
+pub mod device_io;
+pub mod seed;
+pub mod health;
+pub mod changes;
+pub mod search;
+pub mod gain;
+pub mod tagfix;
+pub mod speakable;
+pub mod chapters;
+pub mod track_rules;
+pub mod listing;
+pub mod clean;
+pub mod verify;
+pub mod i18n;
+pub mod progress;
+pub mod events;
+pub mod build_meta;
+pub mod logging;
+pub mod exit_code;
+pub mod confirm;
+pub mod cancel;
+pub mod dedup;
+pub mod moves;
+pub mod device_lock;
+pub mod tui;
+pub mod watch;
+pub mod profiles;
+pub mod track_csv;
+pub mod summary;
+pub mod doctor;
+#[cfg(feature = "gui")]
+pub mod gui;
+pub mod task;
+
+/// --output json 时为 true：标准输出只留给最后的 JSON 汇总，其余输出改写到标准错误
+pub static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// --quiet 时为 true：不输出同步过程中的提示与结果
+pub static QUIET: AtomicBool = AtomicBool::new(false);
+
+#[macro_export]
+macro_rules! outln {
+    ($($arg:tt)*) => {
+        $crate::progress::suspend(|| {
+            if $crate::QUIET.load(::std::sync::atomic::Ordering::Relaxed) {
+                return;
+            }
+            if $crate::JSON_OUTPUT.load(::std::sync::atomic::Ordering::Relaxed) {
+                eprintln!($($arg)*);
+            } else {
+                println!($($arg)*);
+            }
+        })
+    };
+}
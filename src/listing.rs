@@ -0,0 +1,213 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::outln;
+use crate::database::{dbid_to_filename, parse_itunes_sd, read_device_db, read_u32_at, ParsedDatabase};
+use crate::i18n::{self, tr};
+use crate::metadata::read_all_meta;
+use crate::search::{candidate_names, playlist_labels};
+
+// ─── list / dump subcommands ─────────────────────────────────────────────────
+
+/// 反查播放列表名称时读取单个文件标签的时限
+const TAG_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 播放列表的显示名称：主列表（内部名称 "__master__"）显示为 "All songs"
+pub fn playlist_display_name(name: &str) -> &str {
+    if name == "__master__" { "All songs" } else { name }
+}
+
+/// 打印报告中的一类问题，没有问题时不打印
+pub fn print_section(title: &str, items: &[String]) {
+    if items.is_empty() { return; }
+    outln!();
+    outln!("{} ({}):", title, items.len());
+    for item in items {
+        outln!("  [!] {}", item);
+    }
+}
+
+/// 播放列表记录中 listtype 的含义
+pub fn listtype_name(listtype: u32) -> &'static str {
+    match listtype {
+        1 => "主列表",
+        2 => "普通",
+        3 => "播客",
+        4 => "有声书",
+        _ => "未知",
+    }
+}
+
+/// JSON 输出中 listtype 的名称，与播放列表选项的 listtype 写法一致
+fn listtype_key(listtype: u32) -> &'static str {
+    match listtype {
+        1 => "master",
+        2 => "normal",
+        3 => "podcast",
+        4 => "audiobook",
+        _ => "unknown",
+    }
+}
+
+fn filetype_name(filetype: u32) -> &'static str {
+    match filetype {
+        1 => "mp3",
+        2 => "aac",
+        _ => "未知",
+    }
+}
+
+/// 数据库中每个播放列表的名称，按设备上的文件与标签反查
+pub fn labels_for(base: &Path, db: &ParsedDatabase) -> Vec<String> {
+    let paths: Vec<PathBuf> = db.tracks.iter().map(|t| base.join(t.filename.trim_start_matches('/'))).collect();
+    let (metas, _) = read_all_meta(&paths, TAG_TIMEOUT);
+    playlist_labels(db, &candidate_names(base, &metas))
+}
+
+/// `list`：打印现有 iTunesSD 中的曲目与播放列表。`by_playlist` 为 `true` 时
+/// 按播放列表分组列出曲目
+pub fn list_database(base: &Path, by_playlist: bool) -> Result<(), String> {
+    let db = read_device_db(base)?;
+    let labels = labels_for(base, &db);
+    let track_name = |i: u32| db.tracks.get(i as usize).map_or(i18n::text("（无效的曲目索引）"), |t| t.filename.as_str());
+
+    if by_playlist {
+        for (playlist, label) in db.playlists.iter().zip(&labels) {
+            outln!("{}", tr!("{} [{}] ({} 首)", label, i18n::text(listtype_name(playlist.listtype)), playlist.track_indices.len()));
+            for (n, &i) in playlist.track_indices.iter().enumerate() {
+                outln!("  {:>4}. {}", n + 1, track_name(i));
+            }
+        }
+        return Ok(());
+    }
+
+    outln!("{}", tr!("曲目 ({}):", db.tracks.len()));
+    for (i, track) in db.tracks.iter().enumerate() {
+        outln!("{}", tr!("  {}. {}  增益: {}", format!("{:>4}", i + 1), track.filename, track.volume_gain));
+    }
+    outln!();
+    outln!("{}", tr!("播放列表 ({}):", db.playlists.len()));
+    for (playlist, label) in db.playlists.iter().zip(&labels) {
+        outln!("{}", tr!("  {} [{}] ({} 首)", label, i18n::text(listtype_name(playlist.listtype)), playlist.track_indices.len()));
+    }
+    outln!();
+    outln!("{}", tr!("曲目旁白: {}", i18n::text(if db.track_voiceover { "已启用" } else { "未启用" })));
+    Ok(())
+}
+
+/// `dump`：逐字段打印 iTunesSD 的头部、曲目记录与播放列表记录
+pub fn dump_database(base: &Path) -> Result<(), String> {
+    let db_path = base.join("iPod_Control").join("iTunes").join("iTunesSD");
+    let data = fs::read(&db_path).map_err(|e| tr!("无法读取 iTunesSD: {}", e))?;
+    let db = parse_itunes_sd(&data)?;
+
+    let total_tracks = read_u32_at(&data, 12)?;
+    let total_playlists = read_u32_at(&data, 16)?;
+    let tracks_without_podcasts = read_u32_at(&data, 32)?;
+    let track_header_offset = read_u32_at(&data, 36)?;
+    let playlist_header_offset = read_u32_at(&data, 40)?;
+    outln!("{}", tr!("{} ({} 字节)", db_path.display(), data.len()));
+    outln!("[bdhs]");
+    outln!("  total_tracks: {}", total_tracks);
+    outln!("  total_playlists: {}", total_playlists);
+    outln!("  voiceover_enabled: {}", db.track_voiceover as u8);
+    outln!("  tracks_without_podcasts: {}", tracks_without_podcasts);
+    outln!("  track_header_offset: 0x{:x}", track_header_offset);
+    outln!("  playlist_header_offset: 0x{:x}", playlist_header_offset);
+
+    for (i, t) in db.tracks.iter().enumerate() {
+        outln!();
+        outln!("[rths #{}] @0x{:x}", i, t.offset);
+        outln!("  filename: {}", t.filename);
+        outln!("  filetype: {} ({})", t.filetype, i18n::text(filetype_name(t.filetype)));
+        outln!("  start_at_pos_ms: {}", t.start_at_pos_ms);
+        outln!("  stop_at_pos_ms: {}", t.stop_at_pos_ms);
+        outln!("  volume_gain: {}", t.volume_gain);
+        outln!("  dont_skip: {}", t.dont_skip as u8);
+        outln!("  remember: {}", t.remember as u8);
+        outln!("  pregap: 0x{:x}", t.pregap);
+        outln!("  postgap: 0x{:x}", t.postgap);
+        outln!("  album_id: {}", t.album_id);
+        outln!("  track_number: {}", t.track_num);
+        outln!("  disc_number: {}", t.disc_num);
+        outln!("  artist_id: {}", t.artist_id);
+        outln!("  dbid: {}", dbid_to_filename(&t.dbid));
+    }
+
+    let playlist_header = playlist_header_offset as usize;
+    for (i, p) in db.playlists.iter().enumerate() {
+        outln!();
+        let offset = read_u32_at(&data, playlist_header + 20 + i * 4)?;
+        outln!("[lphs #{}] @0x{:x}", i, offset);
+        outln!("  dbid: {}", dbid_to_filename(&p.dbid));
+        outln!("  listtype: {} ({})", p.listtype, i18n::text(listtype_name(p.listtype)));
+        outln!("  number_of_songs: {}", p.track_indices.len());
+        let indices: Vec<String> = p.track_indices.iter().map(|i| i.to_string()).collect();
+        outln!("  indices: {}", indices.join(" "));
+    }
+    Ok(())
+}
+
+/// `dump --json`：以 JSON 输出 iTunesSD 的头部、曲目记录与播放列表记录。曲目附带设备上的文件是否存在，
+/// 播放列表附带反查出的名称与曲目路径
+pub fn dump_database_json(base: &Path) -> Result<(), String> {
+    let db_path = base.join("iPod_Control").join("iTunes").join("iTunesSD");
+    let data = fs::read(&db_path).map_err(|e| tr!("无法读取 iTunesSD: {}", e))?;
+    let db = parse_itunes_sd(&data)?;
+    let labels = labels_for(base, &db);
+
+    let tracks: Vec<serde_json::Value> = db.tracks.iter().enumerate()
+        .map(|(i, t)| serde_json::json!({
+            "index": i,
+            "offset": t.offset,
+            "path": t.filename,
+            "exists": base.join(t.filename.trim_start_matches('/')).is_file(),
+            "filetype": filetype_name(t.filetype),
+            "start_at_pos_ms": t.start_at_pos_ms,
+            "stop_at_pos_ms": t.stop_at_pos_ms,
+            "volume_gain": t.volume_gain,
+            "dont_skip": t.dont_skip,
+            "remember": t.remember,
+            "pregap": t.pregap,
+            "postgap": t.postgap,
+            "album_id": t.album_id,
+            "track_number": t.track_num,
+            "disc_number": t.disc_num,
+            "artist_id": t.artist_id,
+            "dbid": dbid_to_filename(&t.dbid),
+        }))
+        .collect();
+
+    let playlist_header = read_u32_at(&data, 40)? as usize;
+    let playlists = db.playlists.iter().zip(&labels).enumerate()
+        .map(|(i, (p, label))| Ok(serde_json::json!({
+            "index": i,
+            "offset": read_u32_at(&data, playlist_header + 20 + i * 4)?,
+            "name": label,
+            "dbid": dbid_to_filename(&p.dbid),
+            "listtype": listtype_key(p.listtype),
+            "track_indices": p.track_indices,
+            "tracks": p.track_indices.iter()
+                .map(|&t| db.tracks.get(t as usize).map(|t| t.filename.as_str()))
+                .collect::<Vec<_>>(),
+        })))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let dump = serde_json::json!({
+        "path": db_path.display().to_string(),
+        "size": data.len(),
+        "header": {
+            "total_tracks": read_u32_at(&data, 12)?,
+            "total_playlists": read_u32_at(&data, 16)?,
+            "voiceover_enabled": db.track_voiceover,
+            "tracks_without_podcasts": read_u32_at(&data, 32)?,
+            "track_header_offset": read_u32_at(&data, 36)?,
+            "playlist_header_offset": playlist_header,
+        },
+        "tracks": tracks,
+        "playlists": playlists,
+    });
+    println!("{}", serde_json::to_string_pretty(&dump).expect("JSON values always serialize"));
+    Ok(())
+}
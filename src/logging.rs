@@ -0,0 +1,124 @@
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::EnvFilter;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+use crate::events;
+use crate::i18n::tr;
+use crate::progress;
+
+// ─── Logging ─────────────────────────────────────────────────────────────────
+
+/// 终端上的日志格式：只输出消息本身，警告与错误加上对应的前缀；`json` 时改为输出 "log" 事件行
+struct ConsoleFormat {
+    json: bool,
+}
+
+impl<S, N> FormatEvent<S, N> for ConsoleFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let mut message = String::new();
+        ctx.format_fields(Writer::new(&mut message), event)?;
+        if self.json {
+            return writeln!(writer, "{}", events::log_line(&event.metadata().level().as_str().to_lowercase(), &message));
+        }
+        match *event.metadata().level() {
+            Level::ERROR => writeln!(writer, "{}", tr!("错误: {}", message)),
+            Level::WARN => writeln!(writer, "{}", tr!("警告: {}", message)),
+            _ => writeln!(writer, "{}", message),
+        }
+    }
+}
+
+/// 写到标准错误，写入时暂时隐藏进度条
+struct ConsoleWriter;
+
+impl Write for ConsoleWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        progress::suspend(|| io::stderr().write_all(buf))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+fn filter(default: &str, extra: Option<&str>) -> Result<EnvFilter, String> {
+    let directives = match extra {
+        Some(extra) => format!("{},{}", default, extra),
+        None => default.to_string(),
+    };
+    EnvFilter::try_new(&directives).map_err(|e| format!("无效的 --log-filter \"{}\": {}", extra.unwrap_or_default(), e))
+}
+
+/// 终端上显示多少日志，由 --quiet 与 -v 的次数决定
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// --quiet：只显示错误
+    Quiet,
+    /// 默认：警告与错误
+    Normal,
+    /// -v：调试信息
+    Verbose,
+    /// -vv：跟踪信息，如读取的每个标签与数据库记录的偏移
+    Trace,
+}
+
+impl Verbosity {
+    pub fn from_flags(quiet: bool, verbose: u8) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, _) => Verbosity::Trace,
+        }
+    }
+
+    /// 终端上的过滤指令：依赖库始终只显示错误
+    fn console_directives(self) -> &'static str {
+        match self {
+            Verbosity::Quiet => "error",
+            Verbosity::Normal => "error,ipod_shuffle_4g=warn",
+            Verbosity::Verbose => "error,ipod_shuffle_4g=debug",
+            Verbosity::Trace => "error,ipod_shuffle_4g=trace",
+        }
+    }
+}
+
+/// 初始化日志。终端上默认只显示本程序的警告与错误（依赖库只显示错误），详见 `Verbosity`；
+/// `log_file` 记录本程序调试级别（-vv 时为跟踪级别）及以上、依赖库警告级别及以上的日志。
+/// `log_filter` 为 `EnvFilter` 语法的指令，同时作用于终端与日志文件。
+/// `json` 时终端上的每条日志都是一行 JSON 事件（见 `events`）
+pub fn init(verbosity: Verbosity, json: bool, log_file: Option<&Path>, log_filter: Option<&str>) -> Result<(), String> {
+    let console = tracing_subscriber::fmt::layer()
+        .event_format(ConsoleFormat { json })
+        .with_writer(|| ConsoleWriter)
+        .with_filter(filter(verbosity.console_directives(), log_filter)?);
+    let file_directives = if verbosity == Verbosity::Trace { "warn,ipod_shuffle_4g=trace" } else { "warn,ipod_shuffle_4g=debug" };
+    let file = match log_file {
+        Some(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)
+                .map_err(|e| format!("无法打开日志文件 {}: {}", path.display(), e))?;
+            Some(tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(Mutex::new(file))
+                .with_filter(filter(file_directives, log_filter)?))
+        }
+        None => None,
+    };
+    tracing_subscriber::registry().with(console).with(file).try_init().map_err(|e| e.to_string())
+}
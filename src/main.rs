@@ -1,77 +1,530 @@
-use clap::Parser;
+use clap::CommandFactory;
+use std::env;
+use std::ffi::OsString;
+use std::io::IsTerminal;
 use std::fs;
 use std::path::Path;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::Ordering;
+use tracing::{debug, error, warn};
 
-mod cli;
-mod utils;
-mod convert;
-mod audio;
-mod tts;
-mod database;
-mod playlist;
-mod shuffler;
+#[cfg(feature = "gui")]
+use ipod_shuffle_4g::gui;
+use ipod_shuffle_4g::{
+    build_meta, cancel, clean, cli, collation, confirm, device_io, device_lock, doctor, events, exit_code, gain, health,
+    i18n, listing, logging, notify, profiles, progress, search, seed, speakable, summary, track_csv, tts, tts_engine,
+    verify, watch,
+};
+use ipod_shuffle_4g::{outln, tr, JSON_OUTPUT, QUIET};
+use cli::{Cli, Command, GainCommand, OutputFormat};
+use ipod_shuffle_4g::config::{config_path, default_args, preset_args};
+use ipod_shuffle_4g::report::SyncReport;
+use ipod_shuffle_4g::utils::{check_unicode, looks_like_ipod};
+use ipod_shuffle_4g::shuffler::run_shuffler;
 
-use cli::Cli;
-use utils::check_unicode;
-use shuffler::run_shuffler;
+// ─── main ────────────────────────────────────────────────────────────────────
+
+fn main() {
+    // Handle Ctrl+C
+    cancel::install();
 
-static VERBOSE: AtomicBool = AtomicBool::new(false);
+    let mut cli = parse_cli();
 
-macro_rules! vprintln {
-    ($($arg:tt)*) => {
-        if $crate::VERBOSE.load(::std::sync::atomic::Ordering::Relaxed) {
-            println!($($arg)*);
+    if cli.yes || cli.non_interactive {
+        confirm::assume_yes();
+    }
+    if cli.progress_json {
+        progress::enable_json();
+    }
+    if cli.log_json {
+        events::enable();
+    }
+    if cli.non_interactive {
+        progress::hide();
+        if let Some(sync) = cli.sync_args_mut() {
+            sync.output = OutputFormat::Json;
         }
-    };
+    }
+    if cli.quiet {
+        QUIET.store(true, Ordering::Relaxed);
+        progress::hide();
+    }
+    let log_file = cli.log_file.as_deref().map(Path::new);
+    let verbosity = logging::Verbosity::from_flags(cli.quiet, cli.verbose);
+    if let Err(e) = logging::init(verbosity, cli.log_json, log_file, cli.log_filter.as_deref()) {
+        eprintln!("{}", tr!("错误: {}", e));
+        std::process::exit(exit_code::USAGE);
+    }
+
+    if cli.generate_manpage {
+        let man = clap_mangen::Man::new(Cli::command_with_env());
+        if let Err(e) = man.render(&mut std::io::stdout()) {
+            error!("{}", tr!("无法生成手册页: {}", e));
+            std::process::exit(exit_code::FAILURE);
+        }
+        return;
+    }
+    match cli.command {
+        Some(Command::CheckDevice(ref args)) => run_check_device(Path::new(&args.path)),
+        Some(Command::Doctor(ref args)) => run_doctor(Path::new(&args.path)),
+        Some(Command::Search(ref args)) => run_search(args),
+        Some(Command::CheckVoiceover(ref args)) => run_check_voiceover(args),
+        Some(Command::Gain(ref cmd)) => run_gain(cmd),
+        Some(Command::List(ref args)) => run_list(args),
+        Some(Command::Clean(ref args)) => run_clean(args),
+        Some(Command::Verify(ref args)) => run_verify(Path::new(&args.path)),
+        Some(Command::Dump(ref args)) => run_dump(args),
+        Some(Command::ExportCsv(ref args)) => run_export_csv(args),
+        Some(Command::Stats(ref args)) => run_stats(args),
+        #[cfg(feature = "gui")]
+        Some(Command::Gui) => {
+            if let Err(e) = gui::run_gui(cli.lang) {
+                error!("{}", e);
+                std::process::exit(exit_code::FAILURE);
+            }
+            return;
+        }
+        Some(Command::Completions { shell }) => {
+            clap_complete::generate(shell, &mut Cli::command(), env!("CARGO_PKG_NAME"), &mut std::io::stdout());
+            return;
+        }
+        Some(Command::Build(_) | Command::Tui(_) | Command::Inspect(_)) | None => {}
+    }
+
+    if let Some(Command::Inspect(ref mut args)) = cli.command {
+        if args.simulate.is_some() {
+            error!("{}", tr!("inspect 只读取现有设备，不能与 --simulate 一起使用"));
+            std::process::exit(exit_code::USAGE);
+        }
+        args.dry_run = true;
+    }
+
+    let review = matches!(cli.command, Some(Command::Tui(_)));
+    if review && (cli.non_interactive || !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal()) {
+        error!("{}", tr!("tui 需要在终端中运行，不能与 --non-interactive 一起使用"));
+        std::process::exit(exit_code::USAGE);
+    }
+    let cli = cli.sync_args().expect("a sync subcommand or no subcommand");
+    if cli.output == OutputFormat::Json {
+        JSON_OUTPUT.store(true, Ordering::Relaxed);
+    }
+    if cli.watch && env::var_os(watch::CHILD_ENV).is_none() {
+        if review {
+            error!("{}", tr!("--watch 不能用于 tui"));
+            std::process::exit(exit_code::USAGE);
+        }
+        watch::run_watch(cli.device_path(), cli);
+    }
+    let path = cli.device_path();
+    notify::configure(cli.notify, cli.notify_command.clone(), path, cli.dry_run);
+    if cli.simulate.is_some() {
+        if let Err(e) = prepare_simulation(path, cli.force).and_then(|_| seed_simulation(path, &cli.simulate_from)) {
+            error!("{}", e);
+            notify::notify_failure(&e, &SyncReport::default());
+            std::process::exit(exit_code::FAILURE);
+        }
+        outln!("{}", tr!("模拟模式: 将 \"{}\" 当作 iPod 同步", path.display()));
+        // There is no device to protect, so skip the flushes that slow down test runs
+        device_io::disable_fsync();
+    } else {
+        if cli.dry_run {
+            device_io::enable_dry_run();
+        }
+        check_device_path(path, cli.force);
+        if !cli.dry_run {
+            lock_or_exit(path);
+            if let Some(ref name) = cli.profile {
+                if let Err(e) = profiles::remember_path(name, path) {
+                    warn!("{}", e);
+                }
+            }
+        }
+    }
+
+    if cli.rename_unicode {
+        check_unicode(path);
+    }
+
+    outln!("iPod Shuffle 4G Manager v{}", env!("CARGO_PKG_VERSION"));
+    debug!("{}", tr!("请求播放列表旁白: {}", cli.playlist_voiceover));
+    debug!("{}", tr!("请求曲目旁白: {}", cli.track_voiceover));
+    if let Some(config) = config_path(path).filter(|_| !cli.no_config_defaults) {
+        debug!("{}", tr!("配置文件: {}", config.display()));
+    }
+    if let Some(ref name) = cli.preset {
+        debug!("{}", tr!("使用预设: {}", name));
+    }
+    if let Some(ref name) = cli.profile {
+        debug!("{}", tr!("使用配置档案: {}", name));
+    }
+    let engine_config = tts_engine::load_config(cli.tts_engine_config.as_deref()).unwrap_or_else(|e| {
+        error!("{}", e);
+        std::process::exit(exit_code::USAGE);
+    });
+    tts::configure(tts::TtsSettings {
+        engine: cli.tts_engine.clone(),
+        engine_config,
+        voice: cli.tts_voice.clone(),
+        timeout: cli.tts_timeout,
+        retries: cli.retries,
+        limit: cli.tts_limit,
+    });
+    if cli.no_fsync {
+        device_io::disable_fsync();
+    }
+    if let Some(limit) = cli.io_throttle {
+        device_io::set_limit(limit);
+    }
+    if let Err(e) = collation::set_locale(&cli.collation) {
+        error!("{}", e);
+        std::process::exit(exit_code::USAGE);
+    }
+
+    run_shuffler(cli, review);
+    progress::flush();
+    device_lock::release();
+}
+
+/// 锁定设备，无法写入或另一个进程正在写入时退出
+fn lock_or_exit(path: &Path) {
+    match device_lock::lock_device(path) {
+        Ok(()) => {}
+        Err(device_lock::LockError::Unwritable(e)) => {
+            eprintln!("{}", tr!("无法获得 iPod 目录的写入权限"));
+            eprintln!("{}", e);
+            notify::notify_failure(&e, &SyncReport::default());
+            std::process::exit(exit_code::PERMISSION_DENIED);
+        }
+        Err(device_lock::LockError::Busy(e)) => {
+            error!("{}", e);
+            notify::notify_failure(&e, &SyncReport::default());
+            std::process::exit(exit_code::DEVICE_BUSY);
+        }
+    }
 }
 
-// Re-export the macro for use in submodules
-pub(crate) use vprintln;
+/// 检查同步目标是否是可写入的 iPod，否则退出
+fn check_device_path(path: &Path, force: bool) {
+    if !path.is_dir() {
+        let message = tr!("寻找 iPod 目录出错。也许它没有连接或挂载？");
+        eprintln!("{}", message);
+        notify::notify_failure(&message, &SyncReport::default());
+        std::process::exit(exit_code::DEVICE_NOT_FOUND);
+    }
 
-// ─── main ────────────────────────────────────────────────────────────────────
+    // Refuse to run against directories that don't look like an iPod: the
+    // scan converts and deletes every FLAC it finds under the target path
+    if !looks_like_ipod(path) && !force {
+        let message = tr!("目录 \"{}\" 中没有 iPod_Control，看起来不像 iPod。", path.display());
+        eprintln!("{}", message);
+        eprintln!("{}", tr!("本工具会转换并删除其中所有 FLAC 文件。如果确定要继续，请使用 --force。"));
+        notify::notify_failure(&message, &SyncReport::default());
+        std::process::exit(exit_code::DEVICE_NOT_FOUND);
+    }
 
-fn main() {
-    // Handle Ctrl+C
-    ctrlc_handler();
+    if device_io::dry_run() { return; }
 
-    let cli = Cli::parse();
+    if let Err(e) = device_io::check_writable(path) {
+        eprintln!("{}", tr!("无法获得 iPod 目录的写入权限"));
+        eprintln!("{}", e);
+        notify::notify_failure(&e, &SyncReport::default());
+        std::process::exit(exit_code::PERMISSION_DENIED);
+    }
+}
 
-    if cli.verbose {
-        VERBOSE.store(true, Ordering::Relaxed);
+/// 为 --simulate 准备模拟的 iPod：目录不存在时创建，并建立 iPod_Control 目录结构。
+/// 只接受空目录或之前模拟过的目录，以免误把普通文件夹当作 iPod（会删除其中的 FLAC）
+fn prepare_simulation(path: &Path, force: bool) -> Result<(), String> {
+    fs::create_dir_all(path).map_err(|e| tr!("无法创建模拟目录 \"{}\": {}", path.display(), e))?;
+    let empty = fs::read_dir(path)
+        .map_err(|e| tr!("无法读取 \"{}\": {}", path.display(), e))?
+        .next()
+        .is_none();
+    if !empty && !looks_like_ipod(path) && !force {
+        return Err(tr!(
+            "\"{}\" 不是空目录，也不是之前模拟过的 iPod。请使用空目录，或加上 --force。",
+            path.display(),
+        ));
+    }
+    for dir in ["iTunes", "Music", "Speakable/Playlists", "Speakable/Tracks"] {
+        let dir = path.join("iPod_Control").join(dir);
+        fs::create_dir_all(&dir).map_err(|e| tr!("无法创建 \"{}\": {}", dir.display(), e))?;
     }
+    Ok(())
+}
 
-    // Validate path
-    let path = Path::new(&cli.path);
+/// 按 --simulate-from 把现有的设备或音乐库放入模拟的 iPod
+fn seed_simulation(path: &Path, sources: &[String]) -> Result<(), String> {
+    for source in sources {
+        let stats = seed::seed_simulation(Path::new(source), path)?;
+        outln!(
+            "{}",
+            tr!(
+                "已放入 \"{}\": 硬链接 {} 个，写时复制 {} 个，复制 {} 个，{} 个已存在",
+                source, stats.linked, stats.reflinked, stats.copied, stats.existing,
+            )
+        );
+    }
+    Ok(())
+}
+
+/// `check-device`：只读检查设备并打印体检报告，发现问题时以状态码 1 退出
+fn run_check_device(path: &Path) -> ! {
     if !path.is_dir() {
-        eprintln!("寻找 iPod 目录出错。也许它没有连接或挂载？");
-        std::process::exit(1);
+        eprintln!("{}", tr!("寻找 iPod 目录出错。也许它没有连接或挂载？"));
+        std::process::exit(exit_code::DEVICE_NOT_FOUND);
     }
+    outln!("iPod Shuffle 4G Manager v{}", env!("CARGO_PKG_VERSION"));
+    outln!("{}", tr!("正在检查设备 {}，需要完整读取每个音频文件，这可能需要一段时间...", path.display()));
+    let report = health::check_device(path);
+    health::print_health_report(&report);
+    std::process::exit(if report.problem_count() == 0 { exit_code::SUCCESS } else { exit_code::FAILURE });
+}
+
+/// `doctor`：诊断常见问题并给出修复建议，发现问题时以状态码 1 退出
+fn run_doctor(path: &Path) -> ! {
+    require_device(path);
+    let report = doctor::diagnose(path);
+    doctor::print_doctor_report(&report);
+    std::process::exit(if report.problem_count() == 0 { exit_code::SUCCESS } else { exit_code::FAILURE });
+}
 
-    // Check write permission by trying to create a temp file
-    let test_file = path.join(".ipod_shuffle_write_test");
-    match fs::write(&test_file, b"test") {
-        Ok(_) => { let _ = fs::remove_file(&test_file); }
-        Err(_) => {
-            eprintln!("无法获得 iPod 目录的写入权限");
-            std::process::exit(1);
+/// `check-voiceover`：检查并按需修复 Speakable 中的旁白
+fn run_check_voiceover(args: &cli::VoiceoverCheckArgs) -> ! {
+    let path = Path::new(&args.path);
+    if !path.is_dir() {
+        eprintln!("{}", tr!("寻找 iPod 目录出错。也许它没有连接或挂载？"));
+        std::process::exit(exit_code::DEVICE_NOT_FOUND);
+    }
+    let engine_config = tts_engine::load_config(args.tts_engine_config.as_deref()).unwrap_or_else(|e| {
+        error!("{}", e);
+        std::process::exit(exit_code::USAGE);
+    });
+    tts::configure(tts::TtsSettings {
+        engine: args.tts_engine.clone(),
+        engine_config,
+        voice: args.tts_voice.clone(),
+        timeout: args.tts_timeout,
+        retries: args.retries,
+        limit: None,
+    });
+    if args.repair {
+        lock_or_exit(path);
+    }
+    match speakable::check_speakable(path, args.repair) {
+        Ok(report) => {
+            speakable::print_speakable_report(path, &report, args.repair);
+            std::process::exit(if report.problem_count() == 0 { exit_code::SUCCESS } else { exit_code::FAILURE });
+        }
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(exit_code::USAGE);
         }
     }
+}
 
-    if cli.rename_unicode {
-        check_unicode(path);
+/// `search`：在设备数据库（或 --live 时在设备文件）中搜索曲目
+fn run_search(args: &cli::SearchArgs) -> ! {
+    let path = Path::new(&args.path);
+    if !path.is_dir() {
+        eprintln!("{}", tr!("寻找 iPod 目录出错。也许它没有连接或挂载？"));
+        std::process::exit(exit_code::DEVICE_NOT_FOUND);
+    }
+    match search::search_tracks(path, &args.query.join(" "), args.live) {
+        Ok(hits) => {
+            search::print_hits(&hits);
+            std::process::exit(if hits.is_empty() { exit_code::FAILURE } else { exit_code::SUCCESS });
+        }
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(exit_code::USAGE);
+        }
     }
+}
 
-    println!("iPod Shuffle 4G Manager v{}", env!("CARGO_PKG_VERSION"));
-    vprintln!("请求播放列表旁白: {}", cli.playlist_voiceover);
-    vprintln!("请求曲目旁白: {}", cli.track_voiceover);
+/// `gain`：单独分析响度或修改设备数据库中的增益
+fn run_gain(cmd: &GainCommand) -> ! {
+    let path = Path::new(match cmd {
+        GainCommand::Analyze(args) | GainCommand::Apply(args) => &args.path,
+        GainCommand::Clear { path, .. } => path,
+    });
+    if !path.is_dir() {
+        eprintln!("{}", tr!("找不到目录 \"{}\"。", path.display()));
+        std::process::exit(exit_code::DEVICE_NOT_FOUND);
+    }
+    let result = match cmd {
+        GainCommand::Analyze(args) => gain::analyze_dir(path, args),
+        GainCommand::Apply(args) => gain::apply_to_device(path, args),
+        GainCommand::Clear { gain, .. } => gain::clear_device(path, *gain),
+    };
+    if let Err(e) = result {
+        error!("{}", e);
+        std::process::exit(exit_code::FAILURE);
+    }
+    std::process::exit(exit_code::SUCCESS);
+}
 
-    run_shuffler(&cli);
+/// 子命令的目标目录不存在时退出
+fn require_device(path: &Path) {
+    if !path.is_dir() {
+        eprintln!("{}", tr!("寻找 iPod 目录出错。也许它没有连接或挂载？"));
+        std::process::exit(exit_code::DEVICE_NOT_FOUND);
+    }
 }
 
-fn ctrlc_handler() {
-    let _ = ctrlc::set_handler(|| {
-        eprintln!("\n检测到中断，正在退出...");
-        std::process::exit(1);
-    });
+/// `list`：列出现有 iTunesSD 中的曲目与播放列表
+fn run_list(args: &cli::ListArgs) -> ! {
+    let path = Path::new(&args.path);
+    require_device(path);
+    if let Err(e) = listing::list_database(path, args.playlists) {
+        error!("{}", e);
+        std::process::exit(exit_code::FAILURE);
+    }
+    std::process::exit(exit_code::SUCCESS);
 }
+
+/// `clean`：删除同步生成的文件
+fn run_clean(args: &cli::CleanArgs) -> ! {
+    let path = Path::new(&args.path);
+    require_device(path);
+    let targets = clean::clean_targets(path, args.textbooks);
+    let labels: Vec<String> = targets.iter().map(|t| t.label.clone()).collect();
+    lock_or_exit(path);
+    if !confirm::confirm(&tr!("将删除以下同步生成的文件："), &labels) {
+        outln!("{}", tr!("已取消，设备未被修改。"));
+        std::process::exit(exit_code::FAILURE);
+    }
+    match clean::remove_targets(&targets) {
+        Ok(removed) if removed.is_empty() => outln!("{}", tr!("没有需要清理的文件。")),
+        Ok(removed) => {
+            for item in &removed {
+                outln!("[-] {}", item);
+            }
+            outln!("{}", tr!("已清理，下次 build 时会重新生成这些文件。"));
+        }
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(exit_code::FAILURE);
+        }
+    }
+    std::process::exit(exit_code::SUCCESS);
+}
+
+/// `verify`：校验现有 iTunesSD，发现问题时以状态码 1 退出
+fn run_verify(path: &Path) -> ! {
+    require_device(path);
+    let report = verify::verify_database(path);
+    verify::print_verify_report(path, &report);
+    std::process::exit(if report.problem_count() == 0 { exit_code::SUCCESS } else { exit_code::FAILURE });
+}
+
+/// `dump`：逐字段输出现有 iTunesSD，--json 时输出 JSON
+fn run_dump(args: &cli::DumpArgs) -> ! {
+    let path = Path::new(&args.path);
+    require_device(path);
+    let dumped = if args.json { listing::dump_database_json(path) } else { listing::dump_database(path) };
+    if let Err(e) = dumped {
+        error!("{}", e);
+        std::process::exit(exit_code::FAILURE);
+    }
+    std::process::exit(exit_code::SUCCESS);
+}
+
+/// `stats`：打印现有数据库的曲库概况
+fn run_stats(args: &cli::StatsArgs) -> ! {
+    let path = Path::new(&args.path);
+    require_device(path);
+    match summary::summarize(path) {
+        Ok(s) => summary::print_summary(&s, args.top),
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(exit_code::FAILURE);
+        }
+    }
+    std::process::exit(exit_code::SUCCESS);
+}
+
+/// `export-csv`：将现有数据库中曲目的元数据导出为表格
+fn run_export_csv(args: &cli::ExportCsvArgs) -> ! {
+    let path = Path::new(&args.path);
+    require_device(path);
+    match track_csv::export_tracks(path, args.out.as_deref(), args.tsv) {
+        Ok(count) => {
+            if let Some(ref out) = args.out {
+                outln!("{}", tr!("已将 {} 首曲目导出到 {}", count, out.display()));
+            }
+        }
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(exit_code::FAILURE);
+        }
+    }
+    std::process::exit(exit_code::SUCCESS);
+}
+
+/// 解析命令行。设备上的配置文件顶层的默认选项与 --preset 的预设会展开为参数，
+/// 按“配置文件、预设、配置档案”的顺序插入命令行后再解析一次，后给出的同名选项覆盖先给出的；
+/// 已在命令行上或由环境变量给出的选项不插入
+fn parse_cli() -> Cli {
+    let args: Vec<OsString> = env::args_os().collect();
+    let (cli, matches) = Cli::try_parse_with_matches(&args).unwrap_or_else(|e| e.exit());
+    i18n::init(cli.lang);
+    let Some(sync) = cli.sync_args() else { return cli };
+    let profile = sync.profile.as_deref().map(|name| profiles::load_profile(name).unwrap_or_else(|e| {
+        eprintln!("{}", tr!("错误: {}", e));
+        std::process::exit(exit_code::USAGE);
+    }));
+    // Without PATH the device comes from the profile, and is added to the command line below
+    let profile_path = match (sync.target(), &profile) {
+        (None, Some(profile)) => Some(profile.path.clone().unwrap_or_else(|| {
+            eprintln!("{}", tr!(
+                "错误: 配置档案 \"{}\" 没有设备路径。请给出 PATH，或在档案中设置 path",
+                sync.profile.as_deref().unwrap_or_default(),
+            ));
+            std::process::exit(exit_code::USAGE);
+        })),
+        _ => None,
+    };
+    let Some(path) = sync.target().or(profile_path.as_deref()) else { return cli };
+    let base = Path::new(path);
+
+    let mut inserted = Vec::new();
+    if !sync.no_config_defaults {
+        inserted.extend(default_args(base).unwrap_or_else(|e| {
+            eprintln!("{}", tr!("错误: {}", e));
+            std::process::exit(exit_code::USAGE);
+        }));
+    }
+    // A preset chosen by the profile applies only when none is given on the command line
+    let preset = sync.preset.clone().or_else(|| profile.as_ref().and_then(|p| p.preset.clone()));
+    if let Some(ref name) = preset {
+        inserted.extend(preset_args(base, name).unwrap_or_else(|e| {
+            eprintln!("{}", tr!("错误: {}", e));
+            std::process::exit(exit_code::USAGE);
+        }));
+        if sync.preset.is_none() {
+            inserted.push(format!("--preset={}", name));
+        }
+    }
+    if let Some(ref profile) = profile {
+        inserted.extend(profile.args.iter().cloned());
+    }
+    inserted.retain(|arg| !Cli::set_explicitly(&matches, arg));
+    if inserted.is_empty() && profile_path.is_none() {
+        build_meta::record_args(args.iter().map(|a| a.to_string_lossy().into_owned()).collect());
+        return cli;
+    }
+
+    // The inserted options go right after the program name, or after the subcommand when given
+    let split = Cli::subcommand_index(&args).map_or(1, |i| i + 1);
+    let mut full: Vec<OsString> = args[..split].to_vec();
+    full.extend(inserted.iter().map(OsString::from));
+    full.extend(profile_path.iter().map(OsString::from));
+    full.extend(args[split..].iter().cloned());
+    build_meta::record_args(full.iter().map(|a| a.to_string_lossy().into_owned()).collect());
+    Cli::try_parse_with_env(full).unwrap_or_else(|e| {
+        let _ = e.print();
+        eprintln!("{}", tr!("提示: 部分选项来自配置文件、预设或配置档案: {}", inserted.join(" ")));
+        std::process::exit(exit_code::USAGE);
+    })
+}
+
@@ -0,0 +1,70 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+use tracing::debug;
+
+use crate::device_io::dry_run;
+use crate::utils::ext_lower;
+
+// ─── Voice memo ingestion ────────────────────────────────────────────────────
+
+/// 录音文件的扩展名
+pub const MEMO_EXT: &[&str] = &[".wav", ".m4a", ".mp3"];
+
+/// 目标已存在时在文件名后追加序号，避免覆盖之前归档的录音
+fn unique_dest(dest: PathBuf) -> PathBuf {
+    if !dest.exists() { return dest; }
+    let stem = dest.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let ext = dest.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    (1..).map(|i| dest.with_file_name(format!("{} ({}){}", stem, i, ext)))
+        .find(|p| !p.exists())
+        .unwrap()
+}
+
+/// 复制后校验大小再删除源文件，归档目录可以位于其他文件系统
+fn move_file(src: &Path, dest: &Path) -> Result<(), String> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("无法创建目录: {}", e))?;
+    }
+    let copied = fs::copy(src, dest).map_err(|e| format!("复制失败: {}", e))?;
+    let expected = fs::metadata(src).map(|m| m.len()).map_err(|e| e.to_string())?;
+    if copied != expected {
+        let _ = fs::remove_file(dest);
+        return Err("复制后的文件大小不一致".to_string());
+    }
+    fs::remove_file(src).map_err(|e| format!("无法删除设备上的录音: {}", e))
+}
+
+/// 将设备上 `memo_dirs`（相对 iPod 根目录）内的录音移动到 `archive`，保留相对路径。
+/// 返回成功移动的 (源, 目标) 列表与失败信息；演练时只列出将要移动的录音
+pub fn archive_memos(
+    base: &Path,
+    memo_dirs: &[String],
+    archive: &Path,
+) -> (Vec<(PathBuf, PathBuf)>, Vec<String>) {
+    let mut moved = Vec::new();
+    let mut failures = Vec::new();
+
+    for dir in memo_dirs {
+        let root = base.join(dir);
+        if !root.is_dir() { continue; }
+        for entry in WalkDir::new(&root).sort_by_file_name().into_iter().filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if !entry.file_type().is_file() || !MEMO_EXT.contains(&ext_lower(path).as_str()) {
+                continue;
+            }
+            let rel = path.strip_prefix(base).unwrap_or(path);
+            let dest = unique_dest(archive.join(rel));
+            let result = if dry_run() { Ok(()) } else { move_file(path, &dest) };
+            match result {
+                Ok(()) => {
+                    debug!("[>] 归档录音 {} -> {}", path.display(), dest.display());
+                    moved.push((path.to_path_buf(), dest));
+                }
+                Err(e) => failures.push(format!("{}: {}", rel.display(), e)),
+            }
+        }
+    }
+
+    (moved, failures)
+}
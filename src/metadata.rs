@@ -0,0 +1,311 @@
+use lofty::prelude::*;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::trace;
+
+use crate::cancel;
+use crate::i18n::tr;
+use crate::progress;
+use crate::timeout::{run_with_timeout, TaskError};
+
+// ─── Scan-time track metadata ────────────────────────────────────────────────
+
+/// 曲目编号的合理上限，更大的值多半是误填的年份、编目号等
+pub const MAX_TRACK_NUMBER: u32 = 999;
+
+/// 碟号的合理上限
+pub const MAX_DISC_NUMBER: u32 = 99;
+
+/// 音频文件旁覆盖标签的元数据文件的后缀，如 "Song.mp3" 旁的 "Song.meta.toml"
+pub const META_SIDECAR_EXT: &str = ".meta.toml";
+
+/// 标签中不合理或无法解析的曲目编号、碟号
+#[derive(Clone)]
+pub struct SuspiciousNumber {
+    /// "曲目编号" 或 "碟号"
+    pub field: &'static str,
+    /// 标签中的原始文本
+    pub raw: String,
+    /// 实际写入数据库的值，`None` 表示忽略该标签
+    pub used: Option<u32>,
+}
+
+impl SuspiciousNumber {
+    /// 对该值的处理，用于提示与报告
+    pub fn action(&self) -> String {
+        match self.used {
+            Some(v) => tr!("按 {} 写入", v),
+            None => tr!("已忽略"),
+        }
+    }
+}
+
+/// 扫描阶段读取的曲目元数据，后续的过滤、分组和数据库构建共用同一份结果
+#[derive(Clone, Default)]
+pub struct TrackMeta {
+    /// 文件是否带有可读标签，或元数据文件给出了标题、艺术家（否则不分配专辑/艺术家 ID）
+    pub has_tag: bool,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    /// 专辑艺术家，合辑中各曲目的艺术家不同时用它区分同名专辑
+    pub album_artist: Option<String>,
+    pub genre: Option<String>,
+    /// 标签中的发行日期原文，如 "2024-03-05" 或 "2024"，用于播客单集的旁白
+    pub date: Option<String>,
+    /// 曲目编号，已排除不合理的值（见 `suspicious_numbers`）
+    pub track: Option<u32>,
+    pub disc: Option<u32>,
+    pub suspicious_numbers: Vec<SuspiciousNumber>,
+    pub duration_ms: Option<u64>,
+    pub bitrate_kbps: Option<u32>,
+    pub sample_rate: Option<u32>,
+    /// MP3 带有 Xing/VBRI 头（可变码率）
+    pub vbr: bool,
+    /// MP4 中的音频受 FairPlay 等 DRM 保护
+    pub protected: bool,
+    /// 旁白朗读的文字，由元数据文件或 --metadata-csv 给出；未设置时按标签推算
+    pub voiceover: Option<String>,
+    /// 元数据文件给出的增益 (0-99)，优先于 -g 与自动增益
+    pub gain: Option<u32>,
+}
+
+impl TrackMeta {
+    /// 按模板字段名取值，用于 ID3 分组模板
+    pub fn field(&self, name: &str) -> Option<&str> {
+        match name {
+            "title" => self.title.as_deref(),
+            "artist" => self.artist.as_deref(),
+            "album" => self.album.as_deref(),
+            "genre" => self.genre.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// 读取单个文件的标签与时长，读取失败时返回空元数据
+pub fn read_track_meta(path: &Path) -> TrackMeta {
+    let mut meta = TrackMeta::default();
+    let tagged = match lofty::read_from_path(path) {
+        Ok(t) => t,
+        Err(e) => {
+            trace!("{}", tr!("无法读取标签 {}: {}", path.display(), e));
+            return meta;
+        }
+    };
+    let props = tagged.properties();
+    meta.duration_ms = u64::try_from(props.duration().as_millis()).ok();
+    meta.bitrate_kbps = props.audio_bitrate();
+    meta.sample_rate = props.sample_rate();
+    meta.vbr = tagged.file_type() == lofty::file::FileType::Mpeg && mp3_is_vbr(path);
+    meta.protected = tagged.file_type() == lofty::file::FileType::Mp4 && mp4_is_protected(path);
+    if let Some(tag) = tagged.primary_tag().or_else(|| tagged.first_tag()) {
+        meta.has_tag = true;
+        meta.title = tag.title().map(|s| s.to_string());
+        meta.artist = tag.artist().map(|s| s.to_string());
+        meta.album = tag.album().map(|s| s.to_string());
+        meta.album_artist = tag.get_string(&ItemKey::AlbumArtist).map(|s| s.to_string());
+        meta.genre = tag.genre().map(|s| s.to_string());
+        meta.date = [ItemKey::ReleaseDate, ItemKey::RecordingDate, ItemKey::Year].iter()
+            .find_map(|key| tag.get_string(key))
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty());
+        let suspicious = &mut meta.suspicious_numbers;
+        meta.track = number_tag(tag, [ItemKey::TrackNumber, ItemKey::TrackTotal], "曲目编号", MAX_TRACK_NUMBER, suspicious);
+        meta.disc = number_tag(tag, [ItemKey::DiscNumber, ItemKey::DiscTotal], "碟号", MAX_DISC_NUMBER, suspicious);
+    }
+    trace!("{}", tr!("读取标签 {}: 标题 {}，艺术家 {}，专辑 {}，曲目 {}，碟号 {}，时长 {} ms",
+        path.display(), or_dash(&meta.title), or_dash(&meta.artist), or_dash(&meta.album),
+        or_dash(&meta.track), or_dash(&meta.disc), or_dash(&meta.duration_ms)));
+    meta
+}
+
+/// 跟踪日志中的可选字段，没有值时为 "-"
+fn or_dash<T: std::fmt::Display>(value: &Option<T>) -> String {
+    value.as_ref().map_or_else(|| "-".to_string(), T::to_string)
+}
+
+/// 曲目旁的元数据文件，如 "Song.mp3" 旁的 "Song.meta.toml"
+pub fn meta_sidecar_path(track: &Path) -> PathBuf {
+    let stem = track.file_stem().unwrap_or_default().to_string_lossy();
+    track.with_file_name(format!("{}{}", stem, META_SIDECAR_EXT))
+}
+
+/// 以曲目旁元数据文件中的 title、artist、gain 与 voiceover 覆盖标签，用于无法或不想修改标签的文件。
+/// 文件不存在时返回 `Ok(false)`
+pub fn apply_meta_sidecar(track: &Path, meta: &mut TrackMeta) -> Result<bool, String> {
+    let path = meta_sidecar_path(track);
+    let text = match fs::read_to_string(&path) {
+        Ok(t) => t,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(format!("无法读取 {}: {}", path.display(), e)),
+    };
+    let table = text.parse::<toml::Table>()
+        .map_err(|e| format!("{} 格式错误: {}", path.display(), e))?;
+    // Everything is checked before anything is applied, so a bad file leaves the tags alone
+    let mut sidecar = TrackMeta::default();
+    for (key, value) in &table {
+        let wrong_type = || format!("{}: \"{}\" 的值类型不正确", path.display(), key);
+        match key.as_str() {
+            "title" => sidecar.title = Some(value.as_str().ok_or_else(wrong_type)?.to_string()),
+            "artist" => sidecar.artist = Some(value.as_str().ok_or_else(wrong_type)?.to_string()),
+            "voiceover" => sidecar.voiceover = Some(value.as_str().ok_or_else(wrong_type)?.to_string()),
+            "gain" => {
+                let gain = value.as_integer().ok_or_else(wrong_type)?;
+                sidecar.gain = Some(u32::try_from(gain).ok().filter(|&g| g <= 99)
+                    .ok_or_else(|| format!("{}: 增益应为 0-99: {}", path.display(), gain))?);
+            }
+            _ => return Err(format!("{}: 未知的键 \"{}\"，可用的键: title, artist, gain, voiceover", path.display(), key)),
+        }
+    }
+    if sidecar.title.is_some() || sidecar.artist.is_some() {
+        meta.has_tag = true;
+    }
+    meta.title = sidecar.title.or(meta.title.take());
+    meta.artist = sidecar.artist.or(meta.artist.take());
+    meta.voiceover = sidecar.voiceover.or(meta.voiceover.take());
+    meta.gain = sidecar.gain.or(meta.gain);
+    Ok(true)
+}
+
+/// 为每首曲目应用旁边的元数据文件（见 `apply_meta_sidecar`），返回应用的文件数与无法使用的原因
+pub fn apply_meta_sidecars(tracks: &[PathBuf], metadata: &mut HashMap<PathBuf, TrackMeta>) -> (usize, Vec<String>) {
+    let mut applied = 0;
+    let mut failures = Vec::new();
+    for t in tracks {
+        let Some(meta) = metadata.get_mut(t) else { continue };
+        match apply_meta_sidecar(t, meta) {
+            Ok(true) => applied += 1,
+            Ok(false) => {}
+            Err(e) => failures.push(e),
+        }
+    }
+    (applied, failures)
+}
+
+/// 读取曲目编号或碟号。除 "3" 与 "3/12" 外，再接受 "7bis"、"07 of 12" 这类以数字开头的写法；
+/// 无法解析或超过 `max` 的值被忽略，0 视为未设置。可疑的值记入 `suspicious`
+fn number_tag(
+    tag: &lofty::tag::Tag, [key, total_key]: [ItemKey; 2], field: &'static str, max: u32,
+    suspicious: &mut Vec<SuspiciousNumber>,
+) -> Option<u32> {
+    // lofty files an ID3v2 TRCK/TPOS it can't parse under the total instead of the number
+    let raw = match tag.get_string(&key) {
+        Some(raw) => raw,
+        None => tag.get_string(&total_key).filter(|t| t.trim().parse::<u32>().is_err()).unwrap_or_default(),
+    };
+    let raw = raw.trim();
+    if raw.is_empty() { return None; }
+    let number = raw.split('/').next().unwrap_or_default().trim();
+    let digits: String = number.chars().take_while(|c| c.is_ascii_digit()).collect();
+    let value = digits.parse::<u64>().ok();
+    let used = value.filter(|&v| v <= max as u64).map(|v| v as u32);
+    if digits.len() != number.len() || value != used.map(u64::from) {
+        suspicious.push(SuspiciousNumber { field, raw: raw.to_string(), used: used.filter(|&v| v > 0) });
+    }
+    used.filter(|&v| v > 0)
+}
+
+/// 从文件顶层到音轨描述（stsd）依次经过的 MP4 盒子
+const MP4_STSD_PATH: [&[u8; 4]; 6] = [b"moov", b"trak", b"mdia", b"minf", b"stbl", b"stsd"];
+
+/// 加密音轨的样本描述类型：iTunes Store 的 FairPlay（drms）与通用加密（enca）
+const MP4_PROTECTED_ENTRIES: [&[u8; 4]; 2] = [b"drms", b"enca"];
+
+/// 检查 MP4 各音轨的样本描述，判断音频是否加密
+fn mp4_is_protected(path: &Path) -> bool {
+    let Ok(mut file) = fs::File::open(path) else { return false };
+    let Ok(len) = file.metadata().map(|m| m.len()) else { return false };
+    mp4_find_protected(&mut file, 0, len, 0).unwrap_or(false)
+}
+
+fn mp4_find_protected(file: &mut fs::File, start: u64, end: u64, depth: usize) -> std::io::Result<bool> {
+    let mut pos = start;
+    while pos + 8 <= end {
+        let mut header = [0u8; 8];
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut header)?;
+        let (size, header_len) = match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) {
+            0 => (end - pos, 8),
+            1 => {
+                let mut large = [0u8; 8];
+                file.read_exact(&mut large)?;
+                (u64::from_be_bytes(large), 16)
+            }
+            n => (n as u64, 8),
+        };
+        if size < header_len || pos + size > end { return Ok(false); }
+        if &header[4..8] == MP4_STSD_PATH[depth] {
+            if depth + 1 == MP4_STSD_PATH.len() {
+                // Version, flags and entry count precede the first sample entry's size and type
+                let mut entry = [0u8; 16];
+                file.seek(SeekFrom::Start(pos + header_len))?;
+                file.read_exact(&mut entry)?;
+                if MP4_PROTECTED_ENTRIES.iter().any(|t| &entry[12..16] == *t) {
+                    return Ok(true);
+                }
+            } else if mp4_find_protected(file, pos + header_len, pos + size, depth + 1)? {
+                return Ok(true);
+            }
+        }
+        pos += size;
+    }
+    Ok(false)
+}
+
+/// 检查第一帧中的 Xing/VBRI 头判断 MP3 是否为可变码率（LAME 的 "Info" 头表示 CBR）
+fn mp3_is_vbr(path: &Path) -> bool {
+    let Ok(mut file) = fs::File::open(path) else { return false };
+    let mut head = [0u8; 10];
+    if file.read_exact(&mut head).is_err() { return false; }
+    let mut start = 0u64;
+    if &head[..3] == b"ID3" {
+        let size = head[6..10].iter().fold(0u64, |acc, &b| (acc << 7) | (b & 0x7F) as u64);
+        start = 10 + size + if head[5] & 0x10 != 0 { 10 } else { 0 };
+    }
+    let mut buf = vec![0u8; 8192];
+    let n = match file.seek(SeekFrom::Start(start)).and_then(|_| file.read(&mut buf)) {
+        Ok(n) => n,
+        Err(_) => return false,
+    };
+    let buf = &buf[..n];
+    let Some(sync) = buf.windows(2).position(|w| w[0] == 0xFF && w[1] & 0xE0 == 0xE0) else { return false };
+    let frame = &buf[sync..buf.len().min(sync + 200)];
+    frame.windows(4).any(|w| w == b"Xing" || w == b"VBRI")
+}
+
+/// 并发读取所有曲目的元数据。单个文件的读取超过 `limit` 或崩溃时，
+/// 该文件会被放入返回的隔离列表，而不是拖住整个扫描
+pub fn read_all_meta(
+    tracks: &[PathBuf], limit: Duration,
+) -> (HashMap<PathBuf, TrackMeta>, Vec<(PathBuf, TaskError)>) {
+    let bar = progress::bar("读取标签", tracks.len());
+    let results: Vec<(PathBuf, Result<TrackMeta, TaskError>)> = tracks.par_iter()
+        .map(|t| {
+            // The sync stops right after this pass, so unread files need no metadata
+            if cancel::requested() {
+                bar.inc(1);
+                return (t.clone(), Ok(TrackMeta::default()));
+            }
+            let path = t.clone();
+            let result = run_with_timeout(limit, move || read_track_meta(&path));
+            bar.inc(1);
+            (t.clone(), result)
+        })
+        .collect();
+    bar.finish_and_clear();
+
+    let mut metadata = HashMap::with_capacity(results.len());
+    let mut quarantined = Vec::new();
+    for (track, result) in results {
+        match result {
+            Ok(meta) => { metadata.insert(track, meta); }
+            Err(e) => quarantined.push((track, e)),
+        }
+    }
+    (metadata, quarantined)
+}
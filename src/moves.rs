@@ -0,0 +1,56 @@
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::build_meta::{fingerprint, previous_fingerprints};
+use crate::database::ParsedDatabase;
+
+// ─── Move/rename detection ───────────────────────────────────────────────────
+
+/// 上次同步之后在设备上被移动或重命名的曲目
+pub struct TrackMove {
+    /// 上次数据库中的 iPod 路径
+    pub from: String,
+    pub to: PathBuf,
+}
+
+/// 按上次构建信息中的指纹找出被移动或重命名的曲目：原路径上的文件已不存在，
+/// 而某个新出现的曲目与它的指纹相同。`ipod_path` 给出曲目在数据库中的路径。
+/// 只有存在这样的原路径时才会读取新曲目
+pub fn detect_moves(
+    base: &Path,
+    previous: &ParsedDatabase,
+    tracks: &[PathBuf],
+    ipod_path: impl Fn(&Path) -> String,
+) -> Vec<TrackMove> {
+    let old_names: HashSet<&str> = previous.tracks.iter().map(|t| t.filename.as_str()).collect();
+    let new_names: Vec<String> = tracks.iter().map(|t| ipod_path(t)).collect();
+    let current: HashSet<&str> = new_names.iter().map(String::as_str).collect();
+
+    let mut orphans: HashMap<String, String> = HashMap::new();
+    for (name, fp) in previous_fingerprints(base) {
+        if old_names.contains(name.as_str())
+            && !current.contains(name.as_str())
+            && !base.join(name.trim_start_matches('/')).exists()
+        {
+            orphans.entry(fp).or_insert(name);
+        }
+    }
+    if orphans.is_empty() {
+        return Vec::new();
+    }
+
+    let candidates: Vec<&PathBuf> = tracks.iter().zip(&new_names)
+        .filter(|(_, name)| !old_names.contains(name.as_str()))
+        .map(|(t, _)| t)
+        .collect();
+    let fingerprints: Vec<Option<String>> = candidates.par_iter().map(|t| fingerprint(t).ok()).collect();
+
+    let mut moves = Vec::new();
+    for (track, fp) in candidates.into_iter().zip(fingerprints) {
+        if let Some(from) = fp.and_then(|fp| orphans.remove(&fp)) {
+            moves.push(TrackMove { from, to: track.clone() });
+        }
+    }
+    moves
+}
@@ -0,0 +1,103 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use crate::i18n::tr;
+use crate::report::{render_json, RunOutcome, SyncReport};
+use crate::timeout::run_process;
+
+// ─── Completion notifications ────────────────────────────────────────────────
+
+/// --notify-command 的时限，卡住的命令不会让同步一直不退出
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 桌面通知的标题
+const TITLE: &str = "iPod Shuffle 4G";
+
+/// 同步的结果，以 IPOD_SYNC_STATUS 传给 --notify-command
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Success,
+    Failure,
+    DryRun,
+}
+
+impl Status {
+    fn name(self) -> &'static str {
+        match self {
+            Status::Success => "success",
+            Status::Failure => "failure",
+            Status::DryRun => "dry-run",
+        }
+    }
+}
+
+/// 显示桌面通知（notify-rust：Linux 通过 D-Bus 通知服务，macOS 与 Windows 使用系统通知中心）
+fn show_desktop(message: &str) {
+    match notify_rust::Notification::new().appname(TITLE).summary(TITLE).body(message).show() {
+        Ok(_) => debug!("{}", tr!("已发送桌面通知: {}", message)),
+        Err(e) => warn!("{}", tr!("无法显示桌面通知: {}", e)),
+    }
+}
+
+/// 通过 shell 运行 --notify-command：标准输入为 JSON 汇总，环境变量给出结果、一行说明与设备路径
+fn run_command(command_line: &str, status: Status, message: &str, base: &Path, summary_json: &str) {
+    let mut command = if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.arg("/C").arg(command_line);
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.arg("-c").arg(command_line);
+        c
+    };
+    command.env("IPOD_SYNC_STATUS", status.name())
+        .env("IPOD_SYNC_MESSAGE", message)
+        .env("IPOD_SYNC_DEVICE", base);
+    if let Err(e) = run_process(command, Some(summary_json.as_bytes()), COMMAND_TIMEOUT) {
+        warn!("{}", tr!("--notify-command 失败: {}", e));
+    }
+}
+
+/// 同步的 --notify 与 --notify-command 设置
+struct Settings {
+    desktop: bool,
+    command: Option<String>,
+    base: PathBuf,
+    dry_run: bool,
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// 同步开始前记下通知设置，此后同步从任何位置结束或退出都会按设置通知
+pub fn configure(desktop: bool, command: Option<String>, base: &Path, dry_run: bool) {
+    let _ = SETTINGS.set(Settings { desktop, command, base: base.to_path_buf(), dry_run });
+}
+
+/// 同步结束时按 --notify 与 --notify-command 通知用户。`message` 为一行说明，
+/// `summary_json` 为 --output json 的汇总
+pub fn notify_completion(status: Status, message: &str, summary_json: &str) {
+    let Some(settings) = SETTINGS.get() else { return };
+    if settings.desktop {
+        show_desktop(message);
+    }
+    if let Some(ref command) = settings.command {
+        run_command(command, status, message, &settings.base, summary_json);
+    }
+}
+
+/// 同步因错误、Ctrl+C 或 --strict 提前退出时发送失败通知，无人值守的同步失败时同样能收到消息。
+/// 汇总为目前为止的 `report`，其中 error 为 `reason`
+pub fn notify_failure(reason: &str, report: &SyncReport) {
+    let Some(settings) = SETTINGS.get() else { return };
+    if !settings.desktop && settings.command.is_none() { return; }
+    let outcome = RunOutcome {
+        dry_run: settings.dry_run,
+        error: Some(reason.to_string()),
+        voiceovers: (0, 0, 0),
+        report_path: None,
+    };
+    notify_completion(Status::Failure, &tr!("同步失败: {}", reason), &render_json(report, &outcome));
+}
@@ -0,0 +1,398 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::collation::{compare, compare_paths};
+use crate::database::make_dbid;
+use crate::device_io::write_file_atomic;
+use crate::metadata::TrackMeta;
+use crate::utils::{is_subpath, normalize_path};
+
+// ─── Master order ────────────────────────────────────────────────────────────
+
+/// 分组用的艺术家键：无艺术家标签时退回到所在目录名
+fn artist_key(track: &PathBuf, metadata: &HashMap<PathBuf, TrackMeta>) -> String {
+    metadata.get(track)
+        .and_then(|m| m.artist.clone())
+        .filter(|a| !a.is_empty())
+        .unwrap_or_else(|| {
+            track.parent()
+                .and_then(|p| p.file_name())
+                .map(|f| f.to_string_lossy().to_string())
+                .unwrap_or_default()
+        })
+        .to_lowercase()
+}
+
+/// 将同一艺术家的曲目在列表中尽量均匀地分散开（类似智能随机），
+/// 同一艺术家内部保持原有顺序。结果是确定的，便于多次同步之间保持一致
+pub fn spread_by_artist(tracks: &[PathBuf], metadata: &HashMap<PathBuf, TrackMeta>) -> Vec<PathBuf> {
+    let mut groups: Vec<(String, Vec<PathBuf>)> = Vec::new();
+    let mut group_index: HashMap<String, usize> = HashMap::new();
+    for t in tracks {
+        let key = artist_key(t, metadata);
+        let idx = *group_index.entry(key.clone()).or_insert_with(|| {
+            groups.push((key, Vec::new()));
+            groups.len() - 1
+        });
+        groups[idx].1.push(t.clone());
+    }
+
+    // Each artist's k tracks are placed at (j + offset) / k, with a per-artist
+    // offset derived from its name so different artists don't line up
+    let mut placed: Vec<(f64, usize, PathBuf)> = Vec::with_capacity(tracks.len());
+    for (gi, (key, group)) in groups.into_iter().enumerate() {
+        let k = group.len() as f64;
+        let offset = make_dbid(key.as_bytes())[0] as f64 / 256.0;
+        for (j, t) in group.into_iter().enumerate() {
+            placed.push(((j as f64 + offset) / k, gi, t));
+        }
+    }
+    placed.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+    placed.into_iter().map(|(_, _, t)| t).collect()
+}
+
+/// 上次数据库中已有的曲目按原来的先后排在前面，新曲目按当前顺序追加在末尾，
+/// 使曲目索引在两次同步之间尽量不变。`previous` 为上次数据库中的曲目文件名（iPod 路径），
+/// `ipod_path` 给出曲目在数据库中的文件名
+pub fn keep_previous_order(
+    tracks: &[PathBuf], previous: &[String], ipod_path: impl Fn(&Path) -> String,
+) -> Vec<PathBuf> {
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+    for (i, name) in previous.iter().enumerate() {
+        positions.entry(name.as_str()).or_insert(i);
+    }
+    let mut keyed: Vec<(usize, PathBuf)> = tracks.iter()
+        .map(|t| (positions.get(ipod_path(t).as_str()).copied().unwrap_or(usize::MAX), t.clone()))
+        .collect();
+    // Stable, so new tracks keep their current order at the end
+    keyed.sort_by_key(|(pos, _)| *pos);
+    keyed.into_iter().map(|(_, t)| t).collect()
+}
+
+// ─── Audiobook parts ─────────────────────────────────────────────────────────
+
+/// 名称中表示分册、分段的关键字，其后的罗马数字（如 "Book II"）也按编号识别
+const PART_KEYWORDS: &[&str] = &[
+    "book", "part", "pt", "volume", "vol", "chapter", "ch", "disc", "disk", "cd", "episode", "ep",
+];
+
+/// 罗马数字的值。只接受规范写法（"IV" 而不是 "IIII"），避免把普通单词当成编号
+fn roman_value(token: &str) -> Option<u32> {
+    const NUMERALS: &[(u32, &str)] = &[
+        (1000, "M"), (900, "CM"), (500, "D"), (400, "CD"), (100, "C"), (90, "XC"),
+        (50, "L"), (40, "XL"), (10, "X"), (9, "IX"), (5, "V"), (4, "IV"), (1, "I"),
+    ];
+    let upper = token.to_ascii_uppercase();
+    let (mut rest, mut value) = (upper.as_str(), 0u32);
+    for &(n, numeral) in NUMERALS {
+        // At most three repeats of a numeral, and the subtractive pairs at most once
+        for _ in 0..if numeral.len() == 1 { 3 } else { 1 } {
+            match rest.strip_prefix(numeral) {
+                Some(r) => { rest = r; value += n; }
+                None => break,
+            }
+        }
+    }
+    (rest.is_empty() && value > 0).then_some(value)
+}
+
+/// 名称中的编号与编号之前的文字。数字按数值比较（"03" 与 "3" 相同），跟在关键字之后的罗马数字同样算作编号，
+/// 如 "Dune Book II Part 03" -> ("dune book", [2, 3])
+fn part_numbers(name: &str) -> (String, Vec<u32>) {
+    // Runs of digits and runs of letters, with their byte offsets
+    let class = |c: char| if c.is_ascii_digit() { 1 } else if c.is_alphabetic() { 2 } else { 0 };
+    let mut tokens: Vec<(usize, &str)> = Vec::new();
+    let mut run: Option<(usize, u8)> = None;
+    for (i, c) in name.char_indices().chain(std::iter::once((name.len(), ' '))) {
+        if let Some((start, k)) = run {
+            if k != class(c) {
+                tokens.push((start, &name[start..i]));
+                run = None;
+            }
+        }
+        if run.is_none() && class(c) != 0 {
+            run = Some((i, class(c)));
+        }
+    }
+
+    let mut prefix: Option<String> = None;
+    let mut numbers = Vec::new();
+    let mut previous = "";
+    for (at, token) in tokens {
+        let number = if token.bytes().all(|b| b.is_ascii_digit()) {
+            Some(token.trim_start_matches('0').parse::<u32>().unwrap_or(0))
+        } else if PART_KEYWORDS.contains(&previous.to_lowercase().as_str()) {
+            roman_value(token)
+        } else {
+            None
+        };
+        match number {
+            Some(n) => {
+                prefix.get_or_insert_with(|| name[..at].trim().to_lowercase());
+                numbers.push(n);
+            }
+            None => previous = token,
+        }
+    }
+    (prefix.unwrap_or_else(|| name.trim().to_lowercase()), numbers)
+}
+
+/// 有声书分段的排序键：系列（专辑标签，或所在目录与文件名中编号之前的文字）、目录与文件名中的编号、碟号与曲目编号
+fn audiobook_key(track: &Path, metadata: &HashMap<PathBuf, TrackMeta>) -> (String, Vec<u32>, u32, u32) {
+    let meta = metadata.get(track);
+    let dir = track.parent().and_then(|p| p.file_name()).unwrap_or_default().to_string_lossy();
+    let stem = track.file_stem().unwrap_or_default().to_string_lossy();
+    let (dir_prefix, mut numbers) = part_numbers(&dir);
+    let (stem_prefix, stem_numbers) = part_numbers(&stem);
+    numbers.extend(stem_numbers);
+    let series = match meta.and_then(|m| m.album.as_deref()).filter(|a| !a.trim().is_empty()) {
+        Some(album) => {
+            let (album_prefix, album_numbers) = part_numbers(album);
+            numbers.splice(0..0, album_numbers);
+            album_prefix
+        }
+        None => format!("{}/{}", dir_prefix, stem_prefix),
+    };
+    (series, numbers, meta.and_then(|m| m.disc).unwrap_or(0), meta.and_then(|m| m.track).unwrap_or(0))
+}
+
+/// 将有声书的各个文件按系列与分段编号排列，如 "Book 1 Part 2" 在 "Book 1 Part 10" 之前、"Book II" 在 "Book III" 之前。
+/// 编号相同时依次比较碟号、曲目编号与路径
+pub fn sort_audiobook_parts(tracks: &[PathBuf], metadata: &HashMap<PathBuf, TrackMeta>) -> Vec<PathBuf> {
+    let mut keyed: Vec<_> = tracks.iter()
+        .map(|t| (audiobook_key(t, metadata), t.clone()))
+        .collect();
+    keyed.sort_by(|(a, pa), (b, pb)| {
+        compare(&a.0, &b.0)
+            .then_with(|| a.1.cmp(&b.1))
+            .then_with(|| (a.2, a.3).cmp(&(b.2, b.3)))
+            .then_with(|| compare_paths(pa, pb))
+    });
+    keyed.into_iter().map(|(_, t)| t).collect()
+}
+
+// ─── Shuffled order ──────────────────────────────────────────────────────────
+
+/// iPod_Control/iTunes 下保存 --order shuffle 种子的文件，同一台设备的每次同步沿用同一个种子
+pub const SEED_FILE: &str = "shuffle_seed.txt";
+
+/// --order shuffle 与播放列表选项 `order = "shuffle"` 使用的种子
+pub struct ShuffleSeed {
+    base: PathBuf,
+    seed: u64,
+    /// 种子是本次生成的，还没有保存到设备上
+    fresh: bool,
+}
+
+impl ShuffleSeed {
+    /// 读取设备上保存的种子。没有种子、种子无法读取或 `reshuffle` 时生成新的种子，由 `save` 保存
+    pub fn load(base: &Path, reshuffle: bool) -> Self {
+        let saved = (!reshuffle)
+            .then(|| fs::read_to_string(base.join("iPod_Control").join("iTunes").join(SEED_FILE)).ok())
+            .flatten()
+            .and_then(|t| u64::from_str_radix(t.trim(), 16).ok());
+        let seed = saved.unwrap_or_else(|| {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+            u64::from_le_bytes(make_dbid(format!("{}:{}", now, std::process::id()).as_bytes()))
+        });
+        ShuffleSeed { base: base.to_path_buf(), seed, fresh: saved.is_none() }
+    }
+
+    pub fn fresh(&self) -> bool {
+        self.fresh
+    }
+
+    /// 将种子保存到设备上，在数据库写入之后调用
+    pub fn save(&self) -> Result<(), String> {
+        let path = self.base.join("iPod_Control").join("iTunes").join(SEED_FILE);
+        write_file_atomic(&path, format!("{:016x}\n", self.seed)).map_err(|e| format!("无法写入 {}: {}", path.display(), e))
+    }
+
+    /// 按种子打乱曲目。每首曲目的位置只取决于种子与它相对 iPod 根目录的路径（不含扩展名，
+    /// 转换为 MP3 前后相同），因此增删曲目时其余曲目的先后保持不变
+    pub fn shuffle(&self, tracks: &[PathBuf]) -> Vec<PathBuf> {
+        let mut keyed: Vec<([u8; 8], PathBuf)> = tracks.iter()
+            .map(|t| {
+                let rel = t.strip_prefix(&self.base).unwrap_or(t).with_extension("");
+                let key = format!("{:016x}:{}", self.seed, rel.to_string_lossy().replace('\\', "/"));
+                (make_dbid(key.as_bytes()), t.clone())
+            })
+            .collect();
+        keyed.sort_by_key(|(key, _)| *key);
+        keyed.into_iter().map(|(_, t)| t).collect()
+    }
+}
+
+// ─── Pinned tracks (order.txt) ───────────────────────────────────────────────
+
+/// iPod 根目录下固定主列表首尾曲目的文件
+pub const ORDER_FILE: &str = "order.txt";
+
+/// 固定位置的曲目：路径（文件或目录）或专辑标签
+enum Pin {
+    Path(PathBuf),
+    Album(String),
+}
+
+impl Pin {
+    fn matches(&self, track: &Path, metadata: &HashMap<PathBuf, TrackMeta>) -> bool {
+        match self {
+            Pin::Path(p) => is_subpath(track, p),
+            Pin::Album(a) => metadata.get(track)
+                .and_then(|m| m.album.as_deref())
+                .is_some_and(|album| album.eq_ignore_ascii_case(a)),
+        }
+    }
+}
+
+/// order.txt 中的规则，按出现顺序排列
+#[derive(Default)]
+pub struct OrderPins {
+    first: Vec<Pin>,
+    last: Vec<Pin>,
+}
+
+impl OrderPins {
+    pub fn is_empty(&self) -> bool {
+        self.first.is_empty() && self.last.is_empty()
+    }
+}
+
+/// 读取 order.txt，不存在时返回空规则。每行一条规则：
+///
+/// ```text
+/// # 注释
+/// first: Podcasts/Queue        # 文件或目录，相对 iPod 根目录
+/// first: album:Daily News      # 按专辑标签匹配
+/// last: iPod_Control/Music/Christmas
+/// ```
+///
+/// 省略 `first:`/`last:` 时视为 `first:`
+pub fn load_order_pins(base: &Path) -> Result<OrderPins, String> {
+    let path = base.join(ORDER_FILE);
+    let text = match fs::read_to_string(&path) {
+        Ok(t) => t,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(OrderPins::default()),
+        Err(e) => return Err(format!("无法读取 {}: {}", path.display(), e)),
+    };
+    let mut pins = OrderPins::default();
+    for line in text.lines() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        if line.is_empty() || line.starts_with('#') { continue; }
+        let (to_end, rule) = if let Some(rest) = line.strip_prefix("last:") {
+            (true, rest.trim())
+        } else {
+            (false, line.strip_prefix("first:").unwrap_or(line).trim())
+        };
+        let pin = match rule.strip_prefix("album:") {
+            Some(album) => Pin::Album(album.trim().to_string()),
+            None => Pin::Path(normalize_path(&base.join(rule.trim_start_matches('/')))),
+        };
+        if to_end { pins.last.push(pin); } else { pins.first.push(pin); }
+    }
+    Ok(pins)
+}
+
+/// 将匹配 `first` 规则的曲目移到主列表开头、匹配 `last` 规则的移到末尾，
+/// 按规则的先后分组，每组内部保持原有顺序。同时匹配多条规则时以第一条 `first` 规则为准
+pub fn apply_order_pins(tracks: &[PathBuf], metadata: &HashMap<PathBuf, TrackMeta>, pins: &OrderPins) -> Vec<PathBuf> {
+    let mut first: Vec<Vec<PathBuf>> = pins.first.iter().map(|_| Vec::new()).collect();
+    let mut last: Vec<Vec<PathBuf>> = pins.last.iter().map(|_| Vec::new()).collect();
+    let mut middle = Vec::new();
+    for t in tracks {
+        if let Some(i) = pins.first.iter().position(|p| p.matches(t, metadata)) {
+            first[i].push(t.clone());
+        } else if let Some(i) = pins.last.iter().position(|p| p.matches(t, metadata)) {
+            last[i].push(t.clone());
+        } else {
+            middle.push(t.clone());
+        }
+    }
+    first.into_iter().flatten().chain(middle).chain(last.into_iter().flatten()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(names: &[&str]) -> Vec<PathBuf> {
+        names.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn roman_values() {
+        assert_eq!(roman_value("IV"), Some(4));
+        assert_eq!(roman_value("xii"), Some(12));
+        assert_eq!(roman_value("MCMXCIV"), Some(1994));
+        assert_eq!(roman_value("IIII"), None);
+        assert_eq!(roman_value("VX"), None);
+        assert_eq!(roman_value("Ivy"), None);
+        assert_eq!(roman_value(""), None);
+    }
+
+    #[test]
+    fn part_numbers_of_names() {
+        assert_eq!(part_numbers("Dune Book II Part 03"), ("dune book".to_string(), vec![2, 3]));
+        assert_eq!(part_numbers("Chapter 007"), ("chapter".to_string(), vec![7]));
+        assert_eq!(part_numbers("Part IV"), ("part".to_string(), vec![4]));
+        // A roman numeral only counts after a keyword
+        assert_eq!(part_numbers("Vivid"), ("vivid".to_string(), vec![]));
+        assert_eq!(part_numbers("Mix Tape"), ("mix tape".to_string(), vec![]));
+    }
+
+    #[test]
+    fn audiobook_parts_in_numeric_order() {
+        let tracks = paths(&["/b/Dune/Part 10.mp3", "/b/Dune/Part 02.mp3", "/b/Dune/Part 1.mp3"]);
+        let sorted = sort_audiobook_parts(&tracks, &HashMap::new());
+        assert_eq!(sorted, paths(&["/b/Dune/Part 1.mp3", "/b/Dune/Part 02.mp3", "/b/Dune/Part 10.mp3"]));
+    }
+
+    #[test]
+    fn audiobook_parts_in_roman_order() {
+        let tracks = paths(&["/b/Dune Book III/01.mp3", "/b/Dune Book I/02.mp3", "/b/Dune Book II/01.mp3", "/b/Dune Book I/01.mp3"]);
+        let sorted = sort_audiobook_parts(&tracks, &HashMap::new());
+        assert_eq!(sorted, paths(&[
+            "/b/Dune Book I/01.mp3", "/b/Dune Book I/02.mp3", "/b/Dune Book II/01.mp3", "/b/Dune Book III/01.mp3",
+        ]));
+    }
+
+    fn ipod_name(track: &Path) -> String {
+        track.file_name().unwrap_or_default().to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn previous_order_first_then_new_tracks() {
+        let tracks = paths(&["/m/a.mp3", "/m/b.mp3", "/m/c.mp3", "/m/d.mp3"]);
+        let previous = vec!["c.mp3".to_string(), "gone.mp3".to_string(), "a.mp3".to_string()];
+        let kept = keep_previous_order(&tracks, &previous, ipod_name);
+        assert_eq!(kept, paths(&["/m/c.mp3", "/m/a.mp3", "/m/b.mp3", "/m/d.mp3"]));
+    }
+
+    fn album(name: &str) -> TrackMeta {
+        TrackMeta { album: Some(name.to_string()), ..Default::default() }
+    }
+
+    #[test]
+    fn pins_group_tracks_by_rule() {
+        let tracks = paths(&["/i/Music/x.mp3", "/i/Queue/q1.mp3", "/i/Music/news.mp3", "/i/Xmas/s.mp3", "/i/Queue/q2.mp3"]);
+        let metadata = HashMap::from([(PathBuf::from("/i/Music/news.mp3"), album("Daily News"))]);
+        let pins = OrderPins {
+            first: vec![Pin::Album("daily news".to_string()), Pin::Path(PathBuf::from("/i/Queue"))],
+            last: vec![Pin::Path(PathBuf::from("/i/Xmas"))],
+        };
+        let pinned = apply_order_pins(&tracks, &metadata, &pins);
+        assert_eq!(pinned, paths(&["/i/Music/news.mp3", "/i/Queue/q1.mp3", "/i/Queue/q2.mp3", "/i/Music/x.mp3", "/i/Xmas/s.mp3"]));
+    }
+
+    #[test]
+    fn pins_apply_on_top_of_previous_order() {
+        let tracks = paths(&["/i/Queue/q.mp3", "/i/Music/a.mp3", "/i/Music/b.mp3"]);
+        let previous = vec!["b.mp3".to_string(), "a.mp3".to_string(), "q.mp3".to_string()];
+        let pins = OrderPins { first: vec![Pin::Path(PathBuf::from("/i/Queue"))], last: Vec::new() };
+        let kept = keep_previous_order(&tracks, &previous, ipod_name);
+        let pinned = apply_order_pins(&kept, &HashMap::new(), &pins);
+        assert_eq!(pinned, paths(&["/i/Queue/q.mp3", "/i/Music/b.mp3", "/i/Music/a.mp3"]));
+    }
+}
@@ -0,0 +1,93 @@
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+use super::{PlaylistSource, ResolveContext};
+use crate::collation::compare_paths;
+
+// ─── Directory playlists ─────────────────────────────────────────────────────
+
+/// 目录播放列表的选项文件
+pub const OPTIONS_FILE: &str = "playlist.toml";
+
+/// 目录播放列表的命名模板（--dir-playlist-name）。`{name}` 为目录名，`{parent}` 为上级目录名，
+/// `{path}` 为相对自动播放列表根目录的路径，`{path:N}` 为其最后 N 级，各级之间以 " - " 连接
+#[derive(Clone)]
+pub struct NameTemplate(String);
+
+fn placeholder_re() -> Regex {
+    Regex::new(r"\{(\w+)(?::(\d+))?\}").unwrap()
+}
+
+impl NameTemplate {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        for caps in placeholder_re().captures_iter(s) {
+            match (&caps[1], caps.get(2).map(|n| n.as_str())) {
+                ("name" | "parent" | "path", None) => {}
+                ("path", Some(n)) if n.parse::<usize>().is_ok_and(|n| n > 0) => {}
+                ("path", Some(n)) => return Err(format!("{{path:N}} 中的 N 必须是正整数: {}", n)),
+                _ => return Err(format!("未知的模板变量 \"{}\"，可用 {{name}}、{{parent}}、{{path}}、{{path:N}}", &caps[0])),
+            }
+        }
+        Ok(NameTemplate(s.to_string()))
+    }
+
+    /// `root` 下的目录 `dir` 按模板得到的播放列表名称
+    pub fn render(&self, dir: &Path, root: &Path) -> String {
+        let file_name = |p: &Path| p.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let mut levels: Vec<String> = dir.strip_prefix(root).unwrap_or(dir).components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        if levels.is_empty() {
+            levels.push(file_name(dir));
+        }
+        placeholder_re().replace_all(&self.0, |caps: &regex::Captures| match &caps[1] {
+            // The stem, as directory playlists were always named, so existing dbids stay put
+            "name" => dir.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+            "parent" => dir.parent().map(file_name).unwrap_or_default(),
+            _ => {
+                let n = caps.get(2).and_then(|n| n.as_str().parse().ok()).unwrap_or(levels.len());
+                levels[levels.len().saturating_sub(n)..].join(" - ")
+            }
+        }).into_owned()
+    }
+}
+
+/// 目录中的所有曲目，按名称逐级排序，默认以目录名命名
+pub struct DirectoryPlaylist {
+    dir: PathBuf,
+    name: String,
+}
+
+impl DirectoryPlaylist {
+    pub fn new(dir: PathBuf) -> Self {
+        let name = dir.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        DirectoryPlaylist { dir, name }
+    }
+
+    /// 以 `name` 代替目录名命名
+    pub fn named(dir: PathBuf, name: String) -> Self {
+        DirectoryPlaylist { dir, name }
+    }
+}
+
+impl PlaylistSource for DirectoryPlaylist {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    // Members come from the scan's track list rather than a second walk of the device,
+    // which also applies the scan's hidden and exclusion policies
+    fn resolve(&self, ctx: &ResolveContext<'_>) -> Vec<u32> {
+        let dir = ctx.identity.key(&self.dir);
+        let mut members: Vec<u32> = ctx.track_positions.iter()
+            .filter(|(key, _)| key.starts_with(&dir))
+            .map(|(_, &i)| i as u32)
+            .collect();
+        members.sort_by(|&a, &b| compare_paths(&ctx.tracks[a as usize], &ctx.tracks[b as usize]));
+        members
+    }
+
+    fn options_file(&self) -> Option<PathBuf> {
+        Some(self.dir.join(OPTIONS_FILE))
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::{PlaylistSource, ResolveContext};
+use crate::collation::{compare, compare_paths};
+use crate::metadata::TrackMeta;
+
+// ─── Tag-grouped playlists ───────────────────────────────────────────────────
+
+/// 不在有声书目录中的 .m4b 与 .aa 文件组成的有声书列表
+pub const AUDIOBOOK_PLAYLIST: &str = "Audiobooks";
+
+/// 预先选定的一组曲目，如按标签模板分组的结果
+pub struct GroupedPlaylist {
+    name: String,
+    files: Vec<PathBuf>,
+}
+
+impl GroupedPlaylist {
+    pub fn new(name: String, files: Vec<PathBuf>) -> Self {
+        GroupedPlaylist { name, files }
+    }
+}
+
+impl PlaylistSource for GroupedPlaylist {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn resolve(&self, ctx: &ResolveContext<'_>) -> Vec<u32> {
+        self.files.iter().filter_map(|f| ctx.index_of(f)).collect()
+    }
+}
+
+pub fn group_tracks_by_id3_template(
+    tracks: &[PathBuf], metadata: &HashMap<PathBuf, TrackMeta>, template: &str,
+) -> Vec<(String, Vec<PathBuf>)> {
+    let re = regex::Regex::new(r"\{.*?\}").unwrap();
+    let template_vars: Vec<String> = re.find_iter(template).map(|m| m.as_str().to_string()).collect();
+    let mut grouped: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    let empty = TrackMeta::default();
+    for track in tracks {
+        let meta = metadata.get(track).unwrap_or(&empty);
+        let mut key = template.to_string();
+        let mut any_present = false;
+        for var in &template_vars {
+            let field = &var[1..var.len()-1];
+            let val = meta.field(field).unwrap_or_default();
+            if !val.is_empty() { any_present = true; }
+            key = key.replace(var.as_str(), val);
+        }
+        if any_present {
+            grouped.entry(key).or_default().push(track.clone());
+        }
+    }
+    let mut result: Vec<(String, Vec<PathBuf>)> = grouped.into_iter().collect();
+    result.sort_by(|a, b| compare(&a.0, &b.0));
+    result
+}
+
+/// 按专辑艺术家（没有时为艺术家）与专辑标签分组，每组按碟号、曲目编号排序（没有编号的曲目排在最后，
+/// 再按路径），与曲目所在的目录无关。没有专辑标签的曲目不属于任何一组。
+/// 不同艺术家的同名专辑各成一组，列表名称以 "专辑 (艺术家)" 区分
+pub fn group_tracks_by_album(
+    tracks: &[PathBuf], metadata: &HashMap<PathBuf, TrackMeta>,
+) -> Vec<(String, Vec<PathBuf>)> {
+    let mut grouped: HashMap<(String, String), Vec<PathBuf>> = HashMap::new();
+    for track in tracks {
+        let Some(meta) = metadata.get(track) else { continue };
+        let album = meta.album.as_deref().unwrap_or("").trim();
+        if album.is_empty() {
+            continue;
+        }
+        let artist = meta.album_artist.as_deref().map(str::trim).filter(|a| !a.is_empty())
+            .or_else(|| meta.artist.as_deref().map(str::trim))
+            .unwrap_or("");
+        grouped.entry((artist.to_string(), album.to_string())).or_default().push(track.clone());
+    }
+    let mut same_name: HashMap<String, usize> = HashMap::new();
+    for (_, album) in grouped.keys() {
+        *same_name.entry(album.clone()).or_default() += 1;
+    }
+    let position = |t: &PathBuf| { let meta = metadata.get(t);
+        (meta.and_then(|m| m.disc).unwrap_or(1), meta.and_then(|m| m.track).unwrap_or(u32::MAX)) };
+    let mut result: Vec<(String, Vec<PathBuf>)> = grouped.into_iter().map(|((artist, album), mut files)| {
+        files.sort_by(|a, b| position(a).cmp(&position(b)).then_with(|| compare_paths(a, b)));
+        let name = if same_name[&album] > 1 && !artist.is_empty() { format!("{} ({})", album, artist) } else { album };
+        (name, files)
+    }).collect();
+    result.sort_by(|a, b| compare(&a.0, &b.0));
+    result
+}
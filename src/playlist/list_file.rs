@@ -0,0 +1,170 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use encoding_rs::Encoding;
+use tracing::{debug, error};
+
+use super::{PlaylistProvider, PlaylistSource, ResolveContext};
+use crate::utils::{normalize_path, validate_unicode};
+
+// ─── Playlist files (.m3u, .m3u8, .pls) ──────────────────────────────────────
+
+/// .m3u 与 .pls 不是 UTF-8 时按哪种编码解码（--list-encoding）。.m3u8 总是按 UTF-8 解码
+#[derive(Clone, Copy)]
+pub enum ListEncoding {
+    /// 依次尝试 UTF-8、GBK，最后按 Windows-1252（Latin-1）
+    Auto,
+    Fixed(&'static Encoding),
+}
+
+impl ListEncoding {
+    /// 解析 "auto" 或 WHATWG 编码名称，如 "gbk"、"big5"、"shift_jis"、"latin1"
+    pub fn parse(s: &str) -> Result<Self, String> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(ListEncoding::Auto);
+        }
+        Encoding::for_label(s.trim().as_bytes())
+            .map(ListEncoding::Fixed)
+            .ok_or_else(|| format!("未知的编码 \"{}\"", s))
+    }
+
+    /// 解码列表文件内容。有 UTF-8 BOM 或内容本身是有效的 UTF-8 时总是按 UTF-8
+    pub fn decode(self, bytes: &[u8]) -> String {
+        let bytes = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+        if let Ok(utf8) = std::str::from_utf8(bytes) {
+            return utf8.to_string();
+        }
+        let encoding = match self {
+            ListEncoding::Fixed(encoding) => encoding,
+            ListEncoding::Auto => {
+                // GBK accepts many Latin-1 byte pairs, so only take results that contain CJK text
+                let (gbk, had_errors) = encoding_rs::GBK.decode_without_bom_handling(bytes);
+                if !had_errors && gbk.chars().any(|c| ('\u{4E00}'..='\u{9FFF}').contains(&c)) {
+                    return gbk.into_owned();
+                }
+                encoding_rs::WINDOWS_1252
+            }
+        };
+        encoding.decode_without_bom_handling(bytes).0.into_owned()
+    }
+}
+
+pub fn parse_m3u(data: &str, rename: bool) -> Vec<String> {
+    data.lines()
+        .filter(|l| !l.starts_with('#') && !l.trim().is_empty())
+        .map(|l| {
+            let path = l.trim().to_string();
+            if rename { validate_unicode(&path) } else { path }
+        })
+        .collect()
+}
+
+fn parse_pls(data: &str, rename: bool) -> Vec<String> {
+    let mut sort_tracks: Vec<(i32, String)> = Vec::new();
+    for line in data.lines() {
+        let parts: Vec<&str> = line.trim().splitn(2, '=').collect();
+        if parts.len() == 2 && parts[0].to_lowercase().starts_with("file") {
+            if let Ok(num) = parts[0][4..].parse::<i32>() {
+                let mut filename = percent_encoding::percent_decode_str(parts[1].trim())
+                    .decode_utf8_lossy()
+                    .to_string();
+                if filename.to_lowercase().starts_with("file://") {
+                    filename = filename[7..].to_string();
+                }
+                if rename {
+                    filename = validate_unicode(&filename);
+                }
+                sort_tracks.push((num, filename));
+            }
+        }
+    }
+    sort_tracks.sort_by_key(|(n, _)| *n);
+    sort_tracks.into_iter().map(|(_, f)| f).collect()
+}
+
+/// 列表文件中的曲目，按文件中的顺序，以文件名命名。
+/// 相对路径相对于列表文件所在目录
+pub struct ListFile {
+    path: PathBuf,
+    parse: fn(&str, bool) -> Vec<String>,
+    /// 忽略 --list-encoding，总是按 UTF-8 解码
+    utf8: bool,
+}
+
+impl PlaylistSource for ListFile {
+    fn name(&self) -> String {
+        self.path.file_stem().unwrap_or_default().to_string_lossy().to_string()
+    }
+
+    fn resolve(&self, ctx: &ResolveContext<'_>) -> Vec<u32> {
+        let raw = fs::read(&self.path).unwrap_or_default();
+        let data = if self.utf8 {
+            let text = String::from_utf8_lossy(&raw);
+            text.strip_prefix('\u{feff}').unwrap_or(&text).to_string()
+        } else {
+            if std::str::from_utf8(&raw).is_err() {
+                debug!("{} 不是 UTF-8，按 --list-encoding 解码", self.path.display());
+            }
+            ctx.encoding.decode(&raw)
+        };
+        let playlist_dir = self.path.parent().unwrap_or(ctx.base);
+        (self.parse)(&data, ctx.rename).iter().filter_map(|rel| {
+            let p = if Path::new(rel).exists() {
+                PathBuf::from(rel)
+            } else {
+                playlist_dir.join(rel)
+            };
+            let canon = normalize_path(&p);
+            let index = ctx.index_of(&canon);
+            if index.is_none() {
+                error!("无法找到曲目 \"{}\"。跳过。", canon.display());
+                ctx.missing.borrow_mut().push(canon);
+            }
+            index
+        }).collect()
+    }
+
+    fn source_file(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+
+    /// 与列表文件同名的 .toml 文件，如 "Road Trip.m3u" 旁的 "Road Trip.toml"
+    fn options_file(&self) -> Option<PathBuf> {
+        Some(self.path.with_extension("toml"))
+    }
+}
+
+pub struct M3uProvider;
+
+impl PlaylistProvider for M3uProvider {
+    fn extensions(&self) -> &'static [&'static str] {
+        &[".m3u"]
+    }
+
+    fn open(&self, path: &Path) -> Box<dyn PlaylistSource> {
+        Box::new(ListFile { path: path.to_path_buf(), parse: parse_m3u, utf8: false })
+    }
+}
+
+pub struct M3u8Provider;
+
+impl PlaylistProvider for M3u8Provider {
+    fn extensions(&self) -> &'static [&'static str] {
+        &[".m3u8"]
+    }
+
+    fn open(&self, path: &Path) -> Box<dyn PlaylistSource> {
+        Box::new(ListFile { path: path.to_path_buf(), parse: parse_m3u, utf8: true })
+    }
+}
+
+pub struct PlsProvider;
+
+impl PlaylistProvider for PlsProvider {
+    fn extensions(&self) -> &'static [&'static str] {
+        &[".pls"]
+    }
+
+    fn open(&self, path: &Path) -> Box<dyn PlaylistSource> {
+        Box::new(ListFile { path: path.to_path_buf(), parse: parse_pls, utf8: false })
+    }
+}
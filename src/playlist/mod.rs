@@ -0,0 +1,93 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::utils::{ext_lower, PathIdentity};
+
+mod directory;
+mod grouped;
+mod list_file;
+mod options;
+
+pub use directory::{DirectoryPlaylist, NameTemplate};
+pub use grouped::{group_tracks_by_album, group_tracks_by_id3_template, GroupedPlaylist, AUDIOBOOK_PLAYLIST};
+pub use list_file::{parse_m3u, ListEncoding};
+pub use options::{config_options, read_sidecar, ListType, PlaylistOptions, CONFIG_TABLE};
+
+// ─── Playlist sources ────────────────────────────────────────────────────────
+
+/// 解析播放列表时可用的上下文
+pub struct ResolveContext<'a> {
+    pub base: &'a Path,
+    /// 是否按 --rename-unicode 转换列表中的路径
+    pub rename: bool,
+    /// 非 UTF-8 的 .m3u 与 .pls 的解码方式
+    pub encoding: ListEncoding,
+    /// 本次同步扫描到的曲目（主列表顺序）
+    pub tracks: &'a [PathBuf],
+    pub track_positions: &'a HashMap<PathBuf, usize>,
+    pub identity: PathIdentity,
+    /// --dedup 跳过的文件（按 `identity.key`）对应保留的文件，列表中引用前者时改用后者
+    pub duplicate_of: HashMap<PathBuf, PathBuf>,
+    /// 解析时在列表中找到、却不在本次同步曲目中的路径，由调用方取出并记录
+    pub missing: RefCell<Vec<PathBuf>>,
+}
+
+impl ResolveContext<'_> {
+    /// 曲目在主列表中的索引，不在本次同步的曲目中时为 `None`。内容重复而被跳过的文件使用保留的那个
+    pub fn index_of(&self, path: &Path) -> Option<u32> {
+        let key = self.identity.key(path);
+        let key = self.duplicate_of.get(&key).map_or(key, |original| self.identity.key(original));
+        self.track_positions.get(&key).map(|&i| i as u32)
+    }
+}
+
+/// 一个播放列表来源：列表文件、目录、按标签分组等
+pub trait PlaylistSource {
+    /// 播放列表名称，同时决定其 dbid 与语音旁白文本
+    fn name(&self) -> String;
+
+    /// 按顺序解析出播放列表中曲目在主列表中的索引
+    fn resolve(&self, ctx: &ResolveContext<'_>) -> Vec<u32>;
+
+    /// 来源于设备上的某个文件时返回该文件
+    fn source_file(&self) -> Option<&Path> {
+        None
+    }
+
+    /// 该播放列表的选项文件（见 `PlaylistOptions`），不支持时为 `None`
+    fn options_file(&self) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// 由扫描到的文件创建播放列表来源。新的列表格式实现该 trait 并加入 `PROVIDERS` 即可
+pub trait PlaylistProvider: Sync {
+    /// 处理的扩展名（小写，含 "."）
+    fn extensions(&self) -> &'static [&'static str];
+
+    fn open(&self, path: &Path) -> Box<dyn PlaylistSource>;
+}
+
+static PROVIDERS: &[&dyn PlaylistProvider] = &[
+    &list_file::M3uProvider, &list_file::M3u8Provider, &list_file::PlsProvider,
+];
+
+fn provider_for(ext: &str) -> Option<&'static dyn PlaylistProvider> {
+    PROVIDERS.iter().copied().find(|p| p.extensions().contains(&ext))
+}
+
+/// 扩展名（小写，含 "."）是否为某种播放列表文件
+pub fn is_list_ext(ext: &str) -> bool {
+    provider_for(ext).is_some()
+}
+
+/// 将扫描到的文件作为播放列表打开，不是播放列表文件时返回 `None`
+pub fn open_list_file(path: &Path) -> Option<Box<dyn PlaylistSource>> {
+    provider_for(&ext_lower(path)).map(|p| p.open(path))
+}
+
+/// 解析播放列表，返回名称与曲目索引
+pub fn resolve_playlist_tracks(source: &dyn PlaylistSource, ctx: &ResolveContext<'_>) -> (String, Vec<u32>) {
+    (source.name(), source.resolve(ctx))
+}
@@ -0,0 +1,125 @@
+use clap::ValueEnum;
+use std::fs;
+use std::path::Path;
+
+use crate::cli::TrackOrder;
+use crate::utils::parse_title_pattern;
+
+// ─── Per-playlist options ────────────────────────────────────────────────────
+
+/// 配置文件中按名称设置播放列表选项的表，如 `[playlist."Road Trip"]`
+pub const CONFIG_TABLE: &str = "playlist";
+
+/// 播放列表在 iTunesSD 中的类型
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ListType {
+    Normal,
+    Podcast,
+    Audiobook,
+}
+
+impl ListType {
+    /// 写入播放列表记录的 listtype 值（1 为主列表）
+    pub fn code(self) -> u32 {
+        match self {
+            ListType::Normal => 2,
+            ListType::Podcast => 3,
+            ListType::Audiobook => 4,
+        }
+    }
+}
+
+/// 单个播放列表的设置，未设置的项沿用全局选项
+#[derive(Clone, Default)]
+pub struct PlaylistOptions {
+    /// 是否生成该播放列表的语音旁白，覆盖 --playlist-voiceover
+    pub voiceover: Option<bool>,
+    /// 旁白使用的 Edge TTS 语音，覆盖 --tts-voice
+    pub voice: Option<String>,
+    /// 列表内曲目的顺序，默认保持来源中的顺序
+    pub order: Option<TrackOrder>,
+    pub listtype: Option<ListType>,
+    /// 列表中曲目的额外增益 (dB，可为负)，曲目属于多个列表时取绝对值最大的一个
+    pub gain_boost: Option<i32>,
+    /// 将列表中的曲目移出主列表（All songs）的随机播放，只能通过播放列表菜单播放
+    pub exclude_from_master: Option<bool>,
+    /// 从列表中去掉标题匹配任一正则表达式的曲目，如现场版、伴奏
+    pub exclude_titles: Option<Vec<regex::Regex>>,
+}
+
+impl PlaylistOptions {
+    /// 以 `other` 中已设置的项覆盖当前设置
+    pub fn merge(&mut self, other: PlaylistOptions) {
+        if other.voiceover.is_some() { self.voiceover = other.voiceover; }
+        if other.voice.is_some() { self.voice = other.voice; }
+        if other.order.is_some() { self.order = other.order; }
+        if other.listtype.is_some() { self.listtype = other.listtype; }
+        if other.gain_boost.is_some() { self.gain_boost = other.gain_boost; }
+        if other.exclude_from_master.is_some() { self.exclude_from_master = other.exclude_from_master; }
+        if other.exclude_titles.is_some() { self.exclude_titles = other.exclude_titles; }
+    }
+
+    pub fn listtype_code(&self) -> u32 {
+        self.listtype.unwrap_or(ListType::Normal).code()
+    }
+
+    /// 解析一个选项表，键与命令行选项的写法一致（如 `gain-boost`）
+    pub fn from_table(table: &toml::Table) -> Result<Self, String> {
+        let mut opts = PlaylistOptions::default();
+        for (key, value) in table {
+            let wrong_type = || format!("选项 \"{}\" 的值类型不正确", key);
+            match key.as_str() {
+                "voiceover" => opts.voiceover = Some(value.as_bool().ok_or_else(wrong_type)?),
+                "voice" => opts.voice = Some(value.as_str().ok_or_else(wrong_type)?.to_string()),
+                "order" => {
+                    let s = value.as_str().ok_or_else(wrong_type)?;
+                    opts.order = Some(TrackOrder::from_str(s, true)
+                        .map_err(|_| format!("未知的顺序 \"{}\"，可选: path, artist-spread, shuffle", s))?);
+                }
+                "listtype" => {
+                    let s = value.as_str().ok_or_else(wrong_type)?;
+                    opts.listtype = Some(ListType::from_str(s, true)
+                        .map_err(|_| format!("未知的列表类型 \"{}\"，可选: normal, podcast, audiobook", s))?);
+                }
+                "gain-boost" => {
+                    let v = value.as_integer().ok_or_else(wrong_type)?;
+                    opts.gain_boost = Some(i32::try_from(v).map_err(|_| wrong_type())?.clamp(-99, 99));
+                }
+                "exclude-from-master" => opts.exclude_from_master = Some(value.as_bool().ok_or_else(wrong_type)?),
+                "exclude-titles" => {
+                    let patterns = value.as_array().ok_or_else(wrong_type)?.iter()
+                        .map(|v| v.as_str().ok_or_else(wrong_type).and_then(parse_title_pattern))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    opts.exclude_titles = Some(patterns);
+                }
+                _ => return Err(format!("未知的播放列表选项 \"{}\"", key)),
+            }
+        }
+        Ok(opts)
+    }
+}
+
+/// 读取播放列表旁的选项文件，不存在时返回 `None`
+pub fn read_sidecar(path: &Path) -> Result<Option<PlaylistOptions>, String> {
+    let text = match fs::read_to_string(path) {
+        Ok(t) => t,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("无法读取 {}: {}", path.display(), e)),
+    };
+    let table = text.parse::<toml::Table>()
+        .map_err(|e| format!("{} 格式错误: {}", path.display(), e))?;
+    PlaylistOptions::from_table(&table)
+        .map(Some)
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// 配置文件中为名为 `name` 的播放列表设置的选项
+pub fn config_options(config: Option<&toml::Table>, name: &str) -> Result<Option<PlaylistOptions>, String> {
+    let Some(table) = config.and_then(|c| c.get(CONFIG_TABLE)).and_then(|p| p.get(name)) else {
+        return Ok(None);
+    };
+    let table = table.as_table().ok_or_else(|| format!("[{}.\"{}\"] 必须是一个表", CONFIG_TABLE, name))?;
+    PlaylistOptions::from_table(table)
+        .map(Some)
+        .map_err(|e| format!("[{}.\"{}\"]: {}", CONFIG_TABLE, name, e))
+}
@@ -0,0 +1,113 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::table_to_args;
+
+// ─── Host-side profiles ──────────────────────────────────────────────────────
+
+/// 电脑上的配置文件，位于用户配置目录下，定义配置档案与外部转换程序
+pub const PROFILES_FILE: &str = "profiles.toml";
+
+/// 记录各配置档案上次使用的设备路径，由程序维护
+const LAST_PATHS_FILE: &str = "last_paths.toml";
+
+/// 定义配置档案的表，如 `[profile.work]`
+const PROFILE_TABLE: &str = "profile";
+
+/// 本程序的用户配置目录：Windows 为 %APPDATA%，macOS 为 ~/Library/Application Support，
+/// 其他系统为 $XDG_CONFIG_HOME 或 ~/.config
+pub fn config_dir() -> Option<PathBuf> {
+    let dir = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|h| PathBuf::from(h).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()).map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+    };
+    dir.map(|d| d.join(env!("CARGO_PKG_NAME")))
+}
+
+/// 一个配置档案
+pub struct Profile {
+    /// 档案中的 path，未设置时为上次使用该档案时的设备路径
+    pub path: Option<String>,
+    /// 档案选用的预设
+    pub preset: Option<String>,
+    /// 档案中其余的选项展开成的命令行参数
+    pub args: Vec<String>,
+}
+
+fn read_table(path: &Path) -> Result<Option<toml::Table>, String> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let text = fs::read_to_string(path).map_err(|e| format!("无法读取 {}: {}", path.display(), e))?;
+    text.parse::<toml::Table>()
+        .map(Some)
+        .map_err(|e| format!("{} 格式错误: {}", path.display(), e))
+}
+
+/// 读取电脑上的配置文件，不存在时返回 `None`。会运行程序的设置（如 [converters]）只从这里读取，
+/// 不从设备上的配置文件读取
+pub fn load_host_config() -> Result<Option<toml::Table>, String> {
+    let Some(dir) = config_dir() else { return Ok(None) };
+    read_table(&dir.join(PROFILES_FILE))
+}
+
+fn string_value(name: &str, key: &str, value: Option<toml::Value>) -> Result<Option<String>, String> {
+    match value {
+        None => Ok(None),
+        Some(toml::Value::String(s)) => Ok(Some(s)),
+        Some(_) => Err(format!("配置档案 \"{}\" 的 {} 必须是字符串", name, key)),
+    }
+}
+
+/// 读取配置档案 `name`。档案未定义时返回错误，说明中列出已定义的档案
+pub fn load_profile(name: &str) -> Result<Profile, String> {
+    let dir = config_dir().ok_or("无法确定用户配置目录")?;
+    let file = dir.join(PROFILES_FILE);
+    let config = read_table(&file)?.unwrap_or_default();
+    let profiles = config.get(PROFILE_TABLE).and_then(|p| p.as_table());
+    let Some(value) = profiles.and_then(|p| p.get(name)) else {
+        let known: Vec<&str> = profiles.map(|p| p.keys().map(String::as_str).collect()).unwrap_or_default();
+        if known.is_empty() {
+            return Err(format!("未知的配置档案 \"{}\"。请在 {} 中以 [profile.{}] 定义", name, file.display(), name));
+        }
+        return Err(format!("未知的配置档案 \"{}\"。{} 中的配置档案: {}", name, file.display(), known.join(", ")));
+    };
+    let mut table = value.as_table()
+        .ok_or_else(|| format!("配置档案 \"{}\" 必须是一个表", name))?
+        .clone();
+    if table.contains_key(PROFILE_TABLE) {
+        return Err(format!("配置档案 \"{}\" 中不能再指定配置档案", name));
+    }
+    let path = match string_value(name, "path", table.remove("path"))? {
+        Some(path) => Some(path),
+        None => last_path(&dir, name),
+    };
+    let preset = string_value(name, "preset", table.remove("preset"))?;
+    let args = table_to_args(&table).map_err(|e| format!("配置档案 \"{}\": {}", name, e))?;
+    Ok(Profile { path, preset, args })
+}
+
+fn last_path(dir: &Path, name: &str) -> Option<String> {
+    let paths = read_table(&dir.join(LAST_PATHS_FILE)).ok()??;
+    paths.get(name).and_then(|p| p.as_str()).map(str::to_string)
+}
+
+/// 记下配置档案 `name` 这次使用的设备路径，下次未给出 PATH 时使用
+pub fn remember_path(name: &str, device: &Path) -> Result<(), String> {
+    let dir = config_dir().ok_or("无法确定用户配置目录")?;
+    let file = dir.join(LAST_PATHS_FILE);
+    let mut paths = read_table(&file)?.unwrap_or_default();
+    let device = std::path::absolute(device).unwrap_or_else(|_| device.to_path_buf());
+    let device = device.to_string_lossy().into_owned();
+    if paths.get(name).and_then(|p| p.as_str()) == Some(device.as_str()) {
+        return Ok(());
+    }
+    paths.insert(name.to_string(), toml::Value::String(device));
+    fs::create_dir_all(&dir).map_err(|e| format!("无法创建 {}: {}", dir.display(), e))?;
+    fs::write(&file, paths.to_string()).map_err(|e| format!("无法写入 {}: {}", file.display(), e))
+}
@@ -0,0 +1,128 @@
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use crate::i18n;
+
+// ─── Per-phase progress bars ─────────────────────────────────────────────────
+
+/// --non-interactive 时为 true：即使标准错误是终端也不绘制进度条
+static HIDDEN: AtomicBool = AtomicBool::new(false);
+
+/// 不再绘制进度条，须在第一次输出之前调用
+pub fn hide() {
+    HIDDEN.store(true, Ordering::Relaxed);
+}
+
+/// --progress-json 时为 true：各阶段的进度以 JSON 行写到标准错误
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// 以 JSON 报告的阶段：稳定的阶段名、进度条与上次报告的位置
+static TRACKED: Mutex<Vec<(&'static str, ProgressBar, Option<u64>)>> = Mutex::new(Vec::new());
+
+/// 两次 JSON 进度报告之间的间隔
+const REPORT_INTERVAL: Duration = Duration::from_millis(200);
+
+/// JSON 进度中的阶段名，不随 --lang 变化
+const PHASE_IDS: &[(&str, &str)] = &[
+    ("读取标签", "read_tags"),
+    ("转换", "convert"),
+    ("分析响度", "analyze_loudness"),
+    ("曲目旁白", "track_voiceovers"),
+    ("合成", "synthesize"),
+    ("生成数据库", "build_database"),
+    ("写入数据库", "write_database"),
+];
+
+/// 不绘制进度条，改为每隔一段时间在标准错误输出一行 JSON，如
+/// `{"progress":{"phase":"convert","label":"转换","pos":3,"total":10,"done":false}}`，
+/// 供在子进程中运行本程序的图形界面显示各阶段的进度。须在第一次输出之前调用
+pub fn enable_json() {
+    hide();
+    JSON.store(true, Ordering::Relaxed);
+    thread::spawn(|| loop {
+        thread::sleep(REPORT_INTERVAL);
+        report_json();
+    });
+}
+
+/// 是否以 JSON 报告进度（--progress-json）
+pub fn json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// 报告位置有变化或已经结束的阶段，结束的阶段随后不再报告
+fn report_json() {
+    let mut tracked = TRACKED.lock().unwrap();
+    tracked.retain_mut(|(phase, bar, last)| {
+        let pos = bar.position();
+        let done = bar.is_finished();
+        if done || *last != Some(pos) {
+            *last = Some(pos);
+            let id = PHASE_IDS.iter().find(|(p, _)| p == phase).map_or(*phase, |(_, id)| id);
+            let line = serde_json::json!({
+                "progress": { "phase": id, "label": i18n::text(phase), "pos": pos, "total": bar.length(), "done": done },
+            });
+            suspend(|| eprintln!("{}", line));
+        }
+        !done
+    });
+}
+
+/// 开始以 JSON 报告一个阶段，同时报告之前结束的阶段
+fn track(phase: &'static str, bar: &ProgressBar) {
+    if JSON.load(Ordering::Relaxed) {
+        TRACKED.lock().unwrap().push((phase, bar.clone(), None));
+        report_json();
+    }
+}
+
+/// 立即报告最后的进度，在同步结束时调用，以免最后一个阶段的结束来不及报告
+pub fn flush() {
+    if JSON.load(Ordering::Relaxed) {
+        report_json();
+    }
+}
+
+/// 所有阶段共享的进度条区域，标准错误不是终端时不绘制
+fn multi() -> &'static MultiProgress {
+    static MULTI: OnceLock<MultiProgress> = OnceLock::new();
+    MULTI.get_or_init(|| {
+        let target = if io::stderr().is_terminal() && !HIDDEN.load(Ordering::Relaxed) {
+            ProgressDrawTarget::stderr()
+        } else {
+            ProgressDrawTarget::hidden()
+        };
+        MultiProgress::with_draw_target(target)
+    })
+}
+
+/// 总量已知的阶段（如扫描、转换、响度分析）的进度条，显示完成数量与预计剩余时间
+pub fn bar(phase: &'static str, total: usize) -> ProgressBar {
+    let template = format!("{{prefix:>10}} [{{bar:30}}] {{pos}}/{{len}} ({{percent}}%) {} {{eta}}", i18n::text("剩余"));
+    let style = ProgressStyle::with_template(&template)
+        .expect("valid progress template")
+        .progress_chars("=> ");
+    let bar = multi().add(ProgressBar::new(total as u64).with_style(style));
+    bar.set_prefix(i18n::text(phase));
+    track(phase, &bar);
+    bar
+}
+
+/// 无法预知总量的阶段（如写入数据库）的进度指示，显示已用时间
+pub fn spinner(phase: &'static str) -> ProgressBar {
+    let style = ProgressStyle::with_template("{spinner} {prefix} {elapsed}").expect("valid progress template");
+    let spinner = multi().add(ProgressBar::new_spinner().with_style(style));
+    spinner.set_prefix(i18n::text(phase));
+    spinner.enable_steady_tick(Duration::from_millis(120));
+    track(phase, &spinner);
+    spinner
+}
+
+/// 暂时隐藏进度条执行 `f`，用于打印消息，避免与进度条交错
+pub fn suspend<R>(f: impl FnOnce() -> R) -> R {
+    multi().suspend(f)
+}
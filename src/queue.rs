@@ -0,0 +1,112 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::error;
+
+use crate::build_meta::previous_play_counts;
+use crate::device_io::write_file_atomic;
+use crate::playlist::{ListEncoding, PlaylistSource, ResolveContext};
+use crate::stats::play_counts;
+use crate::utils::{normalize_path, PathIdentity};
+
+// ─── Listening queue ("Play Next") ───────────────────────────────────────────
+
+/// 设备根目录下的收听队列，每行一首曲目，路径相对于设备根目录
+pub const QUEUE_FILE: &str = "queue.txt";
+
+/// 由队列生成的播放列表，排在播放列表菜单的最前面
+pub const QUEUE_PLAYLIST: &str = "Play Next";
+
+/// 空行与以 "#" 开头的注释行不是曲目
+fn is_entry(line: &str) -> bool {
+    let line = line.trim();
+    !line.is_empty() && !line.starts_with('#')
+}
+
+/// 队列中一行对应的文件。路径可以以 "/" 开头，也可以用 Windows 的 "\"；
+/// 排入队列的 FLAC 转换后按同名的 MP3 查找
+fn entry_path(base: &Path, line: &str) -> PathBuf {
+    let rel = line.trim().replace('\\', "/");
+    let path = normalize_path(&base.join(rel.trim_start_matches('/')));
+    let mp3 = path.with_extension("mp3");
+    if !path.exists() && mp3.exists() {
+        return normalize_path(&mp3);
+    }
+    path
+}
+
+fn read_queue(path: &Path, encoding: ListEncoding) -> Option<String> {
+    fs::read(path).ok().map(|raw| encoding.decode(&raw))
+}
+
+/// queue.txt 中自上次同步以来播放过的曲目：iTunesStats 中的播放次数与上次同步时记入构建信息的不同
+/// （设备重新开始计数时变小，之后播放过才算）。须在重建数据库之前调用（见 `play_counts`）；
+/// 设备上还没有播放统计或上次同步没有记下播放次数的曲目不算已播放。返回这些行，文件保持不变
+pub fn played_entries(base: &Path, encoding: ListEncoding) -> Result<Vec<String>, String> {
+    let Some(text) = read_queue(&base.join(QUEUE_FILE), encoding) else { return Ok(Vec::new()) };
+    let previous = previous_play_counts(base);
+    let identity = PathIdentity::detect(base);
+    let played: HashSet<PathBuf> = play_counts(base)?.into_iter()
+        .filter(|(f, now)| *now > 0 && previous.get(f).is_some_and(|before| before != now))
+        .map(|(f, _)| identity.key(&normalize_path(&base.join(f.trim_start_matches('/')))))
+        .collect();
+    Ok(text.lines()
+        .filter(|line| is_entry(line) && played.contains(&identity.key(&entry_path(base, line))))
+        .map(|line| line.trim().to_string())
+        .collect())
+}
+
+/// 从 queue.txt 中删除 `entries`（`played_entries` 返回的行），注释与其余行保持不变（改写后保存为 UTF-8）
+pub fn remove_entries(base: &Path, encoding: ListEncoding, entries: &[String]) -> Result<(), String> {
+    let path = base.join(QUEUE_FILE);
+    let Some(text) = read_queue(&path, encoding) else { return Ok(()) };
+    let kept: String = text.lines()
+        .filter(|line| !(is_entry(line) && entries.iter().any(|e| e == line.trim())))
+        .map(|line| format!("{}\n", line))
+        .collect();
+    write_file_atomic(&path, kept).map_err(|e| format!("无法写入 {}: {}", path.display(), e))
+}
+
+/// 收听队列对应的播放列表，按 queue.txt 中的顺序
+pub struct QueuePlaylist {
+    path: PathBuf,
+    encoding: ListEncoding,
+    /// 已播放、在确认后才从文件中删除的行（演练时不删除）
+    skip: Vec<String>,
+}
+
+impl QueuePlaylist {
+    /// 设备根目录有 queue.txt 且其中还有曲目时返回其播放列表
+    pub fn open(base: &Path, encoding: ListEncoding, skip: Vec<String>) -> Option<Self> {
+        let path = base.join(QUEUE_FILE);
+        let text = read_queue(&path, encoding)?;
+        let queued = text.lines().any(|l| is_entry(l) && !skip.iter().any(|s| s == l.trim()));
+        queued.then(|| QueuePlaylist { path: normalize_path(&path), encoding, skip })
+    }
+}
+
+impl PlaylistSource for QueuePlaylist {
+    fn name(&self) -> String {
+        QUEUE_PLAYLIST.to_string()
+    }
+
+    fn resolve(&self, ctx: &ResolveContext<'_>) -> Vec<u32> {
+        let text = read_queue(&self.path, self.encoding).unwrap_or_default();
+        text.lines()
+            .filter(|l| is_entry(l) && !self.skip.iter().any(|s| s == l.trim()))
+            .filter_map(|line| {
+                let path = entry_path(ctx.base, line);
+                let index = ctx.index_of(&path);
+                if index.is_none() {
+                    error!("无法找到曲目 \"{}\"。跳过。", path.display());
+                    ctx.missing.borrow_mut().push(path);
+                }
+                index
+            })
+            .collect()
+    }
+
+    fn source_file(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+}
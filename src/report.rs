@@ -0,0 +1,654 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::changes::ChangeSummary;
+use crate::cli::ReportFormat;
+use crate::exit_code;
+use crate::metadata::SuspiciousNumber;
+
+// ─── Per-run sync report ─────────────────────────────────────────────────────
+
+/// FLAC 转换记录，`output` 为 `None` 表示转换失败
+pub struct ConversionEntry {
+    pub source: String,
+    pub output: Option<String>,
+}
+
+/// 自动增益决策记录，`loudness_db` 为 `None` 表示响度估算失败并回退到默认增益
+pub struct GainEntry {
+    pub track: String,
+    pub loudness_db: Option<f64>,
+    pub gain: u32,
+    /// 按 --no-gain-genre/--no-gain-dir 规则跳过了自动增益
+    pub skipped: bool,
+}
+
+/// 码率不低于该值的曲目计为高码率
+const HIGH_BITRATE_KBPS: u32 = 256;
+
+/// 设备支持的最高采样率
+const MAX_SAMPLE_RATE: u32 = 48_000;
+
+/// 曲目的播放特性，用于分析码率、增益与旁白对续航和播放表现的影响
+pub struct PlaybackEntry {
+    pub track: String,
+    pub bitrate_kbps: Option<u32>,
+    pub sample_rate: Option<u32>,
+    pub vbr: bool,
+    pub gain: u32,
+}
+
+impl PlaybackEntry {
+    fn high_bitrate(&self) -> bool {
+        self.bitrate_kbps.is_some_and(|b| b >= HIGH_BITRATE_KBPS)
+    }
+
+    fn high_sample_rate(&self) -> bool {
+        self.sample_rate.is_some_and(|r| r > MAX_SAMPLE_RATE)
+    }
+}
+
+/// 一条语音旁白朗读的文字与使用的语音
+pub struct VoiceoverEntry {
+    /// 曲目路径或播放列表名称
+    pub label: String,
+    pub is_playlist: bool,
+    pub text: String,
+    pub voice: String,
+    /// 文字中占多数的书写系统，没有文字时为 `None`
+    pub script: Option<&'static str>,
+    /// 文字的书写系统与语音的语言可能不一致，如中文语音朗读英文标题
+    pub mismatch: bool,
+}
+
+/// 一个播放列表中曲目旁白的语言覆盖情况
+pub struct VoiceoverCoverage {
+    pub playlist: String,
+    pub tracks: usize,
+    /// 有旁白的曲目
+    pub voiced: usize,
+    /// 各书写系统的曲目数
+    pub scripts: Vec<(&'static str, usize)>,
+    pub mismatches: usize,
+}
+
+/// 同步问题的类别，决定 --strict 时的退出码
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// 文件转换或音频处理失败
+    Conversion,
+    /// 播放列表被跳过，或列表中的曲目找不到
+    Playlist,
+    Other,
+}
+
+/// 曲目没有写入数据库的原因
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// 读取标签或解码时失败、超时或崩溃
+    Undecodable,
+    /// 受 DRM 保护，设备无法播放
+    Protected,
+    /// iPod 路径超过数据库文件名字段的长度
+    PathTooLong,
+    /// 转换为 MP3 失败，源文件保留在设备上
+    ConversionFailed,
+}
+
+impl SkipReason {
+    /// --output json 中的名称，不随 --lang 变化
+    pub fn name(self) -> &'static str {
+        match self {
+            SkipReason::Undecodable => "undecodable",
+            SkipReason::Protected => "drm",
+            SkipReason::PathTooLong => "path_too_long",
+            SkipReason::ConversionFailed => "conversion_failed",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SkipReason::Undecodable => "无法解码",
+            SkipReason::Protected => "受 DRM 保护",
+            SkipReason::PathTooLong => "路径过长",
+            SkipReason::ConversionFailed => "转换失败",
+        }
+    }
+}
+
+/// 因问题而没有写入数据库的文件
+pub struct SkippedTrack {
+    pub track: String,
+    pub reason: SkipReason,
+    /// 具体的错误，如解码器或转换程序的输出
+    pub detail: String,
+}
+
+/// 单次同步的汇总信息，写入设备供之后在任意电脑上查看
+#[derive(Default)]
+pub struct SyncReport {
+    pub tracks: Vec<String>,
+    pub playlists: Vec<(String, usize)>,
+    pub conversions: Vec<ConversionEntry>,
+    pub gains: Vec<GainEntry>,
+    pub failures: Vec<String>,
+    /// 与 `failures` 一一对应的类别
+    pub failure_kinds: Vec<FailureKind>,
+    /// 与 FLAC 同名的 MP3 及其处理结果
+    pub shadowed: Vec<(String, String)>,
+    /// 扫描时因隐藏策略跳过的文件和目录树
+    pub skipped_hidden: Vec<String>,
+    /// 被过滤规则排除的曲目及原因
+    pub excluded: Vec<(String, String)>,
+    /// --dedup 跳过的重复文件及与之内容相同、被保留的文件
+    pub duplicates: Vec<(String, String)>,
+    /// 重复文件占用的空间
+    pub duplicate_bytes: u64,
+    /// 从设备移到本地归档的录音及其归档位置
+    pub memos: Vec<(String, String)>,
+    /// 无法解码、受 DRM 保护、路径过长或转换失败而没有写入数据库的文件
+    pub skipped_tracks: Vec<SkippedTrack>,
+    /// 曲目编号或碟号不合理的曲目
+    pub suspicious_numbers: Vec<(String, SuspiciousNumber)>,
+    /// 数据库中每首曲目的播放特性
+    pub playback: Vec<PlaybackEntry>,
+    /// 与设备上原有数据库的差异，原数据库不存在或无法解析时为 `None`
+    pub changes: Option<ChangeSummary>,
+    pub track_voiceovers: usize,
+    pub playlist_voiceovers: usize,
+    /// 本次同步写入或沿用的旁白
+    pub voiceovers: Vec<VoiceoverEntry>,
+    pub voiceover_coverage: Vec<VoiceoverCoverage>,
+    pub albums: usize,
+    pub artists: usize,
+}
+
+impl SyncReport {
+    pub fn failure(&mut self, msg: impl Into<String>) {
+        self.failure_of(FailureKind::Other, msg);
+    }
+
+    pub fn failure_of(&mut self, kind: FailureKind, msg: impl Into<String>) {
+        self.failures.push(msg.into());
+        self.failure_kinds.push(kind);
+    }
+
+    pub fn skip_track(&mut self, track: impl Into<String>, reason: SkipReason, detail: impl Into<String>) {
+        self.skipped_tracks.push(SkippedTrack { track: track.into(), reason, detail: detail.into() });
+    }
+
+    /// --strict 时的退出码：没有问题时为 0，否则按最严重的问题类别给出
+    pub fn strict_exit_code(&self) -> i32 {
+        if self.failure_kinds.contains(&FailureKind::Conversion) {
+            exit_code::CONVERSION_FAILED
+        } else if self.failure_kinds.contains(&FailureKind::Playlist) {
+            exit_code::PLAYLIST_FAILED
+        } else if self.failures.is_empty() {
+            exit_code::SUCCESS
+        } else {
+            exit_code::WARNINGS
+        }
+    }
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn escape_markdown(s: &str) -> String {
+    s.replace('|', "\\|")
+}
+
+/// 设备的 (可用空间, 总容量)，查询失败时返回 `None`
+pub fn device_space(base: &Path) -> Option<(u64, u64)> {
+    Some((fs2::available_space(base).ok()?, fs2::total_space(base).ok()?))
+}
+
+fn space_line(space: Option<(u64, u64)>) -> String {
+    match space {
+        Some((free, total)) => format!("{} 可用 / 共 {}", format_bytes(free), format_bytes(total)),
+        None => "未知".to_string(),
+    }
+}
+
+fn conversion_status(c: &ConversionEntry) -> String {
+    match &c.output {
+        Some(out) => format!("→ {}", out),
+        None => "失败".to_string(),
+    }
+}
+
+fn gain_loudness(g: &GainEntry) -> String {
+    if g.skipped {
+        return "按规则跳过".to_string();
+    }
+    match g.loudness_db {
+        Some(db) => format!("{:.2} dBFS", db),
+        None => "估算失败".to_string(),
+    }
+}
+
+fn count(n: usize, total: usize) -> String {
+    if total == 0 { return n.to_string(); }
+    format!("{} ({:.0}%)", n, n as f64 / total as f64 * 100.0)
+}
+
+/// 码率、增益与旁白的统计，供排查耗电或设备异常时参考
+fn playback_rows(report: &SyncReport) -> Vec<Vec<String>> {
+    let entries = &report.playback;
+    let total = entries.len();
+    let high_bitrate = entries.iter().filter(|e| e.high_bitrate()).count();
+    let vbr = entries.iter().filter(|e| e.vbr).count();
+    let high_rate = entries.iter().filter(|e| e.high_sample_rate()).count();
+    let gained: Vec<u32> = entries.iter().map(|e| e.gain).filter(|&g| g > 0).collect();
+    let bitrates: Vec<u32> = entries.iter().filter_map(|e| e.bitrate_kbps).collect();
+    let avg_bitrate = match bitrates.len() {
+        0 => "未知".to_string(),
+        n => format!("{} kbps", bitrates.iter().map(|&b| b as u64).sum::<u64>() / n as u64),
+    };
+    let gain_line = match gained.iter().max() {
+        Some(max) => format!("{} (最高 {})", count(gained.len(), total), max),
+        None => "0".to_string(),
+    };
+
+    vec![
+        vec!["平均码率".into(), avg_bitrate, String::new()],
+        vec![format!("高码率 (≥ {} kbps)", HIGH_BITRATE_KBPS), count(high_bitrate, total),
+             "读取闪存和解码更频繁，耗电略高".into()],
+        vec!["可变码率 (VBR)".into(), count(vbr, total),
+             "部分固件上显示的进度和续播位置可能不准确".into()],
+        vec![format!("采样率高于 {} kHz", MAX_SAMPLE_RATE / 1000), count(high_rate, total),
+             "设备不支持，可能被跳过或无声".into()],
+        vec!["带音量增益".into(), gain_line, "增益越高耳机放大器耗电越多，也更容易削波失真".into()],
+        vec!["曲目语音旁白".into(), report.track_voiceovers.to_string(), "每次切换曲目时播放".into()],
+        vec!["播放列表语音旁白".into(), report.playlist_voiceovers.to_string(), String::new()],
+    ]
+}
+
+/// 每种语音朗读的曲目与播放列表旁白数量
+fn voice_rows(voiceovers: &[VoiceoverEntry]) -> Vec<Vec<String>> {
+    let mut voices: Vec<&str> = voiceovers.iter().map(|v| v.voice.as_str()).collect();
+    voices.sort();
+    voices.dedup();
+    voices.into_iter()
+        .map(|voice| {
+            let of_voice = || voiceovers.iter().filter(move |v| v.voice == voice);
+            vec![
+                voice.to_string(),
+                of_voice().filter(|v| !v.is_playlist).count().to_string(),
+                of_voice().filter(|v| v.is_playlist).count().to_string(),
+                of_voice().filter(|v| v.mismatch).count().to_string(),
+            ]
+        })
+        .collect()
+}
+
+/// 报告中的一个表格小节，HTML 与 Markdown 共用同一份结构
+struct Section {
+    title: &'static str,
+    columns: &'static [&'static str],
+    rows: Vec<Vec<String>>,
+}
+
+fn sections(report: &SyncReport, space: Option<(u64, u64)>) -> Vec<Section> {
+    let mut out = Vec::new();
+
+    out.push(Section {
+        title: "概览",
+        columns: &["项目", "数值"],
+        rows: vec![
+            vec!["曲目".into(), report.tracks.len().to_string()],
+            vec!["专辑".into(), report.albums.to_string()],
+            vec!["艺术家".into(), report.artists.to_string()],
+            vec!["播放列表".into(), report.playlists.len().to_string()],
+            vec!["FLAC 转换".into(), report.conversions.len().to_string()],
+            vec!["失败".into(), report.failures.len().to_string()],
+            vec!["已排除".into(), report.excluded.len().to_string()],
+            vec!["内容重复".into(), report.duplicates.len().to_string()],
+            vec!["跳过的文件".into(), report.skipped_tracks.len().to_string()],
+            vec!["已归档录音".into(), report.memos.len().to_string()],
+            vec!["设备空间".into(), space_line(space)],
+        ],
+    });
+
+    if let Some(ref c) = report.changes {
+        out.push(Section {
+            title: "与上次同步相比",
+            columns: &["项目", "数值"],
+            rows: vec![
+                vec!["新增曲目".into(), c.added.len().to_string()],
+                vec!["移除曲目".into(), c.removed.len().to_string()],
+                vec!["新增播放列表".into(), c.playlists_added.to_string()],
+                vec!["移除播放列表".into(), c.playlists_removed.to_string()],
+                vec!["修改的播放列表".into(), c.playlists_changed.to_string()],
+                vec!["增益调整".into(), c.gains.len().to_string()],
+                vec!["移动或重命名".into(), c.moved.len().to_string()],
+            ],
+        });
+
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        rows.extend(c.added.iter().map(|t| vec!["新增".into(), t.clone(), String::new()]));
+        rows.extend(c.removed.iter().map(|t| vec!["移除".into(), t.clone(), String::new()]));
+        rows.extend(c.gains.iter().map(|(t, old, new)| vec!["增益".into(), t.clone(), format!("{} → {}", old, new)]));
+        rows.extend(c.moved.iter().map(|(old, new)| vec!["移动".into(), new.clone(), format!("原路径 {}", old)]));
+        if !rows.is_empty() {
+            out.push(Section {
+                title: "曲目变更",
+                columns: &["变更", "曲目", "说明"],
+                rows,
+            });
+        }
+    }
+
+    if !report.failures.is_empty() {
+        out.push(Section {
+            title: "失败",
+            columns: &["原因"],
+            rows: report.failures.iter().map(|f| vec![f.clone()]).collect(),
+        });
+    }
+
+    if !report.skipped_tracks.is_empty() {
+        out.push(Section {
+            title: "跳过的文件",
+            columns: &["文件", "原因", "详情"],
+            rows: report.skipped_tracks.iter()
+                .map(|t| vec![t.track.clone(), t.reason.label().into(), t.detail.clone()])
+                .collect(),
+        });
+    }
+
+    if !report.suspicious_numbers.is_empty() {
+        out.push(Section {
+            title: "不合理的曲目编号",
+            columns: &["曲目", "字段", "标签中的值", "处理"],
+            rows: report.suspicious_numbers.iter()
+                .map(|(t, n)| vec![t.clone(), n.field.to_string(), n.raw.clone(), n.action()])
+                .collect(),
+        });
+    }
+
+    if !report.excluded.is_empty() {
+        out.push(Section {
+            title: "已排除",
+            columns: &["曲目", "原因"],
+            rows: report.excluded.iter().map(|(t, r)| vec![t.clone(), r.clone()]).collect(),
+        });
+    }
+
+    if !report.duplicates.is_empty() {
+        out.push(Section {
+            title: "内容重复的文件",
+            columns: &["文件", "与之相同的曲目"],
+            rows: report.duplicates.iter().map(|(d, o)| vec![d.clone(), o.clone()]).collect(),
+        });
+    }
+
+    if !report.skipped_hidden.is_empty() {
+        out.push(Section {
+            title: "跳过的隐藏项",
+            columns: &["路径"],
+            rows: report.skipped_hidden.iter().map(|p| vec![p.clone()]).collect(),
+        });
+    }
+
+    if !report.memos.is_empty() {
+        out.push(Section {
+            title: "录音归档",
+            columns: &["录音", "归档位置"],
+            rows: report.memos.iter().map(|(m, d)| vec![m.clone(), d.clone()]).collect(),
+        });
+    }
+
+    if !report.playback.is_empty() {
+        out.push(Section {
+            title: "播放特性",
+            columns: &["项目", "数值", "说明"],
+            rows: playback_rows(report),
+        });
+
+        let notable: Vec<Vec<String>> = report.playback.iter()
+            .filter(|e| e.high_bitrate() || e.vbr || e.high_sample_rate())
+            .map(|e| vec![
+                e.track.clone(),
+                e.bitrate_kbps.map(|b| format!("{} kbps", b)).unwrap_or_default(),
+                if e.vbr { "VBR".into() } else { "CBR".into() },
+                e.sample_rate.map(|r| format!("{} Hz", r)).unwrap_or_default(),
+            ])
+            .collect();
+        if !notable.is_empty() {
+            out.push(Section {
+                title: "高码率、VBR 或高采样率的曲目",
+                columns: &["曲目", "码率", "模式", "采样率"],
+                rows: notable,
+            });
+        }
+    }
+
+    if !report.voiceovers.is_empty() {
+        out.push(Section {
+            title: "旁白语音",
+            columns: &["语音", "曲目", "播放列表", "可能不匹配"],
+            rows: voice_rows(&report.voiceovers),
+        });
+    }
+
+    if !report.voiceover_coverage.is_empty() {
+        out.push(Section {
+            title: "播放列表的旁白语言",
+            columns: &["播放列表", "有旁白的曲目", "文字", "可能不匹配"],
+            rows: report.voiceover_coverage.iter()
+                .map(|c| {
+                    let scripts: Vec<String> = c.scripts.iter().map(|(s, n)| format!("{} {}", s, n)).collect();
+                    vec![c.playlist.clone(), count(c.voiced, c.tracks), scripts.join("、"), c.mismatches.to_string()]
+                })
+                .collect(),
+        });
+    }
+
+    let mismatched: Vec<Vec<String>> = report.voiceovers.iter()
+        .filter(|v| v.mismatch)
+        .map(|v| vec![v.label.clone(), v.text.clone(), v.voice.clone(), v.script.unwrap_or_default().to_string()])
+        .collect();
+    if !mismatched.is_empty() {
+        out.push(Section {
+            title: "文字与语音可能不匹配的旁白",
+            columns: &["曲目或播放列表", "文字", "语音", "文字类型"],
+            rows: mismatched,
+        });
+    }
+
+    out.push(Section {
+        title: "播放列表",
+        columns: &["名称", "曲目数"],
+        rows: report.playlists.iter().map(|(n, c)| vec![n.clone(), c.to_string()]).collect(),
+    });
+
+    if !report.conversions.is_empty() {
+        out.push(Section {
+            title: "FLAC 转换",
+            columns: &["源文件", "结果"],
+            rows: report.conversions.iter().map(|c| vec![c.source.clone(), conversion_status(c)]).collect(),
+        });
+    }
+
+    if !report.shadowed.is_empty() {
+        out.push(Section {
+            title: "FLAC/MP3 同名冲突",
+            columns: &["MP3", "处理"],
+            rows: report.shadowed.iter().map(|(p, a)| vec![p.clone(), a.clone()]).collect(),
+        });
+    }
+
+    if !report.gains.is_empty() {
+        out.push(Section {
+            title: "自动增益",
+            columns: &["曲目", "响度", "增益"],
+            rows: report.gains.iter()
+                .map(|g| vec![g.track.clone(), gain_loudness(g), g.gain.to_string()])
+                .collect(),
+        });
+    }
+
+    out.push(Section {
+        title: "曲目",
+        columns: &["#", "路径"],
+        rows: report.tracks.iter().enumerate().map(|(i, t)| vec![(i + 1).to_string(), t.clone()]).collect(),
+    });
+
+    out
+}
+
+pub fn render_html(report: &SyncReport, space: Option<(u64, u64)>) -> String {
+    let mut h = String::new();
+    h.push_str("<!DOCTYPE html>\n<html lang=\"zh-CN\">\n<head>\n<meta charset=\"utf-8\">\n");
+    h.push_str("<title>iPod Shuffle 同步报告</title>\n");
+    h.push_str("<style>body{font-family:sans-serif;margin:2em}table{border-collapse:collapse}\
+                td,th{border:1px solid #ccc;padding:2px 8px;text-align:left}</style>\n");
+    h.push_str("</head>\n<body>\n");
+    let _ = writeln!(h, "<h1>iPod Shuffle 4G Manager v{} 同步报告</h1>", env!("CARGO_PKG_VERSION"));
+    for sec in sections(report, space) {
+        let _ = writeln!(h, "<h2>{}</h2>\n<table>", sec.title);
+        h.push_str("<tr>");
+        for c in sec.columns {
+            let _ = write!(h, "<th>{}</th>", escape_html(c));
+        }
+        h.push_str("</tr>\n");
+        for row in &sec.rows {
+            h.push_str("<tr>");
+            for cell in row {
+                let _ = write!(h, "<td>{}</td>", escape_html(cell));
+            }
+            h.push_str("</tr>\n");
+        }
+        h.push_str("</table>\n");
+    }
+    h.push_str("</body>\n</html>\n");
+    h
+}
+
+pub fn render_markdown(report: &SyncReport, space: Option<(u64, u64)>) -> String {
+    let mut m = String::new();
+    let _ = writeln!(m, "# iPod Shuffle 4G Manager v{} 同步报告", env!("CARGO_PKG_VERSION"));
+    for sec in sections(report, space) {
+        let _ = writeln!(m, "\n## {}\n", sec.title);
+        let _ = writeln!(m, "| {} |", sec.columns.join(" | "));
+        let _ = writeln!(m, "|{}", " --- |".repeat(sec.columns.len()));
+        for row in &sec.rows {
+            let cells: Vec<String> = row.iter().map(|c| escape_markdown(c)).collect();
+            let _ = writeln!(m, "| {} |", cells.join(" | "));
+        }
+    }
+    m
+}
+
+/// 报告之外的同步结果，与报告一起组成 --output json 的汇总
+pub struct RunOutcome<'a> {
+    pub dry_run: bool,
+    /// 写入数据库失败时的错误，成功或演练时为 `None`
+    pub error: Option<String>,
+    /// (新生成的, 因 --tts-limit 留到下次的, 删除的) 旁白数量
+    pub voiceovers: (usize, usize, usize),
+    pub report_path: Option<&'a Path>,
+}
+
+/// --output json 的汇总：一个 JSON 对象，键名与结构保持稳定，供脚本解析
+pub fn render_json(report: &SyncReport, outcome: &RunOutcome<'_>) -> String {
+    let changes = report.changes.as_ref().map(|c| serde_json::json!({
+        "tracks_added": c.added,
+        "tracks_removed": c.removed,
+        "playlists_added": c.playlists_added,
+        "playlists_removed": c.playlists_removed,
+        "playlists_changed": c.playlists_changed,
+        "gains_changed": c.gains.iter()
+            .map(|(track, old, new)| serde_json::json!({ "track": track, "old": old, "new": new }))
+            .collect::<Vec<_>>(),
+        "tracks_moved": c.moved.iter()
+            .map(|(from, to)| serde_json::json!({ "from": from, "to": to }))
+            .collect::<Vec<_>>(),
+    }));
+    let (generated, deferred, removed) = outcome.voiceovers;
+    let summary = serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "success": outcome.error.is_none(),
+        "dry_run": outcome.dry_run,
+        "error": outcome.error,
+        "tracks": report.tracks.len(),
+        "albums": report.albums,
+        "artists": report.artists,
+        "playlists": report.playlists.iter()
+            .map(|(name, tracks)| serde_json::json!({ "name": name, "tracks": tracks }))
+            .collect::<Vec<_>>(),
+        "changes": changes,
+        "conversions": report.conversions.iter()
+            .map(|c| serde_json::json!({ "source": c.source, "output": c.output }))
+            .collect::<Vec<_>>(),
+        "gains": report.gains.iter()
+            .map(|g| serde_json::json!({
+                "track": g.track, "gain": g.gain, "loudness_db": g.loudness_db, "skipped": g.skipped,
+            }))
+            .collect::<Vec<_>>(),
+        "voiceovers": {
+            "tracks": report.track_voiceovers,
+            "playlists": report.playlist_voiceovers,
+            "generated": generated,
+            "deferred": deferred,
+            "removed": removed,
+        },
+        "excluded": report.excluded.iter()
+            .map(|(track, reason)| serde_json::json!({ "track": track, "reason": reason }))
+            .collect::<Vec<_>>(),
+        "duplicates": {
+            "files": report.duplicates.iter()
+                .map(|(file, original)| serde_json::json!({ "file": file, "same_as": original }))
+                .collect::<Vec<_>>(),
+            "bytes": report.duplicate_bytes,
+        },
+        "quarantined": report.skipped_tracks.iter()
+            .filter(|t| t.reason == SkipReason::Undecodable)
+            .map(|t| serde_json::json!({ "track": t.track, "reason": t.detail }))
+            .collect::<Vec<_>>(),
+        "skipped_tracks": report.skipped_tracks.iter()
+            .map(|t| serde_json::json!({ "track": t.track, "reason": t.reason.name(), "detail": t.detail }))
+            .collect::<Vec<_>>(),
+        "warnings": report.failures,
+        "report": outcome.report_path.map(|p| p.display().to_string()),
+    });
+    serde_json::to_string_pretty(&summary).expect("JSON values always serialize")
+}
+
+/// 将报告写入 `iPod_Control/last_sync_report.{html,md}`，返回写入的路径
+pub fn write_report(base: &Path, report: &SyncReport, format: ReportFormat) -> io::Result<Option<PathBuf>> {
+    let space = device_space(base);
+    let (content, ext) = match format {
+        ReportFormat::Html => (render_html(report, space), "html"),
+        ReportFormat::Markdown => (render_markdown(report, space), "md"),
+        ReportFormat::None => return Ok(None),
+    };
+    let path = base.join("iPod_Control").join(format!("last_sync_report.{}", ext));
+    fs::write(&path, content)?;
+    Ok(Some(path))
+}
@@ -0,0 +1,177 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use walkdir::WalkDir;
+
+use crate::outln;
+use crate::abrepeat::CUTS_EXT;
+use crate::cli::AUDIO_EXT;
+use crate::collation::compare;
+use crate::database::{dbid_to_filename, parse_itunes_sd, playlist_dbid, ParsedDatabase};
+use crate::metadata::{apply_meta_sidecars, read_all_meta, TrackMeta};
+use crate::playlist::is_list_ext;
+use crate::textbook::TEXT_EXT;
+use crate::utils::{ext_lower, is_system_hidden, path_to_ipod};
+
+// ─── Track search ────────────────────────────────────────────────────────────
+
+/// 搜索时读取单个文件标签的时限
+const TAG_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 一条搜索结果
+pub struct SearchHit {
+    /// iPod 路径，如 "/iPod_Control/Music/a.mp3"
+    pub filename: String,
+    pub meta: TrackMeta,
+    /// 数据库中的 dbid，不在数据库中时为 `None`
+    pub dbid: Option<[u8; 8]>,
+    pub playlists: Vec<String>,
+}
+
+/// 设备上可能生成过的播放列表名称。iTunesSD 只保存由名称生成的 dbid，
+/// 因此按扫描到的目录、列表文件、生成器与标签推算出候选名称再反查
+pub fn candidate_names(base: &Path, metas: &HashMap<PathBuf, TrackMeta>) -> HashSet<String> {
+    let mut names: HashSet<String> = ["Bedtime".to_string()].into_iter().collect();
+    names.extend((1..=99).map(|i| format!("Trip Mix {}", i)));
+
+    let walker = WalkDir::new(base).into_iter()
+        .filter_entry(|e| e.depth() == 0 || !is_system_hidden(&e.file_name().to_string_lossy()));
+    for entry in walker.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        if entry.file_type().is_dir() {
+            names.insert(stem);
+            continue;
+        }
+        let ext = ext_lower(path);
+        if is_list_ext(&ext) {
+            names.insert(stem);
+        } else if ext == CUTS_EXT {
+            names.insert(format!("{} A-B", stem));
+        } else if TEXT_EXT.contains(&ext.as_str()) {
+            names.insert(stem);
+        }
+    }
+
+    for meta in metas.values() {
+        let artist = meta.artist.clone().unwrap_or_default();
+        let album = meta.album.clone().unwrap_or_default();
+        names.insert(format!("{} - {}", artist, album));
+        names.extend([artist, album, meta.genre.clone().unwrap_or_default()]);
+    }
+    names
+}
+
+/// 为数据库中的每个播放列表找到名称，找不到时以 dbid 标识
+pub fn playlist_labels(db: &ParsedDatabase, candidates: &HashSet<String>) -> Vec<String> {
+    // Playlist voiceover doesn't change the dbid of anything but the master list
+    let by_dbid: HashMap<[u8; 8], &str> = candidates.iter()
+        .map(|n| (playlist_dbid(n, true), n.as_str()))
+        .collect();
+    db.playlists.iter()
+        .map(|p| {
+            if p.listtype == 1 {
+                "All songs".to_string()
+            } else if let Some(name) = by_dbid.get(&p.dbid) {
+                name.to_string()
+            } else {
+                format!("未知播放列表 ({})", dbid_to_filename(&p.dbid))
+            }
+        })
+        .collect()
+}
+
+fn matches(query: &[String], filename: &str, meta: &TrackMeta) -> bool {
+    let haystack = [Some(filename), meta.title.as_deref(), meta.artist.as_deref(), meta.album.as_deref()]
+        .iter()
+        .flatten()
+        .map(|s| s.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("\n");
+    query.iter().all(|word| haystack.contains(word))
+}
+
+/// 按标题、艺术家、专辑或路径搜索曲目（不区分大小写，多个词需全部匹配）。
+/// 默认搜索设备上的 iTunesSD；`live` 为 `true` 时搜索设备上的所有音频文件，
+/// 包括还未写入数据库的文件
+pub fn search_tracks(base: &Path, query: &str, live: bool) -> Result<Vec<SearchHit>, String> {
+    let db_path = base.join("iPod_Control").join("iTunes").join("iTunesSD");
+    let db = match fs::read(&db_path) {
+        Ok(data) => Some(parse_itunes_sd(&data)?),
+        Err(_) if live => None,
+        Err(e) => return Err(format!("无法读取 iTunesSD: {}（可使用 --live 直接搜索设备上的文件）", e)),
+    };
+
+    let filenames: Vec<String> = if live {
+        WalkDir::new(base)
+            .sort_by(|a, b| compare(&a.file_name().to_string_lossy(), &b.file_name().to_string_lossy()))
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !is_system_hidden(&e.file_name().to_string_lossy()))
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file() && AUDIO_EXT.contains(&ext_lower(e.path()).as_str()))
+            .filter(|e| !e.path().starts_with(base.join("iPod_Control").join("Speakable")))
+            .filter_map(|e| path_to_ipod(e.path(), base).ok())
+            .collect()
+    } else {
+        db.iter().flat_map(|d| d.tracks.iter().map(|t| t.filename.clone())).collect()
+    };
+
+    let paths: Vec<PathBuf> = filenames.iter().map(|f| base.join(f.trim_start_matches('/'))).collect();
+    let (mut metas, _) = read_all_meta(&paths, TAG_TIMEOUT);
+    apply_meta_sidecars(&paths, &mut metas);
+
+    let labels = db.as_ref()
+        .map(|d| playlist_labels(d, &candidate_names(base, &metas)))
+        .unwrap_or_default();
+    let mut membership: HashMap<&str, (usize, [u8; 8])> = HashMap::new();
+    if let Some(ref d) = db {
+        for (i, t) in d.tracks.iter().enumerate() {
+            membership.entry(t.filename.as_str()).or_insert((i, t.dbid));
+        }
+    }
+
+    let query: Vec<String> = query.split_whitespace().map(|w| w.to_lowercase()).collect();
+    let mut hits = Vec::new();
+    for (filename, path) in filenames.iter().zip(&paths) {
+        let meta = metas.get(path).cloned().unwrap_or_default();
+        if !matches(&query, filename, &meta) { continue; }
+
+        let entry = membership.get(filename.as_str());
+        let playlists = match (entry, &db) {
+            (Some(&(index, _)), Some(d)) => d.playlists.iter().zip(&labels)
+                .filter(|(p, _)| p.track_indices.contains(&(index as u32)))
+                .map(|(_, label)| label.clone())
+                .collect(),
+            _ => Vec::new(),
+        };
+        hits.push(SearchHit { filename: filename.clone(), meta, dbid: entry.map(|e| e.1), playlists });
+    }
+    Ok(hits)
+}
+
+/// 打印搜索结果
+pub fn print_hits(hits: &[SearchHit]) {
+    for (i, hit) in hits.iter().enumerate() {
+        outln!("[{}] {}", i + 1, hit.filename);
+        let fields = [("标题", &hit.meta.title), ("艺术家", &hit.meta.artist), ("专辑", &hit.meta.album)];
+        let tags: Vec<String> = fields.iter()
+            .filter_map(|(k, v)| v.as_ref().map(|v| format!("{}: {}", k, v)))
+            .collect();
+        if !tags.is_empty() {
+            outln!("    {}", tags.join("  "));
+        }
+        match hit.dbid {
+            Some(ref dbid) => {
+                outln!("    dbid: {}", dbid_to_filename(dbid));
+                if hit.playlists.is_empty() {
+                    outln!("    播放列表: （无）");
+                } else {
+                    outln!("    播放列表: {}", hit.playlists.join(", "));
+                }
+            }
+            None => outln!("    不在数据库中"),
+        }
+    }
+    outln!("找到 {} 首曲目", hits.len());
+}
@@ -0,0 +1,88 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+use crate::cli::AUDIO_EXT;
+use crate::i18n::tr;
+use crate::utils::{ext_lower, is_subpath, looks_like_ipod};
+
+// ─── Seeding a simulated iPod ────────────────────────────────────────────────
+
+/// --simulate-from 放入的文件数，按放入的方式分类
+#[derive(Default)]
+pub struct SeedStats {
+    /// 硬链接，与原文件共享数据
+    pub linked: usize,
+    /// 写时复制（reflink），数据块在修改前共享
+    pub reflinked: usize,
+    /// 不在同一文件系统或文件系统不支持以上两种方式时完整复制
+    pub copied: usize,
+    /// 模拟目录中已有同名文件，保持不变
+    pub existing: usize,
+}
+
+enum Seeded {
+    Linked,
+    Reflinked,
+    Copied,
+}
+
+/// `source` 在模拟的 iPod 中的位置：设备（或之前模拟过的目录）按原有结构放入根目录，
+/// 其他目录当作音乐库放入 iPod_Control/Music/<目录名>
+fn seed_target(source: &Path, sim: &Path) -> PathBuf {
+    if looks_like_ipod(source) {
+        return sim.to_path_buf();
+    }
+    let name = source.file_name().map(|n| n.to_os_string()).unwrap_or_else(|| "Library".into());
+    sim.join("iPod_Control").join("Music").join(name)
+}
+
+/// 只有音频文件使用硬链接：同步不会原地改写它们（写入标签前先断开链接，见 `device_io::unshare`），
+/// 数据库、缓存与旁白等会被改写的文件使用写时复制或复制
+fn place(src: &Path, dest: &Path) -> io::Result<Seeded> {
+    if cfg!(unix) && AUDIO_EXT.contains(&ext_lower(src).as_str()) && fs::hard_link(src, dest).is_ok() {
+        return Ok(Seeded::Linked);
+    }
+    Ok(match reflink_copy::reflink_or_copy(src, dest)? {
+        None => Seeded::Reflinked,
+        Some(_) => Seeded::Copied,
+    })
+}
+
+/// 把现有的设备或音乐库放入 --simulate 的目录。同一文件系统上不复制音频数据，
+/// 模拟同步中的转换、重命名与删除不会影响 `source`
+pub fn seed_simulation(source: &Path, sim: &Path) -> Result<SeedStats, String> {
+    if !source.is_dir() {
+        return Err(tr!("--simulate-from 的目录 \"{}\" 不存在", source.display()));
+    }
+    if is_subpath(sim, source) || is_subpath(source, sim) {
+        return Err(tr!("--simulate-from 的目录 \"{}\" 与模拟目录相互包含", source.display()));
+    }
+    let target = seed_target(source, sim);
+    let mut stats = SeedStats::default();
+    for entry in WalkDir::new(source).sort_by_file_name() {
+        let entry = entry.map_err(|e| tr!("无法读取 \"{}\": {}", source.display(), e))?;
+        let rel = entry.path().strip_prefix(source).expect("walkdir yields paths under its root");
+        let dest = target.join(rel);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&dest).map_err(|e| tr!("无法创建 \"{}\": {}", dest.display(), e))?;
+            continue;
+        }
+        // Symlinks are left out; the simulation only reads what is actually in the directory
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        if dest.exists() {
+            stats.existing += 1;
+            continue;
+        }
+        match place(entry.path(), &dest).map_err(|e| tr!("无法放入 \"{}\": {}", dest.display(), e))? {
+            Seeded::Linked => stats.linked += 1,
+            Seeded::Reflinked => stats.reflinked += 1,
+            Seeded::Copied => stats.copied += 1,
+        }
+    }
+    Ok(stats)
+}
@@ -1,206 +1,1479 @@
-use crate::vprintln;
+use crate::outln;
+use crate::i18n::{self, tr};
+use crate::progress;
+use crate::events;
+use indicatif::ProgressBar;
 use rayon::prelude::*;
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
+use tracing::{debug, error, warn};
 use walkdir::WalkDir;
 
-use crate::cli::{Cli, LIST_EXT, MUSIC_EXT};
-use crate::audio::estimate_track_loudness_db;
-use crate::convert::convert_flac_to_mp3;
-use crate::database::{build_itunes_sd, build_track_info, BuildContext};
+use crate::cli::{OutputFormat, ShadowPolicy, SyncArgs, TagFixPolicy, TrackKind, TrackOrder, AUDIOBOOK_EXT, MUSIC_EXT};
+use crate::abrepeat::{generate_ab_repeat, ABREPEAT_DIR, CUTS_EXT};
+use crate::collation::{compare, compare_paths};
+use crate::checkpoint::Checkpoint;
+use crate::convert::{convert_flac_to_mp3, fade_out_to_mp3, is_partial, EncodeOptions, FadeOut};
+use crate::build_meta::{previous_rotated_out, previous_sync_counts, record_play_counts, write_build_meta, META_FILE};
+use crate::changes::{summarize_changes, ChangeSummary};
+use crate::chapters::read_chapters;
+use crate::config::load_config;
+use crate::converters::load_converters;
+use crate::profiles::load_host_config;
+use crate::database::{
+    build_itunes_sd, build_track_info, do_text_to_speech, make_dbid, parse_itunes_sd, playlist_dbid, track_voiceover_text,
+    tracks_of_listtype, voiceover_path, BuildContext, ReservedFields, TrackInfo, MAX_FILENAME_BYTES,
+};
+use crate::favorites::{update_favorites, FAVORITES_FILE};
+use crate::notify::{notify_completion, notify_failure, Status};
+use crate::queue::{played_entries, remove_entries, QueuePlaylist, QUEUE_FILE};
+use crate::track_rules::TrackRules;
+use crate::gain::{analyze_loudness, carry_over_loudness, compute_gains, GainRules};
+use crate::generators::{select_for_duration, split_balanced, BEDTIME_DIR};
+use crate::listing::{listtype_name, playlist_display_name};
+use crate::memos::archive_memos;
+use crate::metadata::{apply_meta_sidecars, read_all_meta, TrackMeta};
+use crate::moves::detect_moves;
+use crate::device_io::write_file_atomic;
+use crate::ordering::{
+    apply_order_pins, keep_previous_order, load_order_pins, sort_audiobook_parts, spread_by_artist, ShuffleSeed, ORDER_FILE,
+};
 use crate::playlist::{
-    group_tracks_by_id3_template, resolve_playlist_tracks, PlaylistSource,
+    group_tracks_by_album, group_tracks_by_id3_template, open_list_file, resolve_playlist_tracks,
+    DirectoryPlaylist, GroupedPlaylist, ListType, PlaylistOptions, PlaylistSource, ResolveContext, config_options,
+    read_sidecar, AUDIOBOOK_PLAYLIST,
+};
+use crate::tagfix::{fix_tags, write_fixes, TagFix};
+use crate::track_csv::{load_overrides, TrackOverride};
+use crate::confirm::{confirm, preview};
+use crate::dedup::find_duplicates;
+use crate::cancel;
+use crate::device_lock;
+use crate::exit_code;
+use crate::report::{
+    format_bytes, render_json, write_report, ConversionEntry, FailureKind, GainEntry, PlaybackEntry, RunOutcome, SkipReason,
+    SyncReport, VoiceoverCoverage, VoiceoverEntry,
 };
-use crate::utils::{ext_lower, is_subpath};
+use crate::speakable;
+use crate::stats::play_counts;
+use crate::tts::{self, SpokenVoiceover};
+use crate::tui;
+use crate::textbook::{generate_audiobook, TEXTBOOK_DIR, TEXT_EXT};
+use crate::utils::{
+    ext_lower, hidden_entry_allowed, is_subpath, is_system_hidden, looks_like_ipod, normalize_path,
+    path_to_ipod, PathIdentity,
+};
+
+/// 生成器的来源播放列表：未指定名称时为主列表
+fn source_playlist(playlists: &[(String, Vec<u32>)], name: Option<&str>) -> Option<Vec<u32>> {
+    match name {
+        None => playlists.first().map(|(_, i)| i.clone()),
+        Some(name) => playlists.iter().find(|(n, _)| n == name).map(|(_, i)| i.clone()),
+    }
+}
+
+/// 报告中使用的路径显示形式（优先使用 iPod 相对路径）
+fn display_path(p: &Path, base: &Path) -> String {
+    path_to_ipod(p, base).unwrap_or_else(|_| p.display().to_string())
+}
+
+/// Ctrl+C 之后在两个阶段之间停止同步：进行中的转换与旁白已经完成或删除，设备上的数据库保持不变，
+/// 检查点留给下次同步续传
+fn exit_if_cancelled(report: &SyncReport) {
+    if !cancel::requested() { return; }
+    let converted = report.conversions.iter().filter(|c| c.output.is_some()).count();
+    warn!("{}", tr!("同步已中断：本次转换了 {} 个文件，iPod 数据库没有修改。下次同步会从中断处继续。", converted));
+    notify_failure(i18n::text("同步已被 Ctrl+C 中断，iPod 数据库没有修改"), report);
+    device_lock::release();
+    std::process::exit(exit_code::INTERRUPTED);
+}
+
+/// --strict 将以非零状态码结束同步时的失败说明，用于结束时的通知
+fn strict_failure(strict: bool, report: &SyncReport) -> Option<String> {
+    (strict && report.strict_exit_code() != exit_code::SUCCESS)
+        .then(|| tr!("--strict: 同步时出现 {} 个问题", report.failures.len()))
+}
+
+/// --strict：同步中出现过任何问题时，按最严重的问题类别以非零状态码退出
+fn exit_if_strict(strict: bool, report: &SyncReport) {
+    let code = report.strict_exit_code();
+    if strict && code != exit_code::SUCCESS {
+        error!("{}", tr!("--strict: 同步时出现 {} 个问题，以状态码 {} 退出", report.failures.len(), code));
+        device_lock::release();
+        std::process::exit(code);
+    }
+}
+
+/// 将无法写入数据库的文件移出曲目列表，并连同原因与详情记录到报告
+fn skip_tracks(
+    tracks: &mut Vec<PathBuf>, reason: SkipReason, entries: Vec<(PathBuf, String)>, report: &mut SyncReport, base: &Path,
+) {
+    if entries.is_empty() { return; }
+    let bad: HashSet<&PathBuf> = entries.iter().map(|(t, _)| t).collect();
+    tracks.retain(|t| !bad.contains(t));
+    for (t, detail) in entries {
+        warn!("{}", tr!("跳过 {}（{}: {}）。", t.display(), i18n::text(reason.label()), detail));
+        report.skip_track(display_path(&t, base), reason, detail);
+    }
+}
+
+/// 按 --max-size 与 --max-total-duration 选取曲目：按当前（路径）顺序依次选入，放不下的曲目跳过，
+/// 后面较小或较短的曲目仍可选入。时长未知的曲目按 0 计。返回被跳过的曲目及原因
+fn select_within_caps(
+    tracks: &mut Vec<PathBuf>,
+    metadata: &HashMap<PathBuf, TrackMeta>,
+    max_size: Option<u64>,
+    max_ms: Option<u64>,
+) -> Vec<(PathBuf, &'static str)> {
+    let (mut size, mut ms) = (0u64, 0u64);
+    let mut left_out = Vec::new();
+    tracks.retain(|t| {
+        let track_size = fs::metadata(t).map(|m| m.len()).unwrap_or(0);
+        let track_ms = metadata.get(t).and_then(|m| m.duration_ms).unwrap_or(0);
+        let reason = if max_size.is_some_and(|max| size + track_size > max) {
+            "超出 --max-size"
+        } else if max_ms.is_some_and(|max| ms + track_ms > max) {
+            "超出 --max-total-duration"
+        } else {
+            size += track_size;
+            ms += track_ms;
+            return true;
+        };
+        left_out.push((t.clone(), reason));
+        false
+    });
+    left_out
+}
+
+/// --rotate-after 的选入优先级，按先后依次选入
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum RotationGroup {
+    /// 在上次的数据库中，还没有达到 N 次同步
+    Kept,
+    /// 上次没有选入。从未被轮换出去的曲目为 0，其余按被轮换出去的先后从 1 开始，先轮换出去的先换入
+    RotatingIn(usize),
+    /// 已连续 N 次同步写入数据库，这次让位
+    RotatedOut,
+}
+
+/// 按上次构建信息中的同步次数与轮换出去的曲目为每首曲目分组。`ipod_path` 给出曲目在数据库中的文件名
+fn rotation_groups(
+    tracks: &[PathBuf],
+    counts: &HashMap<String, u32>,
+    rotated_out: &[String],
+    after: u32,
+    ipod_path: impl Fn(&PathBuf) -> String,
+) -> HashMap<PathBuf, RotationGroup> {
+    let waiting: HashMap<&str, usize> = rotated_out.iter().enumerate().map(|(i, t)| (t.as_str(), i + 1)).collect();
+    tracks.iter()
+        .map(|t| {
+            let name = ipod_path(t);
+            let group = match counts.get(&name) {
+                None => RotationGroup::RotatingIn(waiting.get(name.as_str()).copied().unwrap_or(0)),
+                Some(&n) if n < after => RotationGroup::Kept,
+                Some(_) => RotationGroup::RotatedOut,
+            };
+            (t.clone(), group)
+        })
+        .collect()
+}
+
+/// 应用 --metadata-csv 表格中的标题与旁白文字，返回其中的增益。只有与按标签算出的值不同的单元格才生效，
+/// 原样读回的导出表格不会把当时的值固定下来；增益要等自动增益算出后再比较。
+/// 演练时 FLAC 按将转换成的 MP3 匹配，与导出的路径一致
+fn apply_csv_overrides(
+    overrides: &HashMap<String, TrackOverride>,
+    tracks: &[PathBuf],
+    projected_mp3: &HashMap<PathBuf, PathBuf>,
+    metadata: &mut HashMap<PathBuf, TrackMeta>,
+    identity: PathIdentity,
+    base: &Path,
+) -> HashMap<PathBuf, u32> {
+    let by_key: HashMap<PathBuf, &TrackOverride> = overrides.iter()
+        .map(|(path, o)| (identity.key(&base.join(path.trim_start_matches('/'))), o))
+        .collect();
+    let mut gains = HashMap::new();
+    let mut matched = 0;
+    for t in tracks {
+        let Some(o) = by_key.get(&identity.key(projected_mp3.get(t).unwrap_or(t))) else { continue };
+        matched += 1;
+        let meta = metadata.entry(t.clone()).or_default();
+        // The spoken text depends on the title, so it is compared before a new title goes in
+        let computed_voiceover = [meta.voiceover.clone(), Some(track_voiceover_text(t, meta))];
+        if o.voiceover.is_some() && !computed_voiceover.contains(&o.voiceover) {
+            meta.voiceover = o.voiceover.clone();
+        }
+        if o.title.is_some() && o.title != meta.title {
+            meta.title = o.title.clone();
+        }
+        if let Some(gain) = o.gain {
+            gains.insert(t.clone(), gain);
+        }
+    }
+    debug!("{}", tr!("已按表格覆盖 {} 首曲目的元数据", matched));
+    if matched < overrides.len() {
+        warn!("{}", tr!("表格中有 {} 首曲目不在本次同步中，已忽略", overrides.len() - matched));
+    }
+    gains
+}
+
+/// 为每个待转换的文件决定转换的目标 (源文件, MP3, 是否覆盖)，并按 --shadow-policy 处理同名的 MP3
+fn resolve_convert_jobs(
+    convert_files: &[PathBuf], policy: ShadowPolicy, checkpoint: &Checkpoint, base: &Path, report: &mut SyncReport,
+) -> Vec<(PathBuf, PathBuf, bool)> {
+    let mut jobs = Vec::new();
+    for src in convert_files {
+        // An interrupted run already wrote this output; it only has to drop the source
+        if let Some(out) = checkpoint.converted(src) {
+            jobs.push((src.clone(), out, false));
+            continue;
+        }
+        let mp3 = src.with_extension("mp3");
+        if !mp3.exists() {
+            jobs.push((src.clone(), mp3, false));
+            continue;
+        }
+        let (out, overwrite, action) = match policy {
+            ShadowPolicy::PreferMp3 => (mp3.clone(), false, "保留 MP3，删除源文件"),
+            ShadowPolicy::PreferFlac => (mp3.clone(), true, "由源文件重新转换覆盖"),
+            ShadowPolicy::KeepBothWithSuffix => {
+                let stem = src.file_stem().unwrap_or_default().to_string_lossy();
+                let format = ext_lower(src).trim_start_matches('.').to_uppercase();
+                (src.with_file_name(format!("{} ({}).mp3", stem, format)), false, "两者都保留")
+            }
+        };
+        debug!("{}", tr!("[!] {} 与 {} 同名: {}", mp3.display(), display_path(src, base), i18n::text(action)));
+        report.shadowed.push((display_path(&mp3, base), i18n::text(action).to_string()));
+        jobs.push((src.clone(), out, overwrite));
+    }
+    jobs
+}
+
+/// 同步开始删除文件前列出将被删除的旧旁白、待转换的源文件与收听队列中已播放的曲目，并请用户确认（见 `confirm`）。
+/// 演练时不询问，只供图形界面预先显示（见 `preview`）
+fn confirm_deletions(base: &Path, convert_files: &[PathBuf], tts_resume: bool, queue_played: usize, dry_run: bool) -> bool {
+    let mut items = Vec::new();
+    if !tts_resume {
+        let speakable = base.join("iPod_Control").join("Speakable");
+        let voiceovers = ["Playlists", "Tracks"].iter()
+            .filter_map(|d| fs::read_dir(speakable.join(d)).ok())
+            .map(|entries| entries.count())
+            .sum::<usize>();
+        if voiceovers > 0 {
+            items.push(tr!("iPod_Control/Speakable 中的 {} 个语音旁白（之后重新生成）", voiceovers));
+        }
+    }
+    if queue_played > 0 {
+        items.push(tr!("{} 中已播放的 {} 首曲目（移出队列）", QUEUE_FILE, queue_played));
+    }
+    items.extend(convert_files.iter().map(|f| tr!("{}（转换为 MP3 后删除）", display_path(f, base))));
+    let title = tr!("同步将删除设备上的以下文件：");
+    if dry_run {
+        preview(&title, &items);
+        return true;
+    }
+    confirm(&title, &items)
+}
+
+/// 按 --min-duration 与 --max-duration 判断是否保留曲目，排除时记入报告。读不出时长的曲目保留
+fn within_duration_limits(
+    track: &Path, metadata: &HashMap<PathBuf, TrackMeta>, cli: &SyncArgs, report: &mut SyncReport, base: &Path,
+) -> bool {
+    let Some(ms) = metadata.get(track).and_then(|m| m.duration_ms) else { return true };
+    let reason = if cli.min_duration.is_some_and(|min| ms < min.as_millis() as u64) {
+        "短于 --min-duration"
+    } else if cli.max_duration.is_some_and(|max| ms > max.as_millis() as u64) {
+        "长于 --max-duration"
+    } else {
+        return true;
+    };
+    debug!("{}", tr!("[-] 排除曲目 {} ({}, {}s)", track.display(), i18n::text(reason), format!("{:.1}", ms as f64 / 1000.0)));
+    report.excluded.push((display_path(track, base), i18n::text(reason).to_string()));
+    false
+}
+
+/// 删除 `root` 中不在 `kept` 里的生成目录（其来源已被删除）；演练时只列出
+fn remove_stale_dirs(root: &Path, kept: &HashSet<PathBuf>, dry_run: bool, base: &Path) {
+    let Ok(entries) = fs::read_dir(root) else { return };
+    for entry in entries.filter_map(|e| e.ok()) {
+        if kept.contains(&entry.path()) { continue; }
+        if dry_run {
+            outln!("{}", tr!("[演练] 将删除 {}", display_path(&entry.path(), base)));
+        } else {
+            let _ = fs::remove_dir_all(entry.path());
+        }
+    }
+}
+
+/// 按播放列表选项筛选、排列曲目所需的曲库信息
+struct Library<'a> {
+    tracks: &'a [PathBuf],
+    metadata: &'a HashMap<PathBuf, TrackMeta>,
+    seed: &'a ShuffleSeed,
+}
+
+/// 按选项重新排列播放列表中的曲目
+fn reorder_playlist(indices: &mut Vec<u32>, order: TrackOrder, library: &Library<'_>) {
+    let Library { tracks, metadata, seed } = *library;
+    match order {
+        TrackOrder::Path => {
+            indices.sort_by(|&a, &b| compare(&tracks[a as usize].to_string_lossy(), &tracks[b as usize].to_string_lossy()));
+        }
+        TrackOrder::ArtistSpread => reorder_by_paths(indices, tracks, |paths| spread_by_artist(paths, metadata)),
+        TrackOrder::Shuffle => reorder_by_paths(indices, tracks, |paths| seed.shuffle(paths)),
+    }
+}
+
+/// 按 `order` 对曲目路径的排列重新排列索引；同一文件的多个条目（如章节）保持原来的先后
+fn reorder_by_paths(indices: &mut Vec<u32>, tracks: &[PathBuf], order: impl FnOnce(&[PathBuf]) -> Vec<PathBuf>) {
+    let paths: Vec<PathBuf> = indices.iter().map(|&i| tracks[i as usize].clone()).collect();
+    let mut by_path: HashMap<PathBuf, Vec<u32>> = HashMap::new();
+    for (&i, p) in indices.iter().zip(&paths).rev() {
+        by_path.entry(p.clone()).or_default().push(i);
+    }
+    *indices = order(&paths).iter()
+        .filter_map(|p| by_path.get_mut(p).and_then(|v| v.pop()))
+        .collect();
+}
+
+/// 曲目标题（没有标题标签时为文件名）是否匹配任一模式
+fn title_matches(patterns: &[regex::Regex], track: &Path, metadata: &HashMap<PathBuf, TrackMeta>) -> bool {
+    let stem = track.file_stem().unwrap_or_default().to_string_lossy();
+    let title = metadata.get(track).and_then(|m| m.title.as_deref()).unwrap_or(&stem);
+    patterns.iter().any(|p| p.is_match(title))
+}
+
+/// 将标题匹配 --exclude-title 的曲目移出主列表并清除其 dont_skip 标志，与 `exclude_from_master` 相同，
+/// 这些曲目仍可通过播放列表菜单播放
+fn exclude_titles_from_master(
+    playlists: &mut [(String, Vec<u32>)],
+    patterns: &[regex::Regex],
+    tracks: &[PathBuf],
+    metadata: &HashMap<PathBuf, TrackMeta>,
+    track_infos: &mut [TrackInfo],
+    report: &mut SyncReport,
+    base: &Path,
+) {
+    if patterns.is_empty() { return; }
+    let Some((_, master)) = playlists.iter_mut().find(|(name, _)| name == "__master__") else { return };
+    let reason = "标题匹配 --exclude-title（移出主列表）";
+    master.retain(|&i| {
+        let track = &tracks[i as usize];
+        if !title_matches(patterns, track, metadata) { return true; }
+        debug!("{}", tr!("[-] 排除曲目 {} ({})", track.display(), i18n::text(reason)));
+        track_infos[i as usize].dont_skip = false;
+        report.excluded.push((display_path(track, base), i18n::text(reason).to_string()));
+        false
+    });
+    if master.is_empty() {
+        warn!("{}", tr!("所有曲目都被移出了主列表，\"All songs\" 将为空。"));
+    }
+}
+
+/// 合并配置文件与选项文件中的播放列表设置，并应用其中的标题排除、顺序与增益。
+/// 返回供写入数据库使用的选项表
+fn apply_playlist_options(
+    playlists: &mut [(String, Vec<u32>)],
+    mut sidecars: HashMap<String, PlaylistOptions>,
+    config: Option<&toml::Table>,
+    library: &Library<'_>,
+    track_infos: &mut [TrackInfo],
+    report: &mut SyncReport,
+) -> HashMap<String, PlaylistOptions> {
+    let mut options = HashMap::new();
+    let mut boosts: HashMap<u32, i32> = HashMap::new();
+    for (name, indices) in playlists.iter_mut() {
+        if name == "__master__" { continue; }
+        let mut opts = match config_options(config, name) {
+            Ok(o) => o.unwrap_or_default(),
+            Err(e) => {
+                warn!("{}", e);
+                report.failure(e);
+                PlaylistOptions::default()
+            }
+        };
+        if let Some(sidecar) = sidecars.remove(name.as_str()) {
+            opts.merge(sidecar);
+        }
+        if let Some(ref patterns) = opts.exclude_titles {
+            let before = indices.len();
+            indices.retain(|&i| !title_matches(patterns, &library.tracks[i as usize], library.metadata));
+            if indices.len() < before {
+                debug!("{}", tr!("[-] 已从播放列表 \"{}\" 中去掉 {} 首标题匹配 exclude-titles 的曲目", name, before - indices.len()));
+            }
+        }
+        if let Some(order) = opts.order {
+            reorder_playlist(indices, order, library);
+        }
+        if let Some(boost) = opts.gain_boost {
+            for &i in indices.iter() {
+                let b = boosts.entry(i).or_insert(0);
+                if boost.abs() > b.abs() { *b = boost; }
+            }
+        }
+        options.insert(name.clone(), opts);
+    }
+    for (i, boost) in boosts {
+        let info = &mut track_infos[i as usize];
+        info.volume_gain = (info.volume_gain as i32 + boost).clamp(0, 99) as u32;
+    }
+    options
+}
+
+/// 将设置了 `exclude-from-master` 的播放列表中的曲目移出主列表，并清除其 dont_skip 标志，
+/// 使随机播放在列表之间选曲时跳过它们；这些曲目仍可通过播放列表菜单播放
+fn exclude_from_master(
+    playlists: &mut [(String, Vec<u32>)], options: &HashMap<String, PlaylistOptions>, track_infos: &mut [TrackInfo],
+) {
+    let excluded: HashSet<u32> = playlists.iter()
+        .filter(|(name, _)| options.get(name).is_some_and(|o| o.exclude_from_master == Some(true)))
+        .flat_map(|(_, indices)| indices.iter().copied())
+        .collect();
+    if excluded.is_empty() { return; }
+    for &i in &excluded {
+        track_infos[i as usize].dont_skip = false;
+    }
+    if let Some((_, master)) = playlists.iter_mut().find(|(name, _)| name == "__master__") {
+        master.retain(|i| !excluded.contains(i));
+        debug!("{}", tr!("[-] 已将 {} 首曲目移出主列表", excluded.len()));
+        if master.is_empty() {
+            warn!("{}", tr!("所有曲目都被移出了主列表，\"All songs\" 将为空。"));
+        }
+    }
+}
 
-pub fn run_shuffler(cli: &Cli) {
-    let base = PathBuf::from(&cli.path);
-    let base = fs::canonicalize(&base).unwrap_or(base);
+/// 未设置 `order` 的有声书列表按系列与分段编号排列（见 `sort_audiobook_parts`），
+/// 并将曲目记录的曲目编号改为在列表中的位置，使设备按文件先后播放
+fn order_audiobooks(
+    playlists: &mut [(String, Vec<u32>)],
+    options: &HashMap<String, PlaylistOptions>,
+    tracks: &[PathBuf],
+    metadata: &HashMap<PathBuf, TrackMeta>,
+    track_infos: &mut [TrackInfo],
+) {
+    for (name, indices) in playlists.iter_mut() {
+        let Some(opts) = options.get(name) else { continue };
+        if opts.listtype != Some(ListType::Audiobook) { continue; }
+        if opts.order.is_none() {
+            reorder_by_paths(indices, tracks, |paths| sort_audiobook_parts(paths, metadata));
+        }
+        for (position, &i) in indices.iter().enumerate() {
+            track_infos[i as usize].track_num = u16::try_from(position + 1).unwrap_or(u16::MAX);
+        }
+    }
+}
+
+/// 播客与有声书列表中的曲目记住播放位置，且不参与随机播放；有声书还会移出主列表
+fn mark_resumable_tracks(
+    playlists: &mut [(String, Vec<u32>)], options: &HashMap<String, PlaylistOptions>, track_infos: &mut [TrackInfo],
+) {
+    let audiobooks = tracks_of_listtype(playlists, options, ListType::Audiobook);
+    for i in tracks_of_listtype(playlists, options, ListType::Podcast).into_iter().chain(audiobooks.iter().copied()) {
+        let info = &mut track_infos[i as usize];
+        info.remember = true;
+        info.dont_skip = false;
+    }
+    if audiobooks.is_empty() { return; }
+    if let Some((_, master)) = playlists.iter_mut().find(|(name, _)| name == "__master__") {
+        master.retain(|i| !audiobooks.contains(i));
+        debug!("{}", tr!("[-] 已将 {} 首有声书移出主列表", audiobooks.len()));
+        if master.is_empty() {
+            warn!("{}", tr!("所有曲目都被移出了主列表，\"All songs\" 将为空。"));
+        }
+    }
+}
+
+/// 整理本次同步的旁白：每条旁白朗读的文字与语音，以及每个播放列表中曲目旁白的语言
+fn voiceover_stats(
+    spoken: &[SpokenVoiceover],
+    base: &Path,
+    tracks: &[PathBuf],
+    track_infos: &[TrackInfo],
+    playlists: &[(String, Vec<u32>)],
+    playlist_voiceover: bool,
+) -> (Vec<VoiceoverEntry>, Vec<VoiceoverCoverage>) {
+    let mut labels: HashMap<PathBuf, (String, bool)> = HashMap::new();
+    for (t, info) in tracks.iter().zip(track_infos) {
+        labels.entry(voiceover_path(base, &info.dbid, false)).or_insert((display_path(t, base), false));
+    }
+    for (name, _) in playlists {
+        labels.insert(voiceover_path(base, &playlist_dbid(name, playlist_voiceover), true), (playlist_display_name(name).to_string(), true));
+    }
+
+    let mut seen = HashSet::new();
+    let mut entries = Vec::new();
+    let mut by_path: HashMap<PathBuf, usize> = HashMap::new();
+    for s in spoken {
+        if !seen.insert(&s.path) { continue; }
+        let (label, is_playlist) = labels.get(&s.path).cloned()
+            .unwrap_or_else(|| (display_path(&s.path, base), false));
+        by_path.insert(s.path.clone(), entries.len());
+        entries.push(VoiceoverEntry {
+            label,
+            is_playlist,
+            text: s.text.clone(),
+            voice: s.voice.clone(),
+            script: tts::dominant_script(&s.text).map(|sc| sc.name()),
+            mismatch: tts::likely_mismatch(&s.text, &s.voice),
+        });
+    }
+
+    let mut coverage = Vec::new();
+    for (name, indices) in playlists {
+        let voiced: Vec<&VoiceoverEntry> = indices.iter()
+            .filter_map(|&i| by_path.get(&voiceover_path(base, &track_infos[i as usize].dbid, false)))
+            .map(|&e| &entries[e])
+            .collect();
+        if voiced.is_empty() { continue; }
+        let mut scripts: Vec<(&'static str, usize)> = Vec::new();
+        for script in voiced.iter().filter_map(|e| e.script) {
+            match scripts.iter_mut().find(|(s, _)| *s == script) {
+                Some((_, n)) => *n += 1,
+                None => scripts.push((script, 1)),
+            }
+        }
+        scripts.sort_by_key(|&(_, n)| std::cmp::Reverse(n));
+        coverage.push(VoiceoverCoverage {
+            playlist: playlist_display_name(name).to_string(),
+            tracks: indices.len(),
+            voiced: voiced.len(),
+            scripts,
+            mismatches: voiced.iter().filter(|e| e.mismatch).count(),
+        });
+    }
+    (entries, coverage)
+}
+
+/// 演练结束时打印将写入的数据库内容
+fn print_preview(
+    track_infos: &[TrackInfo],
+    playlists: &[(String, Vec<u32>)],
+    options: &HashMap<String, PlaylistOptions>,
+    report: &SyncReport,
+    voiceovers: &tts::SessionSummary,
+) {
+    outln!();
+    outln!("{}", tr!("[演练] 数据库将包含 {} 首曲目:", track_infos.len()));
+    for (i, info) in track_infos.iter().enumerate() {
+        outln!("{}", tr!("  {}. {}  增益: {}", format!("{:>4}", i + 1), info.filename, info.volume_gain));
+    }
+    outln!("{}", tr!("[演练] 以及 {} 个播放列表:", playlists.len()));
+    for (name, indices) in playlists {
+        let listtype = match name.as_str() {
+            "__master__" => 1,
+            _ => options.get(name).map_or(ListType::Normal.code(), |o| o.listtype_code()),
+        };
+        outln!("{}", tr!("  {} [{}] ({} 首)", playlist_display_name(name), i18n::text(listtype_name(listtype)), indices.len()));
+    }
+    if voiceovers.generated > 0 {
+        outln!("{}", tr!("[演练] 将生成 {} 条语音旁白", voiceovers.generated));
+    }
+    if voiceovers.deferred > 0 {
+        outln!("{}", tr!("[演练] {} 条语音旁白超出 --tts-limit，将留待下次同步", voiceovers.deferred));
+    }
+    if voiceovers.removed > 0 {
+        outln!("{}", tr!("[演练] 将删除 {} 条不再使用的旁白", voiceovers.removed));
+    }
+    if let Some(ref c) = report.changes {
+        outln!("{}", c.line());
+    }
+    if !report.failures.is_empty() {
+        outln!("{}", tr!("同步时会出现 {} 个问题，见上方的警告。", report.failures.len()));
+    }
+    outln!("{}", tr!("演练结束，设备未被修改。"));
+}
+
+/// 执行一次同步。`review` 为 true 时（tui 子命令）在写入数据库前打开终端界面检查结果
+pub fn run_shuffler(cli: &SyncArgs, review: bool) {
+    let base = cli.device_path().to_path_buf();
+    let base = normalize_path(&base);
 
     let track_voiceover = cli.track_voiceover;
     let playlist_voiceover = cli.playlist_voiceover;
     let rename = cli.rename_unicode;
+    let dry_run = cli.dry_run;
     let trackgain = cli.track_gain;
+    let mut report = SyncReport::default();
+    let encode_opts = EncodeOptions {
+        bitrate: cli.bitrate,
+        timeout: cli.convert_timeout,
+        retries: cli.retries,
+    };
+
+    // A bad override table stops the run before anything on the device changes
+    let csv_overrides = match cli.metadata_csv {
+        Some(ref file) => load_overrides(file).unwrap_or_else(|e| {
+            error!("{}", e);
+            notify_failure(&e, &report);
+            std::process::exit(exit_code::USAGE);
+        }),
+        None => HashMap::new(),
+    };
+    // A checkpoint left on the device means the last sync was cut short
+    let mut checkpoint = Checkpoint::load(&base);
+    if cli.no_resume {
+        checkpoint.discard();
+    } else if checkpoint.interrupted() {
+        outln!("{}", tr!("上次同步没有完成，从中断处继续: 已完成的转换与语音旁白不会重做（--no-resume 从头开始）"));
+    }
+    let tts_resume = cli.tts_resume || checkpoint.interrupted();
+    let converters = load_host_config().and_then(|c| load_converters(c.as_ref())).unwrap_or_else(|e| {
+        error!("{}", e);
+        notify_failure(&e, &report);
+        std::process::exit(exit_code::USAGE);
+    });
+
+    // The voiceover file names below depend on the configured layout
+    if let Err(e) = speakable::configure_layout(&base) {
+        warn!("{}", e);
+        report.failure(e);
+    }
 
     // Initialize directories
-    for dirname in &["iPod_Control/Speakable/Playlists", "iPod_Control/Speakable/Tracks"] {
-        let p = base.join(dirname);
-        let _ = fs::remove_dir_all(&p);
+    if dry_run {
+        outln!("{}", tr!("演练模式: 只报告将要进行的修改，不会写入或删除设备上的任何文件"));
+    } else {
+        for dirname in &[
+            "iPod_Control/iTunes", "iPod_Control/Music",
+            "iPod_Control/Speakable/Playlists", "iPod_Control/Speakable/Tracks",
+        ] {
+            let _ = fs::create_dir_all(base.join(dirname));
+        }
+    }
+
+    // Favorites must be read before the scan so the playlist file is picked up,
+    // and before the new database replaces the one iTunesStats refers to
+    let mut favorites_playlist: Option<PathBuf> = None;
+    if cli.favorites && dry_run {
+        outln!("{}", tr!("[演练] 不更新收藏列表，使用现有的 {}", FAVORITES_FILE));
+        let path = base.join(FAVORITES_FILE);
+        if path.exists() {
+            favorites_playlist = Some(normalize_path(&path));
+        }
+    } else if cli.favorites {
+        match update_favorites(&base) {
+            Ok((path, added)) => {
+                outln!("{}", tr!("收藏列表已更新: 新增 {} 首曲目", added));
+                favorites_playlist = Some(normalize_path(&path));
+            }
+            Err(e) => {
+                warn!("{}", tr!("无法更新收藏列表: {}", e));
+                report.failure(tr!("无法更新收藏列表: {}", e));
+                let path = base.join(FAVORITES_FILE);
+                if path.exists() {
+                    favorites_playlist = Some(normalize_path(&path));
+                }
+            }
+        }
+    }
+
+    // Played queue entries are matched against the iTunesStats of the database about to be replaced;
+    // they leave queue.txt only once the deletions are confirmed
+    let queue_played = played_entries(&base, cli.list_encoding).unwrap_or_else(|e| {
+        warn!("{}", tr!("无法清除 {} 中已播放的曲目: {}", QUEUE_FILE, e));
+        report.failure(tr!("无法清除 {} 中已播放的曲目: {}", QUEUE_FILE, e));
+        Vec::new()
+    });
+    if !queue_played.is_empty() && dry_run {
+        outln!("{}", tr!("[演练] 将从 {} 清除 {} 首已播放的曲目", QUEUE_FILE, queue_played.len()));
+    }
+    for entry in &queue_played {
+        debug!("{}", tr!("[-] 已播放，移出队列: {}", entry));
     }
-    for dirname in &[
-        "iPod_Control/iTunes", "iPod_Control/Music",
-        "iPod_Control/Speakable/Playlists", "iPod_Control/Speakable/Tracks",
-    ] {
-        let _ = fs::create_dir_all(base.join(dirname));
+    // Saved with the new database, so that the next sync can tell which tracks were played since
+    if let Ok(counts) = play_counts(&base) {
+        record_play_counts(counts);
+    }
+
+    // Voice memos are moved off the device before the scan so they never reach the database
+    if let Some(ref archive) = cli.archive_memos {
+        let (moved, failures) = archive_memos(&base, &cli.memo_dirs, archive);
+        if !moved.is_empty() && dry_run {
+            outln!("{}", tr!("[演练] 将把 {} 个录音归档到 {}", moved.len(), archive.display()));
+        } else if !moved.is_empty() {
+            outln!("{}", tr!("已将 {} 个录音归档到 {}", moved.len(), archive.display()));
+        }
+        for (src, dest) in moved {
+            report.memos.push((display_path(&src, &base), dest.display().to_string()));
+        }
+        for f in failures {
+            warn!("{}", tr!("无法归档录音 {}", f));
+            report.failure(tr!("无法归档录音 {}", f));
+        }
     }
 
     let speakable_root = base.join("iPod_Control").join("Speakable");
     let music_root = base.join("iPod_Control").join("Music");
+    let bedtime_root = base.join("iPod_Control").join(BEDTIME_DIR);
+    let abrepeat_root = base.join("iPod_Control").join(ABREPEAT_DIR);
+    let textbook_root = base.join("iPod_Control").join(TEXTBOOK_DIR);
+    let mut excluded_roots = vec![speakable_root.clone(), bedtime_root, abrepeat_root.clone(), textbook_root.clone()];
+    if cli.archive_memos.is_some() {
+        excluded_roots.extend(cli.memo_dirs.iter().map(|d| base.join(d)));
+    }
 
     // Collect files
-    let mut flac_files: Vec<PathBuf> = Vec::new();
+    let mut convert_files: Vec<PathBuf> = Vec::new();
+    // Half-written MP3s of conversions an interruption cut short
+    let mut partial_files: Vec<PathBuf> = Vec::new();
     let mut other_audio_files: Vec<PathBuf> = Vec::new();
-    let mut playlist_sources: Vec<PlaylistSource> = Vec::new();
+    let mut playlist_sources: Vec<Box<dyn PlaylistSource>> = Vec::new();
+    let mut cuts_files: Vec<PathBuf> = Vec::new();
 
-    for entry in WalkDir::new(&base).sort_by_file_name().into_iter().filter_map(|e| e.ok()) {
-        let path = entry.path();
-        if let Some(name) = path.file_name() {
-            if name.to_string_lossy().starts_with('.') && path != base.as_path() {
+    // Scan roots: the whole device by default, or the configured library roots.
+    // Each root carries its track kind and the base directory for --auto-dir-playlists.
+    let mut scan_roots: Vec<(PathBuf, TrackKind, PathBuf)> = Vec::new();
+    // Playlists of podcast and audiobook directories, with the list type they get unless their options say otherwise
+    let mut kind_lists: HashMap<String, ListType> = HashMap::new();
+    let list_type = |kind: TrackKind| match kind {
+        TrackKind::Podcast => ListType::Podcast,
+        TrackKind::Audiobook => ListType::Audiobook,
+        TrackKind::Music => ListType::Normal,
+    };
+    if cli.roots.is_empty() {
+        scan_roots.push((base.clone(), TrackKind::Music, music_root.clone()));
+    } else {
+        for root in &cli.roots {
+            let dir = base.join(&root.dir);
+            if !dir.is_dir() {
+                warn!("{}", tr!("库根目录 \"{}\" 不存在。跳过。", dir.display()));
+                report.failure(tr!("库根目录 \"{}\" 不存在", root.dir));
+                continue;
+            }
+            let dir = normalize_path(&dir);
+            // Tracks outside the device would be written as unplayable entries, and their FLACs converted and deleted
+            if !is_subpath(&dir, &base) {
+                warn!("{}", tr!("库根目录 \"{}\" 不在 iPod 上。跳过。", dir.display()));
+                report.failure(tr!("库根目录 \"{}\" 不在 iPod 上", root.dir));
                 continue;
             }
+            if root.kind != TrackKind::Music {
+                let source = DirectoryPlaylist::new(dir.clone());
+                kind_lists.insert(source.name(), list_type(root.kind));
+                playlist_sources.push(Box::new(source));
+            }
+            scan_roots.push((dir.clone(), root.kind, dir));
+        }
+    }
+    // Podcast and audiobook directories keep the scan range; only those outside every root are walked on their own
+    let mut kind_dirs: Vec<(PathBuf, TrackKind)> = Vec::new();
+    let marked_dirs = cli.podcast_dirs.iter().map(|d| (d, TrackKind::Podcast))
+        .chain(cli.audiobook_dirs.iter().map(|d| (d, TrackKind::Audiobook)));
+    for (dir, kind) in marked_dirs {
+        let path = base.join(dir);
+        if !path.is_dir() {
+            let (message, failure) = if kind == TrackKind::Podcast {
+                (tr!("播客目录 \"{}\" 不存在。跳过。", path.display()), tr!("播客目录 \"{}\" 不存在", dir))
+            } else {
+                (tr!("有声书目录 \"{}\" 不存在。跳过。", path.display()), tr!("有声书目录 \"{}\" 不存在", dir))
+            };
+            warn!("{}", message);
+            report.failure(failure);
+            continue;
+        }
+        let path = normalize_path(&path);
+        if !is_subpath(&path, &base) {
+            let (message, failure) = if kind == TrackKind::Podcast {
+                (tr!("播客目录 \"{}\" 不在 iPod 上。跳过。", path.display()), tr!("播客目录 \"{}\" 不在 iPod 上", dir))
+            } else {
+                (tr!("有声书目录 \"{}\" 不在 iPod 上。跳过。", path.display()), tr!("有声书目录 \"{}\" 不在 iPod 上", dir))
+            };
+            warn!("{}", message);
+            report.failure(failure);
+            continue;
+        }
+        let source = DirectoryPlaylist::new(path.clone());
+        kind_lists.insert(source.name(), list_type(kind));
+        playlist_sources.push(Box::new(source));
+        if !scan_roots.iter().any(|(root, _, _)| is_subpath(&path, root)) {
+            scan_roots.push((path.clone(), kind, path.clone()));
+        }
+        kind_dirs.push((path, kind));
+    }
+    // .m4b/.aa files outside the audiobook directories, collected into one audiobook list
+    let mut loose_audiobooks: HashSet<PathBuf> = HashSet::new();
+
+    let mut track_kinds: HashMap<PathBuf, TrackKind> = HashMap::new();
+    let mut skipped_hidden: Vec<PathBuf> = Vec::new();
+    // --only narrows each root down to the listed subdirectories
+    let mut only: Vec<PathBuf> = cli.only.iter()
+        .filter_map(|dir| {
+            let path = base.join(dir);
+            if path.is_dir() { return Some(normalize_path(&path)); }
+            warn!("{}", tr!("扫描目录 \"{}\" 不存在。跳过。", path.display()));
+            report.failure(tr!("扫描目录 \"{}\" 不存在", dir));
+            None
+        })
+        .collect();
+    // Outer directories first, so nested ones are covered instead of walked twice
+    only.sort_by_key(|d| d.components().count());
+    let mut walks: Vec<(PathBuf, TrackKind, PathBuf)> = Vec::new();
+    for (root, kind, dir_playlist_root) in &scan_roots {
+        if cli.only.is_empty() {
+            walks.push((root.clone(), *kind, dir_playlist_root.clone()));
+            continue;
+        }
+        for dir in &only {
+            let start = if is_subpath(dir, root) { dir } else if is_subpath(root, dir) { root } else { continue };
+            if !walks.iter().any(|(w, _, _)| is_subpath(start, w)) {
+                walks.push((start.clone(), *kind, dir_playlist_root.clone()));
+            }
         }
-        let rel = path.strip_prefix(&base).unwrap_or(path);
-        let has_hidden = rel.components().any(|c| {
-            let s = c.as_os_str().to_string_lossy();
-            s.starts_with('.') && s != "."
+    }
+
+    let mut outside_links: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for (root, kind, dir_playlist_root) in &walks {
+        let walker = WalkDir::new(root)
+            .follow_links(cli.follow_symlinks)
+            .sort_by(|a, b| compare(&a.file_name().to_string_lossy(), &b.file_name().to_string_lossy()))
+            .into_iter().filter_entry(|e| {
+            if e.depth() == 0 { return true; }
+            if excluded_roots.iter().any(|r| e.path() == r) { return false; }
+            // The device reads its own filesystem, so a link target elsewhere can never be played
+            if cli.follow_symlinks && e.path_is_symlink() {
+                let target = normalize_path(e.path());
+                if !is_subpath(&target, &base) {
+                    outside_links.push((e.path().to_path_buf(), target));
+                    return false;
+                }
+            }
+            if cli.exclude.iter().any(|g| g.matches(e.path(), &base)) {
+                debug!("{}", tr!("[-] 已排除 {}", e.path().display()));
+                return false;
+            }
+            let name = e.file_name().to_string_lossy();
+            if hidden_entry_allowed(&name, e.file_type().is_dir(), cli.hidden, &cli.allow_hidden) {
+                return true;
+            }
+            if !is_system_hidden(&name) {
+                skipped_hidden.push(e.path().to_path_buf());
+            }
+            false
         });
-        if has_hidden { continue; }
-        if is_subpath(path, &speakable_root) { continue; }
+        for entry in walker {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    if let (Some(path), Some(ancestor)) = (e.path(), e.loop_ancestor()) {
+                        warn!("{}", tr!("符号链接 {} 指向其上级目录 {}，形成循环。跳过。", path.display(), ancestor.display()));
+                        report.failure(tr!("符号链接 {} 形成循环", display_path(path, &base)));
+                    }
+                    continue;
+                }
+            };
+            let path = entry.path();
+            if excluded_roots.iter().any(|r| is_subpath(path, r)) { continue; }
 
-        if entry.file_type().is_file() {
-            let ext = ext_lower(path);
-            let full = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
-            if ext == ".flac" {
-                flac_files.push(full);
-            } else if MUSIC_EXT.contains(&ext.as_str()) {
-                other_audio_files.push(full);
-            } else if LIST_EXT.contains(&ext.as_str()) {
-                playlist_sources.push(PlaylistSource::File(full));
+            if entry.file_type().is_file() {
+                let ext = ext_lower(path);
+                let full = normalize_path(path);
+                if is_partial(&full) {
+                    partial_files.push(full);
+                    continue;
+                }
+                let convert = ext == ".flac" || converters.contains_key(&ext);
+                if convert || MUSIC_EXT.contains(&ext.as_str()) {
+                    let kind = match kind_dirs.iter().find(|(d, _)| is_subpath(&full, d)) {
+                        Some((_, marked)) => *marked,
+                        None if *kind == TrackKind::Music && AUDIOBOOK_EXT.contains(&ext.as_str()) => {
+                            loose_audiobooks.insert(full.clone());
+                            TrackKind::Audiobook
+                        }
+                        None => *kind,
+                    };
+                    track_kinds.entry(full.clone()).or_insert(kind);
+                }
+                if convert {
+                    convert_files.push(full);
+                } else if MUSIC_EXT.contains(&ext.as_str()) {
+                    other_audio_files.push(full);
+                } else if ext == CUTS_EXT {
+                    cuts_files.push(full);
+                } else if let Some(source) = open_list_file(&full) {
+                    playlist_sources.push(source);
+                }
             }
+
+            if let Some(max_depth) = cli.auto_dir_playlists {
+                if *kind == TrackKind::Music
+                    && entry.file_type().is_dir()
+                    && !kind_dirs.iter().any(|(d, _)| is_subpath(path, d))
+                    && is_subpath(path, dir_playlist_root)
+                    && path != dir_playlist_root.as_path()
+                {
+                    let depth = path.strip_prefix(dir_playlist_root)
+                        .map(|r| r.components().count() as i32)
+                        .unwrap_or(0);
+                    if max_depth < 0 || depth <= max_depth {
+                        let name = cli.dir_playlist_name.render(path, dir_playlist_root);
+                        playlist_sources.push(Box::new(DirectoryPlaylist::named(normalize_path(path), name)));
+                    }
+                }
+            }
+        }
+    }
+
+    // The favorites list lives at the device root, which --root may not cover
+    if let Some(fav) = favorites_playlist {
+        let listed = playlist_sources.iter().any(|s| s.source_file() == Some(fav.as_path()));
+        if !listed {
+            playlist_sources.extend(open_list_file(&fav));
+        }
+    }
+    // The queue goes first so that "Play Next" tops the playlist menu
+    if let Some(queue) = QueuePlaylist::open(&base, cli.list_encoding, queue_played.clone()) {
+        playlist_sources.insert(0, Box::new(queue));
+    }
+
+    for (link, target) in &outside_links {
+        warn!("{}", tr!("符号链接 {} 指向 iPod 之外的 {}，设备无法播放。跳过。", link.display(), target.display()));
+        report.failure(tr!("符号链接 {} 指向 iPod 之外的 {}", display_path(link, &base), target.display()));
+    }
+
+    for p in &skipped_hidden {
+        debug!("{}", tr!("[-] 跳过隐藏项 {}", p.display()));
+        report.skipped_hidden.push(display_path(p, &base));
+    }
+
+    // Identical copies left behind by a reorganized library are neither converted nor added
+    let mut duplicate_of: HashMap<PathBuf, PathBuf> = HashMap::new();
+    if cli.dedup {
+        let all: Vec<PathBuf> = convert_files.iter().chain(&other_audio_files).cloned().collect();
+        let duplicates = find_duplicates(&all);
+        let dropped: HashSet<&PathBuf> = duplicates.iter().map(|d| &d.path).collect();
+        convert_files.retain(|f| !dropped.contains(f));
+        other_audio_files.retain(|f| !dropped.contains(f));
+        for d in &duplicates {
+            debug!("{}", tr!("[=] {} 与 {} 内容相同，跳过", d.path.display(), d.original.display()));
+            report.duplicates.push((display_path(&d.path, &base), display_path(&d.original, &base)));
+            report.duplicate_bytes += d.size;
+            duplicate_of.insert(d.path.clone(), d.original.clone());
+        }
+        if !duplicates.is_empty() {
+            outln!("{}", tr!("跳过 {} 个内容重复的文件，删除它们可以节省 {}", duplicates.len(), format_bytes(report.duplicate_bytes)));
         }
+    }
+
+    // Sources the duration filter would drop are neither converted nor deleted
+    if cli.min_duration.is_some() || cli.max_duration.is_some() {
+        let (durations, _) = read_all_meta(&convert_files, cli.file_timeout);
+        exit_if_cancelled(&report);
+        convert_files.retain(|f| within_duration_limits(f, &durations, cli, &mut report, &base));
+    }
 
-        if let Some(max_depth) = cli.auto_dir_playlists {
-            if entry.file_type().is_dir()
-                && is_subpath(path, &music_root)
-                && path != music_root.as_path()
-            {
-                let depth = path.strip_prefix(&music_root)
-                    .map(|r| r.components().count() as i32)
-                    .unwrap_or(0);
-                if max_depth < 0 || depth <= max_depth {
-                    playlist_sources.push(PlaylistSource::Directory(
-                        fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
-                    ));
+    // Nothing has been deleted so far; ask before the old voiceovers and the conversion sources go.
+    // A resumed run keeps the voiceovers finished last time
+    if !confirm_deletions(&base, &convert_files, tts_resume, queue_played.len(), dry_run) {
+        outln!("{}", tr!("已取消。"));
+        std::process::exit(exit_code::FAILURE);
+    }
+    if !dry_run {
+        if !queue_played.is_empty() {
+            match remove_entries(&base, cli.list_encoding, &queue_played) {
+                Ok(()) => outln!("{}", tr!("已从 {} 清除 {} 首已播放的曲目", QUEUE_FILE, queue_played.len())),
+                Err(e) => {
+                    warn!("{}", tr!("无法清除 {} 中已播放的曲目: {}", QUEUE_FILE, e));
+                    report.failure(tr!("无法清除 {} 中已播放的曲目: {}", QUEUE_FILE, e));
                 }
             }
         }
+        // Wrongly cased directories would otherwise get a correctly cased twin next to them
+        speakable::repair_layout(&base, speakable::check_layout(&base), true);
+        if let Err(e) = checkpoint.start() {
+            warn!("{}", e);
+        }
+        cancel::enable_graceful();
+        for partial in &partial_files {
+            debug!("{}", tr!("[-] 删除中断的转换留下的 {}", partial.display()));
+            let _ = fs::remove_file(partial);
+        }
+        if !tts_resume {
+            for dirname in &["iPod_Control/Speakable/Playlists", "iPod_Control/Speakable/Tracks"] {
+                let p = base.join(dirname);
+                let _ = fs::remove_dir_all(&p);
+                let _ = fs::create_dir_all(&p);
+            }
+        }
     }
 
-    // FLAC conversion
+    // FLAC and external conversion
     let mut tracks: Vec<PathBuf> = Vec::new();
+    // Keyed by device identity so "Song.MP3" and "song.mp3" on FAT count once
+    let identity = PathIdentity::detect(&base);
+    if identity.fold_case() {
+        debug!("{}", tr!("设备文件系统不区分大小写，按不区分大小写的路径去重"));
+    }
     let mut track_set: HashSet<PathBuf> = HashSet::new();
+    // A dry run keeps each source as the track and lists the MP3 it would become in the database
+    let mut projected_mp3: HashMap<PathBuf, PathBuf> = HashMap::new();
 
-    if !flac_files.is_empty() {
-        println!("发现 {} 个 FLAC 文件，开始并发转换...", flac_files.len());
-        let total = flac_files.len();
-        let completed = AtomicUsize::new(0);
-        let converted: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+    if !convert_files.is_empty() && dry_run {
+        let jobs = resolve_convert_jobs(&convert_files, cli.shadow_policy, &checkpoint, &base, &mut report);
+        outln!("{}", tr!("[演练] 将转换 {} 个文件为 MP3，转换后删除源文件:", convert_files.len()));
+        for (flac, out, overwrite) in jobs {
+            let reuse = out.exists() && !overwrite;
+            let action = if reuse { "沿用现有的 MP3" } else { "转换" };
+            outln!("  {} -> {}（{}）", display_path(&flac, &base), display_path(&out, &base), i18n::text(action));
+            report.conversions.push(ConversionEntry {
+                source: display_path(&flac, &base),
+                output: Some(display_path(&out, &base)),
+            });
+            if !track_set.insert(identity.key(&out)) { continue; }
+            if reuse {
+                if let Some(kind) = track_kinds.get(&flac).copied() {
+                    track_kinds.insert(out.clone(), kind);
+                }
+                tracks.push(out);
+            } else {
+                projected_mp3.insert(flac.clone(), out);
+                tracks.push(flac);
+            }
+        }
+    } else if !convert_files.is_empty() {
+        let jobs = resolve_convert_jobs(&convert_files, cli.shadow_policy, &checkpoint, &base, &mut report);
 
-        flac_files.par_iter().for_each(|flac_path| {
-            if let Some(mp3) = convert_flac_to_mp3(flac_path) {
-                converted.lock().unwrap().push(mp3);
+        outln!("{}", tr!("发现 {} 个需要转换为 MP3 的文件，开始并发转换...", convert_files.len()));
+        let converted: Mutex<Vec<(PathBuf, Result<PathBuf, String>)>> = Mutex::new(Vec::new());
+
+        let bar = progress::bar("转换", jobs.len());
+        jobs.par_iter().for_each(|(src, mp3_path, overwrite)| {
+            if cancel::requested() { return; }
+            events::conversion_started(&display_path(src, &base));
+            // An overwrite would find the old MP3 after an interruption, so only fresh outputs are recorded
+            if !*overwrite {
+                checkpoint.record_conversion(src, mp3_path);
             }
-            let done = completed.fetch_add(1, Ordering::Relaxed) + 1;
-            let pct = done as f64 / total as f64 * 100.0;
-            eprint!("\r正在转换: [{}/{}] {:.1}%", done, total, pct);
+            let result = match converters.get(&ext_lower(src)) {
+                Some(converter) => converter.convert(src, mp3_path, *overwrite, encode_opts),
+                None => convert_flac_to_mp3(src, mp3_path, *overwrite, encode_opts),
+            };
+            converted.lock().unwrap().push((src.clone(), result));
+            bar.inc(1);
         });
-        eprintln!();
-        println!("FLAC 转换完成！");
+        bar.finish_and_clear();
+        let mut converted = converted.into_inner().unwrap();
+        if cancel::requested() {
+            warn!("{}", tr!("已中断，{} 个文件没有转换，源文件保留在设备上。", jobs.len() - converted.len()));
+        } else {
+            outln!("{}", tr!("转换完成！"));
+        }
 
-        for mp3 in converted.into_inner().unwrap() {
-            if track_set.insert(mp3.clone()) {
-                tracks.push(mp3);
+        converted.sort();
+        for (flac, result) in converted {
+            report.conversions.push(ConversionEntry {
+                source: display_path(&flac, &base),
+                output: result.as_ref().ok().map(|mp3| display_path(mp3, &base)),
+            });
+            match result {
+                Ok(mp3) => {
+                    events::conversion_finished(&display_path(&flac, &base), &display_path(&mp3, &base));
+                    if let Some(kind) = track_kinds.get(&flac).copied() {
+                        track_kinds.insert(mp3.clone(), kind);
+                    }
+                    if track_set.insert(identity.key(&mp3)) {
+                        tracks.push(mp3);
+                    }
+                }
+                Err(e) => {
+                    error!("{}", tr!("转换失败 {}: {}", flac.display(), e));
+                    events::conversion_failed(&display_path(&flac, &base), &e);
+                    report.failure_of(FailureKind::Conversion, tr!("转换失败: {}", display_path(&flac, &base)));
+                    report.skip_track(display_path(&flac, &base), SkipReason::ConversionFailed, e);
+                }
             }
         }
     }
 
-    // Add other audio files
+    exit_if_cancelled(&report);
+
+    // Add other audio files (MP3s shadowed by a conversion source were resolved above)
     for full in other_audio_files {
-        if ext_lower(&full) == ".mp3" {
-            let flac_source = full.with_extension("flac");
-            if flac_source.exists() { continue; }
-        }
-        if track_set.insert(full.clone()) {
+        if track_set.insert(identity.key(&full)) {
             tracks.push(full);
         }
     }
 
-    tracks.sort_by(|a, b| {
-        a.to_string_lossy().to_lowercase().cmp(&b.to_string_lossy().to_lowercase())
-    });
+    tracks.sort_by(|a, b| compare_paths(a, b));
+
+    // Track metadata + duration filters
+    let (mut metadata, quarantined) = read_all_meta(&tracks, cli.file_timeout);
+    exit_if_cancelled(&report);
+    let quarantined = quarantined.into_iter().map(|(t, e)| (t, e.describe("读取标签"))).collect();
+    skip_tracks(&mut tracks, SkipReason::Undecodable, quarantined, &mut report, &base);
+    let protected = tracks.iter()
+        .filter(|t| metadata.get(*t).is_some_and(|m| m.protected))
+        .map(|t| (t.clone(), tr!("iPod 只能播放未加密的音频")))
+        .collect();
+    skip_tracks(&mut tracks, SkipReason::Protected, protected, &mut report, &base);
+    // The database's filename field would silently truncate these, leaving entries that never play
+    let too_long = tracks.iter()
+        .map(|t| (t, display_path(projected_mp3.get(t).unwrap_or(t), &base).len()))
+        .filter(|&(_, len)| len > MAX_FILENAME_BYTES)
+        .map(|(t, len)| (t.clone(), tr!("{} 字节，上限为 {} 字节", len, MAX_FILENAME_BYTES)))
+        .collect();
+    skip_tracks(&mut tracks, SkipReason::PathTooLong, too_long, &mut report, &base);
+
+    if cli.tag_fixes != TagFixPolicy::Off {
+        let fixes = fix_tags(&tracks, &mut metadata);
+        let mut policy = cli.tag_fixes;
+        if policy == TagFixPolicy::Device && !looks_like_ipod(&base) {
+            warn!("{}", tr!("\"{}\" 不是 iPod，标签修正只在内存中使用，不会写入文件。", base.display()));
+            policy = TagFixPolicy::Memory;
+        }
+        if policy == TagFixPolicy::Device && dry_run {
+            outln!("{}", tr!("[演练] 标签修正只在内存中使用，不会写回文件。"));
+            policy = TagFixPolicy::Memory;
+        }
+        // Only files on the device are rewritten; fixes for any other file stay in memory
+        let (written, in_memory): (Vec<&TagFix>, Vec<&TagFix>) = match policy {
+            TagFixPolicy::Device => fixes.iter().partition(|f| is_subpath(&f.track, &base)),
+            _ => (Vec::new(), fixes.iter().collect()),
+        };
+        let tracks_fixed = |fixes: &[&TagFix]| fixes.iter().map(|f| &f.track).collect::<HashSet<_>>().len();
+        if policy == TagFixPolicy::Device {
+            for (t, e) in write_fixes(&written) {
+                warn!("{}", tr!("无法写入标签 {}: {}", t.display(), e));
+                report.failure(tr!("无法写入标签 {}: {}", display_path(&t, &base), e));
+            }
+            outln!("{}", tr!("已修正并写回 {} 首曲目的 {} 处标签。", tracks_fixed(&written), written.len()));
+        }
+        if !in_memory.is_empty() {
+            outln!("{}", tr!("已在内存中修正 {} 首曲目的 {} 处标签（未修改文件）。", tracks_fixed(&in_memory), in_memory.len()));
+        }
+    }
+
+    // Bad track/disc numbers were left out of the metadata; list them so the tags can be fixed
+    for t in &tracks {
+        let Some(meta) = metadata.get(t) else { continue };
+        for n in &meta.suspicious_numbers {
+            debug!("{}", tr!("[!] {} 的{} \"{}\" 不合理，{}", t.display(), i18n::text(n.field), n.raw, n.action()));
+            report.suspicious_numbers.push((display_path(t, &base), n.clone()));
+        }
+    }
+    if !report.suspicious_numbers.is_empty() {
+        outln!("{}", tr!("{} 处曲目编号或碟号标签不合理，已忽略或按开头的数字写入，详见同步报告", report.suspicious_numbers.len()));
+    }
+
+    // Sidecar files beat the embedded (and fixed) tags, and an edited table beats both
+    let (sidecars, sidecar_failures) = apply_meta_sidecars(&tracks, &mut metadata);
+    if sidecars > 0 {
+        debug!("{}", tr!("已按 {} 个元数据文件覆盖标签", sidecars));
+    }
+    for e in sidecar_failures {
+        warn!("{}", e);
+        report.failure(e);
+    }
+    let csv_gains = if csv_overrides.is_empty() {
+        HashMap::new()
+    } else {
+        apply_csv_overrides(&csv_overrides, &tracks, &projected_mp3, &mut metadata, identity, &base)
+    };
+
+    // Generated tracks stay out of the master list and get one playlist per source
+    let mut generated_playlists: Vec<(String, Vec<PathBuf>)> = Vec::new();
+
+    // A/B repeat: segments generated from "<stem>.cuts"
+    if let Some(repeat) = cli.ab_repeat {
+        let mut kept: HashSet<PathBuf> = HashSet::new();
+        for cuts in &cuts_files {
+            let Some(src) = tracks.iter().find(|t| t.with_extension("") == cuts.with_extension("")) else {
+                warn!("{}", tr!("找不到切分列表 \"{}\" 对应的音频文件。跳过。", cuts.display()));
+                report.failure(tr!("找不到切分列表 \"{}\" 对应的音频文件", display_path(cuts, &base)));
+                continue;
+            };
+            let stem = src.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            let out_dir = abrepeat_root.join(&stem);
+            kept.insert(out_dir.clone());
+            if dry_run {
+                outln!("{}", tr!("[演练] 将为 {} 生成复读分段与播放列表 \"{} A-B\"（不包含在下面的预览中）", display_path(src, &base), stem));
+                continue;
+            }
+            match generate_ab_repeat(src, cuts, &out_dir, repeat, cli.ab_pause.as_millis() as u64, cli.bitrate) {
+                Ok(segments) => {
+                    metadata.extend(read_all_meta(&segments, cli.file_timeout).0);
+                    generated_playlists.push((format!("{} A-B", stem), segments));
+                }
+                Err(e) => {
+                    error!("{}", tr!("无法生成 \"{}\" 的复读分段: {}", stem, e));
+                    report.failure_of(FailureKind::Conversion, tr!("无法生成 \"{}\" 的复读分段: {}", display_path(src, &base), e));
+                }
+            }
+        }
+        // Drop segments whose cut list was removed
+        remove_stale_dirs(&abrepeat_root, &kept, dry_run, &base);
+    }
+    if cli.min_duration.is_some() || cli.max_duration.is_some() {
+        tracks.retain(|t| within_duration_limits(t, &metadata, cli, &mut report, &base));
+    }
+    // Tracks --rotate-after took off the device, in the order they will be rotated back in
+    let mut rotated_out: Vec<String> = Vec::new();
+    if cli.max_size.is_some() || cli.max_total_duration.is_some() {
+        let max_ms = cli.max_total_duration.map(|d| d.as_millis() as u64);
+        let ipod_path = |t: &PathBuf| display_path(projected_mp3.get(t).unwrap_or(t), &base);
+        let rotation = cli.rotate_after.map(|after| {
+            rotation_groups(&tracks, &previous_sync_counts(&base), &previous_rotated_out(&base), after, ipod_path)
+        });
+        let path_order: HashMap<PathBuf, usize> = tracks.iter().cloned().enumerate().map(|(i, t)| (t, i)).collect();
+        if let Some(ref groups) = rotation {
+            tracks.sort_by_key(|t| groups[t]);
+        }
+        let mut left_out = select_within_caps(&mut tracks, &metadata, cli.max_size, max_ms);
+        if let Some(ref groups) = rotation {
+            tracks.sort_by_key(|t| path_order[t]);
+            for (t, reason) in left_out.iter_mut() {
+                if groups[t] == RotationGroup::RotatedOut { *reason = "已轮换（--rotate-after）"; }
+            }
+            // Tracks still waiting keep their place in line, the ones rotated out now queue behind them
+            let mut waiting: Vec<(RotationGroup, String)> = left_out.iter()
+                .filter(|(t, _)| matches!(groups[t], RotationGroup::RotatingIn(n) if n > 0) || groups[t] == RotationGroup::RotatedOut)
+                .map(|(t, _)| (groups[t], ipod_path(t)))
+                .collect();
+            waiting.sort_by_key(|(group, _)| *group);
+            rotated_out = waiting.into_iter().map(|(_, t)| t).collect();
+            let rotated = left_out.iter().filter(|(t, _)| groups[t] == RotationGroup::RotatedOut).count();
+            if rotated > 0 {
+                outln!("{}", tr!("已轮换 {} 首曲目，换入上次没有选入的曲目", rotated));
+            }
+        }
+        if !left_out.is_empty() {
+            warn!("{}", tr!("已达到曲目总量上限，{} 首曲目没有写入数据库（见同步报告）", left_out.len()));
+        }
+        for (t, reason) in left_out {
+            debug!("{}", tr!("[-] 排除曲目 {} ({})", t.display(), i18n::text(reason)));
+            report.excluded.push((display_path(&t, &base), i18n::text(reason).to_string()));
+        }
+    } else if cli.rotate_after.is_some() {
+        warn!("{}", tr!("--rotate-after 需要与 --max-size 或 --max-total-duration 一起使用，已忽略"));
+    }
+
+    // Text audiobooks: .txt/.epub files synthesized via TTS, resumable like audiobooks
+    if let Some(ref dir) = cli.text_audiobooks {
+        let books_dir = base.join(dir);
+        let books: Vec<PathBuf> = WalkDir::new(&books_dir).sort_by_file_name().into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file() && TEXT_EXT.contains(&ext_lower(e.path()).as_str()))
+            .map(|e| e.into_path())
+            .collect();
+        if !books_dir.is_dir() {
+            warn!("{}", tr!("有声书文本目录 \"{}\" 不存在。跳过。", books_dir.display()));
+            report.failure(tr!("有声书文本目录 \"{}\" 不存在", dir));
+        }
+        let mut kept: HashSet<PathBuf> = HashSet::new();
+        for book in &books {
+            let stem = book.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            let out_dir = textbook_root.join(&stem);
+            kept.insert(out_dir.clone());
+            if dry_run {
+                outln!("{}", tr!("[演练] 将合成有声书 \"{}\"（不包含在下面的预览中）", stem));
+                continue;
+            }
+            match generate_audiobook(book, &out_dir, cli.audiobook_part.as_millis() as u64) {
+                Ok(parts) => {
+                    for p in &parts {
+                        track_kinds.insert(p.clone(), TrackKind::Audiobook);
+                    }
+                    metadata.extend(read_all_meta(&parts, cli.file_timeout).0);
+                    kind_lists.insert(stem.clone(), ListType::Audiobook);
+                    generated_playlists.push((stem, parts));
+                }
+                Err(e) => {
+                    error!("{}", tr!("无法合成有声书 \"{}\": {}", stem, e));
+                    report.failure(tr!("无法合成有声书 \"{}\": {}", display_path(book, &base), e));
+                }
+            }
+        }
+        remove_stale_dirs(&textbook_root, &kept, dry_run, &base);
+    }
+
+    // Files moved on the device since the last sync keep their record, cached loudness and position
+    let db_path = base.join("iPod_Control").join("iTunes").join("iTunesSD");
+    let mut previous = fs::read(&db_path).ok().and_then(|d| parse_itunes_sd(&d).ok());
+    let mut moved: Vec<(String, String)> = Vec::new();
+    if let Some(ref mut prev) = previous {
+        let ipod_path = |t: &Path| display_path(projected_mp3.get(t).map_or(t, |p| p), &base);
+        let moves = detect_moves(&base, prev, &tracks, ipod_path);
+        let mut renamed: Vec<(PathBuf, PathBuf)> = Vec::new();
+        for m in moves {
+            let to = ipod_path(&m.to);
+            debug!("{}", tr!("[>] 检测到移动的曲目 {} -> {}", m.from, to));
+            prev.rename_track(&m.from, &to);
+            renamed.push((base.join(m.from.trim_start_matches('/')), m.to));
+            moved.push((m.from, to));
+        }
+        if !renamed.is_empty() {
+            carry_over_loudness(&base, &renamed);
+            outln!("{}", tr!("{} 首曲目在上次同步后被移动或重命名，沿用其原有记录", moved.len()));
+        }
+    }
 
     // Auto track gain
     let mut track_gain_overrides: HashMap<PathBuf, u32> = HashMap::new();
+    let mut voiceover_levels: HashMap<PathBuf, f64> = HashMap::new();
     if cli.auto_track_gain && !tracks.is_empty() {
-        println!("正在分析曲目响度并计算自动增益...");
-        let total = tracks.len();
-        let completed = AtomicUsize::new(0);
-        let loudness_map: Mutex<HashMap<PathBuf, f64>> = Mutex::new(HashMap::new());
-
-        tracks.par_iter().for_each(|track| {
-            if let Some(db) = estimate_track_loudness_db(track, 45.0) {
-                loudness_map.lock().unwrap().insert(track.clone(), db);
-            }
-            let done = completed.fetch_add(1, Ordering::Relaxed) + 1;
-            let pct = done as f64 / total as f64 * 100.0;
-            eprint!("\r正在分析: [{}/{}] {:.1}%", done, total, pct);
-        });
-        eprintln!();
+        outln!("{}", tr!("正在分析曲目响度并计算自动增益..."));
+        // Rules are consulted up front so skipped tracks neither get an override nor move the reference
+        let (mut to_analyze, skipped) = GainRules::new(&base, &cli.gain_rules).partition(&tracks, &metadata);
+        for t in &skipped {
+            debug!("{}", tr!("[-] 按规则跳过自动增益 {}", t.display()));
+        }
+        // Skipped tracks are still measured so their voiceovers can be levelled to match
+        if track_voiceover {
+            to_analyze.extend(skipped.iter().cloned());
+        }
+        let skipped: HashSet<PathBuf> = skipped.into_iter().collect();
+        let analysis = analyze_loudness(&base, &to_analyze, cli.file_timeout, &cli.loudness);
+        exit_if_cancelled(&report);
+        skip_tracks(&mut tracks, SkipReason::Undecodable, analysis.failures, &mut report, &base);
 
-        let lmap = loudness_map.into_inner().unwrap();
-        if lmap.is_empty() {
-            println!("警告: 未能分析任何曲目的响度，自动音量均衡已跳过。");
+        let lmap = analysis.loudness;
+        let gain_lmap: HashMap<PathBuf, f64> = lmap.iter()
+            .filter(|(t, _)| !skipped.contains(*t))
+            .map(|(t, &db)| (t.clone(), db))
+            .collect();
+        if gain_lmap.is_empty() {
+            warn!("{}", tr!("未能分析任何曲目的响度，自动音量均衡已跳过。"));
         } else {
-            let reference = lmap.values().cloned().fold(f64::NEG_INFINITY, f64::max);
-            for (track, db) in &lmap {
-                let gain = ((reference - db).round() as u32).clamp(0, 99);
-                track_gain_overrides.insert(track.clone(), gain);
+            let (gains, reference) = compute_gains(&gain_lmap, None, cli.loudness.range);
+            track_gain_overrides = gains;
+            for track in &tracks {
+                report.gains.push(GainEntry {
+                    track: display_path(track, &base),
+                    loudness_db: lmap.get(track).copied(),
+                    gain: track_gain_overrides.get(track).copied().unwrap_or(trackgain),
+                    skipped: skipped.contains(track),
+                });
             }
-            println!("自动音量均衡完成: 已为 {}/{} 首曲目写入增益（参考响度 {:.2} dBFS）。",
-                track_gain_overrides.len(), tracks.len(), reference);
+            outln!("{}", tr!("自动音量均衡完成: 已为 {}/{} 首曲目写入增益（参考响度 {} dBFS）。",
+                track_gain_overrides.len(), tracks.len() - skipped.len(), format!("{:.2}", reference)));
+        }
+        if track_voiceover {
+            // The volume_gain field only applies to the track, so its voiceover is levelled
+            // to the loudness the track will actually play at
+            voiceover_levels = lmap.iter()
+                .map(|(t, &db)| {
+                    let gain = track_gain_overrides.get(t).copied().unwrap_or(trackgain);
+                    (t.clone(), db + gain as f64)
+                })
+                .collect();
+        }
+    }
+
+    // Gains from sidecar files and then the table win over the automatic ones
+    for t in &tracks {
+        if let Some(gain) = metadata.get(t).and_then(|m| m.gain) {
+            track_gain_overrides.insert(t.clone(), gain);
+        }
+    }
+    for (t, gain) in csv_gains {
+        // An unedited export repeats the gain this sync computes anyway; pinning it would switch off auto gain
+        if track_gain_overrides.get(&t).copied().unwrap_or(trackgain) != gain {
+            track_gain_overrides.insert(t, gain);
         }
     }
 
     // ID3 auto playlists
     if let Some(ref tmpl) = cli.auto_id3_playlists {
-        let grouped = group_tracks_by_id3_template(&tracks, tmpl);
+        let grouped = group_tracks_by_id3_template(&tracks, &metadata, tmpl);
         for (name, files) in grouped {
-            playlist_sources.push(PlaylistSource::Grouped(name, files));
+            playlist_sources.push(Box::new(GroupedPlaylist::new(name, files)));
+        }
+    }
+    if cli.auto_album_playlists {
+        for (name, files) in group_tracks_by_album(&tracks, &metadata) {
+            playlist_sources.push(Box::new(GroupedPlaylist::new(name, files)));
+        }
+    }
+    let audiobook_files: Vec<PathBuf> = tracks.iter().filter(|t| loose_audiobooks.contains(*t)).cloned().collect();
+    if !audiobook_files.is_empty() {
+        kind_lists.insert(AUDIOBOOK_PLAYLIST.to_string(), ListType::Audiobook);
+        playlist_sources.push(Box::new(GroupedPlaylist::new(AUDIOBOOK_PLAYLIST.to_string(), audiobook_files)));
+    }
+
+    // Master order
+    let shuffle_seed = ShuffleSeed::load(&base, cli.reshuffle);
+    match cli.order {
+        TrackOrder::Path => {}
+        TrackOrder::ArtistSpread => tracks = spread_by_artist(&tracks, &metadata),
+        TrackOrder::Shuffle => tracks = shuffle_seed.shuffle(&tracks),
+    }
+    // The pins go on top, so order.txt still applies to tracks already on the device
+    if cli.keep_order {
+        if let Some(ref prev) = previous {
+            let names: Vec<String> = prev.tracks.iter().map(|t| t.filename.clone()).collect();
+            tracks = keep_previous_order(&tracks, &names, |t| display_path(projected_mp3.get(t).map_or(t, |p| p), &base));
+            debug!("{}", tr!("已保持上次数据库中的曲目顺序"));
+        }
+    }
+
+    match load_order_pins(&base) {
+        Ok(pins) if !pins.is_empty() => {
+            tracks = apply_order_pins(&tracks, &metadata, &pins);
+            debug!("{}", tr!("已按 {} 调整主列表顺序", ORDER_FILE));
+        }
+        Ok(_) => {}
+        Err(e) => {
+            warn!("{}", e);
+            report.failure(e);
         }
     }
 
     // Build track position map
     let track_positions: HashMap<PathBuf, usize> = tracks.iter()
         .enumerate()
-        .map(|(i, t)| (t.clone(), i))
+        .map(|(i, t)| (identity.key(t), i))
         .collect();
 
-    // Build track infos
+    // Build track infos; track voiceovers are generated here, playlist ones with the database
+    tts::begin_session(&base, tts_resume);
     let mut albums: Vec<String> = Vec::new();
     let mut album_index: HashMap<String, u32> = HashMap::new();
     let mut artists: Vec<String> = Vec::new();
     let mut artist_index: HashMap<String, u32> = HashMap::new();
 
+    let config = load_config(&base).unwrap_or_else(|e| {
+        warn!("{}", e);
+        None
+    });
+    let rules = TrackRules::new(&base, identity, cli.pregap, cli.postgap, config.as_ref()).unwrap_or_else(|e| {
+        warn!("{}", e);
+        report.failure(e.clone());
+        TrackRules::global(identity, cli.pregap, cli.postgap)
+    });
+
     let mut track_infos = Vec::new();
     let mut ctx = BuildContext {
         base: &base,
         trackgain,
         track_gain_overrides: &track_gain_overrides,
+        voiceover_levels: &voiceover_levels,
+        metadata: &metadata,
+        track_kinds: &track_kinds,
+        rules: &rules,
         albums: &mut albums,
         album_index: &mut album_index,
         artists: &mut artists,
         artist_index: &mut artist_index,
         track_voiceover,
-        playlist_voiceover,
     };
+    // Track voiceovers are generated one by one here, so that's the slow part
+    let bar = if track_voiceover { progress::bar("曲目旁白", tracks.len()) } else { ProgressBar::hidden() };
     for t in &tracks {
-        vprintln!("[*] 添加曲目 {}", t.display());
-        let info = build_track_info(t, &mut ctx);
+        if cancel::requested() { break; }
+        debug!("{}", tr!("[*] 添加曲目 {}", t.display()));
+        let mut info = build_track_info(t, &mut ctx);
+        if let Some(mp3) = projected_mp3.get(t) {
+            info.filename = display_path(mp3, &base);
+            info.filetype = 1;
+        }
+        events::track_added(track_infos.len(), &info.filename);
         track_infos.push(info);
+        bar.inc(1);
     }
+    bar.finish_and_clear();
+    exit_if_cancelled(&report);
 
     // Build playlists
     let master_indices: Vec<u32> = (0..tracks.len() as u32).collect();
@@ -208,36 +1481,372 @@ pub fn run_shuffler(cli: &Cli) {
         ("__master__".to_string(), master_indices),
     ];
 
+    let resolve_ctx = ResolveContext {
+        base: &base, rename, encoding: cli.list_encoding, tracks: &tracks, track_positions: &track_positions, identity,
+        duplicate_of: duplicate_of.iter().map(|(dup, original)| (identity.key(dup), original.clone())).collect(),
+        missing: RefCell::new(Vec::new()),
+    };
+    let mut sidecar_options: HashMap<String, PlaylistOptions> = HashMap::new();
     for src in &playlist_sources {
-        let (name, indices) = resolve_playlist_tracks(src, &base, rename, &track_positions);
+        let (name, indices) = resolve_playlist_tracks(src.as_ref(), &resolve_ctx);
+        for missing in resolve_ctx.missing.take() {
+            report.failure_of(FailureKind::Playlist, tr!("播放列表 \"{}\" 中的曲目 \"{}\" 不存在", name, display_path(&missing, &base)));
+        }
+        match src.options_file().map(|f| read_sidecar(&f)) {
+            Some(Ok(Some(opts))) => { sidecar_options.insert(name.clone(), opts); }
+            Some(Err(e)) => {
+                warn!("{}", e);
+                report.failure(e);
+            }
+            _ => {}
+        }
         if indices.is_empty() {
-            eprintln!("错误: 播放列表 \"{}\" 不包含任何曲目。跳过。", name);
+            error!("{}", tr!("播放列表 \"{}\" 不包含任何曲目。跳过。", name));
+            report.failure_of(FailureKind::Playlist, tr!("播放列表 \"{}\" 不包含任何曲目，已跳过", name));
         } else {
-            vprintln!("[+] 添加播放列表 {}", name);
+            debug!("{}", tr!("[+] 添加播放列表 {}", name));
             all_playlists.push((name, indices));
         }
     }
 
+    // Options of the scanned playlists apply before the generators below read the master list
+    let mut playlist_options = apply_playlist_options(
+        &mut all_playlists, sidecar_options, config.as_ref(),
+        &Library { tracks: &tracks, metadata: &metadata, seed: &shuffle_seed }, &mut track_infos, &mut report,
+    );
+    exclude_from_master(&mut all_playlists, &playlist_options, &mut track_infos);
+    exclude_titles_from_master(
+        &mut all_playlists, &cli.exclude_title, &tracks, &metadata, &mut track_infos, &mut report, &base,
+    );
+    let generated_start = all_playlists.len();
+
+    let durations: Vec<u64> = tracks.iter()
+        .map(|t| metadata.get(t).and_then(|m| m.duration_ms).unwrap_or(0))
+        .collect();
+
+    // Trip mixes: split the library (or one playlist) into N equal-duration parts
+    if let Some(n) = cli.trip_mix {
+        match source_playlist(&all_playlists, cli.trip_mix_source.as_deref()) {
+            Some(indices) => {
+                for (i, group) in split_balanced(&indices, &durations, n as usize).into_iter().enumerate() {
+                    let name = format!("Trip Mix {}", i + 1);
+                    let total: u64 = group.iter().map(|&t| durations[t as usize]).sum();
+                    debug!("{}", tr!("[+] 添加播放列表 {} ({} 首, {} 分钟)", name, group.len(), format!("{:.1}", total as f64 / 60000.0)));
+                    all_playlists.push((name, group));
+                }
+            }
+            None => {
+                let name = cli.trip_mix_source.as_deref().unwrap_or_default();
+                error!("{}", tr!("找不到 --trip-mix-source 指定的播放列表 \"{}\"。", name));
+                report.failure_of(FailureKind::Playlist, tr!("找不到旅行混音的来源播放列表 \"{}\"", name));
+            }
+        }
+    }
+
+    // Bedtime: a fixed-duration playlist whose final track fades out
+    if let Some(target) = cli.bedtime {
+        let bedtime_dir = base.join("iPod_Control").join(BEDTIME_DIR);
+        if !dry_run {
+            let _ = fs::remove_dir_all(&bedtime_dir);
+        }
+        let source = source_playlist(&all_playlists, cli.bedtime_source.as_deref());
+        let (mut selected, cut) = match &source {
+            Some(indices) => select_for_duration(indices, &durations, target.as_millis() as u64),
+            None => (Vec::new(), None),
+        };
+        if let Some(last) = selected.pop() {
+            let src = tracks[last as usize].clone();
+            let end_ms = cut.unwrap_or(durations[last as usize]);
+            let stem = src.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            let out = bedtime_dir.join(format!("{} (fade).mp3", stem));
+            let fade = FadeOut { end_ms, fade_ms: cli.bedtime_fade.as_millis() as u64 };
+            let faded = if dry_run {
+                outln!("{}", tr!("[演练] 将生成淡出曲目 {}", display_path(&out, &base)));
+                Some(out)
+            } else {
+                fade_out_to_mp3(&src, &out, fade, encode_opts)
+            };
+            match faded {
+                Some(out) => {
+                    // The faded copy shares the original's tags and voiceover
+                    let mut info = build_track_info(&src, &mut ctx);
+                    info.filename = display_path(&out, &base);
+                    info.filetype = 1;
+                    info.stop_at_pos_ms = u32::try_from(end_ms).unwrap_or(u32::MAX);
+                    selected.push(track_infos.len() as u32);
+                    events::track_added(track_infos.len(), &info.filename);
+                    track_infos.push(info);
+                    tracks.push(out);
+                }
+                None => {
+                    report.failure_of(FailureKind::Conversion, tr!("睡前播放列表的淡出编码失败: {}", display_path(&src, &base)));
+                    selected.push(last);
+                }
+            }
+            debug!("{}", tr!("[+] 添加播放列表 Bedtime ({} 首)", selected.len()));
+            all_playlists.push(("Bedtime".to_string(), selected));
+        } else {
+            let name = cli.bedtime_source.as_deref().unwrap_or("All songs");
+            error!("{}", tr!("无法从 \"{}\" 生成睡前播放列表。", name));
+            report.failure_of(FailureKind::Playlist, tr!("无法从 \"{}\" 生成睡前播放列表", name));
+        }
+    }
+
+    for (name, segments) in generated_playlists {
+        let mut indices = Vec::with_capacity(segments.len());
+        for seg in segments {
+            debug!("{}", tr!("[*] 添加生成的曲目 {}", seg.display()));
+            let info = build_track_info(&seg, &mut ctx);
+            indices.push(track_infos.len() as u32);
+            events::track_added(track_infos.len(), &info.filename);
+            track_infos.push(info);
+            tracks.push(seg);
+        }
+        debug!("{}", tr!("[+] 添加播放列表 {} ({} 首)", name, indices.len()));
+        all_playlists.push((name, indices));
+    }
+
+    // Chapters: one entry per ID3 chapter of long MP3s, each playing its part of the file
+    if cli.chapters {
+        let min_ms = cli.chapter_min_length.as_millis() as u64;
+        for (i, &duration) in durations.iter().enumerate() {
+            let src = tracks[i].clone();
+            if duration < min_ms || ext_lower(&src) != ".mp3" { continue; }
+            let chapters = match read_chapters(&src) {
+                Ok(c) => c,
+                Err(e) => {
+                    warn!("{}", tr!("无法读取章节 {}: {}", src.display(), e));
+                    report.failure(tr!("无法读取章节 {}: {}", display_path(&src, &base), e));
+                    continue;
+                }
+            };
+            if chapters.len() < 2 { continue; }
+
+            let title = metadata.get(&src).and_then(|m| m.title.clone())
+                .unwrap_or_else(|| src.file_stem().unwrap_or_default().to_string_lossy().to_string());
+            let duration = u32::try_from(duration).unwrap_or(u32::MAX);
+            let mut indices = Vec::with_capacity(chapters.len());
+            for (n, chapter) in chapters.iter().enumerate() {
+                let start = chapter.start_ms;
+                // Some taggers leave the end unset; the chapter then runs until the next one
+                let end = match chapters.get(n + 1) {
+                    _ if chapter.end_ms > start && chapter.end_ms <= duration => chapter.end_ms,
+                    Some(next) if next.start_ms > start => next.start_ms.min(duration),
+                    _ => duration,
+                };
+                if end <= start { continue; }
+                let chapter_title = chapter.title.clone().unwrap_or_else(|| tr!("第 {} 章", n + 1));
+                let text = format!("{} - {}", chapter_title, title);
+                let mut info = build_track_info(&src, &mut ctx);
+                info.start_at_pos_ms = start;
+                info.stop_at_pos_ms = end;
+                info.track_num = (n + 1) as u16;
+                info.remember = true;
+                info.dont_skip = false;
+                info.dbid = make_dbid(text.as_bytes());
+                if track_voiceover {
+                    do_text_to_speech(&text, &info.dbid, false, &base, voiceover_levels.get(&src).copied(), None);
+                }
+                indices.push(track_infos.len() as u32);
+                events::track_added(track_infos.len(), &info.filename);
+                track_infos.push(info);
+                tracks.push(src.clone());
+            }
+            debug!("{}", tr!("[+] 添加章节播放列表 {} ({} 章)", title, indices.len()));
+            all_playlists.push((title, indices));
+        }
+    }
+
+    let generated_options = apply_playlist_options(
+        &mut all_playlists[generated_start..], HashMap::new(), config.as_ref(),
+        &Library { tracks: &tracks, metadata: &metadata, seed: &shuffle_seed }, &mut track_infos, &mut report,
+    );
+    exclude_from_master(&mut all_playlists, &generated_options, &mut track_infos);
+    playlist_options.extend(generated_options);
+    for (name, &listtype) in &kind_lists {
+        if let Some(opts) = playlist_options.get_mut(name) {
+            opts.listtype.get_or_insert(listtype);
+        }
+    }
+    order_audiobooks(&mut all_playlists, &playlist_options, &tracks, &metadata, &mut track_infos);
+    mark_resumable_tracks(&mut all_playlists, &playlist_options, &mut track_infos);
+
+    if review {
+        let sources: Vec<String> = tracks.iter().map(|t| display_path(t, &base)).collect();
+        match tui::review(&sources, &track_infos, &all_playlists, &report, track_voiceover) {
+            Ok(Some(selection)) => selection.apply(&mut tracks, &mut track_infos, &mut all_playlists),
+            Ok(None) => {
+                // Conversions and voiceovers done so far stay on the device and are reused as is,
+                // so there is nothing left to resume
+                checkpoint.finish();
+                outln!("{}", tr!("已取消，数据库未被修改。已完成的转换与生成的旁白保留在设备上，下次同步时直接使用。"));
+                std::process::exit(exit_code::FAILURE);
+            }
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(exit_code::FAILURE);
+            }
+        }
+    }
+
     // Build and write database
-    println!("正在写入数据库。这可能需要一段时间...");
+    if !dry_run {
+        outln!("{}", tr!("正在写入数据库。这可能需要一段时间..."));
+    }
+    let no_reserved = ReservedFields::default();
+    // Playlist voiceovers are generated along with the database
+    let spinner = progress::spinner("生成数据库");
     let db = build_itunes_sd(
         &track_infos, &all_playlists,
-        track_voiceover, playlist_voiceover, &base,
+        track_voiceover, playlist_voiceover, &base, &playlist_options,
+        previous.as_ref().map_or(&no_reserved, |p| &p.reserved),
     );
+    spinner.finish_and_clear();
+    // Unwritten playlist voiceovers would look unreferenced to the end of the session
+    exit_if_cancelled(&report);
+    let voiceovers = tts::end_session(tts_resume);
+    if voiceovers.removed > 0 {
+        debug!("{}", tr!("已删除 {} 条不再使用的旁白", voiceovers.removed));
+    }
+    (report.voiceovers, report.voiceover_coverage) = voiceover_stats(
+        &voiceovers.spoken, &base, &tracks, &track_infos, &all_playlists, playlist_voiceover,
+    );
+    let mismatched = report.voiceovers.iter().filter(|v| v.mismatch).count();
+    if mismatched > 0 {
+        let hint = if dry_run { "" } else { "，详见同步报告" };
+        outln!("{}", tr!("{} 条旁白的文字与语音的语言可能不一致（如中文语音朗读英文标题）{}", mismatched, i18n::text(hint)));
+    }
 
-    let db_path = base.join("iPod_Control").join("iTunes").join("iTunesSD");
-    match fs::write(&db_path, &db) {
+    report.tracks = tracks.iter().map(|t| display_path(t, &base)).collect();
+    report.playlists = all_playlists.iter()
+        .map(|(name, indices)| (playlist_display_name(name).to_string(), indices.len()))
+        .collect();
+    report.albums = albums.len();
+    report.artists = artists.len();
+    report.playback = tracks.iter().zip(&track_infos)
+        .map(|(t, info)| {
+            let meta = metadata.get(t);
+            PlaybackEntry {
+                track: display_path(t, &base),
+                bitrate_kbps: meta.and_then(|m| m.bitrate_kbps),
+                sample_rate: meta.and_then(|m| m.sample_rate),
+                vbr: meta.is_some_and(|m| m.vbr),
+                gain: info.volume_gain,
+            }
+        })
+        .collect();
+    if track_voiceover {
+        // The Bedtime copy reuses its original's voiceover
+        report.track_voiceovers = track_infos.iter().map(|i| i.dbid).collect::<HashSet<_>>().len();
+    }
+    report.playlist_voiceovers = all_playlists.iter()
+        .filter(|(name, _)| match playlist_options.get(name) {
+            Some(opts) => opts.voiceover.unwrap_or(playlist_voiceover),
+            None => playlist_voiceover,
+        })
+        .count();
+
+    report.changes = previous.as_ref()
+        .map(|prev| summarize_changes(prev, &track_infos, &all_playlists, playlist_voiceover))
+        .map(|c| ChangeSummary { moved, ..c });
+    if let Some(ref c) = report.changes {
+        for t in &c.added { debug!("{}", tr!("[+] 新增曲目 {}", t)); }
+        for t in &c.removed { debug!("{}", tr!("[-] 移除曲目 {}", t)); }
+        for (t, old, new) in &c.gains { debug!("{}", tr!("[~] 调整增益 {} ({} -> {})", t, old, new)); }
+    }
+    let json = cli.output == OutputFormat::Json;
+    let voiceover_counts = (voiceovers.generated, voiceovers.deferred, voiceovers.removed);
+    if dry_run {
+        print_preview(&track_infos, &all_playlists, &playlist_options, &report, &voiceovers);
+        let outcome = RunOutcome { dry_run, error: None, voiceovers: voiceover_counts, report_path: None };
+        let summary = render_json(&report, &outcome);
+        if json {
+            println!("{}", summary);
+        }
+        match strict_failure(cli.strict, &report) {
+            Some(reason) => notify_failure(&reason, &report),
+            None => notify_completion(Status::DryRun, i18n::text("演练完成，设备未被修改"), &summary),
+        }
+        exit_if_strict(cli.strict, &report);
+        return;
+    }
+    let spinner = progress::spinner("写入数据库");
+    let write_result = write_file_atomic(&db_path, &db);
+    spinner.finish_and_clear();
+    match write_result {
+        Ok(()) => match write_build_meta(
+            &base, &db, &track_infos, &all_playlists, &playlist_options, playlist_voiceover, &rotated_out,
+        ) {
+            Ok(p) => debug!("{}", tr!("构建信息已写入 {}", p.display())),
+            Err(e) => {
+                warn!("{}", tr!("写入构建信息失败: {}", e));
+                report.failure(tr!("写入 {} 失败: {}", META_FILE, e));
+            }
+        },
+        Err(ref e) => report.failure(tr!("写入 iPod 数据库失败: {}", e)),
+    }
+    let shuffled = cli.order == TrackOrder::Shuffle
+        || playlist_options.values().any(|o| o.order == Some(TrackOrder::Shuffle));
+    if write_result.is_ok() && shuffled && shuffle_seed.fresh() {
+        if let Err(e) = shuffle_seed.save() {
+            warn!("{}", tr!("保存随机种子失败: {}", e));
+            report.failure(e);
+        }
+    }
+    let report_path = match write_report(&base, &report, cli.report) {
+        Ok(Some(p)) => {
+            debug!("{}", tr!("同步报告已写入 {}", p.display()));
+            Some(p)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            warn!("{}", tr!("写入同步报告失败: {}", e));
+            None
+        }
+    };
+    let outcome = RunOutcome {
+        dry_run,
+        error: write_result.as_ref().err().map(|e| e.to_string()),
+        voiceovers: voiceover_counts,
+        report_path: report_path.as_deref(),
+    };
+    let summary = render_json(&report, &outcome);
+    if json {
+        println!("{}", summary);
+    }
+
+    match write_result {
         Ok(_) => {
-            println!("数据库写入成功:");
-            println!("曲目 {}", tracks.len());
-            println!("专辑 {}", albums.len());
-            println!("艺术家 {}", artists.len());
-            println!("播放列表 {}", all_playlists.len());
+            checkpoint.finish();
+            events::db_written(&db_path, tracks.len(), all_playlists.len());
+            outln!("{}", tr!("数据库写入成功:"));
+            outln!("{}", tr!("曲目 {}", tracks.len()));
+            outln!("{}", tr!("专辑 {}", albums.len()));
+            outln!("{}", tr!("艺术家 {}", artists.len()));
+            outln!("{}", tr!("播放列表 {}", all_playlists.len()));
+            if let Some(ref c) = report.changes {
+                outln!("{}", c.line());
+            }
+            if voiceovers.deferred > 0 {
+                outln!("{}", tr!(
+                    "本次生成 {} 条语音旁白，已达到 --tts-limit，还有 {} 条未生成；请稍后加上 --tts-resume 再次同步以继续",
+                    voiceovers.generated, voiceovers.deferred,
+                ));
+            }
+            let message = match report.failures.len() {
+                0 => tr!("同步完成，可以安全拔出 iPod: {} 首曲目，{} 个播放列表", tracks.len(), all_playlists.len()),
+                n => tr!("同步完成，可以安全拔出 iPod: {} 首曲目，{} 个播放列表，{} 个问题", tracks.len(), all_playlists.len(), n),
+            };
+            match strict_failure(cli.strict, &report) {
+                Some(reason) => notify_failure(&reason, &report),
+                None => notify_completion(Status::Success, &message, &summary),
+            }
         }
         Err(e) => {
-            eprintln!("I/O 错误: {}", e);
-            eprintln!("错误: 写入 iPod 数据库失败。");
-            std::process::exit(1);
+            error!("{}", tr!("写入 iPod 数据库失败: {}", e));
+            notify_completion(Status::Failure, &tr!("同步失败: 写入 iPod 数据库失败: {}", e), &summary);
+            std::process::exit(exit_code::DB_WRITE_FAILED);
         }
     }
+    exit_if_strict(cli.strict, &report);
 }
@@ -0,0 +1,420 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use crate::outln;
+use crate::config::load_config;
+use crate::confirm::confirm;
+use crate::database::{
+    dbid_to_filename, make_dbid, parse_itunes_sd, playlist_dbid, podcast_voiceover_text, set_upper_case_voiceovers,
+    track_voiceover_text, voiceover_file_name, voiceover_path,
+};
+use crate::i18n::tr;
+use crate::listing::print_section;
+use crate::metadata::read_all_meta;
+use crate::playlist::config_options;
+use crate::search::candidate_names;
+use crate::tts::{default_voice, text_to_speech_file};
+
+// ─── Speakable integrity check ───────────────────────────────────────────────
+
+/// 读取单个文件标签的时限
+const TAG_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 数据库引用、应当存在的一条旁白
+pub struct ExpectedVoiceover {
+    pub path: PathBuf,
+    /// 曲目的 iPod 路径或播放列表名称
+    pub label: String,
+    /// 朗读的文本；标签已变化或播放列表名称无法反查时为 `None`，只能通过重新同步修复
+    pub text: Option<String>,
+    pub voice: Option<String>,
+}
+
+/// 旁白完整性检查的结果
+#[derive(Default)]
+pub struct SpeakableReport {
+    pub checked: usize,
+    pub missing: Vec<ExpectedVoiceover>,
+    /// 无法解码的旁白及原因
+    pub corrupt: Vec<(ExpectedVoiceover, String)>,
+    /// Speakable 中没有被数据库引用的文件
+    pub orphaned: Vec<PathBuf>,
+    /// 目录布局的问题；修复后只剩无法修复的
+    pub layout: Vec<LayoutIssue>,
+    /// 修复时已更正的布局问题
+    pub layout_fixed: Vec<String>,
+    /// 修复时重新生成成功的数量
+    pub repaired: usize,
+}
+
+impl SpeakableReport {
+    pub fn problem_count(&self) -> usize {
+        self.missing.len() + self.corrupt.len() + self.layout.iter().filter(|i| i.fix.is_some()).count()
+    }
+}
+
+/// 完整解码一个 WAV 文件
+fn check_wav(path: &Path) -> Result<(), String> {
+    let mut reader = hound::WavReader::open(path).map_err(|e| e.to_string())?;
+    if reader.duration() == 0 {
+        return Err("没有音频数据".to_string());
+    }
+    let decoded: Result<(), hound::Error> = match reader.spec().sample_format {
+        hound::SampleFormat::Int => reader.samples::<i32>().try_for_each(|s| s.map(|_| ())),
+        hound::SampleFormat::Float => reader.samples::<f32>().try_for_each(|s| s.map(|_| ())),
+    };
+    decoded.map_err(|e| e.to_string())
+}
+
+/// 按数据库列出应当存在的旁白。曲目旁白以数据库头部的开关为准；
+/// 主列表的 dbid 非零说明同步时启用了播放列表旁白，配置文件中的单个列表设置会覆盖它
+fn expected_voiceovers(base: &Path) -> Result<(Vec<ExpectedVoiceover>, HashSet<PathBuf>), String> {
+    let db_path = base.join("iPod_Control").join("iTunes").join("iTunesSD");
+    let data = fs::read(&db_path).map_err(|e| format!("无法读取 iTunesSD: {}", e))?;
+    let db = parse_itunes_sd(&data)?;
+
+    let paths: Vec<PathBuf> = db.tracks.iter().map(|t| base.join(t.filename.trim_start_matches('/'))).collect();
+    let (metas, _) = read_all_meta(&paths, TAG_TIMEOUT);
+    let config = load_config(base).unwrap_or_else(|e| {
+        warn!("{}", e);
+        None
+    });
+
+    let mut expected = Vec::new();
+    let mut referenced = HashSet::new();
+    for (track, path) in db.tracks.iter().zip(&paths) {
+        let wav = voiceover_path(base, &track.dbid, false);
+        if !db.track_voiceover || !referenced.insert(wav.clone()) { continue; }
+        let meta = metas.get(path).cloned().unwrap_or_default();
+        // Podcast episodes (resumable tracks) may have been voiced from their podcast tags
+        let podcast = if track.remember { podcast_voiceover_text(&meta, default_voice()) } else { None };
+        let text = podcast.into_iter()
+            .chain([track_voiceover_text(path, &meta)])
+            .find(|t| make_dbid(t.as_bytes()) == track.dbid);
+        expected.push(ExpectedVoiceover {
+            path: wav,
+            label: track.filename.clone(),
+            text,
+            voice: None,
+        });
+    }
+
+    let master_voiceover = db.playlists.iter().any(|p| p.listtype == 1 && p.dbid != [0u8; 8]);
+    let names: HashMap<[u8; 8], String> = candidate_names(base, &metas).into_iter()
+        .map(|n| (playlist_dbid(&n, true), n))
+        .collect();
+    for playlist in &db.playlists {
+        if playlist.dbid == [0u8; 8] { continue; }
+        let wav = voiceover_path(base, &playlist.dbid, true);
+        if !referenced.insert(wav.clone()) { continue; }
+        let name = if playlist.listtype == 1 { Some("All songs".to_string()) } else { names.get(&playlist.dbid).cloned() };
+        let opts = match name.as_deref() {
+            Some(n) if playlist.listtype != 1 => config_options(config.as_ref(), n).ok().flatten().unwrap_or_default(),
+            _ => Default::default(),
+        };
+        if !opts.voiceover.unwrap_or(master_voiceover) { continue; }
+        expected.push(ExpectedVoiceover {
+            path: wav,
+            label: name.clone().unwrap_or_else(|| format!("未知播放列表 ({})", dbid_to_filename(&playlist.dbid))),
+            text: name,
+            voice: opts.voice,
+        });
+    }
+    Ok((expected, referenced))
+}
+
+/// 检查数据库引用的每条旁白是否存在且能够解码，并列出多余的旁白文件。
+/// `repair` 为 `true` 时只重新生成缺失或损坏的旁白，其余文件保持不变。
+/// 数据库无法读取时不修改设备；删除操作系统留下的文件前先询问
+pub fn check_speakable(base: &Path, repair: bool) -> Result<SpeakableReport, String> {
+    configure_layout(base)?;
+    let (expected, referenced) = expected_voiceovers(base)?;
+    let mut layout = check_layout(base);
+    let mut layout_fixed = Vec::new();
+    if repair {
+        let removals: Vec<String> = layout.iter()
+            .filter(|i| matches!(i.fix, Some(LayoutFix::Remove)))
+            .map(|i| i.describe(base))
+            .collect();
+        let remove = confirm(&tr!("将删除 Speakable 中的以下文件："), &removals);
+        (layout_fixed, layout) = repair_layout(base, layout, !remove);
+    }
+
+    let mut report = SpeakableReport { checked: expected.len(), layout, layout_fixed, ..Default::default() };
+
+    for item in expected {
+        debug!("[?] 检查 {}", item.path.display());
+        if !item.path.exists() {
+            report.missing.push(item);
+        } else if let Err(e) = check_wav(&item.path) {
+            report.corrupt.push((item, e));
+        }
+    }
+
+    let speakable = base.join("iPod_Control").join("Speakable");
+    for subdir in ["Playlists", "Tracks"] {
+        let Ok(entries) = fs::read_dir(speakable.join(subdir)) else { continue };
+        let mut orphaned: Vec<PathBuf> = entries.flatten()
+            .map(|e| e.path())
+            .filter(|p| p.is_file() && !referenced.contains(p) && voiceover_dbid(p).is_some())
+            .collect();
+        orphaned.sort();
+        report.orphaned.extend(orphaned);
+    }
+
+    if repair {
+        let mut still_missing = Vec::new();
+        for item in std::mem::take(&mut report.missing) {
+            if regenerate(&item) { report.repaired += 1; } else { still_missing.push(item); }
+        }
+        report.missing = still_missing;
+        let mut still_corrupt = Vec::new();
+        for (item, reason) in std::mem::take(&mut report.corrupt) {
+            if regenerate(&item) { report.repaired += 1; } else { still_corrupt.push((item, reason)); }
+        }
+        report.corrupt = still_corrupt;
+    }
+    Ok(report)
+}
+
+/// 重新生成一条旁白，成功时返回 `true`
+fn regenerate(item: &ExpectedVoiceover) -> bool {
+    let Some(ref text) = item.text else { return false };
+    outln!("[*] 重新生成旁白 {}", item.label);
+    if let Some(dir) = item.path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    // An existing file here is corrupt; remove it so it is not reused as is
+    let _ = fs::remove_file(&item.path);
+    text_to_speech_file(&item.path, text, None, item.voice.as_deref()) && check_wav(&item.path).is_ok()
+}
+
+/// 打印旁白检查结果
+pub fn print_speakable_report(base: &Path, report: &SpeakableReport, repaired: bool) {
+    let describe = |item: &ExpectedVoiceover| {
+        let file = item.path.strip_prefix(base).unwrap_or(&item.path).display().to_string();
+        let hint = if item.text.is_none() { "，需重新同步修复" } else { "" };
+        format!("{}: {}{}", item.label, file, hint)
+    };
+    outln!("已检查 {} 条旁白", report.checked);
+    if report.repaired > 0 {
+        outln!("已重新生成 {} 条旁白", report.repaired);
+    }
+    for fixed in &report.layout_fixed {
+        outln!("[+] {}", fixed);
+    }
+    let (layout, unknown): (Vec<&LayoutIssue>, Vec<&LayoutIssue>) = report.layout.iter().partition(|i| i.fix.is_some());
+    print_section("目录布局的问题", &layout.iter().map(|i| i.describe(base)).collect::<Vec<_>>());
+    print_section("Speakable 中的未知条目（保持不变）", &unknown.iter().map(|i| i.describe(base)).collect::<Vec<_>>());
+    print_section("缺失的旁白", &report.missing.iter().map(describe).collect::<Vec<_>>());
+    print_section("无法解码的旁白", &report.corrupt.iter()
+        .map(|(item, e)| format!("{}（{}）", describe(item), e))
+        .collect::<Vec<_>>());
+    print_section("未被数据库引用的旁白（下次同步时清除）", &report.orphaned.iter()
+        .map(|p| p.strip_prefix(base).unwrap_or(p).display().to_string())
+        .collect::<Vec<_>>());
+    outln!();
+    match report.problem_count() {
+        0 => outln!("旁白完整。"),
+        n if repaired => outln!("仍有 {} 个问题无法修复。", n),
+        n => outln!("发现 {} 个问题，可使用 --repair 更正目录布局并重新生成缺失或损坏的旁白。", n),
+    }
+}
+
+// ─── Speakable layout ────────────────────────────────────────────────────────
+
+/// 配置文件中设置旁白目录布局的表
+pub const LAYOUT_TABLE: &str = "speakable";
+
+/// 同步生成的旁白目录
+const VOICEOVER_DIRS: [&str; 2] = ["Playlists", "Tracks"];
+
+/// iTunes 写入、固件自带提示音所在的目录，原样保留
+const SYSTEM_DIRS: [&str; 3] = ["Messages", "System", "VoiceOverDB"];
+
+/// 目录布局问题的修复方式
+pub enum LayoutFix {
+    /// 改为正确的名称（通常只是大小写不同）
+    Rename(PathBuf),
+    Remove,
+    CreateDir,
+}
+
+/// Speakable 目录布局中的一个问题。`fix` 为 `None` 的只报告，不算作问题
+pub struct LayoutIssue {
+    pub path: PathBuf,
+    pub problem: &'static str,
+    pub fix: Option<LayoutFix>,
+}
+
+impl LayoutIssue {
+    pub fn describe(&self, base: &Path) -> String {
+        let relative = |p: &Path| p.strip_prefix(base).unwrap_or(p).display().to_string();
+        match self.fix {
+            Some(LayoutFix::Rename(ref to)) => format!("{}: {}，应为 {}", relative(&self.path), self.problem, relative(to)),
+            _ => format!("{}: {}", relative(&self.path), self.problem),
+        }
+    }
+}
+
+/// 读取配置文件的 [speakable] 表并应用旁白文件名的大小写：
+/// `filename-case = "upper"` 时使用大写十六进制，默认小写
+pub fn configure_layout(base: &Path) -> Result<(), String> {
+    let config = load_config(base)?;
+    let Some(table) = config.as_ref().and_then(|c| c.get(LAYOUT_TABLE)) else {
+        set_upper_case_voiceovers(false);
+        return Ok(());
+    };
+    let table = table.as_table().ok_or_else(|| format!("[{}] 必须是一个表", LAYOUT_TABLE))?;
+    for (key, value) in table {
+        match (key.as_str(), value.as_str()) {
+            ("filename-case", Some("lower")) => set_upper_case_voiceovers(false),
+            ("filename-case", Some("upper")) => set_upper_case_voiceovers(true),
+            ("filename-case", _) => return Err(format!("[{}] filename-case 只能是 \"lower\" 或 \"upper\"", LAYOUT_TABLE)),
+            _ => return Err(format!("[{}] 中未知的键 \"{}\"", LAYOUT_TABLE, key)),
+        }
+    }
+    Ok(())
+}
+
+/// 形如 `<16 位十六进制>.wav`（不区分大小写）的旁白文件对应的 dbid
+fn voiceover_dbid(path: &Path) -> Option<[u8; 8]> {
+    let name = path.file_name()?.to_str()?;
+    let (hex, ext) = name.split_at_checked(16)?;
+    if !ext.eq_ignore_ascii_case(".wav") { return None; }
+    let mut dbid = [0u8; 8];
+    for (i, byte) in dbid.iter_mut().rev().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(dbid)
+}
+
+/// 操作系统留下的元数据文件，如 macOS 的 `._*` 与 `.DS_Store`
+fn is_os_litter(name: &str) -> bool {
+    name.starts_with("._") || matches!(name, ".DS_Store" | "Thumbs.db" | "desktop.ini")
+}
+
+/// 在 `dir` 中按不区分大小写的方式查找 `name`，返回实际的条目
+fn find_entry(dir: &Path, name: &str) -> Option<PathBuf> {
+    fs::read_dir(dir).ok()?
+        .flatten()
+        .find(|e| e.file_name().to_string_lossy().eq_ignore_ascii_case(name))
+        .map(|e| e.path())
+}
+
+/// 检查 Speakable 的目录布局：目录与旁白文件名的大小写、缺少的旁白目录
+/// 以及操作系统留下的元数据文件（只有这些文件可以删除）。
+/// iTunes 写入的 plist 与提示音目录保持不变，旁白目录中不是旁白的文件等其余无法识别的条目只报告
+pub fn check_layout(base: &Path) -> Vec<LayoutIssue> {
+    let control = base.join("iPod_Control");
+    let canonical = control.join("Speakable");
+    let mut issues = Vec::new();
+    let Some(speakable) = find_entry(&control, "Speakable").filter(|p| p.is_dir()) else {
+        return issues;
+    };
+    if speakable != canonical {
+        issues.push(LayoutIssue { path: speakable.clone(), problem: "目录名称的大小写不正确", fix: Some(LayoutFix::Rename(canonical.clone())) });
+    }
+
+    for dir in VOICEOVER_DIRS {
+        let expected = canonical.join(dir);
+        let Some(actual) = find_entry(&speakable, dir).filter(|p| p.is_dir()) else {
+            issues.push(LayoutIssue { path: expected, problem: "缺少旁白目录", fix: Some(LayoutFix::CreateDir) });
+            continue;
+        };
+        if actual.file_name() != expected.file_name() {
+            issues.push(LayoutIssue { path: actual.clone(), problem: "目录名称的大小写不正确", fix: Some(LayoutFix::Rename(speakable.join(dir))) });
+        }
+        let Ok(entries) = fs::read_dir(&actual) else { continue };
+        let mut paths: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+        paths.sort();
+        for path in paths {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            match voiceover_dbid(&path).filter(|_| path.is_file()) {
+                Some(dbid) => {
+                    let proper = voiceover_file_name(&dbid);
+                    if name != proper {
+                        issues.push(LayoutIssue { fix: Some(LayoutFix::Rename(actual.join(proper))), path, problem: "文件名的大小写不正确" });
+                    }
+                }
+                None if is_os_litter(&name) && path.is_file() => {
+                    issues.push(LayoutIssue { path, problem: "操作系统留下的元数据文件", fix: Some(LayoutFix::Remove) });
+                }
+                None => issues.push(LayoutIssue { path, problem: "不是旁白文件", fix: None }),
+            }
+        }
+    }
+
+    let Ok(entries) = fs::read_dir(&speakable) else { return issues };
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+    paths.sort();
+    for path in paths {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        if VOICEOVER_DIRS.iter().any(|d| d.eq_ignore_ascii_case(&name)) { continue; }
+        if is_os_litter(&name) && path.is_file() {
+            issues.push(LayoutIssue { path, problem: "操作系统留下的元数据文件", fix: Some(LayoutFix::Remove) });
+        } else if let Some(proper) = SYSTEM_DIRS.iter().find(|d| d.eq_ignore_ascii_case(&name)).filter(|_| path.is_dir()) {
+            if name != *proper {
+                issues.push(LayoutIssue { fix: Some(LayoutFix::Rename(speakable.join(proper))), path, problem: "目录名称的大小写不正确" });
+            }
+        } else if !(path.is_file() && name.to_ascii_lowercase().ends_with(".plist")) {
+            issues.push(LayoutIssue { path, problem: "固件不使用的条目", fix: None });
+        }
+    }
+    issues
+}
+
+/// 改名，必要时经过一个临时名称，使只改大小写在不区分大小写的文件系统上也能生效
+fn rename_case(from: &Path, to: &Path) -> std::io::Result<()> {
+    let mut tmp = to.as_os_str().to_owned();
+    tmp.push(".case");
+    let tmp = PathBuf::from(tmp);
+    fs::rename(from, &tmp)?;
+    fs::rename(&tmp, to).inspect_err(|_| { let _ = fs::rename(&tmp, from); })
+}
+
+/// 按 `check_layout` 的结果修复目录布局，返回已修复的说明与剩下的问题。
+/// `casing_only` 为 `true` 时只改名与创建目录（同步时自动进行），不删除任何文件；
+/// 否则还会删除操作系统留下的元数据文件（`LayoutFix::Remove` 只用于这些文件）。
+/// Speakable 目录本身改名后，其中条目的路径随之更新
+pub fn repair_layout(base: &Path, issues: Vec<LayoutIssue>, casing_only: bool) -> (Vec<String>, Vec<LayoutIssue>) {
+    let mut fixed = Vec::new();
+    let mut remaining = Vec::new();
+    // Directories come before their contents, so a renamed parent moves the paths below it
+    let mut moved: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let remap = |path: &Path, moved: &[(PathBuf, PathBuf)]| -> PathBuf {
+        moved.iter().fold(path.to_path_buf(), |path, (from, to)| match path.strip_prefix(from) {
+            Ok(rest) => to.join(rest),
+            Err(_) => path,
+        })
+    };
+    for mut issue in issues {
+        issue.path = remap(&issue.path, &moved);
+        if let Some(LayoutFix::Rename(ref mut to)) = issue.fix {
+            *to = remap(to, &moved);
+        }
+        let result = match issue.fix {
+            Some(LayoutFix::Rename(ref to)) => rename_case(&issue.path, to).map(|_| moved.push((issue.path.clone(), to.clone()))),
+            Some(LayoutFix::CreateDir) => fs::create_dir_all(&issue.path),
+            Some(LayoutFix::Remove) if !casing_only => fs::remove_file(&issue.path),
+            _ => {
+                remaining.push(issue);
+                continue;
+            }
+        };
+        match result {
+            Ok(()) => {
+                debug!("已修复 {}", issue.describe(base));
+                fixed.push(format!("已修复 {}", issue.describe(base)));
+            }
+            Err(e) => {
+                warn!("无法修复 {}: {}", issue.describe(base), e);
+                remaining.push(issue);
+            }
+        }
+    }
+    (fixed, remaining)
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::database::parse_itunes_sd;
+
+// ─── iTunesStats reader ──────────────────────────────────────────────────────
+
+/// 设备记录的单首曲目播放统计，顺序与当时的 iTunesSD 曲目顺序一致
+pub struct TrackStats {
+    pub play_count: u32,
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// 解析 iTunesStats：8 字节文件头（曲目数 + 未知字段），随后每首曲目一条记录，
+/// 记录首个字段为记录长度。格式见 `docs/iTunesStats3gen.md`
+pub fn parse_itunes_stats(data: &[u8]) -> Result<Vec<TrackStats>, String> {
+    let count = u32_at(data, 0).ok_or("iTunesStats 文件头不完整")? as usize;
+    let mut offset = 8;
+    let mut stats = Vec::with_capacity(count);
+    for i in 0..count {
+        let len = u32_at(data, offset).ok_or_else(|| format!("iTunesStats 在第 {} 条记录处被截断", i))? as usize;
+        if len < 24 {
+            return Err(format!("iTunesStats 第 {} 条记录长度无效: {}", i, len));
+        }
+        let field = |n: usize| u32_at(data, offset + 4 * n)
+            .ok_or_else(|| format!("iTunesStats 在第 {} 条记录处被截断", i));
+        stats.push(TrackStats {
+            play_count: field(2)?,
+        });
+        offset += len;
+    }
+    Ok(stats)
+}
+
+/// 读取设备上的 iTunesSD 与 iTunesStats，按索引配对为 (数据库中的 iPod 路径, 统计)
+fn read_stats(base: &Path) -> Result<Vec<(String, TrackStats)>, String> {
+    let itunes = base.join("iPod_Control").join("iTunes");
+    let db = fs::read(itunes.join("iTunesSD")).map_err(|e| format!("无法读取 iTunesSD: {}", e))?;
+    let stats = fs::read(itunes.join("iTunesStats")).map_err(|e| format!("无法读取 iTunesStats: {}", e))?;
+    let parsed = parse_itunes_sd(&db)?;
+    let stats = parse_itunes_stats(&stats)?;
+    if stats.len() != parsed.tracks.len() {
+        return Err(format!(
+            "iTunesStats 记录数 ({}) 与 iTunesSD 曲目数 ({}) 不一致，统计可能已过期",
+            stats.len(), parsed.tracks.len(),
+        ));
+    }
+    Ok(parsed.tracks.into_iter().map(|t| t.filename).zip(stats).collect())
+}
+
+/// 读取设备上的 iTunesSD 与 iTunesStats，返回自上次写入数据库以来播放过的曲目（数据库中的 iPod 路径）。
+///
+/// 统计记录与写入新数据库之前的 iTunesSD 按索引一一对应，因此必须在重建数据库之前调用
+pub fn played_tracks(base: &Path) -> Result<Vec<String>, String> {
+    Ok(read_stats(base)?.into_iter()
+        .filter(|(_, st)| st.play_count > 0)
+        .map(|(filename, _)| filename)
+        .collect())
+}
+
+/// 每首曲目（数据库中的 iPod 路径）的播放次数，设备上还没有 iTunesStats 时为空。
+/// 与 `played_tracks` 一样须在重建数据库之前调用
+pub fn play_counts(base: &Path) -> Result<HashMap<String, u32>, String> {
+    if !base.join("iPod_Control").join("iTunes").join("iTunesStats").exists() {
+        return Ok(HashMap::new());
+    }
+    Ok(read_stats(base)?.into_iter().map(|(filename, st)| (filename, st.play_count)).collect())
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::outln;
+use crate::collation::compare;
+use crate::database::read_device_db;
+use crate::i18n::{self, tr};
+use crate::metadata::{apply_meta_sidecars, read_all_meta};
+use crate::report::{device_space, format_bytes};
+use crate::utils::ext_lower;
+
+// ─── stats subcommand ────────────────────────────────────────────────────────
+
+/// 统计时读取单个文件标签的时限
+const TAG_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 曲库概况：按现有 iTunesSD 统计，build 之后运行即为刚写入的数据库
+pub struct LibrarySummary {
+    pub tracks: usize,
+    pub playlists: usize,
+    /// 曲目总时长（毫秒），按数据库中的起止位置计算
+    pub duration_ms: u64,
+    /// 按曲目数从多到少排列的 (名称, 曲目数)，没有标签的曲目计为空名称
+    pub artists: Vec<(String, usize)>,
+    pub albums: Vec<(String, usize)>,
+    pub genres: Vec<(String, usize)>,
+    /// 按占用空间从大到小排列的 (格式, 曲目数, 字节数)，如 ("MP3", 120, 480000000)
+    pub formats: Vec<(String, usize, u64)>,
+    /// 设备的 (可用空间, 总容量)
+    pub space: Option<(u64, u64)>,
+}
+
+/// 按曲目数从多到少、同数时按名称排列
+fn ranked(counts: HashMap<String, usize>) -> Vec<(String, usize)> {
+    let mut ranked: Vec<(String, usize)> = counts.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| compare(&a.0, &b.0)));
+    ranked
+}
+
+/// 读取设备上的 iTunesSD 与曲目标签（含 .meta.toml 元数据文件），统计曲库概况
+pub fn summarize(base: &Path) -> Result<LibrarySummary, String> {
+    let db = read_device_db(base)?;
+    let paths: Vec<PathBuf> = db.tracks.iter().map(|t| base.join(t.filename.trim_start_matches('/'))).collect();
+    let (mut metas, _) = read_all_meta(&paths, TAG_TIMEOUT);
+    apply_meta_sidecars(&paths, &mut metas);
+
+    let mut artists = HashMap::new();
+    let mut albums = HashMap::new();
+    let mut genres = HashMap::new();
+    let mut formats: HashMap<String, (usize, u64)> = HashMap::new();
+    let mut duration_ms = 0;
+    for (track, path) in db.tracks.iter().zip(&paths) {
+        duration_ms += track.stop_at_pos_ms.saturating_sub(track.start_at_pos_ms) as u64;
+        let meta = metas.get(path).cloned().unwrap_or_default();
+        *artists.entry(meta.artist.unwrap_or_default()).or_default() += 1;
+        *albums.entry(meta.album.unwrap_or_default()).or_default() += 1;
+        *genres.entry(meta.genre.unwrap_or_default()).or_default() += 1;
+        let format = formats.entry(ext_lower(path).trim_start_matches('.').to_uppercase()).or_default();
+        format.0 += 1;
+        format.1 += fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    }
+    let mut formats: Vec<(String, usize, u64)> = formats.into_iter().map(|(f, (n, bytes))| (f, n, bytes)).collect();
+    formats.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+
+    Ok(LibrarySummary {
+        tracks: db.tracks.len(),
+        playlists: db.playlists.len(),
+        duration_ms,
+        artists: ranked(artists),
+        albums: ranked(albums),
+        genres: ranked(genres),
+        formats,
+        space: device_space(base),
+    })
+}
+
+/// 将毫秒写成 "12:03:45"，不足一小时时为 "3:45"
+fn format_duration(ms: u64) -> String {
+    let secs = ms / 1000;
+    match secs / 3600 {
+        0 => format!("{}:{:02}", secs / 60, secs % 60),
+        h => format!("{}:{:02}:{:02}", h, secs / 60 % 60, secs % 60),
+    }
+}
+
+/// 打印按曲目数排列的一组统计，只列出前 `top` 项（为 0 时全部列出）
+fn print_ranking(heading: &str, ranking: &[(String, usize)], unknown: &str, top: usize) {
+    outln!();
+    outln!("{} ({}):", heading, ranking.len());
+    let shown = if top == 0 { ranking.len() } else { top.min(ranking.len()) };
+    for (name, count) in &ranking[..shown] {
+        let name = if name.is_empty() { unknown } else { name.as_str() };
+        outln!("  {:>5}  {}", count, name);
+    }
+    if shown < ranking.len() {
+        outln!("  {}", tr!("……另有 {} 项", ranking.len() - shown));
+    }
+}
+
+/// `stats`：打印曲库概况
+pub fn print_summary(summary: &LibrarySummary, top: usize) {
+    outln!("{}", tr!("曲目: {}", summary.tracks));
+    outln!("{}", tr!("播放列表: {}", summary.playlists));
+    outln!("{}", tr!("总时长: {}", format_duration(summary.duration_ms)));
+
+    print_ranking(i18n::text("艺术家"), &summary.artists, i18n::text("（未知艺术家）"), top);
+    print_ranking(i18n::text("专辑"), &summary.albums, i18n::text("（未知专辑）"), top);
+    print_ranking(i18n::text("流派"), &summary.genres, i18n::text("（未知流派）"), top);
+
+    outln!();
+    outln!("{}", i18n::text("按格式占用的空间:"));
+    for (format, count, bytes) in &summary.formats {
+        outln!("  {:<5} {:>10}  {}", format, format_bytes(*bytes), tr!("{} 首", count));
+    }
+    let used: u64 = summary.formats.iter().map(|f| f.2).sum();
+    outln!("  {}", tr!("合计: {}", format_bytes(used)));
+
+    outln!();
+    match summary.space {
+        Some((free, total)) => outln!("{}", tr!("剩余空间: {} / 共 {}", format_bytes(free), format_bytes(total))),
+        None => outln!("{}", i18n::text("剩余空间: 未知")),
+    }
+}
@@ -0,0 +1,128 @@
+use lofty::config::WriteOptions;
+use lofty::prelude::*;
+use lofty::tag::Tag;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+use crate::device_io::{sync_file, unshare};
+use crate::metadata::TrackMeta;
+
+// ─── Tag corrections ─────────────────────────────────────────────────────────
+
+/// 一处标签修正
+pub struct TagFix {
+    pub track: PathBuf,
+    pub field: &'static str,
+    pub old: Option<String>,
+    pub new: String,
+}
+
+/// 修复被按 Latin-1 读出的 UTF-8 或 GBK 文本（如 "Ã¤Â¸Â­" 或 "ÖÐÎÄ"）。
+/// 只有全部字符都落在 Latin-1 范围、且按另一种编码能无损解码时才修正
+pub fn fix_mojibake(s: &str) -> Option<String> {
+    if !s.chars().any(|c| c as u32 >= 0x80) || s.chars().any(|c| c as u32 > 0xFF) {
+        return None;
+    }
+    let bytes: Vec<u8> = s.chars().map(|c| c as u8).collect();
+    if let Ok(utf8) = String::from_utf8(bytes.clone()) {
+        return Some(utf8);
+    }
+    // GBK accepts many Latin-1 byte pairs, so only take results that contain CJK text
+    let (gbk, had_errors) = encoding_rs::GBK.decode_without_bom_handling(&bytes);
+    let has_cjk = gbk.chars().any(|c| ('\u{4E00}'..='\u{9FFF}').contains(&c));
+    (!had_errors && has_cjk).then(|| gbk.into_owned())
+}
+
+/// 从文件名推断标题与艺术家："01 - Artist - Title"、"Artist - Title" 或 "01. Title"
+fn infer_from_stem(stem: &str) -> (Option<String>, String) {
+    let trimmed = stem.trim_start_matches(|c: char| c.is_ascii_digit());
+    let rest = if trimmed.len() < stem.len() {
+        trimmed.trim_start_matches(|c: char| c == '.' || c == '-' || c == '_' || c.is_whitespace())
+    } else {
+        stem
+    };
+    let rest = if rest.is_empty() { stem } else { rest };
+    match rest.split_once(" - ") {
+        Some((artist, title)) if !artist.trim().is_empty() && !title.trim().is_empty() => {
+            (Some(artist.trim().to_string()), title.trim().to_string())
+        }
+        _ => (None, rest.trim().to_string()),
+    }
+}
+
+/// 在内存中修正曲目的标签：修复乱码的标题、艺术家、专辑与流派，缺少标题时从文件名推断。
+/// 返回所做的修正
+pub fn fix_tags(tracks: &[PathBuf], metadata: &mut HashMap<PathBuf, TrackMeta>) -> Vec<TagFix> {
+    let mut fixes = Vec::new();
+    for track in tracks {
+        let meta = metadata.entry(track.clone()).or_default();
+        let fields: [(&'static str, &mut Option<String>); 4] = [
+            ("title", &mut meta.title), ("artist", &mut meta.artist),
+            ("album", &mut meta.album), ("genre", &mut meta.genre),
+        ];
+        for (field, value) in fields {
+            if let Some(fixed) = value.as_deref().and_then(fix_mojibake) {
+                fixes.push(TagFix { track: track.clone(), field, old: value.clone(), new: fixed.clone() });
+                *value = Some(fixed);
+            }
+        }
+
+        if meta.title.as_deref().is_none_or(|t| t.trim().is_empty()) {
+            let stem = track.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            let (artist, title) = infer_from_stem(&stem);
+            // A title identical to the file name adds nothing: the voiceover already falls back to it
+            if artist.is_none() && title == stem { continue; }
+            if meta.artist.as_deref().is_none_or(|a| a.trim().is_empty()) {
+                if let Some(artist) = artist {
+                    fixes.push(TagFix { track: track.clone(), field: "artist", old: meta.artist.clone(), new: artist.clone() });
+                    meta.artist = Some(artist);
+                }
+            }
+            fixes.push(TagFix { track: track.clone(), field: "title", old: meta.title.clone(), new: title.clone() });
+            meta.title = Some(title);
+            // Inferred names feed the voiceover text and album/artist IDs like real tags
+            meta.has_tag = true;
+        }
+    }
+    for f in &fixes {
+        debug!("[~] 修正标签 {} {}: {:?} -> {:?}", f.track.display(), f.field, f.old.as_deref().unwrap_or(""), f.new);
+    }
+    fixes
+}
+
+fn write_fix(track: &Path, fixes: &[&TagFix]) -> Result<(), String> {
+    let mut tagged = lofty::read_from_path(track).map_err(|e| e.to_string())?;
+    let tag_type = tagged.primary_tag_type();
+    if tagged.primary_tag().is_none() {
+        tagged.insert_tag(Tag::new(tag_type));
+    }
+    let tag = tagged.primary_tag_mut().ok_or("无法创建标签")?;
+    for fix in fixes {
+        match fix.field {
+            "title" => tag.set_title(fix.new.clone()),
+            "artist" => tag.set_artist(fix.new.clone()),
+            "album" => tag.set_album(fix.new.clone()),
+            "genre" => tag.set_genre(fix.new.clone()),
+            _ => {}
+        }
+    }
+    unshare(track).map_err(|e| e.to_string())?;
+    tag.save_to_path(track, WriteOptions::default()).map_err(|e| e.to_string())?;
+    sync_file(track).map_err(|e| e.to_string())
+}
+
+/// 将修正写回文件，返回写入失败的 (文件, 原因)。调用方只传入设备上的文件
+pub fn write_fixes(fixes: &[&TagFix]) -> Vec<(PathBuf, String)> {
+    let mut by_track: HashMap<&Path, Vec<&TagFix>> = HashMap::new();
+    for &f in fixes {
+        by_track.entry(f.track.as_path()).or_default().push(f);
+    }
+    let mut failures = Vec::new();
+    for (track, fixes) in by_track {
+        if let Err(e) = write_fix(track, &fixes) {
+            failures.push((track.to_path_buf(), e));
+        }
+    }
+    failures
+}
@@ -0,0 +1,176 @@
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use crate::i18n::tr;
+
+// ─── Sync tasks for frontends ────────────────────────────────────────────────
+
+/// 检查子进程是否已退出的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 一个阶段（读取标签、转换、曲目旁白、写入数据库等）的进度，来自 --progress-json
+#[derive(Clone)]
+pub struct PhaseProgress {
+    /// 稳定的阶段名，如 "convert"
+    pub phase: String,
+    /// 按 --lang 翻译的阶段名称
+    pub label: String,
+    pub pos: u64,
+    /// 无法预知总量的阶段为 `None`
+    pub total: Option<u64>,
+    pub done: bool,
+}
+
+impl PhaseProgress {
+    /// 解析 --progress-json 输出的一行，不是进度的行返回 `None`
+    fn parse(line: &str) -> Option<Self> {
+        let value: serde_json::Value = serde_json::from_str(line).ok()?;
+        let p = value.get("progress")?;
+        Some(PhaseProgress {
+            phase: p.get("phase")?.as_str()?.to_string(),
+            label: p.get("label")?.as_str()?.to_string(),
+            pos: p.get("pos")?.as_u64()?,
+            total: p.get("total").and_then(|t| t.as_u64()),
+            done: p.get("done")?.as_bool()?,
+        })
+    }
+}
+
+/// 演练时列出的、真正同步前需要用户确认的删除（见 `confirm::preview`）
+#[derive(Clone)]
+pub struct Confirmation {
+    pub title: String,
+    pub items: Vec<String>,
+}
+
+impl Confirmation {
+    /// 解析演练输出的一行，不是确认内容的行返回 `None`
+    fn parse(line: &str) -> Option<Self> {
+        let value: serde_json::Value = serde_json::from_str(line).ok()?;
+        let c = value.get("confirm")?;
+        Some(Confirmation {
+            title: c.get("title")?.as_str()?.to_string(),
+            items: c.get("items")?.as_array()?.iter().filter_map(|i| Some(i.as_str()?.to_string())).collect(),
+        })
+    }
+}
+
+/// 任务的当前状态
+#[derive(Default)]
+pub struct TaskState {
+    /// 子进程输出的文字，不含进度行
+    pub log: Vec<String>,
+    /// 按开始的顺序排列的各阶段进度
+    pub phases: Vec<PhaseProgress>,
+    /// 子进程退出后为其退出状态
+    pub status: Option<Result<ExitStatus, String>>,
+    pub cancelled: bool,
+    /// 演练时为真正同步将要请求确认的删除，没有需要确认的内容时为 `None`
+    pub confirm: Option<Confirmation>,
+}
+
+impl TaskState {
+    fn update(&mut self, progress: PhaseProgress) {
+        match self.phases.iter_mut().find(|p| p.phase == progress.phase && !p.done) {
+            Some(p) => *p = progress,
+            None => self.phases.push(progress),
+        }
+    }
+}
+
+/// 在子进程中运行的一次同步（或其他子命令）。与命令行的行为完全一致，出错退出也不影响调用方；
+/// 各阶段的进度通过 --progress-json 读取，可以随时取消
+pub struct SyncTask {
+    state: Arc<Mutex<TaskState>>,
+    child: Arc<Mutex<Child>>,
+}
+
+impl SyncTask {
+    /// 以 `args` 运行 `program`（本程序的可执行文件），每当有新的输出、进度或任务结束时调用 `on_update`
+    /// （如请求界面重绘）
+    pub fn spawn(program: &Path, args: &[String], on_update: impl Fn() + Send + Sync + 'static) -> Result<Self, String> {
+        let mut child = Command::new(program)
+            .args(args)
+            .arg("--progress-json")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| tr!("无法启动同步: {}", e))?;
+        let state = Arc::new(Mutex::new(TaskState::default()));
+        let on_update = Arc::new(on_update);
+        let forward = |stream: Box<dyn Read + Send>| {
+            let state = state.clone();
+            let on_update = on_update.clone();
+            thread::spawn(move || {
+                for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                    let mut state = state.lock().unwrap();
+                    if let Some(progress) = PhaseProgress::parse(&line) {
+                        state.update(progress);
+                    } else if let Some(confirm) = Confirmation::parse(&line) {
+                        state.confirm = Some(confirm);
+                    } else {
+                        state.log.push(line);
+                    }
+                    drop(state);
+                    on_update();
+                }
+            })
+        };
+        let readers = [
+            child.stdout.take().map(|s| forward(Box::new(s))),
+            child.stderr.take().map(|s| forward(Box::new(s))),
+        ];
+
+        let child = Arc::new(Mutex::new(child));
+        let waiter = (state.clone(), child.clone());
+        thread::spawn(move || {
+            let (state, child) = waiter;
+            // Polled rather than waited on, so cancel() can still reach the child
+            let status = loop {
+                match child.lock().unwrap().try_wait() {
+                    Ok(Some(status)) => break Ok(status),
+                    Ok(None) => {}
+                    Err(e) => break Err(e.to_string()),
+                }
+                thread::sleep(POLL_INTERVAL);
+            };
+            for reader in readers.into_iter().flatten() {
+                let _ = reader.join();
+            }
+            state.lock().unwrap().status = Some(status);
+            on_update();
+        });
+        Ok(SyncTask { state, child })
+    }
+
+    /// 当前的输出、进度与退出状态
+    pub fn state(&self) -> MutexGuard<'_, TaskState> {
+        self.state.lock().unwrap()
+    }
+
+    pub fn running(&self) -> bool {
+        self.state().status.is_none()
+    }
+
+    /// 请求停止，与在命令行按下 Ctrl+C 相同：进行中的转换与旁白完成后在安全的位置退出，
+    /// 不留下写了一半的文件，设备上的数据库保持不变，检查点留给下次同步续传。再次调用时立即退出。
+    /// Windows 上无法向子进程发送 Ctrl+C，只能直接结束子进程，写了一半的文件由下次同步清理
+    pub fn cancel(&self) {
+        if !self.running() { return; }
+        self.state().cancelled = true;
+        let child = &mut *self.child.lock().unwrap();
+        #[cfg(unix)]
+        {
+            use nix::sys::signal::{kill, Signal};
+            use nix::unistd::Pid;
+            let _ = kill(Pid::from_raw(child.id() as i32), Signal::SIGINT);
+        }
+        #[cfg(not(unix))]
+        let _ = child.kill();
+    }
+}
@@ -0,0 +1,251 @@
+use crate::outln;
+use lofty::prelude::*;
+use regex::Regex;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+use crate::device_io::write_file;
+use crate::progress;
+use crate::tts::synthesize_mp3;
+use crate::utils::ext_lower;
+
+// ─── Text-to-audiobook ───────────────────────────────────────────────────────
+
+/// 由文本合成的有声书所在目录（位于 iPod_Control 下，扫描时跳过）
+pub const TEXTBOOK_DIR: &str = "TextBooks";
+
+/// 可以合成为有声书的文本格式
+pub const TEXT_EXT: &[&str] = &[".txt", ".epub"];
+
+/// 单次 TTS 请求的最大字符数
+const CHUNK_CHARS: usize = 1500;
+
+/// Edge TTS 输出为 48kbps CBR，可以由字节数直接换算时长
+const TTS_BYTES_PER_MS: u64 = 48 / 8;
+
+/// 记录生成参数的标记文件，源文件与参数未变时沿用已有分段
+const STAMP_FILE: &str = ".textbook";
+
+/// 从文本文件读取的书籍，章节按阅读顺序排列
+pub struct Book {
+    pub title: String,
+    pub author: Option<String>,
+    pub chapters: Vec<String>,
+}
+
+fn decode_entities(s: &str) -> String {
+    s.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// 将 XHTML 章节转为纯文本，块级元素之间保留换行
+fn xhtml_to_text(html: &str) -> String {
+    let head = Regex::new(r"(?is)<head.*?</head>|<script.*?</script>|<style.*?</style>").unwrap();
+    let block = Regex::new(r"(?i)</?(p|div|br|h[1-6]|li|tr|blockquote)\b[^>]*>").unwrap();
+    let tag = Regex::new(r"(?s)<[^>]*>").unwrap();
+    let s = head.replace_all(html, "");
+    let s = block.replace_all(&s, "\n");
+    let s = tag.replace_all(&s, "");
+    decode_entities(&s)
+}
+
+fn read_zip_entry(archive: &mut zip::ZipArchive<fs::File>, name: &str) -> Result<String, String> {
+    let mut entry = archive.by_name(name).map_err(|e| format!("EPUB 中缺少 {}: {}", name, e))?;
+    let mut out = String::new();
+    entry.read_to_string(&mut out).map_err(|e| format!("无法读取 {}: {}", name, e))?;
+    Ok(out)
+}
+
+fn read_epub(path: &Path) -> Result<Book, String> {
+    let file = fs::File::open(path).map_err(|e| format!("无法打开文件: {}", e))?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("无效的 EPUB: {}", e))?;
+
+    let container = read_zip_entry(&mut archive, "META-INF/container.xml")?;
+    let rootfile = Regex::new(r#"full-path\s*=\s*"([^"]+)""#).unwrap()
+        .captures(&container)
+        .map(|c| c[1].to_string())
+        .ok_or("EPUB 缺少 rootfile")?;
+    let opf = read_zip_entry(&mut archive, &rootfile)?;
+    let opf_dir = match rootfile.rfind('/') {
+        Some(i) => &rootfile[..=i],
+        None => "",
+    };
+
+    let dc = |name: &str| {
+        Regex::new(&format!(r"(?is)<dc:{0}[^>]*>(.*?)</dc:{0}>", name)).unwrap()
+            .captures(&opf)
+            .map(|c| decode_entities(c[1].trim()))
+            .filter(|s| !s.is_empty())
+    };
+    let title = dc("title").unwrap_or_else(|| path.file_stem().unwrap_or_default().to_string_lossy().to_string());
+    let author = dc("creator");
+
+    let attr = |tag: &str, name: &str| {
+        Regex::new(&format!(r#"\b{}\s*=\s*"([^"]*)""#, name)).unwrap()
+            .captures(tag)
+            .map(|c| c[1].to_string())
+    };
+    let mut manifest = std::collections::HashMap::new();
+    for m in Regex::new(r"(?s)<item\b[^>]*>").unwrap().find_iter(&opf) {
+        if let (Some(id), Some(href)) = (attr(m.as_str(), "id"), attr(m.as_str(), "href")) {
+            manifest.insert(id, href);
+        }
+    }
+
+    let mut chapters = Vec::new();
+    for m in Regex::new(r"(?s)<itemref\b[^>]*>").unwrap().find_iter(&opf) {
+        let Some(href) = attr(m.as_str(), "idref").and_then(|id| manifest.get(&id).cloned()) else { continue };
+        let href = percent_encoding::percent_decode_str(&href).decode_utf8_lossy().to_string();
+        let text = xhtml_to_text(&read_zip_entry(&mut archive, &format!("{}{}", opf_dir, href))?);
+        if !text.trim().is_empty() {
+            chapters.push(text);
+        }
+    }
+
+    Ok(Book { title, author, chapters })
+}
+
+/// 读取 `.txt` 或 `.epub` 文件
+pub fn read_book(path: &Path) -> Result<Book, String> {
+    if ext_lower(path) == ".epub" {
+        return read_epub(path);
+    }
+    let bytes = fs::read(path).map_err(|e| format!("无法读取文件: {}", e))?;
+    let text = String::from_utf8_lossy(&bytes);
+    let text = text.strip_prefix('\u{feff}').unwrap_or(&text).to_string();
+    Ok(Book {
+        title: path.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+        author: None,
+        chapters: vec![text],
+    })
+}
+
+/// 将文本按句子切分为不超过 `max_chars` 个字符的 TTS 请求
+pub fn split_chunks(text: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut sentence = String::new();
+
+    let flush = |current: &mut String, chunks: &mut Vec<String>| {
+        let trimmed = current.trim();
+        if !trimmed.is_empty() {
+            chunks.push(trimmed.to_string());
+        }
+        current.clear();
+    };
+
+    for c in text.chars() {
+        sentence.push(if c.is_whitespace() { ' ' } else { c });
+        let boundary = matches!(c, '。' | '！' | '？' | '.' | '!' | '?' | '\n' | '；' | ';');
+        if boundary || sentence.chars().count() >= max_chars {
+            if current.chars().count() + sentence.chars().count() > max_chars {
+                flush(&mut current, &mut chunks);
+            }
+            current.push_str(&sentence);
+            sentence.clear();
+        }
+    }
+    if current.chars().count() + sentence.chars().count() > max_chars {
+        flush(&mut current, &mut chunks);
+    }
+    current.push_str(&sentence);
+    flush(&mut current, &mut chunks);
+    chunks
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn modified(path: &Path) -> Option<std::time::SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn write_part_tags(path: &Path, book: &Book, part: usize) {
+    let Ok(mut tagged) = lofty::read_from_path(path) else { return };
+    if tagged.primary_tag().is_none() {
+        tagged.insert_tag(lofty::tag::Tag::new(lofty::tag::TagType::Id3v2));
+    }
+    if let Some(tag) = tagged.primary_tag_mut() {
+        tag.set_title(format!("{} {:03}", book.title, part));
+        tag.set_album(book.title.clone());
+        tag.set_artist(book.author.clone().unwrap_or_else(|| "TTS".to_string()));
+        tag.set_genre("Audiobook".to_string());
+        tag.set_track(part as u32);
+    }
+    let _ = tagged.save_to_path(path, lofty::config::WriteOptions::default());
+}
+
+/// 将文本文件合成为有声书：每章从新的分段开始，分段累积到约 `part_ms` 毫秒后切分。
+/// 分段写入 `out_dir`，返回按顺序排列的分段路径
+pub fn generate_audiobook(src: &Path, out_dir: &Path, part_ms: u64) -> Result<Vec<PathBuf>, String> {
+    let stem = src.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let part_path = |i: usize| out_dir.join(format!("{} {:03}.mp3", stem, i));
+
+    // Reuse the previous run's parts unless the text or the part length changed
+    let stamp_path = out_dir.join(STAMP_FILE);
+    if let Ok(stamp) = fs::read_to_string(&stamp_path) {
+        let mut fields = stamp.split_whitespace();
+        let same_part = fields.next() == Some(&format!("part={}", part_ms));
+        let count = fields.next().and_then(|f| f.strip_prefix("count=")).and_then(|n| n.parse::<usize>().ok());
+        if let (true, Some(count)) = (same_part, count) {
+            let outputs: Vec<PathBuf> = (1..=count).map(part_path).collect();
+            if modified(&stamp_path) >= modified(src) && outputs.iter().all(|o| o.exists()) {
+                debug!("有声书已是最新: {}", stem);
+                return Ok(outputs);
+            }
+        }
+    }
+
+    let book = read_book(src)?;
+    let chapters: Vec<Vec<String>> = book.chapters.iter()
+        .map(|c| split_chunks(c, CHUNK_CHARS))
+        .filter(|c| !c.is_empty())
+        .collect();
+    let total: usize = chapters.iter().map(|c| c.len()).sum();
+    if total == 0 {
+        return Err("文本为空".to_string());
+    }
+
+    let _ = fs::remove_dir_all(out_dir);
+    fs::create_dir_all(out_dir).map_err(|e| format!("无法创建目录: {}", e))?;
+
+    outln!("正在合成有声书 \"{}\"（{} 章）...", book.title, chapters.len());
+    let mut outputs = Vec::new();
+    let mut part: Vec<u8> = Vec::new();
+    let bar = progress::bar("合成", total);
+    let write_part = |part: &mut Vec<u8>, outputs: &mut Vec<PathBuf>| -> Result<(), String> {
+        if part.is_empty() { return Ok(()); }
+        let path = part_path(outputs.len() + 1);
+        write_file(&path, &*part).map_err(|e| format!("写入 MP3 失败: {}", e))?;
+        write_part_tags(&path, &book, outputs.len() + 1);
+        outputs.push(path);
+        part.clear();
+        Ok(())
+    };
+
+    for chapter in &chapters {
+        write_part(&mut part, &mut outputs)?;
+        for chunk in chapter {
+            let mp3 = synthesize_mp3(&escape_xml(chunk)).map_err(|e| format!("语音合成失败: {}", e))?;
+            part.extend_from_slice(&mp3);
+            if part.len() as u64 / TTS_BYTES_PER_MS >= part_ms {
+                write_part(&mut part, &mut outputs)?;
+            }
+            bar.inc(1);
+        }
+    }
+    write_part(&mut part, &mut outputs)?;
+    bar.finish_and_clear();
+
+    write_file(&stamp_path, format!("part={} count={}\n", part_ms, outputs.len()))
+        .map_err(|e| format!("写入标记文件失败: {}", e))?;
+    Ok(outputs)
+}
@@ -0,0 +1,116 @@
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+// ─── Per-task time limits ────────────────────────────────────────────────────
+
+/// 受时限保护的任务未能正常完成的原因
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskError {
+    TimedOut,
+    Panicked,
+}
+
+impl TaskError {
+    pub fn describe(&self, what: &str) -> String {
+        match self {
+            TaskError::TimedOut => format!("{}超时", what),
+            TaskError::Panicked => format!("{}时崩溃", what),
+        }
+    }
+}
+
+/// 在独立线程中运行 `f`，最多等待 `limit`。
+///
+/// 线程无法被强制终止：超时后任务会在后台继续运行直到自行结束，但调用者不再等待它，
+/// 因此单个卡住的文件不会拖住整个线程池
+pub fn run_with_timeout<T, F>(limit: Duration, f: F) -> Result<T, TaskError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    let spawned = thread::Builder::new().spawn(move || {
+        let _ = tx.send(f());
+    });
+    if spawned.is_err() {
+        return Err(TaskError::Panicked);
+    }
+    match rx.recv_timeout(limit) {
+        Ok(v) => Ok(v),
+        Err(mpsc::RecvTimeoutError::Timeout) => Err(TaskError::TimedOut),
+        // The sender is dropped without sending when the task panics
+        Err(mpsc::RecvTimeoutError::Disconnected) => Err(TaskError::Panicked),
+    }
+}
+
+/// 带时限地运行任务，超时或崩溃时放弃该次尝试并重试，最多重试 `retries` 次。
+/// `retry_on_error` 为 `true` 时任务返回的错误也会重试（如网络请求）
+pub fn run_with_retry<T, F>(limit: Duration, retries: u32, retry_on_error: bool, what: &str, f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: Fn() -> Result<T, String> + Clone + Send + 'static,
+{
+    let mut last = String::new();
+    for attempt in 0..=retries {
+        if attempt > 0 {
+            debug!("{}失败，正在重试 ({}/{}): {}", what, attempt, retries, last);
+        }
+        match run_with_timeout(limit, f.clone()) {
+            Ok(Ok(v)) => return Ok(v),
+            Ok(Err(e)) if !retry_on_error => return Err(e),
+            Ok(Err(e)) => last = e,
+            Err(e) => last = e.describe(what),
+        }
+    }
+    Err(last)
+}
+
+/// 检查外部程序是否已退出的间隔
+const PROCESS_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// 运行外部程序，`input` 写入其标准输入，超过 `limit` 时结束它。
+/// 与线程不同，超时的进程会被真正终止。非零退出时以标准错误的最后一行作为失败原因
+pub fn run_process(mut command: Command, input: Option<&[u8]>, limit: Duration) -> Result<(), String> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let mut child = command
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("无法运行 {}: {}", program, e))?;
+    // A program that never reads its input still gets a broken pipe rather than a hang
+    if let (Some(mut stdin), Some(input)) = (child.stdin.take(), input) {
+        let _ = stdin.write_all(input);
+    }
+    let stderr = child.stderr.take().map(|mut s| thread::spawn(move || {
+        let mut text = String::new();
+        let _ = s.read_to_string(&mut text);
+        text
+    }));
+
+    let deadline = Instant::now() + limit;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => thread::sleep(PROCESS_POLL_INTERVAL),
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("{} 超时", program));
+            }
+            Err(e) => return Err(e.to_string()),
+        }
+    };
+    let stderr = stderr.and_then(|t| t.join().ok()).unwrap_or_default();
+    if !status.success() {
+        return Err(match stderr.lines().rev().map(str::trim).find(|l| !l.is_empty()) {
+            Some(detail) => format!("{} 以 {} 退出: {}", program, status, detail),
+            None => format!("{} 以 {} 退出", program, status),
+        });
+    }
+    Ok(())
+}
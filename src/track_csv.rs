@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::database::{
+    dbid_to_filename, make_dbid, podcast_voiceover_text, read_device_db, track_voiceover_text,
+};
+use crate::metadata::{apply_meta_sidecars, read_all_meta, TrackMeta};
+use crate::search::{candidate_names, playlist_labels};
+use crate::tts::DEFAULT_TTS_VOICE;
+use crate::utils::ext_lower;
+
+// ─── Track metadata tables ───────────────────────────────────────────────────
+
+/// 导出时读取单个文件标签的时限
+const TAG_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 导出表格的列。读回时只使用 path、title、gain 与 voiceover，其余列仅供查看
+const COLUMNS: &[&str] = &[
+    "path", "title", "artist", "album", "genre", "track", "disc", "duration", "gain", "dbid", "voiceover", "playlists",
+];
+
+/// 一首曲目所在的多个播放列表在同一单元格中的分隔符
+const PLAYLIST_SEPARATOR: &str = "; ";
+
+/// 表格中一首曲目的覆盖值，空单元格表示沿用标签或同步选项
+#[derive(Default)]
+pub struct TrackOverride {
+    pub title: Option<String>,
+    pub gain: Option<u32>,
+    pub voiceover: Option<String>,
+}
+
+/// 单元格含有分隔符、引号或换行时加上引号，引号写成两个
+fn quote(field: &str, delimiter: char) -> String {
+    if field.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// 解析 CSV/TSV 文本，支持带引号的单元格（可含分隔符与换行）
+fn parse_records(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => quoted = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => quoted = true,
+            '\r' => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c if c == delimiter => record.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records.retain(|r| r.iter().any(|f| !f.trim().is_empty()));
+    records
+}
+
+/// 由表头判断分隔符：制表符、逗号或分号（部分地区的电子表格软件以分号保存 CSV）
+fn detect_delimiter(header: &str) -> char {
+    ['\t', ',', ';'].into_iter()
+        .max_by_key(|&d| header.matches(d).count())
+        .filter(|&d| header.contains(d))
+        .unwrap_or(',')
+}
+
+/// 旁白朗读的文字：按元数据文件或标签推算，能还原数据库中的 dbid 时才填写
+fn spoken_text(path: &Path, meta: &TrackMeta, dbid: &[u8; 8]) -> String {
+    [meta.voiceover.clone(), Some(track_voiceover_text(path, meta)), podcast_voiceover_text(meta, DEFAULT_TTS_VOICE)]
+        .into_iter()
+        .flatten()
+        .find(|text| make_dbid(text.as_bytes()) == *dbid)
+        .unwrap_or_default()
+}
+
+/// `export-csv`：将现有 iTunesSD 中的每首曲目连同标签、时长、增益、dbid、旁白文字
+/// 与所在播放列表写成表格。`out` 为 `None` 时写到标准输出；`tsv` 为 `true` 或输出文件
+/// 以 ".tsv" 结尾时以制表符分隔。返回导出的曲目数
+pub fn export_tracks(base: &Path, out: Option<&Path>, tsv: bool) -> Result<usize, String> {
+    let db = read_device_db(base)?;
+    let paths: Vec<PathBuf> = db.tracks.iter().map(|t| base.join(t.filename.trim_start_matches('/'))).collect();
+    let (mut metas, _) = read_all_meta(&paths, TAG_TIMEOUT);
+    apply_meta_sidecars(&paths, &mut metas);
+    let labels = playlist_labels(&db, &candidate_names(base, &metas));
+    let delimiter = if tsv || out.is_some_and(|o| ext_lower(o) == ".tsv") { '\t' } else { ',' };
+
+    let mut text = String::new();
+    // Spreadsheet programs only read a UTF-8 CSV as UTF-8 when it starts with a BOM
+    if out.is_some() && delimiter == ',' {
+        text.push('\u{feff}');
+    }
+    let separator = delimiter.to_string();
+    text.push_str(&COLUMNS.join(&separator));
+    text.push('\n');
+    for (i, (track, path)) in db.tracks.iter().zip(&paths).enumerate() {
+        let meta = metas.get(path).cloned().unwrap_or_default();
+        let playlists: Vec<&str> = db.playlists.iter().zip(&labels)
+            .filter(|(p, _)| p.track_indices.contains(&(i as u32)))
+            .map(|(_, label)| label.as_str())
+            .collect();
+        let duration_ms = track.stop_at_pos_ms.saturating_sub(track.start_at_pos_ms);
+        let number = |n: Option<u32>| n.map(|n| n.to_string()).unwrap_or_default();
+        let row = [
+            track.filename.clone(),
+            meta.title.clone().unwrap_or_default(),
+            meta.artist.clone().unwrap_or_default(),
+            meta.album.clone().unwrap_or_default(),
+            meta.genre.clone().unwrap_or_default(),
+            number(meta.track),
+            number(meta.disc),
+            format!("{:.1}", duration_ms as f64 / 1000.0),
+            track.volume_gain.to_string(),
+            dbid_to_filename(&track.dbid),
+            spoken_text(path, &meta, &track.dbid),
+            playlists.join(PLAYLIST_SEPARATOR),
+        ];
+        let row: Vec<String> = row.iter().map(|f| quote(f, delimiter)).collect();
+        text.push_str(&row.join(&separator));
+        text.push('\n');
+    }
+
+    match out {
+        Some(out) => fs::write(out, text).map_err(|e| format!("无法写入 {}: {}", out.display(), e))?,
+        None => print!("{}", text),
+    }
+    Ok(db.tracks.len())
+}
+
+/// 读取 export-csv 导出并编辑过的表格，按 iPod 路径（如 "/iPod_Control/Music/a.mp3"）
+/// 返回每首曲目的覆盖值。必须有 path 列；title、gain、voiceover 列可以删去
+pub fn load_overrides(file: &Path) -> Result<HashMap<String, TrackOverride>, String> {
+    let text = fs::read_to_string(file).map_err(|e| format!("无法读取 {}: {}", file.display(), e))?;
+    let text = text.trim_start_matches('\u{feff}');
+    let delimiter = detect_delimiter(text.lines().next().unwrap_or_default());
+    let mut records = parse_records(text, delimiter).into_iter();
+    let header = records.next().unwrap_or_default();
+    let column = |name: &str| header.iter().position(|h| h.trim().eq_ignore_ascii_case(name));
+    let path_col = column("path").ok_or_else(|| format!("{} 缺少 path 列", file.display()))?;
+    let (title_col, gain_col, voiceover_col) = (column("title"), column("gain"), column("voiceover"));
+
+    let mut overrides = HashMap::new();
+    for (n, record) in records.enumerate() {
+        let cell = |col: Option<usize>| col.and_then(|c| record.get(c)).map(|v| v.trim()).filter(|v| !v.is_empty());
+        let Some(path) = cell(Some(path_col)) else { continue };
+        // The header is line 1
+        let line = n + 2;
+        let gain = match cell(gain_col) {
+            None => None,
+            Some(g) => match g.parse::<u32>() {
+                Ok(g) if g <= 99 => Some(g),
+                _ => return Err(format!("{} 第 {} 行: 无效的增益 \"{}\"，应为 0-99", file.display(), line, g)),
+            },
+        };
+        let path = format!("/{}", path.replace('\\', "/").trim_start_matches('/'));
+        overrides.insert(path, TrackOverride {
+            title: cell(title_col).map(str::to_string),
+            gain,
+            voiceover: cell(voiceover_col).map(str::to_string),
+        });
+    }
+    Ok(overrides)
+}
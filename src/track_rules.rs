@@ -0,0 +1,120 @@
+use std::path::{Path, PathBuf};
+
+use crate::metadata::TrackMeta;
+use crate::utils::PathIdentity;
+
+// ─── Per-folder and per-genre track options ──────────────────────────────────
+
+/// iTunesSD 曲目记录中默认的 pregap/postgap
+pub const DEFAULT_GAP: u32 = 0x200;
+
+/// 配置文件中按目录设置选项的表，如 `[folder."Music/Live"]`，目录相对 iPod 根目录
+pub const FOLDER_TABLE: &str = "folder";
+
+/// 配置文件中按流派设置选项的表，如 `[genre."Meditation"]`，流派不区分大小写
+pub const GENRE_TABLE: &str = "genre";
+
+/// 某个目录中曲目的选项，未设置的项沿用上级目录、流派或全局选项
+struct FolderOptions {
+    dir: PathBuf,
+    pregap: Option<u32>,
+    postgap: Option<u32>,
+    shuffle: Option<bool>,
+}
+
+/// 每首曲目的 pregap/postgap 与是否参与随机播放（dontskip）：
+/// 全局选项加上配置文件中按目录、按流派的覆盖
+pub struct TrackRules {
+    pregap: u32,
+    postgap: u32,
+    /// 按目录深度从深到浅排列，较深的目录优先
+    folders: Vec<FolderOptions>,
+    /// (小写的流派, 是否参与随机播放)
+    genres: Vec<(String, bool)>,
+    identity: PathIdentity,
+}
+
+fn gap_value(dir: &str, key: &str, value: &toml::Value) -> Result<u32, String> {
+    value.as_integer()
+        .and_then(|v| u32::try_from(v).ok())
+        .ok_or_else(|| format!("[{}.\"{}\"] 中 \"{}\" 必须是非负整数", FOLDER_TABLE, dir, key))
+}
+
+fn bool_value(table: &str, name: &str, key: &str, value: &toml::Value) -> Result<bool, String> {
+    value.as_bool().ok_or_else(|| format!("[{}.\"{}\"] 中 \"{}\" 必须是 true 或 false", table, name, key))
+}
+
+/// 配置文件中名为 `name` 的表，每项也必须是表
+fn sub_tables<'a>(config: Option<&'a toml::Table>, name: &str) -> Result<Vec<(&'a String, &'a toml::Table)>, String> {
+    let Some(tables) = config.and_then(|c| c.get(name)) else { return Ok(Vec::new()) };
+    let tables = tables.as_table().ok_or_else(|| format!("[{}] 必须是一个表", name))?;
+    tables.iter()
+        .map(|(key, table)| {
+            table.as_table()
+                .map(|t| (key, t))
+                .ok_or_else(|| format!("[{}.\"{}\"] 必须是一个表", name, key))
+        })
+        .collect()
+}
+
+impl TrackRules {
+    /// 所有曲目都使用同样的 pregap/postgap 并参与随机播放
+    pub fn global(identity: PathIdentity, pregap: u32, postgap: u32) -> Self {
+        TrackRules { pregap, postgap, folders: Vec::new(), genres: Vec::new(), identity }
+    }
+
+    /// 读取配置文件中 `[folder."<目录>"]` 表的 `pregap`、`postgap`、`shuffle`
+    /// 与 `[genre."<流派>"]` 表的 `shuffle`
+    pub fn new(
+        base: &Path, identity: PathIdentity, pregap: u32, postgap: u32, config: Option<&toml::Table>,
+    ) -> Result<Self, String> {
+        let mut folders = Vec::new();
+        for (dir, table) in sub_tables(config, FOLDER_TABLE)? {
+            let mut opts = FolderOptions { dir: identity.key(&base.join(dir)), pregap: None, postgap: None, shuffle: None };
+            for (key, value) in table {
+                match key.as_str() {
+                    "pregap" => opts.pregap = Some(gap_value(dir, key, value)?),
+                    "postgap" => opts.postgap = Some(gap_value(dir, key, value)?),
+                    "shuffle" => opts.shuffle = Some(bool_value(FOLDER_TABLE, dir, key, value)?),
+                    _ => return Err(format!("[{}.\"{}\"]: 未知的目录选项 \"{}\"", FOLDER_TABLE, dir, key)),
+                }
+            }
+            folders.push(opts);
+        }
+        folders.sort_by_key(|f| std::cmp::Reverse(f.dir.components().count()));
+
+        let mut genres = Vec::new();
+        for (genre, table) in sub_tables(config, GENRE_TABLE)? {
+            for (key, value) in table {
+                match key.as_str() {
+                    "shuffle" => genres.push((genre.trim().to_lowercase(), bool_value(GENRE_TABLE, genre, key, value)?)),
+                    _ => return Err(format!("[{}.\"{}\"]: 未知的流派选项 \"{}\"", GENRE_TABLE, genre, key)),
+                }
+            }
+        }
+        Ok(TrackRules { pregap, postgap, folders, genres, identity })
+    }
+
+    fn matching_folders<'a>(&'a self, track: &Path) -> impl Iterator<Item = &'a FolderOptions> {
+        let key = self.identity.key(track);
+        self.folders.iter().filter(move |f| key.starts_with(&f.dir))
+    }
+
+    /// 曲目的 (pregap, postgap)
+    pub fn gaps(&self, track: &Path) -> (u32, u32) {
+        (
+            self.matching_folders(track).find_map(|f| f.pregap).unwrap_or(self.pregap),
+            self.matching_folders(track).find_map(|f| f.postgap).unwrap_or(self.postgap),
+        )
+    }
+
+    /// 曲目是否参与随机播放，即记录中的 dontskip。目录的设置优先于流派，默认参与
+    pub fn shuffle(&self, track: &Path, meta: &TrackMeta) -> bool {
+        if let Some(shuffle) = self.matching_folders(track).find_map(|f| f.shuffle) {
+            return shuffle;
+        }
+        let genre = meta.genre.as_deref().map(|g| g.trim().to_lowercase());
+        genre.and_then(|g| self.genres.iter().find(|(name, _)| *name == g).map(|&(_, s)| s))
+            .unwrap_or(true)
+    }
+}
@@ -1,6 +1,16 @@
-use crate::vprintln;
-use std::io;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tracing::{debug, error, warn};
+
+use crate::cancel;
+use crate::device_io::{dry_run, write_file};
+use crate::events;
+use crate::timeout::run_with_retry;
+use crate::tts_engine::{synthesize_wav, TtsEngine};
 use symphonia::core::audio::SampleBuffer;
 use symphonia::core::codecs::DecoderOptions;
 use symphonia::core::formats::FormatOptions;
@@ -10,35 +20,432 @@ use symphonia::core::probe::Hint;
 
 // ─── Text-to-Speech (Edge TTS via msedge-tts crate) ─────────────────────────
 
-const TTS_VOICE: &str = "zh-CN-XiaoxiaoNeural";
+pub const DEFAULT_TTS_VOICE: &str = "zh-CN-XiaoxiaoNeural";
+
+/// 常用的 Edge TTS 语音，供 shell 补全提示；--tts-voice 也接受其他任何语音名称
+pub const COMMON_TTS_VOICES: &[&str] = &[
+    "zh-CN-XiaoxiaoNeural", "zh-CN-XiaoyiNeural", "zh-CN-YunxiNeural", "zh-CN-YunjianNeural",
+    "zh-CN-YunyangNeural", "zh-TW-HsiaoChenNeural", "zh-TW-YunJheNeural", "zh-HK-HiuMaanNeural",
+    "en-US-AriaNeural", "en-US-JennyNeural", "en-US-GuyNeural", "en-GB-SoniaNeural",
+    "ja-JP-NanamiNeural", "ko-KR-SunHiNeural", "de-DE-KatjaNeural", "fr-FR-DeniseNeural",
+    "es-ES-ElviraNeural",
+];
+
+/// 语音合成的全局设置
+pub struct TtsSettings {
+    /// 语音旁白的合成引擎。由文本合成的有声书总是使用 Edge TTS
+    pub engine: TtsEngine,
+    /// 原样传给外部引擎的 JSON 配置
+    pub engine_config: serde_json::Value,
+    /// 语音名称，Edge TTS 的语音或外部引擎自行解释的名称
+    pub voice: String,
+    /// 单次网络请求的时限
+    pub timeout: Duration,
+    /// 超时或失败后的重试次数
+    pub retries: u32,
+    /// 单次同步最多合成的旁白条数，超出的留待 --tts-resume 时继续
+    pub limit: Option<usize>,
+}
+
+static TTS_SETTINGS: OnceLock<TtsSettings> = OnceLock::new();
+
+/// 设置语音合成参数，需在首次合成前调用
+pub fn configure(settings: TtsSettings) {
+    let _ = TTS_SETTINGS.set(settings);
+}
+
+fn settings() -> &'static TtsSettings {
+    TTS_SETTINGS.get_or_init(|| TtsSettings {
+        engine: TtsEngine::Edge,
+        engine_config: serde_json::Value::Object(Default::default()),
+        voice: DEFAULT_TTS_VOICE.to_string(),
+        timeout: Duration::from_secs(30),
+        retries: 2,
+        limit: None,
+    })
+}
+
+// ─── Voiceover progress across sessions ──────────────────────────────────────
+
+/// 记录已完整生成的旁白文件，供 --tts-resume 跨多次同步继续生成
+pub const PROGRESS_FILE: &str = "tts_progress.txt";
+
+/// 一次同步中旁白生成的进度
+struct Session {
+    base: PathBuf,
+    progress_path: PathBuf,
+    /// 已完整生成的旁白（相对 iPod 根目录的路径）及其语音
+    done: HashMap<String, String>,
+    /// 本次同步的数据库引用的旁白
+    referenced: HashSet<PathBuf>,
+    spoken: Vec<SpokenVoiceover>,
+    generated: usize,
+    deferred: usize,
+    /// 演练且不续传时 Speakable 并没有真正清空，现有的旁白文件视为不存在
+    ignore_existing: bool,
+}
+
+static SESSION: Mutex<Option<Session>> = Mutex::new(None);
+
+/// 本次同步中写入或沿用的一条旁白
+pub struct SpokenVoiceover {
+    pub path: PathBuf,
+    pub text: String,
+    pub voice: String,
+}
+
+/// 本次同步的旁白生成统计
+pub struct SessionSummary {
+    /// 本次请求合成的条数（含失败的）
+    pub generated: usize,
+    /// 因 --tts-limit 留到下次同步生成的条数
+    pub deferred: usize,
+    /// 续传时删除的、不再被数据库引用的旧旁白
+    pub removed: usize,
+    pub spoken: Vec<SpokenVoiceover>,
+}
+
+fn progress_key(base: &Path, path: &Path) -> String {
+    path.strip_prefix(base).unwrap_or(path).to_string_lossy().replace('\\', "/")
+}
+
+/// 开始记录旁白生成进度。`resume` 为 `true` 时沿用上次记录的进度，
+/// 否则清空记录（调用方会同时清空 Speakable 目录）
+pub fn begin_session(base: &Path, resume: bool) {
+    let progress_path = base.join("iPod_Control").join("iTunes").join(PROGRESS_FILE);
+    let mut done = HashMap::new();
+    if resume {
+        if let Ok(text) = fs::read_to_string(&progress_path) {
+            for line in text.lines() {
+                if let Some((file, voice)) = line.split_once('\t') {
+                    done.insert(file.to_string(), voice.to_string());
+                }
+            }
+        }
+        debug!("续传旁白: 上次已生成 {} 条", done.len());
+    } else if !dry_run() {
+        let _ = fs::remove_file(&progress_path);
+    }
+    *SESSION.lock().unwrap() = Some(Session {
+        base: base.to_path_buf(),
+        progress_path,
+        done,
+        referenced: HashSet::new(),
+        spoken: Vec::new(),
+        generated: 0,
+        deferred: 0,
+        ignore_existing: dry_run() && !resume,
+    });
+}
+
+/// 结束本次同步的进度记录。续传时删除 Speakable 中不再被引用的旧旁白，
+/// 并只保留仍被引用的进度条目；演练时只统计将被删除的旁白
+pub fn end_session(resume: bool) -> SessionSummary {
+    let Some(mut session) = SESSION.lock().unwrap().take() else {
+        return SessionSummary { generated: 0, deferred: 0, removed: 0, spoken: Vec::new() };
+    };
+    let mut removed = 0;
+    if resume {
+        let speakable = session.base.join("iPod_Control").join("Speakable");
+        for subdir in ["Playlists", "Tracks"] {
+            let Ok(entries) = fs::read_dir(speakable.join(subdir)) else { continue };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if session.referenced.contains(&path) { continue; }
+                if dry_run() {
+                    debug!("[-] 将删除不再使用的旁白 {}", path.display());
+                    removed += 1;
+                } else if fs::remove_file(&path).is_ok() {
+                    debug!("[-] 删除不再使用的旁白 {}", path.display());
+                    removed += 1;
+                }
+            }
+        }
+    }
+    if resume && !dry_run() {
+        let referenced: HashSet<String> = session.referenced.iter()
+            .map(|p| progress_key(&session.base, p))
+            .collect();
+        session.done.retain(|file, _| referenced.contains(file));
+        let mut lines: Vec<String> = session.done.iter().map(|(f, v)| format!("{}\t{}\n", f, v)).collect();
+        lines.sort();
+        if let Err(e) = fs::write(&session.progress_path, lines.concat()) {
+            warn!("无法写入旁白进度 {}: {}", session.progress_path.display(), e);
+        }
+    }
+    SessionSummary { generated: session.generated, deferred: session.deferred, removed, spoken: session.spoken }
+}
+
+/// 旁白文件在本次同步中的处理方式
+enum Plan {
+    Reuse,
+    Generate,
+    Defer,
+}
+
+/// 决定如何处理一条旁白：续传时只复用进度中记录为完整生成且语音相同的文件，
+/// 中途被打断的半成品会重新生成
+fn plan(path: &Path, voice: &str) -> Plan {
+    let mut guard = SESSION.lock().unwrap();
+    let Some(session) = guard.as_mut() else {
+        return if path.exists() { Plan::Reuse } else { Plan::Generate };
+    };
+    session.referenced.insert(path.to_path_buf());
+    let key = progress_key(&session.base, path);
+    let exists = path.exists() && !session.ignore_existing;
+    if exists && session.done.get(&key).is_some_and(|v| v == voice) {
+        return Plan::Reuse;
+    }
+    if settings().limit.is_some_and(|limit| session.generated >= limit) {
+        // An unrecorded file may be cut short by an interrupted run; better silent than garbled
+        if !dry_run() {
+            let _ = fs::remove_file(path);
+        }
+        session.deferred += 1;
+        return Plan::Defer;
+    }
+    session.generated += 1;
+    Plan::Generate
+}
+
+/// 记录一条已完整生成的旁白；立即追加到进度文件，使意外中断的同步也能续传
+fn record_done(path: &Path, voice: &str) {
+    let mut guard = SESSION.lock().unwrap();
+    let Some(session) = guard.as_mut() else { return };
+    let key = progress_key(&session.base, path);
+    let appended = fs::OpenOptions::new().create(true).append(true).open(&session.progress_path)
+        .and_then(|mut f| writeln!(f, "{}\t{}", key, voice));
+    if let Err(e) = appended {
+        debug!("无法记录旁白进度 {}: {}", session.progress_path.display(), e);
+    }
+    session.done.insert(key, voice.to_string());
+}
+
+/// 记录旁白实际朗读的文字与语音，供同步报告统计
+fn record_spoken(path: &Path, text: &str, voice: &str) {
+    if let Some(session) = SESSION.lock().unwrap().as_mut() {
+        session.spoken.push(SpokenVoiceover { path: path.to_path_buf(), text: text.to_string(), voice: voice.to_string() });
+    }
+}
+
+// ─── Voice language ──────────────────────────────────────────────────────────
+
+/// 文字中属于语音所用语言的书写系统的比例低于该值时，认为两者可能不一致
+pub const MISMATCH_SHARE: f64 = 0.2;
+
+/// 旁白文字的书写系统
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Script {
+    Han,
+    Kana,
+    Hangul,
+    Latin,
+    Cyrillic,
+    Other,
+}
+
+impl Script {
+    pub fn name(self) -> &'static str {
+        match self {
+            Script::Han => "汉字",
+            Script::Kana => "假名",
+            Script::Hangul => "谚文",
+            Script::Latin => "拉丁字母",
+            Script::Cyrillic => "西里尔字母",
+            Script::Other => "其他文字",
+        }
+    }
+
+    fn of(c: char) -> Option<Script> {
+        match c as u32 {
+            0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x3134F => Some(Script::Han),
+            0x3040..=0x30FF | 0x31F0..=0x31FF => Some(Script::Kana),
+            0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Some(Script::Hangul),
+            0x0400..=0x04FF => Some(Script::Cyrillic),
+            _ if c.is_ascii_alphabetic() => Some(Script::Latin),
+            0x00C0..=0x024F if c.is_alphabetic() => Some(Script::Latin),
+            _ if c.is_alphabetic() => Some(Script::Other),
+            _ => None,
+        }
+    }
+
+    /// 一个汉字、假名或谚文所占的比重大致相当于三个字母
+    fn weight(self) -> f64 {
+        match self {
+            Script::Han | Script::Kana | Script::Hangul => 3.0,
+            _ => 1.0,
+        }
+    }
+}
+
+/// 各书写系统在文字中所占的比重（按字符加权），没有文字时为空
+fn script_shares(text: &str) -> Vec<(Script, f64)> {
+    let mut weights: Vec<(Script, f64)> = Vec::new();
+    for script in text.chars().filter_map(Script::of) {
+        match weights.iter_mut().find(|(s, _)| *s == script) {
+            Some((_, w)) => *w += script.weight(),
+            None => weights.push((script, script.weight())),
+        }
+    }
+    let total: f64 = weights.iter().map(|(_, w)| w).sum();
+    for (_, w) in weights.iter_mut() {
+        *w /= total;
+    }
+    weights.sort_by(|a, b| b.1.total_cmp(&a.1));
+    weights
+}
+
+/// 文字中占多数的书写系统
+pub fn dominant_script(text: &str) -> Option<Script> {
+    script_shares(text).first().map(|(s, _)| *s)
+}
+
+/// 全局设置的 TTS 语音名称
+pub fn default_voice() -> &'static str {
+    &settings().voice
+}
+
+/// 按语音的语言朗读日期。`date` 以 "YYYY"、"YYYY-MM" 或 "YYYY-MM-DD" 开头（如标签中的
+/// "2024-03-05T08:00:00Z"），无法解析时返回 `None`
+pub fn spoken_date(date: &str, voice: &str) -> Option<String> {
+    let mut parts = date.get(..10).unwrap_or(date).split('-');
+    let year: u32 = parts.next().filter(|y| y.len() == 4)?.parse().ok()?;
+    let month: Option<u32> = parts.next().and_then(|m| m.parse().ok()).filter(|m| (1..=12).contains(m));
+    let day: Option<u32> = parts.next().and_then(|d| d.parse().ok()).filter(|d| (1..=31).contains(d));
+    let Some(month) = month else { return Some(year.to_string()) };
+    const EN: [&str; 12] = ["January", "February", "March", "April", "May", "June",
+        "July", "August", "September", "October", "November", "December"];
+    const DE: [&str; 12] = ["Januar", "Februar", "März", "April", "Mai", "Juni",
+        "Juli", "August", "September", "Oktober", "November", "Dezember"];
+    const FR: [&str; 12] = ["janvier", "février", "mars", "avril", "mai", "juin",
+        "juillet", "août", "septembre", "octobre", "novembre", "décembre"];
+    const ES: [&str; 12] = ["enero", "febrero", "marzo", "abril", "mayo", "junio",
+        "julio", "agosto", "septiembre", "octubre", "noviembre", "diciembre"];
+    let m = (month - 1) as usize;
+    let region = voice.split('-').nth(1).unwrap_or_default();
+    let text = match (voice_language(voice).as_str(), day) {
+        ("zh" | "ja", Some(d)) => format!("{}年{}月{}日", year, month, d),
+        ("zh" | "ja", None) => format!("{}年{}月", year, month),
+        ("ko", Some(d)) => format!("{}년 {}월 {}일", year, month, d),
+        ("ko", None) => format!("{}년 {}월", year, month),
+        ("en", Some(d)) if region.is_empty() || region == "US" => format!("{} {}, {}", EN[m], d, year),
+        ("en", Some(d)) => format!("{} {} {}", d, EN[m], year),
+        ("en", None) => format!("{} {}", EN[m], year),
+        ("de", Some(d)) => format!("{}. {} {}", d, DE[m], year),
+        ("de", None) => format!("{} {}", DE[m], year),
+        ("fr", Some(d)) => format!("{} {} {}", d, FR[m], year),
+        ("fr", None) => format!("{} {}", FR[m], year),
+        ("es", Some(d)) => format!("{} de {} de {}", d, ES[m], year),
+        ("es", None) => format!("{} de {}", ES[m], year),
+        (_, Some(d)) => format!("{}-{:02}-{:02}", year, month, d),
+        (_, None) => format!("{}-{:02}", year, month),
+    };
+    Some(text)
+}
+
+/// 语音名称中的语言，如 "zh-CN-XiaoxiaoNeural" 为 "zh"
+pub fn voice_language(voice: &str) -> String {
+    voice.split('-').next().unwrap_or_default().to_lowercase()
+}
 
-/// 为给定文本生成语音 WAV 文件（如果文件已存在则跳过）
-pub fn text_to_speech_file(out_wav_path: &Path, text: &str) -> bool {
-    if out_wav_path.exists() {
-        vprintln!("使用现有的 {}", out_wav_path.display());
-        return true;
+/// 某种语言的语音能够正常朗读的书写系统
+fn language_scripts(language: &str) -> &'static [Script] {
+    match language {
+        "zh" | "yue" | "wuu" => &[Script::Han],
+        "ja" => &[Script::Kana, Script::Han],
+        "ko" => &[Script::Hangul, Script::Han],
+        "ru" | "uk" | "bg" | "sr" | "be" | "kk" | "mk" | "mn" => &[Script::Cyrillic],
+        "ar" | "he" | "fa" | "ur" | "ps" | "el" | "ka" | "hy" | "th" | "lo" | "km" | "my" | "si"
+        | "hi" | "bn" | "gu" | "kn" | "ml" | "mr" | "ne" | "ta" | "te" | "am" => &[Script::Other],
+        _ => &[Script::Latin],
     }
+}
+
+/// 文字是否可能不适合用该语音朗读，如中文语音朗读纯英文的标题
+pub fn likely_mismatch(text: &str, voice: &str) -> bool {
+    let shares = script_shares(text);
+    if shares.is_empty() { return false; }
+    let expected = language_scripts(&voice_language(voice));
+    let share: f64 = shares.iter().filter(|(s, _)| expected.contains(s)).map(|(_, w)| w).sum();
+    share < MISMATCH_SHARE
+}
 
-    let text = if text.is_empty() { "unknown" } else { text };
+/// 旁白响度与目标相差不到该值 (dB) 时不再调整，避免每次同步都重写文件
+const LEVEL_TOLERANCE_DB: f64 = 1.0;
 
-    match generate_tts_wav(out_wav_path, text) {
-        Ok(_) => true,
-        Err(e) => {
-            eprintln!("语音生成失败: {}", e);
+/// 为给定文本生成语音 WAV 文件（如果文件已存在则跳过）。
+/// `level` 为目标响度 (dBFS)：给出时新生成或已有的旁白都会调整到该响度，
+/// 使旁白与随后播放的曲目音量相当。达到 --tts-limit 时不生成并返回 `false`
+pub fn text_to_speech_file(out_wav_path: &Path, text: &str, level: Option<f64>, voice: Option<&str>) -> bool {
+    let voice_name = voice.unwrap_or(&settings().voice);
+    match plan(out_wav_path, voice_name) {
+        Plan::Reuse => {
+            debug!("使用现有的 {}", out_wav_path.display());
+            record_spoken(out_wav_path, text, voice_name);
+            if let Some(target) = level.filter(|_| !dry_run()) {
+                if let Err(e) = relevel_wav(out_wav_path, target) {
+                    warn!("无法调整旁白音量 {}: {}", out_wav_path.display(), e);
+                }
+            }
+            true
+        }
+        Plan::Defer => {
+            debug!("已达到 --tts-limit，留待下次生成 {}", out_wav_path.display());
             false
         }
+        Plan::Generate => {
+            if cancel::requested() {
+                return false;
+            }
+            let text = if text.is_empty() { "unknown" } else { text };
+            if dry_run() {
+                debug!("[+] 将生成旁白 {} ({})", out_wav_path.display(), text);
+                record_spoken(out_wav_path, text, voice_name);
+                return true;
+            }
+            match generate_tts_wav(out_wav_path, text, level, voice) {
+                Ok(_) => {
+                    events::voiceover_generated(out_wav_path, text, voice_name);
+                    record_done(out_wav_path, voice_name);
+                    record_spoken(out_wav_path, text, voice_name);
+                    true
+                }
+                Err(e) => {
+                    error!("语音生成失败: {}", e);
+                    false
+                }
+            }
+        }
     }
 }
 
-fn generate_tts_wav(out_wav_path: &Path, text: &str) -> Result<(), Box<dyn std::error::Error>> {
+/// 使用 Edge TTS 合成文本，返回 24kHz 单声道 MP3 数据。
+/// 卡住的连接会在超时后被放弃并重试
+pub fn synthesize_mp3(text: &str) -> Result<Vec<u8>, String> {
+    synthesize_mp3_with(text, None)
+}
+
+/// 同 `synthesize_mp3`，`voice` 给出时代替全局设置的语音
+fn synthesize_mp3_with(text: &str, voice: Option<&str>) -> Result<Vec<u8>, String> {
+    let s = settings();
+    let text = text.to_string();
+    let voice = voice.unwrap_or(&s.voice).to_string();
+    run_with_retry(s.timeout, s.retries, true, "语音合成", move || {
+        synthesize_once(&text, &voice).map_err(|e| e.to_string())
+    })
+}
+
+fn synthesize_once(text: &str, voice: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
     use msedge_tts::tts::client::connect;
     use msedge_tts::tts::SpeechConfig;
 
+    // Voice names look like "zh-CN-XiaoxiaoNeural"; the locale is the first two parts
+    let locale = voice.splitn(3, '-').take(2).collect::<Vec<_>>().join("-");
     let config = SpeechConfig::from(&msedge_tts::voice::Voice {
-        name: TTS_VOICE.to_string(),
-        short_name: Some(TTS_VOICE.to_string()),
+        name: voice.to_string(),
+        short_name: Some(voice.to_string()),
         gender: Some(String::new()),
-        locale: Some("zh-CN".to_string()),
+        locale: Some(locale),
         suggested_codec: Some("audio-24khz-48kbitrate-mono-mp3".to_string()),
         friendly_name: Some(String::new()),
         status: Some(String::new()),
@@ -47,7 +454,90 @@ fn generate_tts_wav(out_wav_path: &Path, text: &str) -> Result<(), Box<dyn std::
 
     let mut tts = connect()?;
     let audio = tts.synthesize(text, &config)?;
-    let mp3_bytes = audio.audio_bytes;
+    Ok(audio.audio_bytes)
+}
+
+/// 将采样缩放到目标 RMS 响度，提升时以峰值不削波为限。
+/// 与目标相差不到 `LEVEL_TOLERANCE_DB` 时不做修改并返回 `false`
+fn apply_level(samples: &mut [i16], target_db: f64) -> bool {
+    if samples.is_empty() { return false; }
+    let sum_squares: f64 = samples.iter().map(|&s| (s as f64 / 32768.0).powi(2)).sum();
+    let rms = (sum_squares / samples.len() as f64).sqrt();
+    let peak = samples.iter().map(|&s| (s as i32).unsigned_abs()).max().unwrap_or(0);
+    if rms <= 1e-12 || peak == 0 { return false; }
+
+    let headroom_db = -20.0 * (peak as f64 / 32767.0).log10();
+    let change_db = (target_db - 20.0 * rms.log10()).min(headroom_db);
+    if change_db.abs() < LEVEL_TOLERANCE_DB { return false; }
+
+    let factor = 10f64.powf(change_db / 20.0);
+    for s in samples.iter_mut() {
+        *s = (*s as f64 * factor).round().clamp(-32768.0, 32767.0) as i16;
+    }
+    true
+}
+
+fn write_wav(path: &Path, spec: hound::WavSpec, samples: &[i16]) -> Result<(), Box<dyn std::error::Error>> {
+    // Render in memory so the device write goes through the throttle in one piece
+    let mut wav = io::Cursor::new(Vec::new());
+    let mut writer = hound::WavWriter::new(&mut wav, spec)?;
+    for &s in samples {
+        writer.write_sample(s)?;
+    }
+    writer.finalize()?;
+    write_file(path, wav.into_inner())?;
+    Ok(())
+}
+
+/// 将已有的旁白调整到目标响度
+fn relevel_wav(path: &Path, target_db: f64) -> Result<(), Box<dyn std::error::Error>> {
+    let mut reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+    if spec.bits_per_sample != 16 || spec.sample_format != hound::SampleFormat::Int {
+        return Ok(());
+    }
+    let mut samples = reader.samples::<i16>().collect::<Result<Vec<_>, _>>()?;
+    drop(reader);
+    if apply_level(&mut samples, target_db) {
+        debug!("[~] 调整旁白音量至 {:.1} dBFS: {}", target_db, path.display());
+        write_wav(path, spec, &samples)?;
+    }
+    Ok(())
+}
+
+/// 读取外部引擎写出的 WAV，统一转换为 16 位整数样本
+fn read_engine_wav(wav: Vec<u8>) -> Result<(hound::WavSpec, Vec<i16>), Box<dyn std::error::Error>> {
+    let mut reader = hound::WavReader::new(io::Cursor::new(wav))?;
+    let spec = reader.spec();
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>()
+            .map(|s| s.map(|s| (s * 32767.0).clamp(-32768.0, 32767.0) as i16))
+            .collect::<Result<Vec<_>, _>>()?,
+        hound::SampleFormat::Int => {
+            let shift = spec.bits_per_sample as i32 - 16;
+            reader.samples::<i32>()
+                .map(|s| s.map(|s| if shift >= 0 { s >> shift } else { s << -shift } as i16))
+                .collect::<Result<Vec<_>, _>>()?
+        }
+    };
+    Ok((hound::WavSpec { bits_per_sample: 16, sample_format: hound::SampleFormat::Int, ..spec }, samples))
+}
+
+fn generate_tts_wav(
+    out_wav_path: &Path, text: &str, level: Option<f64>, voice: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let s = settings();
+    if let TtsEngine::Command(ref program) = s.engine {
+        let voice = voice.unwrap_or(&s.voice);
+        let wav = synthesize_wav(program, &s.engine_config, text, voice, s.timeout, s.retries)?;
+        let (spec, mut samples) = read_engine_wav(wav)?;
+        if let Some(target) = level {
+            apply_level(&mut samples, target);
+        }
+        return write_wav(out_wav_path, spec, &samples);
+    }
+
+    let mp3_bytes = synthesize_mp3_with(text, voice)?;
 
     // Decode MP3 bytes to WAV using symphonia + hound
     let cursor = io::Cursor::new(mp3_bytes);
@@ -73,8 +563,7 @@ fn generate_tts_wav(out_wav_path: &Path, text: &str) -> Result<(), Box<dyn std::
         bits_per_sample: 16,
         sample_format: hound::SampleFormat::Int,
     };
-    let mut writer = hound::WavWriter::create(out_wav_path, spec)?;
-
+    let mut samples = Vec::new();
     while let Ok(packet) = format.next_packet() {
         if packet.track_id() != track.id { continue; }
 
@@ -88,12 +577,11 @@ fn generate_tts_wav(out_wav_path: &Path, text: &str) -> Result<(), Box<dyn std::
         let mut sample_buf = SampleBuffer::<f32>::new(num_frames as u64, dspec);
         sample_buf.copy_interleaved_ref(decoded);
 
-        for &s in sample_buf.samples() {
-            let val = (s * 32767.0).clamp(-32768.0, 32767.0) as i16;
-            writer.write_sample(val)?;
-        }
+        samples.extend(sample_buf.samples().iter().map(|&s| (s * 32767.0).clamp(-32768.0, 32767.0) as i16));
     }
 
-    writer.finalize()?;
-    Ok(())
+    if let Some(target) = level {
+        apply_level(&mut samples, target);
+    }
+    write_wav(out_wav_path, spec, &samples)
 }
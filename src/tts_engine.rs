@@ -0,0 +1,78 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tracing::debug;
+
+use crate::timeout::run_process;
+
+// ─── External voiceover engines ──────────────────────────────────────────────
+
+/// 请求协议的版本，协议有不兼容的改动时才会增加
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// 语音旁白的合成引擎
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TtsEngine {
+    /// 内置的 Edge TTS
+    Edge,
+    /// 外部程序：每条旁白运行一次，从标准输入读取 JSON 请求，将 WAV 写到请求给出的路径
+    Command(PathBuf),
+}
+
+/// 本次运行中临时 WAV 文件的序号，使并行合成的旁白不会互相覆盖
+static NEXT_OUTPUT: AtomicUsize = AtomicUsize::new(0);
+
+/// 读取 --tts-engine-config 给出的 JSON 配置，未给出时为空对象
+pub fn load_config(file: Option<&Path>) -> Result<serde_json::Value, String> {
+    let Some(file) = file else { return Ok(serde_json::json!({})) };
+    let text = fs::read_to_string(file).map_err(|e| format!("无法读取 {}: {}", file.display(), e))?;
+    serde_json::from_str(&text).map_err(|e| format!("{} 不是有效的 JSON: {}", file.display(), e))
+}
+
+/// 一次请求的 JSON，写入外部引擎的标准输入：
+///
+/// ```json
+/// {"version": 1, "text": "周杰伦 晴天", "voice": "zh-CN-XiaoxiaoNeural", "output": "/tmp/….wav", "config": {}}
+/// ```
+///
+/// `config` 为 --tts-engine-config 文件的内容，未给出时为空对象
+fn request(text: &str, voice: &str, output: &Path, config: &serde_json::Value) -> String {
+    serde_json::json!({
+        "version": PROTOCOL_VERSION,
+        "text": text,
+        "voice": voice,
+        "output": output.to_string_lossy(),
+        "config": config,
+    })
+    .to_string()
+}
+
+/// 运行一次外部引擎，成功时返回引擎写入的 WAV 文件内容
+fn run_once(program: &Path, request: &str, output: &Path, limit: Duration) -> Result<Vec<u8>, String> {
+    run_process(Command::new(program), Some(request.as_bytes()), limit)?;
+    fs::read(output).map_err(|e| format!("{} 没有写出 WAV 文件: {}", program.display(), e))
+}
+
+/// 用外部引擎合成 `text`，返回 WAV 文件的内容。超时或失败时最多重试 `retries` 次
+pub fn synthesize_wav(
+    program: &Path, config: &serde_json::Value, text: &str, voice: &str, limit: Duration, retries: u32,
+) -> Result<Vec<u8>, String> {
+    let n = NEXT_OUTPUT.fetch_add(1, Ordering::Relaxed);
+    let output = std::env::temp_dir().join(format!("ipod-shuffle-4g-tts-{}-{}.wav", std::process::id(), n));
+    let request = request(text, voice, &output, config);
+    let mut last = String::new();
+    for attempt in 0..=retries {
+        if attempt > 0 {
+            debug!("语音合成失败，正在重试 ({}/{}): {}", attempt, retries, last);
+        }
+        let result = run_once(program, &request, &output, limit);
+        let _ = fs::remove_file(&output);
+        match result {
+            Ok(wav) => return Ok(wav),
+            Err(e) => last = e,
+        }
+    }
+    Err(last)
+}
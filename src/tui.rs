@@ -0,0 +1,290 @@
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Tabs};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, IsTerminal};
+
+use crate::database::TrackInfo;
+use crate::i18n::{self, tr};
+use crate::listing::playlist_display_name;
+use crate::metadata::SuspiciousNumber;
+use crate::report::SyncReport;
+
+// ─── tui subcommand: review before writing ───────────────────────────────────
+
+/// 在界面中选定、将写入数据库的曲目与播放列表
+pub struct Selection {
+    /// 与 `track_infos` 一一对应
+    pub tracks: Vec<bool>,
+    /// 与播放列表一一对应，主列表总是保留
+    pub playlists: Vec<bool>,
+}
+
+impl Selection {
+    /// 去掉未选中的曲目与播放列表，并重新计算各播放列表中的曲目索引。
+    /// 因此变空的播放列表也一并去掉；`tracks` 与 `infos` 一一对应
+    pub fn apply<T>(&self, tracks: &mut Vec<T>, infos: &mut Vec<TrackInfo>, playlists: &mut Vec<(String, Vec<u32>)>) {
+        let mut new_index: Vec<Option<u32>> = Vec::with_capacity(self.tracks.len());
+        let mut next = 0;
+        for &keep in &self.tracks {
+            new_index.push(keep.then(|| {
+                next += 1;
+                next - 1
+            }));
+        }
+        let mut keep = self.tracks.iter();
+        tracks.retain(|_| *keep.next().unwrap_or(&true));
+        let mut keep = self.tracks.iter();
+        infos.retain(|_| *keep.next().unwrap_or(&true));
+
+        let mut keep = self.playlists.iter();
+        playlists.retain(|_| *keep.next().unwrap_or(&true));
+        for (_, indices) in playlists.iter_mut() {
+            *indices = indices.iter().filter_map(|&i| new_index.get(i as usize).copied().flatten()).collect();
+        }
+        let mut first = true;
+        playlists.retain(|(_, indices)| std::mem::take(&mut first) || !indices.is_empty());
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Tab {
+    Tracks,
+    Playlists,
+    Progress,
+    Warnings,
+}
+
+const TABS: [(Tab, &str); 4] = [
+    (Tab::Tracks, "曲目"),
+    (Tab::Playlists, "播放列表"),
+    (Tab::Progress, "转换与旁白"),
+    (Tab::Warnings, "警告"),
+];
+
+struct App<'a> {
+    infos: &'a [TrackInfo],
+    playlists: &'a [(String, Vec<u32>)],
+    /// 每首曲目的警告，按曲目索引
+    track_warnings: HashMap<usize, Vec<String>>,
+    progress: Vec<String>,
+    warnings: Vec<String>,
+    selection: Selection,
+    tab: usize,
+    states: [ListState; 4],
+    /// 按下 w 之后等待确认
+    confirming: bool,
+}
+
+fn number_warning(n: &SuspiciousNumber) -> String {
+    tr!("{} \"{}\" 不合理，{}", i18n::text(n.field), n.raw, n.action())
+}
+
+impl<'a> App<'a> {
+    fn new(
+        sources: &[String], infos: &'a [TrackInfo], playlists: &'a [(String, Vec<u32>)],
+        report: &SyncReport, track_voiceover: bool,
+    ) -> Self {
+        let mut track_warnings: HashMap<usize, Vec<String>> = HashMap::new();
+        for (i, (source, info)) in sources.iter().zip(infos).enumerate() {
+            let mut listed: Vec<String> = report.failures.iter()
+                .filter(|f| f.contains(source.as_str()) || f.contains(info.filename.as_str()))
+                .cloned()
+                .collect();
+            listed.extend(report.suspicious_numbers.iter().filter(|(t, _)| t == source).map(|(_, n)| number_warning(n)));
+            if !listed.is_empty() {
+                track_warnings.insert(i, listed);
+            }
+        }
+
+        let mut progress: Vec<String> = report.conversions.iter()
+            .map(|c| match &c.output {
+                Some(out) => format!("FLAC {} -> {}", c.source, out),
+                None => tr!("{} 转换失败", c.source),
+            })
+            .collect();
+        if progress.is_empty() {
+            progress.push(tr!("本次没有需要转换的 FLAC 文件"));
+        }
+        if track_voiceover {
+            let voiced: HashSet<[u8; 8]> = infos.iter().map(|i| i.dbid).collect();
+            progress.push(tr!("曲目旁白: 已就绪 {} 条", voiced.len()));
+        }
+        progress.push(tr!("播放列表旁白在写入数据库时生成"));
+
+        let mut warnings: Vec<String> = report.failures.clone();
+        warnings.extend(report.skipped_tracks.iter()
+            .map(|t| tr!("已跳过 {}（{}）: {}", t.track, i18n::text(t.reason.label()), t.detail)));
+        warnings.extend(report.suspicious_numbers.iter().map(|(t, n)| format!("{}: {}", t, number_warning(n))));
+
+        let mut states: [ListState; 4] = Default::default();
+        for state in &mut states {
+            state.select(Some(0));
+        }
+        App {
+            infos,
+            playlists,
+            track_warnings,
+            progress,
+            warnings,
+            selection: Selection { tracks: vec![true; infos.len()], playlists: vec![true; playlists.len()] },
+            tab: 0,
+            states,
+            confirming: false,
+        }
+    }
+
+    fn tab(&self) -> Tab {
+        TABS[self.tab].0
+    }
+
+    fn len(&self) -> usize {
+        match self.tab() {
+            Tab::Tracks => self.infos.len(),
+            Tab::Playlists => self.playlists.len(),
+            Tab::Progress => self.progress.len(),
+            Tab::Warnings => self.warnings.len(),
+        }
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        let len = self.len();
+        if len == 0 { return; }
+        let state = &mut self.states[self.tab];
+        let current = state.selected().unwrap_or(0) as isize;
+        state.select(Some((current + delta).clamp(0, len as isize - 1) as usize));
+    }
+
+    fn toggle(&mut self) {
+        let Some(i) = self.states[self.tab].selected() else { return };
+        match self.tab() {
+            Tab::Tracks => if let Some(t) = self.selection.tracks.get_mut(i) { *t = !*t },
+            // The master playlist cannot be left out
+            Tab::Playlists if i > 0 => if let Some(p) = self.selection.playlists.get_mut(i) { *p = !*p },
+            _ => {}
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, body, footer] = Layout::vertical([
+            Constraint::Length(3), Constraint::Min(3), Constraint::Length(1),
+        ]).areas(frame.area());
+
+        let titles: Vec<String> = TABS.iter().map(|(tab, title)| match tab {
+            Tab::Tracks => format!("{} {}/{}", i18n::text(title),
+                self.selection.tracks.iter().filter(|&&t| t).count(), self.infos.len()),
+            Tab::Playlists => format!("{} {}/{}", i18n::text(title),
+                self.selection.playlists.iter().filter(|&&p| p).count(), self.playlists.len()),
+            Tab::Warnings => format!("{} ({})", i18n::text(title), self.warnings.len()),
+            Tab::Progress => i18n::text(title).to_string(),
+        }).collect();
+        frame.render_widget(
+            Tabs::new(titles).select(self.tab)
+                .block(Block::bordered().title(format!(" iPod Shuffle 4G Manager v{} ", env!("CARGO_PKG_VERSION"))))
+                .highlight_style(Style::new().add_modifier(Modifier::BOLD | Modifier::REVERSED)),
+            header,
+        );
+
+        let check = |on: bool| if on { "[x]" } else { "[ ]" };
+        let items: Vec<ListItem> = match self.tab() {
+            Tab::Tracks => self.infos.iter().zip(&self.selection.tracks).enumerate()
+                .map(|(i, (info, &on))| {
+                    let mut line = format!("{} {}", check(on), info.filename);
+                    if info.start_at_pos_ms > 0 {
+                        line.push_str(&format!(" @{}s", info.start_at_pos_ms / 1000));
+                    }
+                    line.push_str(&tr!("  增益 {}", info.volume_gain));
+                    let warnings = self.track_warnings.get(&i);
+                    if let Some(w) = warnings {
+                        line.push_str(&format!("  ! {}", w.join("; ")));
+                    }
+                    let item = ListItem::new(Line::from(line));
+                    match (on, warnings) {
+                        (false, _) => item.style(Style::new().fg(Color::DarkGray)),
+                        (true, Some(_)) => item.style(Style::new().fg(Color::Yellow)),
+                        (true, None) => item,
+                    }
+                })
+                .collect(),
+            Tab::Playlists => self.playlists.iter().zip(&self.selection.playlists)
+                .map(|((name, indices), &on)| {
+                    let kept = indices.iter().filter(|&&i| self.selection.tracks.get(i as usize) == Some(&true)).count();
+                    let item = ListItem::new(tr!("{} {} ({}/{} 首)", check(on), playlist_display_name(name), kept, indices.len()));
+                    if on { item } else { item.style(Style::new().fg(Color::DarkGray)) }
+                })
+                .collect(),
+            Tab::Progress => self.progress.iter().map(|l| ListItem::new(l.as_str())).collect(),
+            Tab::Warnings => self.warnings.iter().map(|l| ListItem::new(l.as_str())).collect(),
+        };
+        let list = List::new(items)
+            .block(Block::bordered())
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, body, &mut self.states[self.tab]);
+
+        let help = if self.confirming {
+            tr!("写入数据库？y 确认，其他键返回")
+        } else {
+            tr!("Tab/←→ 切换  ↑↓ 移动  空格 选择/取消  a 全选  w 写入  q 放弃")
+        };
+        frame.render_widget(Paragraph::new(help), footer);
+    }
+}
+
+fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<bool> {
+    loop {
+        terminal.draw(|f| app.draw(f))?;
+        let Event::Key(key) = event::read()? else { continue };
+        if key.kind != KeyEventKind::Press { continue; }
+        if app.confirming {
+            if matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+                return Ok(true);
+            }
+            app.confirming = false;
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+            KeyCode::Char('w') | KeyCode::Enter => app.confirming = true,
+            KeyCode::Tab | KeyCode::Right => app.tab = (app.tab + 1) % TABS.len(),
+            KeyCode::BackTab | KeyCode::Left => app.tab = (app.tab + TABS.len() - 1) % TABS.len(),
+            KeyCode::Down | KeyCode::Char('j') => app.move_by(1),
+            KeyCode::Up | KeyCode::Char('k') => app.move_by(-1),
+            KeyCode::PageDown => app.move_by(20),
+            KeyCode::PageUp => app.move_by(-20),
+            KeyCode::Char(' ') => {
+                app.toggle();
+                app.move_by(1);
+            }
+            KeyCode::Char('a') => match app.tab() {
+                Tab::Tracks => app.selection.tracks.fill(true),
+                Tab::Playlists => app.selection.playlists.fill(true),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+}
+
+/// 在终端界面中列出即将写入的曲目、播放列表、转换与旁白情况以及警告，
+/// 让用户取消选择部分曲目或播放列表。`sources` 是曲目来源文件的 iPod 路径，与 `infos` 一一对应。
+/// 确认写入时返回所选内容，放弃时返回 `None`
+pub fn review(
+    sources: &[String], infos: &[TrackInfo], playlists: &[(String, Vec<u32>)],
+    report: &SyncReport, track_voiceover: bool,
+) -> Result<Option<Selection>, String> {
+    if !io::stdout().is_terminal() || !io::stdin().is_terminal() {
+        return Err("tui 需要在终端中运行".to_string());
+    }
+    let mut app = App::new(sources, infos, playlists, report, track_voiceover);
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut app);
+    ratatui::restore();
+    match result {
+        Ok(true) => Ok(Some(app.selection)),
+        Ok(false) => Ok(None),
+        Err(e) => Err(format!("终端界面出错: {}", e)),
+    }
+}
@@ -1,22 +1,203 @@
+use crate::outln;
 use md5::{Digest, Md5};
 use std::fs;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use tracing::error;
 
-use crate::cli::{AUDIO_EXT, LIST_EXT};
+use crate::cli::{HiddenPolicy, AUDIO_EXT};
+use crate::device_io::dry_run;
+use crate::playlist::is_list_ext;
+
+/// 规范化路径：存在时取规范路径，否则转为绝对路径。Windows 上去掉
+/// `canonicalize` 产生的 `\\?\` 前缀，使 `C:\` 与 `\\server\share` 形式保持一致
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let p = fs::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf());
+    strip_verbatim(p)
+}
+
+#[cfg(windows)]
+fn strip_verbatim(p: PathBuf) -> PathBuf {
+    use std::path::Prefix;
+    let mut comps = p.components();
+    let root = match comps.next() {
+        Some(Component::Prefix(prefix)) => match prefix.kind() {
+            Prefix::VerbatimDisk(letter) => format!("{}:\\", letter as char),
+            Prefix::VerbatimUNC(server, share) => {
+                format!(r"\\{}\{}\", server.to_string_lossy(), share.to_string_lossy())
+            }
+            _ => return p,
+        },
+        _ => return p,
+    };
+    let mut out = PathBuf::from(root);
+    out.extend(comps.filter(|c| !matches!(c, Component::RootDir)));
+    out
+}
+
+#[cfg(not(windows))]
+fn strip_verbatim(p: PathBuf) -> PathBuf {
+    p
+}
+
+/// 比较两个路径分量。Windows 上盘符、UNC 前缀与文件名都不区分大小写
+fn component_eq(a: &Component, b: &Component) -> bool {
+    if cfg!(windows) {
+        a.as_os_str().to_string_lossy().to_lowercase() == b.as_os_str().to_string_lossy().to_lowercase()
+    } else {
+        a == b
+    }
+}
+
+/// 若 `path` 位于 `base` 之下，返回其余的路径分量（逐分量比较，而不是字符串前缀）
+fn strip_base<'a>(path: &'a Path, base: &Path) -> Option<Vec<Component<'a>>> {
+    let mut comps = path.components();
+    for b in base.components() {
+        match comps.next() {
+            Some(c) if component_eq(&c, &b) => {}
+            _ => return None,
+        }
+    }
+    Some(comps.collect())
+}
 
 /// 判断 `path` 是否是 `parent` 的子路径
 pub fn is_subpath(path: &Path, parent: &Path) -> bool {
-    match (fs::canonicalize(path), fs::canonicalize(parent)) {
-        (Ok(p), Ok(par)) => p.starts_with(&par),
-        _ => {
-            // fallback without canonicalize for paths that don't exist yet
-            let p = path.to_string_lossy().to_lowercase();
-            let par = parent.to_string_lossy().to_lowercase();
-            p.starts_with(&par)
+    strip_base(&normalize_path(path), &normalize_path(parent)).is_some()
+}
+
+/// 文件在设备上的身份：FAT 等不区分大小写的文件系统上 `Song.MP3` 与 `song.mp3` 是同一个文件
+#[derive(Clone, Copy)]
+pub struct PathIdentity {
+    fold_case: bool,
+}
+
+impl PathIdentity {
+    /// 通过翻转 `dir` 中某个条目名的大小写来探测文件系统是否区分大小写
+    pub fn detect(dir: &Path) -> Self {
+        let names: Vec<String> = fs::read_dir(dir)
+            .map(|entries| entries.flatten().map(|e| e.file_name().to_string_lossy().to_string()).collect())
+            .unwrap_or_default();
+        let fold_case = names.iter()
+            .find(|n| n.chars().any(|c| c.is_ascii_alphabetic()))
+            .map(|n| {
+                let flipped: String = n.chars()
+                    .map(|c| if c.is_ascii_uppercase() { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() })
+                    .collect();
+                !names.contains(&flipped) && dir.join(&flipped).exists()
+            })
+            .unwrap_or(cfg!(any(windows, target_os = "macos")));
+        PathIdentity { fold_case }
+    }
+
+    pub fn fold_case(&self) -> bool {
+        self.fold_case
+    }
+
+    /// 用于去重与查找的键
+    pub fn key(&self, p: &Path) -> PathBuf {
+        if self.fold_case {
+            PathBuf::from(p.to_string_lossy().to_lowercase())
+        } else {
+            p.to_path_buf()
         }
     }
 }
 
+/// 判断目录是否像 iPod 根目录：包含 `iPod_Control`（FAT 上不区分大小写）
+pub fn looks_like_ipod(path: &Path) -> bool {
+    fs::read_dir(path)
+        .map(|entries| entries.flatten().any(|e| {
+            e.file_name().to_string_lossy().eq_ignore_ascii_case("iPod_Control") && e.path().is_dir()
+        }))
+        .unwrap_or(false)
+}
+
+/// 操作系统生成的隐藏项（回收站、索引目录、macOS 资源分叉文件），任何策略下都不扫描
+pub fn is_system_hidden(name: &str) -> bool {
+    name.starts_with("._")
+        || name.starts_with(".Trash")
+        || matches!(name, ".Spotlight-V100" | ".fseventsd" | ".TemporaryItems" | ".DS_Store")
+}
+
+/// 按隐藏文件策略判断扫描时是否保留该条目；非隐藏条目总是保留
+pub fn hidden_entry_allowed(name: &str, is_dir: bool, policy: HiddenPolicy, allow: &[String]) -> bool {
+    if !name.starts_with('.') { return true; }
+    if is_system_hidden(name) { return false; }
+    if allow.iter().any(|a| a == name) { return true; }
+    match policy {
+        HiddenPolicy::Skip => false,
+        HiddenPolicy::SkipFiles => is_dir,
+        HiddenPolicy::Include => true,
+    }
+}
+
+/// 扫描时排除的 glob 模式（--exclude）。`*` 与 `?` 不跨越 "/"，`**` 匹配任意多级目录，
+/// `[...]` 为字符集合；不区分大小写。不含 "/" 的模式匹配任意一级的名称，
+/// 否则匹配相对 iPod 根目录的路径，如 "iPod_Control/Music/Do Not Sync"
+#[derive(Clone)]
+pub struct GlobPattern {
+    regex: regex::Regex,
+    name_only: bool,
+}
+
+impl GlobPattern {
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        let trimmed = pattern.trim().trim_start_matches("./").trim_matches('/');
+        if trimmed.is_empty() {
+            return Err("排除模式不能为空".to_string());
+        }
+        let mut re = String::from("(?i)^");
+        let mut chars = trimmed.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    // "**/" also matches zero directories
+                    if chars.peek() == Some(&'/') {
+                        chars.next();
+                        re.push_str("(?:.*/)?");
+                    } else {
+                        re.push_str(".*");
+                    }
+                }
+                '*' => re.push_str("[^/]*"),
+                '?' => re.push_str("[^/]"),
+                '[' => {
+                    let class: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                    let class = class.strip_prefix('!').map(|c| format!("^{}", c)).unwrap_or(class);
+                    re.push_str(&format!("[{}]", class.replace('\\', "\\\\")));
+                }
+                c => re.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        re.push('$');
+        let regex = regex::Regex::new(&re).map_err(|e| format!("无效的排除模式 \"{}\": {}", pattern, e))?;
+        Ok(GlobPattern { regex, name_only: !trimmed.contains('/') })
+    }
+
+    /// `path` 是否匹配该模式，`base` 为 iPod 根目录
+    pub fn matches(&self, path: &Path, base: &Path) -> bool {
+        if self.name_only {
+            return path.file_name().is_some_and(|n| self.regex.is_match(&n.to_string_lossy()));
+        }
+        let Ok(rel) = path.strip_prefix(base) else { return false };
+        let rel: Vec<String> = rel.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+        self.regex.is_match(&rel.join("/"))
+    }
+}
+
+/// 按标题排除曲目的正则表达式（--exclude-title 与播放列表的 exclude-titles），不区分大小写，
+/// 如 `\((Instrumental|Karaoke)\)`
+pub fn parse_title_pattern(pattern: &str) -> Result<regex::Regex, String> {
+    regex::RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| format!("无效的标题模式 \"{}\": {}", pattern, e))
+}
+
 pub fn raises_unicode_error(s: &str) -> bool {
     s.bytes().any(|b| b > 127)
 }
@@ -69,14 +250,15 @@ pub fn check_unicode(path: &Path) -> bool {
         if item_path.is_file() {
             if let Some(ext) = item_path.extension() {
                 let ext_lower = format!(".{}", ext.to_string_lossy().to_lowercase());
-                if AUDIO_EXT.contains(&ext_lower.as_str()) || LIST_EXT.contains(&ext_lower.as_str()) {
+                if AUDIO_EXT.contains(&ext_lower.as_str()) || is_list_ext(&ext_lower) {
                     ret_flag = true;
                     if raises_unicode_error(&item_name) {
                         let dest_name = format!("{}{}", hash_error_unicode(&item_name), ext_lower);
                         let dest = path.join(&dest_name);
-                        println!("重命名 {} -> {}", item_path.display(), dest.display());
+                        outln!("重命名 {} -> {}", item_path.display(), dest.display());
+                        if dry_run() { continue; }
                         if let Err(e) = fs::rename(&item_path, &dest) {
-                            eprintln!("重命名失败: {} -> {}: {}", item_path.display(), dest.display(), e);
+                            error!("重命名失败: {} -> {}: {}", item_path.display(), dest.display(), e);
                         }
                     }
                 }
@@ -87,9 +269,10 @@ pub fn check_unicode(path: &Path) -> bool {
             if ret_flag && raises_unicode_error(&item_name) {
                 let new_name = hash_error_unicode(&item_name);
                 let dest = path.join(&new_name);
-                println!("重命名 {} -> {}", item_path.display(), dest.display());
+                outln!("重命名 {} -> {}", item_path.display(), dest.display());
+                if dry_run() { continue; }
                 if let Err(e) = fs::rename(&item_path, &dest) {
-                    eprintln!("重命名失败: {} -> {}: {}", item_path.display(), dest.display(), e);
+                    error!("重命名失败: {} -> {}: {}", item_path.display(), dest.display(), e);
                 }
             }
         }
@@ -99,14 +282,19 @@ pub fn check_unicode(path: &Path) -> bool {
 
 /// 将文件的绝对路径转化为 iPod 的相对路径格式
 pub fn path_to_ipod(filename: &Path, base: &Path) -> Result<String, String> {
-    let abs = fs::canonicalize(filename).unwrap_or_else(|_| filename.to_path_buf());
-    let base_abs = fs::canonicalize(base).unwrap_or_else(|_| base.to_path_buf());
-    if !abs.starts_with(&base_abs) {
-        return Err("Cannot get iPod filename, since file is outside the iPod path".into());
+    let abs = normalize_path(filename);
+    let base_abs = normalize_path(base);
+    let rest = strip_base(&abs, &base_abs)
+        .ok_or("Cannot get iPod filename, since file is outside the iPod path")?;
+    let mut parts = Vec::with_capacity(rest.len());
+    for c in rest {
+        match c {
+            Component::Normal(name) => parts.push(name.to_string_lossy().to_string()),
+            Component::CurDir => {}
+            _ => return Err(format!("Invalid iPod filename: {}", abs.display())),
+        }
     }
-    let rel = abs.strip_prefix(&base_abs).unwrap();
-    let ipod_path = format!("/{}", rel.to_string_lossy().replace('\\', "/"));
-    Ok(ipod_path)
+    Ok(format!("/{}", parts.join("/")))
 }
 
 /// 获取路径的小写扩展名（带点号），如 `.mp3`
@@ -115,3 +303,98 @@ pub fn ext_lower(p: &Path) -> String {
         .map(|e| format!(".{}", e.to_string_lossy().to_lowercase()))
         .unwrap_or_default()
 }
+
+/// 解析时长字符串，如 `90`、`15s`、`45m`、`2h`、`1h30m`（纯数字按秒计）
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    if let Ok(secs) = s.parse::<f64>() {
+        if secs < 0.0 { return Err(format!("无效的时长: {}", s)); }
+        return Ok(Duration::from_secs_f64(secs));
+    }
+    let re = regex::Regex::new(r"(?i)^(?:(\d+(?:\.\d+)?)h)?(?:(\d+(?:\.\d+)?)m)?(?:(\d+(?:\.\d+)?)s)?$").unwrap();
+    let caps = re.captures(s).filter(|_| !s.is_empty()).ok_or_else(|| format!("无效的时长: {}", s))?;
+    let part = |i: usize, scale: f64| {
+        caps.get(i).and_then(|m| m.as_str().parse::<f64>().ok()).unwrap_or(0.0) * scale
+    };
+    Ok(Duration::from_secs_f64(part(1, 3600.0) + part(2, 60.0) + part(3, 1.0)))
+}
+
+/// 解析容量，如 "1.8GiB"、"700MB"、"2G" 或字节数。KiB/MiB/GiB/TiB 与单独的 K/M/G/T 按 1024 进位，
+/// KB/MB/GB/TB 按 1000 进位，不区分大小写
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let re = regex::Regex::new(r"(?i)^(\d+(?:\.\d+)?)\s*([kmgt]?)(i?b?)$").unwrap();
+    let caps = re.captures(s).ok_or_else(|| format!("无效的容量: {}", s))?;
+    let value: f64 = caps[1].parse().map_err(|_| format!("无效的容量: {}", s))?;
+    let exponent = match caps[2].to_ascii_lowercase().as_str() {
+        "" => 0,
+        "k" => 1,
+        "m" => 2,
+        "g" => 3,
+        _ => 4,
+    };
+    let suffix = caps[3].to_ascii_lowercase();
+    if exponent == 0 && suffix.starts_with('i') {
+        return Err(format!("无效的容量: {}", s));
+    }
+    let unit: f64 = if suffix == "b" && exponent > 0 { 1000.0 } else { 1024.0 };
+    Ok((value * unit.powi(exponent)) as u64)
+}
+
+/// 解析音频内的时间点（毫秒），接受 "83.5"、"1:23.5"、"1:02:03" 等形式
+pub fn parse_timestamp(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let parts: Vec<&str> = s.split(':').collect();
+    if parts.len() > 3 || parts.iter().any(|p| p.is_empty()) {
+        return Err(format!("无效的时间点: {}", s));
+    }
+    let mut secs = 0.0f64;
+    for (i, p) in parts.iter().enumerate() {
+        let is_last = i == parts.len() - 1;
+        let v: f64 = if is_last {
+            p.parse().map_err(|_| format!("无效的时间点: {}", s))?
+        } else {
+            p.parse::<u32>().map_err(|_| format!("无效的时间点: {}", s))? as f64
+        };
+        if v < 0.0 || (i > 0 && v >= 60.0) { return Err(format!("无效的时间点: {}", s)); }
+        secs = secs * 60.0 + v;
+    }
+    Ok((secs * 1000.0).round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("1.5"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("2M10S"), Ok(Duration::from_secs(130)));
+        assert_eq!(parse_duration(" 0.5h "), Ok(Duration::from_secs(1800)));
+    }
+
+    #[test]
+    fn invalid_durations() {
+        for s in ["", "-5", "10x", "m", "1m1h"] {
+            assert!(parse_duration(s).is_err(), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("2G"), Ok(2 << 30));
+        assert_eq!(parse_size("1.5KiB"), Ok(1536));
+        assert_eq!(parse_size("700MB"), Ok(700_000_000));
+        assert_eq!(parse_size("1 gib"), Ok(1 << 30));
+    }
+
+    #[test]
+    fn invalid_sizes() {
+        for s in ["", "GB", "12iB", "-1M", "3PB"] {
+            assert!(parse_size(s).is_err(), "{:?}", s);
+        }
+    }
+}
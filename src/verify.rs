@@ -0,0 +1,105 @@
+use std::path::Path;
+
+use crate::outln;
+use crate::database::read_device_db;
+use crate::listing::print_section;
+use crate::playlist::ListType;
+use crate::speakable::{check_speakable, SpeakableReport};
+
+// ─── verify subcommand ───────────────────────────────────────────────────────
+
+/// 校验现有 iTunesSD 的结果
+#[derive(Default)]
+pub struct VerifyReport {
+    pub tracks: usize,
+    pub playlists: usize,
+    /// 数据库结构上的问题：无法解析、主列表、索引越界、播放列表顺序
+    pub structure: Vec<String>,
+    /// 数据库引用、但设备上不存在的曲目文件
+    pub missing_files: Vec<String>,
+    /// 旁白检查结果，数据库无法解析时为 `None`
+    pub speakable: Option<SpeakableReport>,
+}
+
+impl VerifyReport {
+    pub fn problem_count(&self) -> usize {
+        self.structure.len()
+            + self.missing_files.len()
+            + self.speakable.as_ref().map_or(0, |s| s.problem_count())
+    }
+}
+
+/// 只读校验设备上的 iTunesSD 及其引用的曲目与旁白
+pub fn verify_database(base: &Path) -> VerifyReport {
+    let mut report = VerifyReport::default();
+    let db = match read_device_db(base) {
+        Ok(db) => db,
+        Err(e) => {
+            report.structure.push(e);
+            return report;
+        }
+    };
+    report.tracks = db.tracks.len();
+    report.playlists = db.playlists.len();
+
+    for (i, track) in db.tracks.iter().enumerate() {
+        if !base.join(track.filename.trim_start_matches('/')).is_file() {
+            report.missing_files.push(track.filename.clone());
+        }
+        if track.stop_at_pos_ms != 0 && track.stop_at_pos_ms <= track.start_at_pos_ms {
+            report.structure.push(format!(
+                "曲目 #{} ({}) 的结束位置 {} ms 不在开始位置 {} ms 之后",
+                i, track.filename, track.stop_at_pos_ms, track.start_at_pos_ms,
+            ));
+        }
+    }
+
+    match db.playlists.iter().filter(|p| p.listtype == 1).count() {
+        1 if db.playlists[0].listtype == 1 => {}
+        1 => report.structure.push("主列表不是第一个播放列表".to_string()),
+        n => report.structure.push(format!("应当恰好有一个主列表，实际有 {} 个", n)),
+    }
+    let podcast = ListType::Podcast.code();
+    if let Some(first) = db.playlists.iter().position(|p| p.listtype == podcast) {
+        if db.playlists[first..].iter().any(|p| p.listtype != podcast) {
+            report.structure.push("播客列表之后还有其他播放列表，播客列表应当排在最后".to_string());
+        }
+    }
+    for (i, playlist) in db.playlists.iter().enumerate() {
+        let invalid = playlist.track_indices.iter().filter(|&&t| t as usize >= db.tracks.len()).count();
+        if invalid > 0 {
+            report.structure.push(format!("播放列表 #{} 中有 {} 个超出曲目数量的索引", i, invalid));
+        }
+    }
+
+    match check_speakable(base, false) {
+        Ok(speakable) => report.speakable = Some(speakable),
+        Err(e) => report.structure.push(e),
+    }
+    report
+}
+
+/// 打印校验结果
+pub fn print_verify_report(base: &Path, report: &VerifyReport) {
+    outln!("数据库: {} 首曲目，{} 个播放列表", report.tracks, report.playlists);
+    print_section("数据库结构问题", &report.structure);
+    print_section("缺失的曲目文件", &report.missing_files);
+    if let Some(ref speakable) = report.speakable {
+        let file = |p: &Path| p.strip_prefix(base).unwrap_or(p).display().to_string();
+        print_section("缺失的旁白", &speakable.missing.iter()
+            .map(|item| format!("{}: {}", item.label, file(&item.path)))
+            .collect::<Vec<_>>());
+        print_section("无法解码的旁白", &speakable.corrupt.iter()
+            .map(|(item, e)| format!("{}: {}（{}）", item.label, file(&item.path), e))
+            .collect::<Vec<_>>());
+        print_section("旁白目录布局的问题", &speakable.layout.iter()
+            .filter(|i| i.fix.is_some())
+            .map(|i| i.describe(base))
+            .collect::<Vec<_>>());
+    }
+    outln!();
+    match report.problem_count() {
+        0 => outln!("数据库完整。"),
+        n => outln!("共发现 {} 个问题。重新运行 build 可以修复；只有旁白出错时也可使用 check-voiceover --repair。", n),
+    }
+}
@@ -0,0 +1,120 @@
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc;
+use std::time::Duration;
+use tracing::{debug, error, warn};
+
+use crate::cli::SyncArgs;
+use crate::exit_code;
+use crate::i18n::tr;
+use crate::outln;
+use crate::utils::is_subpath;
+
+// ─── --watch ─────────────────────────────────────────────────────────────────
+
+/// 监视模式启动的同步进程带有该环境变量，其中的 --watch（如来自配置文件）被忽略
+pub const CHILD_ENV: &str = "IPOD_SHUFFLE_WATCH_CHILD";
+
+/// 最后一次变化之后等待这么久才重新同步，拖入一批文件只会触发一次同步
+const QUIET: Duration = Duration::from_secs(3);
+
+/// 要监视的目录：iPod_Control/Music 与 --root 给出的库根目录
+fn watch_dirs(base: &Path, cli: &SyncArgs) -> Vec<PathBuf> {
+    let mut dirs = vec![base.join("iPod_Control").join("Music")];
+    dirs.extend(cli.roots.iter().map(|r| base.join(&r.dir)));
+    dirs.retain(|d| d.is_dir() && is_subpath(d, base));
+    dirs.sort();
+    dirs.dedup();
+    // A directory inside another one is already covered by the recursive watch
+    let all = dirs.clone();
+    dirs.retain(|d| !all.iter().any(|o| o != d && d.starts_with(o)));
+    dirs
+}
+
+/// 是否是需要重新同步的变化：忽略只读访问，以及隐藏文件（如 macOS 的 `._*`）
+fn relevant(event: &notify::Event) -> bool {
+    !matches!(event.kind, EventKind::Access(_))
+        && event.paths.iter().any(|p| {
+            !p.file_name().is_some_and(|n| n.to_string_lossy().starts_with('.'))
+        })
+}
+
+/// 以同样的参数（去掉 --watch，加上 --yes）运行一次同步，返回退出码
+fn rebuild(args: &[OsString]) -> i32 {
+    let status = env::current_exe()
+        .and_then(|exe| Command::new(exe).args(args).env(CHILD_ENV, "1").status());
+    match status {
+        Ok(s) => s.code().unwrap_or(exit_code::INTERRUPTED),
+        Err(e) => {
+            error!("{}", tr!("无法启动同步: {}", e));
+            exit_code::FAILURE
+        }
+    }
+}
+
+/// --watch：先同步一次，之后监视设备上的曲目目录，文件增删时重新同步，直到按下 Ctrl+C。
+/// 每次同步都在单独的进程中运行，出错不会结束监视；设备被拔出时退出
+pub fn run_watch(base: &Path, cli: &SyncArgs) -> ! {
+    let mut args: Vec<OsString> = env::args_os().skip(1).filter(|a| a != "--watch").collect();
+    // Nobody is there to answer prompts once the watch is running
+    args.push("--yes".into());
+
+    let mut code = rebuild(&args);
+    loop {
+        if code == exit_code::DEVICE_NOT_FOUND || code == exit_code::INTERRUPTED {
+            std::process::exit(code);
+        }
+        if code != exit_code::SUCCESS {
+            warn!("{}", tr!("同步以状态码 {} 结束，继续监视", code));
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = match notify::recommended_watcher(tx) {
+            Ok(w) => w,
+            Err(e) => {
+                error!("{}", tr!("无法监视设备: {}", e));
+                std::process::exit(exit_code::FAILURE);
+            }
+        };
+        let dirs = watch_dirs(base, cli);
+        for dir in &dirs {
+            if let Err(e) = watcher.watch(dir, RecursiveMode::Recursive) {
+                error!("{}", tr!("无法监视 {}: {}", dir.display(), e));
+                std::process::exit(exit_code::FAILURE);
+            }
+        }
+        if dirs.is_empty() {
+            error!("{}", tr!("设备上没有可监视的曲目目录"));
+            std::process::exit(exit_code::DEVICE_NOT_FOUND);
+        }
+        let names: Vec<String> = dirs.iter().map(|d| d.strip_prefix(base).unwrap_or(d).display().to_string()).collect();
+        outln!("{}", tr!("正在监视 {} 中的变化，按 Ctrl+C 退出", names.join(", ")));
+
+        // Wait for the first change, then until the device has been quiet for a while
+        let mut changed: HashSet<PathBuf> = HashSet::new();
+        loop {
+            let event = if changed.is_empty() { rx.recv().ok() } else { rx.recv_timeout(QUIET).ok() };
+            match event {
+                Some(Ok(event)) if relevant(&event) => {
+                    debug!("{}", tr!("[~] 变化: {}", format!("{:?}", event.paths)));
+                    changed.extend(event.paths);
+                }
+                Some(Ok(_)) => {}
+                Some(Err(e)) => warn!("{}", tr!("监视出错: {}", e)),
+                None => break,
+            }
+        }
+        // Stop watching while syncing; the sync's own conversions are picked up by the next watch
+        drop(watcher);
+        if !base.is_dir() {
+            error!("{}", tr!("寻找 iPod 目录出错。也许它没有连接或挂载？"));
+            std::process::exit(exit_code::DEVICE_NOT_FOUND);
+        }
+        outln!("{}", tr!("检测到 {} 个文件有变化，重新同步...", changed.len()));
+        code = rebuild(&args);
+    }
+}
@@ -41,6 +41,8 @@ class ShufflerConfig {
       args.addAll(['--auto-id3-playlists', autoId3Playlists!]);
     }
     if (verbose) args.add('--verbose');
+    // 界面没有确认对话框，子进程的标准输入也不是终端，不加 --yes 时需要删除文件的同步会被取消
+    args.add('--yes');
     args.add(ipodPath);
     return args;
   }